@@ -0,0 +1,166 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::borrow::Cow;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+/// The one-character form of `ESC [`
+const CSI: char = '\u{9b}';
+
+/// `text` without terminal escape sequences: colors and cursor moves
+/// (`ESC [ ... m`), titles and hyperlinks (`ESC ] ... BEL`) and the other
+/// `ESC` sequences. git emits them despite a pipe with `color.ui = always`,
+/// `%C(always,...)` formats or a remote's colored messages.
+pub fn strip(text: &str) -> Cow<'_, str> {
+    if !text.contains([ESC, CSI]) {
+        return Cow::Borrowed(text);
+    }
+    let mut plain = String::with_capacity(text.len());
+    scan(text, |part| {
+        if let Part::Text(part) = part {
+            plain.push_str(part);
+        }
+    });
+    Cow::Owned(plain)
+}
+
+/// One line of a tool's colored output (delta, diff-so-fancy) as styled
+/// spans: the colors and attributes of `ESC [ ... m` are kept, other
+/// escapes dropped and tabs expanded
+pub fn line(text: &str) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut style = Style::default();
+    let mut text_part = String::new();
+    scan(text, |part| match part {
+        Part::Text(part) => text_part.push_str(&part.replace('\t', "    ")),
+        Part::Sgr(params) => {
+            if !text_part.is_empty() {
+                spans.push(Span::styled(std::mem::take(&mut text_part), style));
+            }
+            style = apply_sgr(style, params);
+        }
+    });
+    if !text_part.is_empty() {
+        spans.push(Span::styled(text_part, style));
+    }
+    Line::from(spans)
+}
+
+enum Part<'a> {
+    /// A run of text between escapes
+    Text(&'a str),
+    /// The parameters of an `ESC [ ... m`
+    Sgr(&'a str),
+}
+
+/// Walks `text`, handing its text runs and color changes to `on_part` in
+/// order; other escapes are skipped
+fn scan<'a>(text: &'a str, mut on_part: impl FnMut(Part<'a>)) {
+    let mut chars = text.char_indices().peekable();
+    let mut run_start = 0;
+    while let Some((index, ch)) = chars.next() {
+        if ch != ESC && ch != CSI {
+            continue;
+        }
+        if run_start < index {
+            on_part(Part::Text(&text[run_start..index]));
+        }
+        let control = match ch {
+            ESC => chars.next().map(|(_, ch)| ch),
+            _ => Some('['),
+        };
+        match control {
+            // Parameters and intermediates up to a final byte in `@`..`~`
+            Some('[') => {
+                let params_start = chars.peek().map_or(text.len(), |&(index, _)| index);
+                for (index, ch) in chars.by_ref() {
+                    if ('@'..='~').contains(&ch) {
+                        if ch == 'm' {
+                            on_part(Part::Sgr(&text[params_start..index]));
+                        }
+                        break;
+                    }
+                }
+            }
+            // Strings ended by BEL or `ESC \`
+            Some(']' | 'P' | 'X' | '^' | '_') => {
+                while let Some((_, ch)) = chars.next() {
+                    if ch == BEL {
+                        break;
+                    }
+                    if ch == ESC {
+                        chars.next_if(|&(_, ch)| ch == '\\');
+                        break;
+                    }
+                }
+            }
+            // Intermediates, then the final character
+            Some(' '..='/') => {
+                while chars.next_if(|&(_, ch)| (' '..='/').contains(&ch)).is_some() {}
+                chars.next();
+            }
+            Some(_) | None => {}
+        }
+        run_start = chars.peek().map_or(text.len(), |&(index, _)| index);
+    }
+    if run_start < text.len() {
+        on_part(Part::Text(&text[run_start..]));
+    }
+}
+
+/// `style` after the Select Graphic Rendition parameters `params`
+/// (`1;38;5;208`); unknown ones are ignored
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    // `ESC [ m` resets, like `ESC [ 0 m`
+    if params.is_empty() {
+        return Style::default();
+    }
+    let mut codes = params.split([';', ':']).map(|code| code.parse::<u16>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(Color::Indexed((code - 30) as u8)),
+            90..=97 => style.fg(Color::Indexed((code - 90 + 8) as u8)),
+            40..=47 => style.bg(Color::Indexed((code - 40) as u8)),
+            100..=107 => style.bg(Color::Indexed((code - 100 + 8) as u8)),
+            38 => match extended_color(&mut codes) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            48 => match extended_color(&mut codes) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            39 => style.fg(Color::Reset),
+            49 => style.bg(Color::Reset),
+            _ => style,
+        };
+    }
+    style
+}
+
+/// The color after a 38 or 48: `5;n` from the 256-color palette or
+/// `2;r;g;b`
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()? as u8)),
+        2 => {
+            let (r, g, b) = (codes.next()?, codes.next()?, codes.next()?);
+            Some(Color::Rgb(r as u8, g as u8, b as u8))
+        }
+        _ => None,
+    }
+}
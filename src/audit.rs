@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+/// Trail of what was looked at during an `--audit` session
+#[derive(Debug)]
+pub struct AuditLog {
+    started: DateTime<Utc>,
+    /// Login name of whoever ran the session
+    user: Option<String>,
+    entries: Vec<AuditEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub event: AuditEvent,
+    /// Working directory (or git directory) of the repository
+    pub repository: String,
+    pub commit_id: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditEvent {
+    /// Selected, so its details were on screen
+    Commit,
+    Diff,
+}
+
+impl AuditEvent {
+    fn label(self) -> &'static str {
+        match self {
+            AuditEvent::Commit => "commit",
+            AuditEvent::Diff => "diff",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    tool: String,
+    user: Option<&'a str>,
+    started: DateTime<Utc>,
+    ended: DateTime<Utc>,
+    entries: &'a [AuditEntry],
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        AuditLog {
+            started: Utc::now(),
+            user: std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Adds an entry; a commit that stays selected is recorded once
+    pub fn record(&mut self, event: AuditEvent, repository: &Path, commit_id: &str, summary: &str) {
+        // Through components to drop the trailing slash of a working directory
+        let repository = repository.components().collect::<PathBuf>().display().to_string();
+        if event == AuditEvent::Commit
+            && self.entries.last().is_some_and(|last| {
+                last.commit_id == commit_id && last.repository == repository
+            })
+        {
+            return;
+        }
+        self.entries.push(AuditEntry {
+            at: Utc::now(),
+            event,
+            repository,
+            commit_id: commit_id.to_string(),
+            summary: summary.to_string(),
+        });
+    }
+
+    /// Writes the report: JSON when `path` ends in `.json`, else plain text
+    pub fn write_report(&self, path: &Path) -> std::io::Result<()> {
+        let report = Report {
+            tool: format!("git-tui {}", env!("CARGO_PKG_VERSION")),
+            user: self.user.as_deref(),
+            started: self.started,
+            ended: Utc::now(),
+            entries: &self.entries,
+        };
+        let text = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::to_string_pretty(&report)? + "\n"
+        } else {
+            report.to_text()
+        };
+        std::fs::write(path, text)
+    }
+}
+
+impl Report<'_> {
+    fn to_text(&self) -> String {
+        let time = |at: &DateTime<Utc>| at.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        let mut text = String::new();
+        let _ = writeln!(text, "{} audit report", self.tool);
+        let _ = writeln!(text, "User:    {}", self.user.unwrap_or("(unknown)"));
+        let _ = writeln!(text, "Session: {} to {}", time(&self.started), time(&self.ended));
+        let _ = writeln!(text, "Viewed:  {} entries", self.entries.len());
+
+        let mut repository = None;
+        for entry in self.entries {
+            if repository != Some(&entry.repository) {
+                let _ = writeln!(text, "\nRepository: {}", entry.repository);
+                repository = Some(&entry.repository);
+            }
+            let _ = writeln!(
+                text,
+                "{}  {:<6}  {}  {}",
+                time(&entry.at),
+                entry.event.label(),
+                entry.commit_id,
+                entry.summary
+            );
+        }
+        text
+    }
+}
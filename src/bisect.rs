@@ -0,0 +1,53 @@
+use git2::{Oid, Repository};
+
+/// A `git bisect` session in progress (or finished but not yet reset), read
+/// back from the repository so sessions started outside git-tui show too
+#[derive(Debug, Clone, Default)]
+pub struct BisectState {
+    pub bad: Option<Oid>,
+    pub good: Vec<Oid>,
+    pub skipped: Vec<Oid>,
+    /// Set once git has narrowed the range down to one commit
+    pub culprit: Option<Oid>,
+}
+
+impl BisectState {
+    /// How a commit has been marked, if at all
+    pub fn mark(&self, id: &str) -> Option<&'static str> {
+        let matches = |oid: &Oid| !id.is_empty() && oid.to_string().starts_with(id);
+        if self.culprit.as_ref().is_some_and(matches) {
+            Some("first bad commit")
+        } else if self.bad.as_ref().is_some_and(matches) {
+            Some("bad")
+        } else if self.good.iter().any(matches) {
+            Some("good")
+        } else if self.skipped.iter().any(matches) {
+            Some("skip")
+        } else {
+            None
+        }
+    }
+}
+
+/// The session in `repo`, if one has been started. `BISECT_LOG` and the
+/// `refs/bisect/` refs are per worktree.
+pub fn state(repo: &Repository) -> Option<BisectState> {
+    let log = std::fs::read_to_string(repo.path().join("BISECT_LOG")).ok()?;
+    let target = |name: &str| repo.find_reference(name).ok().and_then(|r| r.target());
+    let targets = |glob: &str| -> Vec<Oid> {
+        repo.references_glob(glob)
+            .map(|refs| refs.flatten().filter_map(|r| r.target()).collect())
+            .unwrap_or_default()
+    };
+
+    Some(BisectState {
+        bad: target("refs/bisect/bad"),
+        good: targets("refs/bisect/good-*"),
+        skipped: targets("refs/bisect/skip-*"),
+        // git logs "# first bad commit: [<id>] <subject>" when it is done
+        culprit: log.lines().rev().find_map(|line| {
+            let rest = line.strip_prefix("# first bad commit: [")?;
+            Oid::from_str(rest.split(']').next()?).ok()
+        }),
+    })
+}
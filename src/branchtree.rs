@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+
+/// Order of branches in the Branches panel (within each group when grouped)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BranchSort {
+    /// Local branches, then remote ones, each by name
+    #[default]
+    Name,
+    /// Most recent tip commit first
+    Date,
+    /// Furthest from the upstream (ahead plus behind) first
+    Divergence,
+}
+
+impl BranchSort {
+    pub fn next(self) -> Self {
+        match self {
+            BranchSort::Name => BranchSort::Date,
+            BranchSort::Date => BranchSort::Divergence,
+            BranchSort::Divergence => BranchSort::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BranchSort::Name => "name",
+            BranchSort::Date => "date",
+            BranchSort::Divergence => "ahead/behind",
+        }
+    }
+}
+
+/// A branch as far as grouping is concerned
+pub struct Item<'a> {
+    pub name: &'a str,
+    pub is_remote: bool,
+    /// Listed first, outside any group (the detached HEAD entry)
+    pub pinned: bool,
+}
+
+/// A line of the Branches panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BranchRow {
+    /// A section (local branches, a remote) or a folder of branches sharing
+    /// a `prefix/`
+    Group {
+        /// Identifies the group across reloads, for its collapsed state
+        key: String,
+        label: String,
+        depth: usize,
+        /// Branches in it, folders included
+        count: usize,
+        collapsed: bool,
+    },
+    Branch {
+        /// Into the branch list
+        index: usize,
+        depth: usize,
+        /// Byte offset of the part of the name shown, after the remote and
+        /// folder prefixes
+        label_start: usize,
+    },
+}
+
+/// Rows for `items`, kept in their order. Each remote's branches go in a
+/// section of their own, left out entirely with `hide_remotes`. Ungrouped,
+/// local branches are listed as they are; grouped, they get a section too,
+/// and branches sharing a `prefix/` are gathered in a folder where their
+/// first one is.
+pub fn rows(items: &[Item<'_>], grouped: bool, hide_remotes: bool, collapsed: &HashSet<String>) -> Vec<BranchRow> {
+    let mut rows = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        if item.pinned {
+            rows.push(BranchRow::Branch { index, depth: 0, label_start: 0 });
+        }
+    }
+
+    let local: Vec<(usize, usize)> = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| !item.pinned && !item.is_remote)
+        .map(|(index, _)| (index, 0))
+        .collect();
+    match grouped {
+        true => push_section(&mut rows, items, "local", "Local", &local, true, collapsed),
+        false => rows.extend(local.into_iter().map(|(index, _)| BranchRow::Branch { index, depth: 0, label_start: 0 })),
+    }
+    if hide_remotes {
+        return rows;
+    }
+
+    let mut remotes: Vec<&str> = Vec::new();
+    for item in items.iter().filter(|item| item.is_remote && !item.pinned) {
+        let remote = remote_name(item.name);
+        if !remotes.contains(&remote) {
+            remotes.push(remote);
+        }
+    }
+    for remote in remotes {
+        let members: Vec<(usize, usize)> = items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| item.is_remote && !item.pinned && remote_name(item.name) == remote)
+            .map(|(index, item)| (index, (remote.len() + 1).min(item.name.len())))
+            .collect();
+        push_section(&mut rows, items, &format!("remote:{}", remote), remote, &members, grouped, collapsed);
+    }
+    rows
+}
+
+/// Keys of the groups a branch sits in, outermost first, so they can be
+/// expanded to show it
+pub fn group_keys(name: &str, is_remote: bool) -> Vec<String> {
+    let (mut key, rest) = match is_remote {
+        true => {
+            let remote = remote_name(name);
+            (format!("remote:{}", remote), &name[(remote.len() + 1).min(name.len())..])
+        }
+        false => ("local".to_string(), name),
+    };
+    let mut keys = vec![key.clone()];
+    let mut segments: Vec<&str> = rest.split('/').collect();
+    segments.pop();
+    for segment in segments {
+        key = format!("{}/{}", key, segment);
+        keys.push(key.clone());
+    }
+    keys
+}
+
+fn remote_name(name: &str) -> &str {
+    name.split('/').next().unwrap_or(name)
+}
+
+fn push_section(
+    rows: &mut Vec<BranchRow>,
+    items: &[Item<'_>],
+    key: &str,
+    label: &str,
+    members: &[(usize, usize)],
+    folders: bool,
+    collapsed: &HashSet<String>,
+) {
+    if members.is_empty() {
+        return;
+    }
+    let is_collapsed = collapsed.contains(key);
+    rows.push(BranchRow::Group {
+        key: key.to_string(),
+        label: label.to_string(),
+        depth: 0,
+        count: members.len(),
+        collapsed: is_collapsed,
+    });
+    if is_collapsed {
+        return;
+    }
+    match folders {
+        true => push_tree(rows, items, key, members, 1, collapsed),
+        false => rows.extend(members.iter().map(|&(index, label_start)| BranchRow::Branch { index, depth: 1, label_start })),
+    }
+}
+
+/// `members` are branch indexes with the offset of the name still to be
+/// grouped; folders are made for prefixes shared by two or more branches
+fn push_tree(
+    rows: &mut Vec<BranchRow>,
+    items: &[Item<'_>],
+    key: &str,
+    members: &[(usize, usize)],
+    depth: usize,
+    collapsed: &HashSet<String>,
+) {
+    let rest = |&(index, start): &(usize, usize)| &items[index].name[start..];
+    let folder_of = |member: &(usize, usize)| rest(member).split_once('/').map(|(folder, _)| folder);
+    let mut seen = HashSet::new();
+
+    for member in members {
+        let &(index, start) = member;
+        let folder = folder_of(member);
+        let in_folder: Vec<(usize, usize)> = match folder {
+            Some(folder) => members
+                .iter()
+                .filter(|m| folder_of(m) == Some(folder))
+                .map(|&(index, start)| (index, start + folder.len() + 1))
+                .collect(),
+            None => Vec::new(),
+        };
+        match folder {
+            Some(folder) if in_folder.len() > 1 => {
+                if !seen.insert(folder) {
+                    continue;
+                }
+                let folder_key = format!("{}/{}", key, folder);
+                let is_collapsed = collapsed.contains(&folder_key);
+                rows.push(BranchRow::Group {
+                    key: folder_key.clone(),
+                    label: format!("{}/", folder),
+                    depth,
+                    count: in_folder.len(),
+                    collapsed: is_collapsed,
+                });
+                if !is_collapsed {
+                    push_tree(rows, items, &folder_key, &in_folder, depth + 1, collapsed);
+                }
+            }
+            _ => rows.push(BranchRow::Branch { index, depth, label_start: start }),
+        }
+    }
+}
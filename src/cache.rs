@@ -0,0 +1,181 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+/// Approximate heap size of a cached value, for memory limits
+pub trait Weigh {
+    fn weight(&self) -> usize;
+}
+
+impl Weigh for String {
+    fn weight(&self) -> usize {
+        std::mem::size_of::<String>() + self.len()
+    }
+}
+
+impl<T: Weigh> Weigh for Vec<T> {
+    fn weight(&self) -> usize {
+        std::mem::size_of::<Vec<T>>() + self.iter().map(Weigh::weight).sum::<usize>()
+    }
+}
+
+/// String-keyed cache that evicts the least recently used entries once the
+/// weight of its values exceeds the limit
+pub struct LruCache<V> {
+    entries: HashMap<String, Entry<V>>,
+    /// Keys by last use, oldest first
+    order: BTreeMap<u64, String>,
+    tick: u64,
+    used: usize,
+    limit: usize,
+}
+
+struct Entry<V> {
+    value: V,
+    weight: usize,
+    last_used: u64,
+}
+
+impl<V: Weigh> LruCache<V> {
+    pub fn new(limit: usize) -> Self {
+        LruCache {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            used: 0,
+            limit,
+        }
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<&V> {
+        let entry = self.entries.get_mut(key)?;
+        self.order.remove(&entry.last_used);
+        self.tick += 1;
+        entry.last_used = self.tick;
+        self.order.insert(self.tick, key.to_string());
+        Some(&entry.value)
+    }
+
+    /// Without updating the entry's last use
+    pub fn peek(&self, key: &str) -> Option<&V> {
+        self.entries.get(key).map(|e| &e.value)
+    }
+
+    /// Stores a value; one heavier than the whole limit is not kept
+    pub fn insert(&mut self, key: String, value: V) {
+        self.remove(&key);
+        let weight = key.len() + value.weight();
+        if weight > self.limit {
+            return;
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, Entry { value, weight, last_used: self.tick });
+        self.used += weight;
+        self.evict();
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.last_used);
+            self.used -= entry.weight;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.used = 0;
+    }
+
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.used > self.limit {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&key) {
+                self.used -= entry.weight;
+            }
+        }
+    }
+}
+
+impl<V: Weigh + Clone> LruCache<V> {
+    /// Entries from least to most recently used, for persisting
+    pub fn to_vec(&self) -> Vec<(String, V)> {
+        self.order
+            .values()
+            .filter_map(|key| self.entries.get(key).map(|e| (key.clone(), e.value.clone())))
+            .collect()
+    }
+
+    /// Adds entries in the order [`to_vec`](Self::to_vec) returned them
+    pub fn extend(&mut self, entries: Vec<(String, V)>) {
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Stable 64-bit FNV-1a hash, for cache file names and ref-tip fingerprints
+pub fn fingerprint<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.iter().chain([&0u8]) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// On-disk cache of one repository, written when git-tui exits and read
+/// when the repository is opened again
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot<T> {
+    version: u32,
+    pub data: T,
+}
+
+impl<T: Serialize + DeserializeOwned> Snapshot<T> {
+    /// Bumped when the cached data changes shape; older files are ignored
+    const VERSION: u32 = 1;
+
+    pub fn load(repo_dir: &Path) -> Option<T> {
+        let text = std::fs::read(snapshot_path(repo_dir)?).ok()?;
+        let snapshot: Snapshot<T> = serde_json::from_slice(&text).ok()?;
+        (snapshot.version == Self::VERSION).then_some(snapshot.data)
+    }
+
+    pub fn save(repo_dir: &Path, data: T) -> std::io::Result<()> {
+        let Some(path) = snapshot_path(repo_dir) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let snapshot = Snapshot { version: Self::VERSION, data };
+        // Through a temporary file so a concurrent reader never sees half a file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&snapshot)?)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+/// `$XDG_CACHE_HOME/git-tui/<hash of the repository path>.json`, else under `~/.cache`
+fn snapshot_path(repo_dir: &Path) -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    let repo_dir = repo_dir.canonicalize().unwrap_or_else(|_| repo_dir.to_path_buf());
+    let name = fingerprint([repo_dir.as_os_str().as_encoded_bytes()]);
+    Some(base.join("git-tui").join(format!("{}.json", name)))
+}
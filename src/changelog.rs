@@ -0,0 +1,117 @@
+use crate::conventional;
+use git2::Oid;
+
+/// How entries are gathered under headings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// By conventional commit type, non-conventional commits last
+    Type,
+    Author,
+}
+
+impl Grouping {
+    pub fn label(self) -> &'static str {
+        match self {
+            Grouping::Type => "by type",
+            Grouping::Author => "by author",
+        }
+    }
+}
+
+/// Commits to write a changelog for, newest first
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub ids: Vec<Oid>,
+    /// What the commits are, for the heading: `v1.2..HEAD`
+    pub label: String,
+    pub grouping: Grouping,
+}
+
+pub struct Entry {
+    pub short_id: String,
+    pub subject: String,
+    pub author: String,
+}
+
+/// Headings for conventional types, in the order sections are written
+const SECTIONS: [(&str, &str); 11] = [
+    ("feat", "Features"),
+    ("fix", "Bug Fixes"),
+    ("perf", "Performance"),
+    ("revert", "Reverts"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build"),
+    ("ci", "Continuous Integration"),
+    ("style", "Style"),
+    ("chore", "Chores"),
+];
+
+/// A Markdown changelog of `entries` (newest first, kept so in each section)
+pub fn markdown(label: &str, entries: &[Entry], grouping: Grouping) -> String {
+    let mut text = format!("# Changes in {}\n", label);
+    match grouping {
+        Grouping::Type => {
+            let breaking: Vec<&Entry> = entries
+                .iter()
+                .filter(|e| conventional::prefix(&e.subject).is_some_and(|p| p.breaking))
+                .collect();
+            push_section(&mut text, "Breaking Changes", breaking.into_iter().map(type_line));
+            for (kind, heading) in SECTIONS {
+                let lines = entries
+                    .iter()
+                    .filter(|e| conventional::prefix(&e.subject).is_some_and(|p| p.kind == kind))
+                    .map(type_line);
+                push_section(&mut text, heading, lines);
+            }
+            let other = entries
+                .iter()
+                .filter(|e| conventional::prefix(&e.subject).is_none())
+                .map(|e| format!("- {} ({})", e.subject, e.short_id));
+            push_section(&mut text, "Other Changes", other);
+        }
+        Grouping::Author => {
+            let mut authors: Vec<&str> = Vec::new();
+            for entry in entries {
+                if !authors.contains(&entry.author.as_str()) {
+                    authors.push(&entry.author);
+                }
+            }
+            authors.sort_by_key(|author| std::cmp::Reverse(entries.iter().filter(|e| e.author == *author).count()));
+            for author in authors {
+                let lines = entries
+                    .iter()
+                    .filter(|e| e.author == author)
+                    .map(|e| format!("- {} ({})", e.subject, e.short_id));
+                push_section(&mut text, author, lines);
+            }
+        }
+    }
+    text
+}
+
+/// `- **scope:** description (abc1234)`, the type left to the heading
+fn type_line(entry: &Entry) -> String {
+    let Some(prefix) = conventional::prefix(&entry.subject) else {
+        return format!("- {} ({})", entry.subject, entry.short_id);
+    };
+    let description = entry.subject[prefix.len..].trim();
+    match prefix.scope {
+        Some(scope) => format!("- **{}:** {} ({})", scope, description, entry.short_id),
+        None => format!("- {} ({})", description, entry.short_id),
+    }
+}
+
+/// A `## heading` and its lines, left out when there are none
+fn push_section(text: &mut String, heading: &str, lines: impl Iterator<Item = String>) {
+    let lines: Vec<String> = lines.collect();
+    if lines.is_empty() {
+        return;
+    }
+    text.push_str(&format!("\n## {}\n\n", heading));
+    for line in lines {
+        text.push_str(&line);
+        text.push('\n');
+    }
+}
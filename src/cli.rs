@@ -0,0 +1,105 @@
+use clap::{Parser, ValueEnum};
+use std::{io::IsTerminal, path::PathBuf};
+
+/// Terminal git graph browser with branch filtering
+#[derive(Debug, Clone, Parser)]
+#[command(name = "git-tui", version)]
+pub struct Cli {
+    /// Repository to open (any path inside it); defaults to the current directory
+    pub path: Option<PathBuf>,
+
+    /// Start with the graph filtered by this branch
+    #[arg(short, long, value_name = "NAME")]
+    pub branch: Option<String>,
+
+    /// Load the whole history up front instead of paging
+    #[arg(long, conflicts_with = "max_count")]
+    pub all: bool,
+
+    /// Load at most N commits (no further paging)
+    #[arg(short = 'n', long, value_name = "N")]
+    pub max_count: Option<usize>,
+
+    /// Disable every operation that modifies the repository
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// Read-only session that records every commit and diff viewed, with
+    /// timestamps, for incident forensics; implies --read-only
+    #[arg(long)]
+    pub audit: bool,
+
+    /// With --audit, write the session report to FILE on exit (JSON if it ends
+    /// in `.json`, else text)
+    #[arg(long, value_name = "FILE", requires = "audit")]
+    pub audit_report: Option<PathBuf>,
+
+    /// Print the graph to stdout and exit instead of starting the interface
+    #[arg(long, visible_alias = "no-tui")]
+    pub print: bool,
+
+    /// With --print, append commit counts per author email domain
+    #[arg(long, requires = "print")]
+    pub stats: bool,
+
+    /// Write branches, commits and graph rows as JSON to FILE (`-` for stdout) and exit
+    #[arg(long, value_name = "FILE", conflicts_with = "print")]
+    pub export_json: Option<PathBuf>,
+
+    /// Write the loaded commit graph as Graphviz DOT to FILE (`-` for stdout)
+    /// and exit; a FILE ending in `.svg` is rendered with `dot`
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["print", "export_json"])]
+    pub export_dot: Option<PathBuf>,
+
+    /// Override a config option for this run, e.g. `--set cache.persist=true`
+    /// or `-c theme.active_border=magenta`. Values are TOML; anything else is
+    /// taken as a string. Repeatable; beats the config files and `GIT_TUI_*`
+    /// variables
+    #[arg(short = 'c', long = "set", value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+
+    /// When --print colors its output
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Only when stdout is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => std::io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// How much history the graph loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryScope {
+    /// Page by page as the selection reaches the end
+    Paged,
+    /// A fixed number of commits
+    MaxCount(usize),
+    /// Everything at once
+    All,
+}
+
+impl Cli {
+    pub fn history_scope(&self) -> HistoryScope {
+        if self.all {
+            HistoryScope::All
+        } else if let Some(n) = self.max_count {
+            HistoryScope::MaxCount(n)
+        } else {
+            HistoryScope::Paged
+        }
+    }
+}
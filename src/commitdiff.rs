@@ -0,0 +1,243 @@
+use crate::lfs;
+use chrono::{DateTime, FixedOffset};
+use git2::{Commit, Delta, DiffDelta, DiffFindOptions, DiffFormat, FileMode, Mailmap, Oid, Patch, Repository, Signature};
+use std::path::Path;
+
+/// A commit with its patch, laid out like `git show --no-color
+/// --format=fuller -p` but made with git2, so showing it starts no process.
+/// The diff is against the only parent, or the empty tree for a root commit.
+/// None for merges: their combined diff (`--cc`) is only in git itself.
+pub fn render(repo: &Repository, oid: Oid, mailmap: Option<&Mailmap>) -> Option<Result<String, git2::Error>> {
+    let commit = match repo.find_commit(oid) {
+        Ok(commit) if commit.parent_count() > 1 => return None,
+        Ok(commit) => commit,
+        Err(e) => return Some(Err(e)),
+    };
+    Some(render_commit(repo, &commit, mailmap))
+}
+
+fn render_commit(repo: &Repository, commit: &Commit<'_>, mailmap: Option<&Mailmap>) -> Result<String, git2::Error> {
+    let author = match mailmap {
+        Some(mailmap) => commit.author_with_mailmap(mailmap)?,
+        None => commit.author().to_owned(),
+    };
+    let committer = match mailmap {
+        Some(mailmap) => commit.committer_with_mailmap(mailmap)?,
+        None => commit.committer().to_owned(),
+    };
+
+    let mut text = format!("commit {}\n", commit.id());
+    text.push_str(&format!("Author:     {}\n", person(&author)));
+    text.push_str(&format!("AuthorDate: {}\n", date(&author)));
+    text.push_str(&format!("Commit:     {}\n", person(&committer)));
+    text.push_str(&format!("CommitDate: {}\n", date(&committer)));
+    text.push('\n');
+    for line in String::from_utf8_lossy(commit.message_bytes()).trim_end().lines() {
+        text.push_str("    ");
+        text.push_str(line);
+        text.push('\n');
+    }
+    text.push('\n');
+
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?.tree()?),
+    };
+    let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    // `git show` follows renames by default
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    let odb = repo.odb()?;
+    let blob_size = |id: Oid| match id.is_zero() {
+        true => None,
+        false => odb.read_header(id).ok().map(|(size, _)| size as u64),
+    };
+    let mut patch = Vec::new();
+    // The LFS or submodule summary of the file being printed, by its ids
+    let mut summarized: Option<((Oid, Oid), Option<String>)> = None;
+    diff.print(DiffFormat::Patch, |delta, _, line| {
+        let ids = (delta.old_file().id(), delta.new_file().id());
+        if summarized.as_ref().is_none_or(|(seen, _)| *seen != ids) {
+            summarized = Some((ids, lfs_summary(repo, &delta).or_else(|| submodule_log(repo, &delta))));
+        }
+        // A pointer file's or submodule's header, then its summary in place
+        // of the hunks changing the pointer text or `Subproject commit` line
+        if let Some((_, Some(summary))) = &summarized {
+            if line.origin() == 'F' {
+                patch.extend_from_slice(line.content());
+                patch.extend_from_slice(format!("{}\n", summary).as_bytes());
+            }
+            return true;
+        }
+        if line.origin() == 'B' {
+            let sizes = (blob_size(delta.old_file().id()), blob_size(delta.new_file().id()));
+            patch.extend_from_slice(format!("{}\n", binary_summary(&delta, sizes)).as_bytes());
+            return true;
+        }
+        push_line(&mut patch, &line);
+        true
+    })?;
+    push_text(&mut text, &patch);
+    Ok(text)
+}
+
+/// The patch between two versions of a file, like a file of
+/// [`render`]'s: for content read from elsewhere than the object database
+pub fn buffers(old: &[u8], old_path: Option<&str>, new: &[u8], new_path: Option<&str>) -> Result<String, git2::Error> {
+    let mut patch = Patch::from_buffers(old, old_path.map(Path::new), new, new_path.map(Path::new), None)?;
+    let mut bytes = Vec::new();
+    patch.print(&mut |delta, _, line| {
+        match line.origin() {
+            'B' => {
+                let sizes = (old_path.map(|_| old.len() as u64), new_path.map(|_| new.len() as u64));
+                bytes.extend_from_slice(format!("{}\n", binary_summary(&delta, sizes)).as_bytes());
+            }
+            _ => push_line(&mut bytes, &line),
+        }
+        true
+    })?;
+    let mut text = String::new();
+    push_text(&mut text, &bytes);
+    Ok(text)
+}
+
+/// A line of a patch as `git show` prints it. Content lines carry their
+/// marker separately; headers, binary notices and the no-newline note come
+/// whole.
+fn push_line(patch: &mut Vec<u8>, line: &git2::DiffLine<'_>) {
+    if matches!(line.origin(), '+' | '-' | ' ') {
+        patch.push(line.origin() as u8);
+    }
+    patch.extend_from_slice(line.content());
+}
+
+/// Invalid UTF-8 is shown replaced rather than failing the whole diff;
+/// CRLF files lose the `\r` git would print
+fn push_text(text: &mut String, patch: &[u8]) {
+    for line in String::from_utf8_lossy(patch).lines() {
+        text.push_str(line.trim_end_matches('\r'));
+        text.push('\n');
+    }
+}
+
+/// `Binary file changed: 12.0 KiB → 14.3 KiB, mode 100644 → 100755` in
+/// place of git's `Binary files a/x and b/x differ`
+fn binary_summary(delta: &DiffDelta<'_>, (old, new): (Option<u64>, Option<u64>)) -> String {
+    let label = |bytes: Option<u64>| bytes.map_or_else(|| "unknown size".to_string(), size);
+    let mut summary = match delta.status() {
+        Delta::Added => format!("Binary file added: {}", label(new)),
+        Delta::Deleted => format!("Binary file deleted: {}", label(old)),
+        _ => format!("Binary file changed: {} → {}", label(old), label(new)),
+    };
+    let (old_mode, new_mode) = (delta.old_file().mode(), delta.new_file().mode());
+    if !matches!(delta.status(), Delta::Added | Delta::Deleted) && old_mode != new_mode {
+        summary.push_str(&format!(", mode {:o} → {:o}", u32::from(old_mode), u32::from(new_mode)));
+    }
+    summary
+}
+
+/// `LFS object: 1.2 MiB (sha256:1a2b3c4d5e6f) → 3.4 MiB (sha256:…)` for a
+/// file that is a Git LFS pointer on each side it exists on; None for other
+/// files, and for a file turned into a pointer or back, whose diff shows it
+fn lfs_summary(repo: &Repository, delta: &DiffDelta<'_>) -> Option<String> {
+    let pointer = |id: Oid| match id.is_zero() {
+        true => Some(None),
+        false => repo.find_blob(id).ok().and_then(|blob| lfs::parse(blob.content())).map(Some),
+    };
+    let label = |pointer: &lfs::Pointer| format!("{} ({})", size(pointer.size), pointer.short_oid());
+    match (pointer(delta.old_file().id())?, pointer(delta.new_file().id())?) {
+        (Some(old), Some(new)) if old.oid == new.oid => None,
+        (Some(old), Some(new)) => Some(format!("LFS object: {} → {}", label(&old), label(&new))),
+        (None, Some(new)) => Some(format!("LFS object added: {}", label(&new))),
+        (Some(old), None) => Some(format!("LFS object deleted: {}", label(&old))),
+        (None, None) => None,
+    }
+}
+
+/// The commits a submodule moved by, as `git diff --submodule=log` lists
+/// them: `Submodule lib 1234567..89abcde:`, then `  > subject` for each
+/// commit gained, newest first, and `  < subject` for each one lost. None
+/// for a submodule added or removed, or not checked out, whose diff shows
+/// the two commit ids.
+fn submodule_log(repo: &Repository, delta: &DiffDelta<'_>) -> Option<String> {
+    const MAX_COMMITS: usize = 100;
+    if delta.old_file().mode() != FileMode::Commit || delta.new_file().mode() != FileMode::Commit {
+        return None;
+    }
+    let path = delta.new_file().path()?;
+    let submodule = repo
+        .find_submodule(path.to_str()?)
+        .and_then(|submodule| submodule.open())
+        .or_else(|_| Repository::open(repo.workdir().unwrap_or(repo.path()).join(path)))
+        .ok()?;
+    let (old, new) = (delta.old_file().id(), delta.new_file().id());
+    let short = |id: Oid| id.to_string()[..7].to_string();
+    let name = path.display();
+    if submodule.find_commit(old).is_err() || submodule.find_commit(new).is_err() {
+        return Some(format!("Submodule {} {}..{} (commits not present)", name, short(old), short(new)));
+    }
+    // Commits reachable from `from` but not `hidden`, `marker` before each subject
+    let log = |from: Oid, hidden: Oid, marker: char| -> Result<Vec<String>, git2::Error> {
+        let mut walk = submodule.revwalk()?;
+        walk.push(from)?;
+        walk.hide(hidden)?;
+        let mut lines = Vec::new();
+        let mut count = 0;
+        for id in walk {
+            count += 1;
+            if count <= MAX_COMMITS {
+                let commit = submodule.find_commit(id?)?;
+                lines.push(format!("  {} {}", marker, commit.summary().unwrap_or_default()));
+            }
+        }
+        if count > MAX_COMMITS {
+            lines.push(format!("  {} … and {} more", marker, count - MAX_COMMITS));
+        }
+        Ok(lines)
+    };
+    let (gained, lost) = (log(new, old, '>').ok()?, log(old, new, '<').ok()?);
+    let (range, note) = match (gained.is_empty(), lost.is_empty()) {
+        (_, true) => ("..", ""),
+        (true, false) => ("...", " (rewind)"),
+        (false, false) => ("...", ""),
+    };
+    let mut summary = format!("Submodule {} {}{}{}{}:", name, short(old), range, short(new), note);
+    for line in gained.iter().chain(&lost) {
+        summary.push('\n');
+        summary.push_str(line);
+    }
+    Some(summary)
+}
+
+/// A byte count the way people read it: `512 B`, `1.2 KiB`, `3.4 MiB`
+pub fn size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn person(signature: &Signature<'_>) -> String {
+    format!(
+        "{} <{}>",
+        String::from_utf8_lossy(signature.name_bytes()),
+        String::from_utf8_lossy(signature.email_bytes())
+    )
+}
+
+/// The time as git prints it by default: `Thu Oct 16 10:00:00 2026 +0200`,
+/// in the signature's own zone
+fn date(signature: &Signature<'_>) -> String {
+    let time = signature.when();
+    FixedOffset::east_opt(time.offset_minutes() * 60)
+        .zip(DateTime::from_timestamp(time.seconds(), 0))
+        .map(|(zone, utc)| utc.with_timezone(&zone).format("%a %b %-d %H:%M:%S %Y %z").to_string())
+        .unwrap_or_else(|| time.seconds().to_string())
+}
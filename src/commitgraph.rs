@@ -0,0 +1,61 @@
+use git2::Repository;
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Whether git can walk history through its commit-graph file, which holds
+/// every commit's parents and generation number so `git log --graph` does
+/// not have to open each commit object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// `core.commitGraph` is off, or the clone is shallow (git ignores the
+    /// file there)
+    Disabled,
+    Missing,
+    /// Packs were added since it was written (a fetch), so the commits in
+    /// them are read the slow way
+    Stale,
+    Fresh,
+}
+
+fn info_dir(common_dir: &Path) -> PathBuf {
+    common_dir.join("objects").join("info")
+}
+
+/// When the commit-graph was last written: the single file, or the chain
+/// of split files `--split` writes
+fn written(common_dir: &Path) -> Option<SystemTime> {
+    let info = info_dir(common_dir);
+    [info.join("commit-graph"), info.join("commit-graphs").join("commit-graph-chain")]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/// When the newest pack was written
+fn newest_pack(common_dir: &Path) -> Option<SystemTime> {
+    std::fs::read_dir(common_dir.join("objects").join("pack"))
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "pack"))
+        .filter_map(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .max()
+}
+
+pub fn state(repo: &Repository, common_dir: &Path) -> State {
+    let enabled = repo.config().ok().and_then(|config| config.get_bool("core.commitGraph").ok()).unwrap_or(true);
+    if !enabled || repo.is_shallow() {
+        return State::Disabled;
+    }
+    match (written(common_dir), newest_pack(common_dir)) {
+        (None, _) => State::Missing,
+        (Some(written), Some(pack)) if pack > written => State::Stale,
+        _ => State::Fresh,
+    }
+}
+
+/// Arguments of the `git` command that brings the commit-graph up to date.
+/// Split files are added to the chain, so an update only covers the commits
+/// that are new.
+pub const WRITE_ARGS: [&str; 5] = ["commit-graph", "write", "--reachable", "--split", "--progress"];
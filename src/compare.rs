@@ -0,0 +1,62 @@
+use git2::{ErrorCode, Mailmap, Oid, Repository, Sort};
+
+/// A commit listed in a comparison
+pub struct Entry {
+    pub id: Oid,
+    pub summary: String,
+    pub author: String,
+}
+
+/// The commits one ref has that the other does not, newest first
+pub struct Side {
+    pub name: String,
+    pub commits: Vec<Entry>,
+}
+
+/// Two refs side by side: what each has that the other lacks (the two
+/// halves of `git log A...B`), and the merge base they forked from
+pub struct Comparison {
+    pub sides: [Side; 2],
+    /// None when they share no history
+    pub merge_base: Option<Entry>,
+}
+
+/// Compares two revisions (branch, tag or anything `git rev-parse` takes)
+pub fn compare(repo: &Repository, names: [&str; 2], mailmap: Option<&Mailmap>) -> Result<Comparison, git2::Error> {
+    let a = repo.revparse_single(names[0])?.peel_to_commit()?.id();
+    let b = repo.revparse_single(names[1])?.peel_to_commit()?.id();
+    let merge_base = match repo.merge_base(a, b) {
+        Ok(base) => Some(entry(repo, base, mailmap)?),
+        Err(e) if e.code() == ErrorCode::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    Ok(Comparison {
+        sides: [
+            Side { name: names[0].to_string(), commits: only_in(repo, a, b, mailmap)? },
+            Side { name: names[1].to_string(), commits: only_in(repo, b, a, mailmap)? },
+        ],
+        merge_base,
+    })
+}
+
+/// Commits reachable from `tip` but not from `other`
+fn only_in(repo: &Repository, tip: Oid, other: Oid, mailmap: Option<&Mailmap>) -> Result<Vec<Entry>, git2::Error> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    walk.push(tip)?;
+    walk.hide(other)?;
+    walk.map(|id| entry(repo, id?, mailmap)).collect()
+}
+
+fn entry(repo: &Repository, id: Oid, mailmap: Option<&Mailmap>) -> Result<Entry, git2::Error> {
+    let commit = repo.find_commit(id)?;
+    let author = match mailmap {
+        Some(mailmap) => commit.author_with_mailmap(mailmap)?,
+        None => commit.author().to_owned(),
+    };
+    Ok(Entry {
+        id,
+        summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default()).into_owned(),
+        author: String::from_utf8_lossy(author.name_bytes()).into_owned(),
+    })
+}
@@ -0,0 +1,685 @@
+use crate::{confirm, github::GitHubSettings, gitlab::GitLabSettings, glyphs::Glyphs, images, keymap::Keymap, repos, theme::Theme, trailers};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// User configuration read from `config.toml`. Every section is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Key binding overrides: `[keys.<context>]` tables mapping action names to key lists
+    pub keys: HashMap<String, HashMap<String, Vec<String>>>,
+    /// Color overrides, see [`Theme::from_config`]
+    pub theme: HashMap<String, String>,
+    /// Graph node characters, see [`Glyphs::from_config`]
+    pub glyphs: HashMap<String, String>,
+    pub filters: FilterConfig,
+    pub refs: RefFilterConfig,
+    pub trailers: TrailerConfig,
+    pub notifications: NotificationConfig,
+    pub cache: CacheConfig,
+    pub branches: BranchPanelConfig,
+    pub dates: DateConfig,
+    pub confirm: ConfirmConfig,
+    pub safety: SafetyConfig,
+    pub repos: ReposConfig,
+    pub layout: LayoutConfig,
+    pub diff: DiffConfig,
+    pub editor: EditorConfig,
+    pub github: GitHubConfig,
+    pub gitlab: GitLabConfig,
+    /// Profiles for the identity picker: `[[identities]]` tables
+    pub identities: Vec<Identity>,
+}
+
+/// Who commits made from git-tui are attributed to
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+    /// `user.signingkey` to sign with, when signing is enabled
+    pub signing_key: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitHubConfig {
+    /// GitHub Enterprise host; its API is at `https://<host>/api/v3` unless `api_url` says otherwise
+    pub host: Option<String>,
+    pub api_url: Option<String>,
+    /// Personal access token; `GITHUB_TOKEN` or `GH_TOKEN` are used when unset
+    pub token: Option<String>,
+}
+
+impl GitHubConfig {
+    fn settings(&self) -> GitHubSettings {
+        let defaults = GitHubSettings::default();
+        let host = self.host.clone().unwrap_or(defaults.host);
+        let api_url = match (&self.api_url, &self.host) {
+            (Some(url), _) => url.clone(),
+            (None, Some(host)) => format!("https://{}/api/v3", host),
+            (None, None) => defaults.api_url,
+        };
+        GitHubSettings { host, api_url, token: self.token.clone() }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitLabConfig {
+    /// Self-managed host; its API is at `https://<host>/api/v4` unless `api_url` says otherwise
+    pub host: Option<String>,
+    pub api_url: Option<String>,
+    /// Personal access token; `GITLAB_TOKEN` is used when unset
+    pub token: Option<String>,
+}
+
+impl GitLabConfig {
+    fn settings(&self) -> GitLabSettings {
+        let defaults = GitLabSettings::default();
+        let api_url = match (&self.api_url, &self.host) {
+            (Some(url), _) => url.clone(),
+            (None, Some(host)) => format!("https://{}/api/v4", host),
+            (None, None) => defaults.api_url,
+        };
+        GitLabSettings {
+            host: self.host.clone().unwrap_or(defaults.host),
+            api_url,
+            token: self.token.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BranchPanelConfig {
+    /// Start with remote branches left out of the Branches panel
+    pub hide_remotes: bool,
+    /// Start with every remote's section folded
+    pub collapse_remotes: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DateConfig {
+    /// strftime-style format of absolute times, shown in the local timezone
+    pub format: String,
+    /// Start with relative times ("3 days ago") in Commit Details
+    pub relative: bool,
+}
+
+impl Default for DateConfig {
+    fn default() -> Self {
+        DateConfig {
+            format: "%Y-%m-%d %H:%M:%S %z".to_string(),
+            relative: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// Start with the selected commit's diff in a pane right of the graph
+    pub split_diff: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffConfig {
+    /// Shell command the diff text is piped through (`delta --paging=never`),
+    /// its colored output shown instead
+    pub filter: Option<String>,
+    /// Protocol drawing image previews: `auto`, `kitty`, `iterm2`, `sixel`
+    /// or `off`; `auto` when unset
+    pub images: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorConfig {
+    /// Shell command opening a file of a diff, `{file}` and `{line}` filled
+    /// in; git's editor when unset
+    pub open: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyConfig {
+    /// Refuse every operation that modifies the repository, as `--read-only`
+    pub read_only: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReposConfig {
+    /// Repositories offered by the repository picker; `~/` is the home
+    /// directory
+    pub paths: Vec<String>,
+    /// Also offer the repositories next to the current one
+    pub discover: bool,
+}
+
+impl Default for ReposConfig {
+    fn default() -> Self {
+        ReposConfig {
+            paths: Vec::new(),
+            discover: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmConfig {
+    /// Confirmations not asked for, by name (`delete_branch`, `hard_reset`, ...)
+    pub skip: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Memory for cached commit metadata and branch relationships
+    pub memory_mb: usize,
+    /// Keep the caches on disk between sessions
+    pub persist: bool,
+    /// Write git's commit-graph when it is missing or behind a fetch
+    pub commit_graph: bool,
+    /// Memory for diffs already shown
+    pub diff_mb: usize,
+    /// Commits either side of the selection whose diffs are made ahead of
+    /// time; 0 turns it off
+    pub prefetch_diffs: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            memory_mb: 64,
+            persist: false,
+            commit_graph: true,
+            diff_mb: 16,
+            prefetch_diffs: 3,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    /// Send a desktop notification (through the terminal) when a long operation finishes
+    pub enabled: bool,
+    /// How long an operation must take to be worth a notification
+    pub after_seconds: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        NotificationConfig {
+            enabled: false,
+            after_seconds: 10,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrailerConfig {
+    /// URL templates per trailer key (`"Tracked-On" = "https://tracker/{value}"`)
+    pub links: HashMap<String, String>,
+    /// Prepare commit messages with a `Signed-off-by` trailer for the committer
+    pub signoff: bool,
+    /// Trailers prepared in commit messages, their values printed by
+    /// commands: `[[trailers.add]]` tables
+    pub add: Vec<TrailerCommand>,
+}
+
+/// A trailer whose value a shell command prints, like a Change-Id
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TrailerCommand {
+    pub key: String,
+    /// Run by the shell in the repository's top-level directory; its first
+    /// line of output is the value
+    pub command: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RefFilterConfig {
+    /// Globs over full ref names (`refs/remotes/gerrit/*`) whose history is
+    /// hidden when the graph is filtered by a branch
+    pub exclude: Vec<String>,
+    /// Exceptions to `exclude`
+    pub include: Vec<String>,
+}
+
+/// Compiled `[refs]` patterns
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RefFilter {
+    pub include: Vec<glob::Pattern>,
+    pub exclude: Vec<glob::Pattern>,
+}
+
+impl RefFilter {
+    pub fn is_empty(&self) -> bool {
+        self.exclude.is_empty()
+    }
+
+    /// True when the history of `refname` should be left out
+    pub fn excludes(&self, refname: &str) -> bool {
+        self.exclude.iter().any(|p| p.matches(refname))
+            && !self.include.iter().any(|p| p.matches(refname))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterConfig {
+    /// Glob patterns (`gerrit/*`, `*/dependabot/*`) of branches to leave out of the Branches panel
+    pub hide_branches: Vec<String>,
+    /// Case-insensitive substrings of an author name or email that mark a bot account
+    pub bot_authors: Vec<String>,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        FilterConfig {
+            hide_branches: Vec::new(),
+            bot_authors: ["[bot]", "dependabot", "renovate"].map(String::from).to_vec(),
+        }
+    }
+}
+
+/// Everything derived from a config file. Validated as a whole so a bad edit
+/// is rejected instead of being applied halfway.
+pub struct Settings {
+    pub keymap: Keymap,
+    pub theme: Theme,
+    pub glyphs: Glyphs,
+    pub hidden_branches: Vec<glob::Pattern>,
+    pub ref_filter: RefFilter,
+    pub bot_authors: Vec<String>,
+    pub trailer_links: Vec<(String, String)>,
+    pub signoff: bool,
+    pub trailer_commands: Vec<TrailerCommand>,
+    pub notify_after: Option<Duration>,
+    /// Bytes
+    pub cache_limit: usize,
+    pub persist_cache: bool,
+    pub commit_graph: bool,
+    pub diff_cache_limit: usize,
+    pub prefetch_distance: usize,
+    pub hide_remotes: bool,
+    pub collapse_remotes: bool,
+    pub split_diff: bool,
+    pub diff_filter: Option<String>,
+    pub open_command: Option<String>,
+    pub image_protocol: Option<images::Protocol>,
+    pub date_format: String,
+    pub relative_dates: bool,
+    pub skip_confirm: HashSet<confirm::Kind>,
+    pub read_only: bool,
+    pub repos: Vec<PathBuf>,
+    pub discover_repos: bool,
+    pub identities: Vec<Identity>,
+    pub github: GitHubSettings,
+    pub gitlab: GitLabSettings,
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub source: ConfigSource,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.message)
+    }
+}
+
+/// Where a configuration layer came from, for error messages
+#[derive(Debug, Clone)]
+pub enum ConfigSource {
+    File(PathBuf),
+    /// Name of a `GIT_TUI_*` variable
+    Env(String),
+    /// A `--set key=value` option
+    Option(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::Env(name) => write!(f, "${}", name),
+            ConfigSource::Option(option) => write!(f, "--set {}", option),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// `$GIT_TUI_CONFIG`, else `$XDG_CONFIG_HOME/git-tui/config.toml`, else
+/// `~/.config/git-tui/config.toml`
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("GIT_TUI_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("git-tui").join("config.toml"))
+}
+
+/// Prefix of the environment variables overriding single options
+const ENV_PREFIX: &str = "GIT_TUI_";
+
+/// Where settings come from, each layer overriding the ones before it:
+/// built-in defaults, the config file, `git-tui.toml` in the repository's
+/// git directory, `GIT_TUI_<SECTION>__<KEY>` environment variables, then
+/// `--set section.key=value` options
+#[derive(Debug, Clone)]
+pub struct Layers {
+    /// Per-repository file; inside the git directory so a clone never brings one along
+    repo_file: Option<PathBuf>,
+    /// `--set` options, in order
+    options: Vec<String>,
+}
+
+impl Layers {
+    pub fn new(git_dir: &Path, options: &[String]) -> Self {
+        Layers {
+            repo_file: Some(crate::watcher::common_dir(git_dir).join("git-tui.toml")),
+            options: options.to_vec(),
+        }
+    }
+
+    /// Files whose edits should be applied live
+    fn files(&self) -> Vec<PathBuf> {
+        config_path().into_iter().chain(self.repo_file.clone()).collect()
+    }
+
+    /// Merges every layer and validates the result. Each layer is checked
+    /// as it is added, so an invalid value is blamed on the layer that set it.
+    pub fn load(&self) -> Result<Settings, ConfigError> {
+        let mut merged = toml::Table::new();
+        let mut settings = None;
+        for (source, readings) in self.read()? {
+            // The first reading that validates is taken; errors are reported for the first
+            let mut first_error = None;
+            for layer in readings {
+                let mut candidate = merged.clone();
+                merge(&mut candidate, layer);
+                let result = toml::Value::Table(candidate.clone())
+                    .try_into::<Config>()
+                    .map_err(|e| e.to_string().trim_end().to_string())
+                    .and_then(|config| config.settings());
+                match result {
+                    Ok(valid) => {
+                        merged = candidate;
+                        settings = Some(valid);
+                        first_error = None;
+                        break;
+                    }
+                    Err(message) => {
+                        first_error.get_or_insert(message);
+                    }
+                }
+            }
+            if let Some(message) = first_error {
+                return Err(ConfigError { source, message });
+            }
+        }
+        match settings {
+            Some(settings) => Ok(settings),
+            None => Config::default().settings().map_err(|message| ConfigError {
+                source: ConfigSource::File(config_path().unwrap_or_default()),
+                message,
+            }),
+        }
+    }
+
+    /// The layers that are present, lowest precedence first, each with the
+    /// ways its value can be read (see [`override_readings`])
+    fn read(&self) -> Result<Vec<(ConfigSource, Vec<toml::Table>)>, ConfigError> {
+        let mut layers = Vec::new();
+        for path in self.files() {
+            if !path.exists() {
+                continue;
+            }
+            let source = ConfigSource::File(path.clone());
+            let error = |message: String| ConfigError { source: source.clone(), message };
+            let text = std::fs::read_to_string(&path).map_err(|e| error(e.to_string()))?;
+            let table = toml::from_str(&text).map_err(|e: toml::de::Error| error(e.to_string().trim_end().to_string()))?;
+            layers.push((source, vec![table]));
+        }
+
+        let mut vars: Vec<(String, std::ffi::OsString)> = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name.contains("__"))
+            .collect();
+        vars.sort();
+        for (name, value) in vars {
+            let source = ConfigSource::Env(name.clone());
+            let value = value.into_string().map_err(|_| ConfigError {
+                source: source.clone(),
+                message: "value is not valid UTF-8".to_string(),
+            })?;
+            let path: Vec<String> = name[ENV_PREFIX.len()..].split("__").map(str::to_lowercase).collect();
+            layers.push((source, override_readings(&path, &value)));
+        }
+
+        for option in &self.options {
+            let source = ConfigSource::Option(option.clone());
+            let Some((key, value)) = option.split_once('=') else {
+                return Err(ConfigError { source, message: "expected section.key=value".to_string() });
+            };
+            let path: Vec<String> = key.trim().split('.').map(str::to_string).collect();
+            layers.push((source, override_readings(&path, value)));
+        }
+        Ok(layers)
+    }
+}
+
+/// One-option tables: `["cache", "persist"]` and `true` give `cache.persist = true`.
+/// The value is read as TOML (`128`, `true`, `["a", "b"]`) and, failing that
+/// or when the option wants one, as a plain string (so `208` also works as a
+/// color index).
+fn override_readings(path: &[String], value: &str) -> Vec<toml::Table> {
+    let string = toml::Value::String(value.to_string());
+    match toml::from_str::<toml::Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+    {
+        Some(parsed) if !parsed.is_str() => vec![override_table(path, parsed), override_table(path, string)],
+        Some(parsed) => vec![override_table(path, parsed)],
+        None => vec![override_table(path, string)],
+    }
+}
+
+fn override_table(path: &[String], mut value: toml::Value) -> toml::Table {
+    for key in path.iter().rev() {
+        let mut table = toml::Table::new();
+        table.insert(key.clone(), value);
+        value = toml::Value::Table(table);
+    }
+    match value {
+        toml::Value::Table(table) => table,
+        _ => toml::Table::new(),
+    }
+}
+
+/// Merges `layer` into `base`: tables key by key, anything else replaced whole
+fn merge(base: &mut toml::Table, layer: toml::Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+impl Config {
+    pub fn settings(&self) -> Result<Settings, String> {
+        Ok(Settings {
+            keymap: Keymap::with_overrides(&self.keys)?,
+            theme: Theme::from_config(&self.theme)?,
+            glyphs: Glyphs::from_config(&self.glyphs)?,
+            hidden_branches: compile_patterns(&self.filters.hide_branches, "filters.hide_branches")?,
+            ref_filter: RefFilter {
+                include: compile_patterns(&self.refs.include, "refs.include")?,
+                exclude: compile_patterns(&self.refs.exclude, "refs.exclude")?,
+            },
+            bot_authors: self
+                .filters
+                .bot_authors
+                .iter()
+                .filter(|b| !b.is_empty())
+                .map(|b| b.to_lowercase())
+                .collect(),
+            trailer_links: self
+                .trailers
+                .links
+                .iter()
+                .map(|(key, url)| (key.clone(), url.clone()))
+                .collect(),
+            signoff: self.trailers.signoff,
+            trailer_commands: self.trailer_commands()?,
+            notify_after: self
+                .notifications
+                .enabled
+                .then(|| Duration::from_secs(self.notifications.after_seconds)),
+            cache_limit: self.cache.memory_mb.saturating_mul(1024 * 1024),
+            persist_cache: self.cache.persist,
+            commit_graph: self.cache.commit_graph,
+            diff_cache_limit: self.cache.diff_mb.saturating_mul(1024 * 1024),
+            prefetch_distance: self.cache.prefetch_diffs,
+            hide_remotes: self.branches.hide_remotes,
+            collapse_remotes: self.branches.collapse_remotes,
+            split_diff: self.layout.split_diff,
+            diff_filter: self.diff.filter.clone().filter(|filter| !filter.trim().is_empty()),
+            open_command: self.open_command()?,
+            image_protocol: images::Protocol::from_setting(self.diff.images.as_deref().unwrap_or("auto"))?,
+            date_format: self.date_format()?,
+            relative_dates: self.dates.relative,
+            skip_confirm: self.skip_confirm()?,
+            read_only: self.safety.read_only,
+            repos: self.repos.paths.iter().map(|path| repos::expand_home(path)).collect(),
+            discover_repos: self.repos.discover,
+            identities: self.identities()?,
+            github: self.github.settings(),
+            gitlab: self.gitlab.settings(),
+        })
+    }
+
+    fn open_command(&self) -> Result<Option<String>, String> {
+        match self.editor.open {
+            Some(ref template) if !template.contains("{file}") => {
+                Err(format!("editor.open: {:?} has no {{file}} placeholder", template))
+            }
+            ref open => Ok(open.clone()),
+        }
+    }
+
+    fn trailer_commands(&self) -> Result<Vec<TrailerCommand>, String> {
+        for trailer in &self.trailers.add {
+            if !trailers::is_token(&trailer.key) {
+                return Err(format!("trailers.add: '{}' is not a trailer key (letters, digits and '-')", trailer.key));
+            }
+            if trailer.command.trim().is_empty() {
+                return Err(format!("trailers.add: {} has no command", trailer.key));
+            }
+        }
+        Ok(self.trailers.add.clone())
+    }
+
+    /// Checked up front, as formatting a date with a bad format panics
+    fn date_format(&self) -> Result<String, String> {
+        let format = &self.dates.format;
+        if chrono::format::StrftimeItems::new(format).any(|item| item == chrono::format::Item::Error) {
+            return Err(format!("dates.format: invalid format '{}'", format));
+        }
+        Ok(format.clone())
+    }
+
+    fn skip_confirm(&self) -> Result<HashSet<confirm::Kind>, String> {
+        self.confirm
+            .skip
+            .iter()
+            .map(|name| {
+                confirm::Kind::from_name(name).ok_or_else(|| {
+                    let names: Vec<&str> = confirm::Kind::ALL.iter().map(|kind| kind.name()).collect();
+                    format!("confirm.skip: unknown confirmation '{}' (one of {})", name, names.join(", "))
+                })
+            })
+            .collect()
+    }
+
+    fn identities(&self) -> Result<Vec<Identity>, String> {
+        for identity in &self.identities {
+            if identity.name.trim().is_empty() || identity.email.trim().is_empty() {
+                return Err("every entry in identities needs a name and an email".to_string());
+            }
+        }
+        Ok(self.identities.clone())
+    }
+}
+
+/// Polls the config files' modification times so edits can be applied live.
+/// Polling (rather than inotify) also catches editors that replace the file.
+pub struct ConfigWatcher {
+    layers: Layers,
+    modified: Vec<Option<SystemTime>>,
+    last_check: Instant,
+}
+
+impl ConfigWatcher {
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    pub fn new(layers: Layers) -> Self {
+        let modified = layers.files().iter().map(|p| modified_time(p)).collect();
+        ConfigWatcher {
+            layers,
+            modified,
+            last_check: Instant::now(),
+        }
+    }
+
+    /// Returns the freshly loaded settings when a file changed since the last call
+    pub fn poll(&mut self) -> Option<Result<Settings, ConfigError>> {
+        if self.last_check.elapsed() < Self::INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let modified: Vec<_> = self.layers.files().iter().map(|p| modified_time(p)).collect();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(self.layers.load())
+    }
+}
+
+fn compile_patterns(patterns: &[String], key: &str) -> Result<Vec<glob::Pattern>, String> {
+    patterns
+        .iter()
+        .map(|p| {
+            glob::Pattern::new(p).map_err(|e| format!("invalid pattern '{}' in {}: {}", p, key, e))
+        })
+        .collect()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
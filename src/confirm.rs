@@ -0,0 +1,112 @@
+use std::path::Path;
+
+/// Actions that ask before going ahead. Each can be set to go ahead without
+/// asking, from the dialog or with `[confirm] skip` in the config; rewriting
+/// already pushed commits and running commands from the config always ask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    DeleteBranch,
+    /// `--soft` or `--mixed`, which keep the working tree
+    Reset,
+    HardReset,
+    /// `git rebase -i`
+    Rebase,
+    RemoveWorktree,
+    PruneWorktrees,
+    AbortOperation,
+    Undo,
+    DisableSparse,
+    /// Resolving a conflicted file with one side's version, or deleting it
+    TakeSide,
+    /// A command line from the config, before it first runs in the session
+    RunCommand,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 10] = [
+        Kind::DeleteBranch,
+        Kind::Reset,
+        Kind::HardReset,
+        Kind::Rebase,
+        Kind::RemoveWorktree,
+        Kind::PruneWorktrees,
+        Kind::AbortOperation,
+        Kind::Undo,
+        Kind::DisableSparse,
+        Kind::TakeSide,
+    ];
+
+    /// Name in the `[confirm] skip` list
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::DeleteBranch => "delete_branch",
+            Kind::Reset => "reset",
+            Kind::HardReset => "hard_reset",
+            Kind::Rebase => "rebase",
+            Kind::RemoveWorktree => "remove_worktree",
+            Kind::PruneWorktrees => "prune_worktrees",
+            Kind::AbortOperation => "abort_operation",
+            Kind::Undo => "undo",
+            Kind::DisableSparse => "disable_sparse",
+            Kind::TakeSide => "take_side",
+            Kind::RunCommand => "run_command",
+        }
+    }
+
+    /// For the dialog title
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::DeleteBranch => "delete branch",
+            Kind::Reset => "reset",
+            Kind::HardReset => "hard reset",
+            Kind::Rebase => "interactive rebase",
+            Kind::RemoveWorktree => "remove worktree",
+            Kind::PruneWorktrees => "prune worktrees",
+            Kind::AbortOperation => "abort",
+            Kind::Undo => "undo",
+            Kind::DisableSparse => "disable sparse checkout",
+            Kind::TakeSide => "take side",
+            Kind::RunCommand => "command from the config",
+        }
+    }
+
+    /// Whether it may be turned off; not for commands from the config,
+    /// whose confirmation is what stands between a config file and a shell
+    pub fn skippable(self) -> bool {
+        self != Kind::RunCommand
+    }
+
+    pub fn from_name(name: &str) -> Option<Kind> {
+        Kind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// Adds `kind` to `[confirm] skip` in the config file at `path`, creating
+/// the file if needed. The rest of the file, comments included, is kept as
+/// it is.
+pub fn skip_in_config(path: &Path, kind: Kind) -> Result<(), String> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let mut doc: toml_edit::DocumentMut = text.parse().map_err(|e| format!("{}: {}", path.display(), e))?;
+    let table = doc
+        .entry("confirm")
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .ok_or_else(|| format!("{}: confirm is not a table", path.display()))?;
+    let skip = table
+        .entry("skip")
+        .or_insert(toml_edit::value(toml_edit::Array::new()))
+        .as_array_mut()
+        .ok_or_else(|| format!("{}: confirm.skip is not a list", path.display()))?;
+    if !skip.iter().any(|value| value.as_str() == Some(kind.name())) {
+        skip.push(kind.name());
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::write(path, doc.to_string()).map_err(|e| format!("{}: {}", path.display(), e))
+}
@@ -0,0 +1,97 @@
+use git2::{IndexConflict, Repository};
+use std::path::Path;
+
+/// A path the index holds more than one side of
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub path: String,
+    pub kind: &'static str,
+}
+
+impl Conflict {
+    /// Whether taking `ours` (or theirs) means deleting the file, because
+    /// that side deleted it
+    pub fn deletes(&self, ours: bool) -> bool {
+        match ours {
+            true => matches!(self.kind, "deleted by us" | "added by them" | "both deleted"),
+            false => matches!(self.kind, "deleted by them" | "added by us" | "both deleted"),
+        }
+    }
+}
+
+/// Path and kind of a conflict from which sides have the file, worded like
+/// `git status`
+pub fn describe(entries: &IndexConflict) -> Option<Conflict> {
+    let entry = entries.our.as_ref().or(entries.their.as_ref()).or(entries.ancestor.as_ref())?;
+    let kind = match (entries.ancestor.is_some(), entries.our.is_some(), entries.their.is_some()) {
+        (true, true, true) => "both modified",
+        (false, true, true) => "both added",
+        (true, false, true) => "deleted by us",
+        (true, true, false) => "deleted by them",
+        (false, true, false) => "added by us",
+        (false, false, true) => "added by them",
+        _ => "both deleted",
+    };
+    Some(Conflict { path: String::from_utf8_lossy(&entry.path).into_owned(), kind })
+}
+
+/// Conflicts in the repository's index, by path
+pub fn list(repo: &Repository) -> Result<Vec<Conflict>, git2::Error> {
+    let mut index = repo.index()?;
+    // The repository's index is cached; git commands change the file
+    index.read(false)?;
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+    let mut conflicts: Vec<Conflict> = index.conflicts()?.flatten().filter_map(|c| describe(&c)).collect();
+    conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(conflicts)
+}
+
+/// Which side of a conflict a line of a conflicted file belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// `<<<<<<<`, `|||||||`, `=======` and `>>>>>>>` lines
+    Marker,
+    Ours,
+    /// Only there with `merge.conflictStyle = diff3` (or `zdiff3`)
+    Base,
+    Theirs,
+}
+
+/// The lines between conflict markers in a working tree file, markers
+/// included, with a blank line between hunks. Empty when the file is gone
+/// or has no markers left.
+pub fn hunks(workdir: &Path, path: &str) -> Vec<(Side, String)> {
+    let Ok(bytes) = std::fs::read(workdir.join(path)) else {
+        return Vec::new();
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    let mut lines = Vec::new();
+    let mut side = None;
+    for line in text.lines() {
+        let marker = match side {
+            None if line.starts_with("<<<<<<<") => Some(Side::Ours),
+            Some(Side::Ours) if line.starts_with("|||||||") => Some(Side::Base),
+            Some(Side::Ours | Side::Base) if line.starts_with("=======") => Some(Side::Theirs),
+            Some(Side::Theirs) if line.starts_with(">>>>>>>") => None,
+            _ => {
+                if let Some(side) = side {
+                    lines.push((side, line.to_string()));
+                }
+                continue;
+            }
+        };
+        if side.is_none() && !lines.is_empty() {
+            lines.push((Side::Marker, String::new()));
+        }
+        lines.push((Side::Marker, line.to_string()));
+        side = marker;
+    }
+    lines
+}
+
+/// Whether a working tree file still has conflict markers in it
+pub fn has_markers(workdir: &Path, path: &str) -> bool {
+    !hunks(workdir, path).is_empty()
+}
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Someone with commits in the view, under the name and email the mailmap
+/// gives them
+pub struct Contributor {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+    /// Author date of their newest commit
+    pub last: DateTime<Utc>,
+}
+
+/// The authors of `git log --use-mailmap --format=%aN%x00%aE%x00%at`
+/// output, most commits first, then most recently active
+pub fn tally(log: &str) -> Vec<Contributor> {
+    let mut found: HashMap<(&str, &str), Contributor> = HashMap::new();
+    for line in log.lines() {
+        let mut fields = line.split('\0');
+        let (Some(name), Some(email), Some(time)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let time = time.trim().parse().ok().and_then(|seconds| DateTime::from_timestamp(seconds, 0)).unwrap_or_default();
+        let contributor = found.entry((name, email)).or_insert_with(|| Contributor {
+            name: name.to_string(),
+            email: email.to_string(),
+            commits: 0,
+            last: time,
+        });
+        contributor.commits += 1;
+        contributor.last = contributor.last.max(time);
+    }
+    let mut contributors: Vec<Contributor> = found.into_values().collect();
+    contributors.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| b.last.cmp(&a.last)));
+    contributors
+}
@@ -0,0 +1,71 @@
+/// Types of the Conventional Commits convention, as commonly used
+pub const TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+
+/// The `type(scope)!:` start of a conventional commit subject
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prefix<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    /// Marked `!`: a breaking change
+    pub breaking: bool,
+    /// Bytes of the subject the prefix takes, colon included
+    pub len: usize,
+}
+
+/// The conventional prefix of a subject such as `feat(parser)!: ...`, for
+/// one of the [`TYPES`]
+pub fn prefix(subject: &str) -> Option<Prefix<'_>> {
+    let (head, _) = subject.split_once(':')?;
+    let (head, breaking) = match head.strip_suffix('!') {
+        Some(head) => (head, true),
+        None => (head, false),
+    };
+    let (kind, scope) = match head.split_once('(') {
+        Some((kind, rest)) => (kind, Some(rest.strip_suffix(')')?)),
+        None => (head, None),
+    };
+    if !TYPES.contains(&kind) {
+        return None;
+    }
+    if scope.is_some_and(|scope| scope.is_empty() || scope.contains(['(', ')'])) {
+        return None;
+    }
+    Some(Prefix { kind, scope, breaking, len: head.len() + usize::from(breaking) + 1 })
+}
+
+/// Only commits with a message line starting with one of some
+/// conventional types: `git log --grep` cannot tell the subject from the
+/// body, and dropping rows from `--graph` output would break its lanes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeFilter {
+    pub types: Vec<String>,
+}
+
+impl TypeFilter {
+    /// Types separated by spaces or commas: `feat fix` or `feat,fix`
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut types: Vec<String> = Vec::new();
+        for word in input.split([' ', ',']).filter(|w| !w.is_empty()) {
+            let word = word.to_lowercase();
+            if !word.chars().all(|c| c.is_ascii_lowercase()) {
+                return Err(format!("not a commit type: '{}'", word));
+            }
+            if !types.contains(&word) {
+                types.push(word);
+            }
+        }
+        Ok(TypeFilter { types })
+    }
+
+    /// A basic regex for `git log --grep` matching a line, of the subject or
+    /// the body, that starts with one of the types
+    pub fn log_arg(&self) -> String {
+        format!("--grep=^\\({}\\)\\(([^)]*)\\)\\?!\\?:", self.types.join("\\|"))
+    }
+
+    pub fn label(&self) -> String {
+        self.types.join(",")
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::{Days, Months, NaiveDate};
+
+/// Commit-date window for the graph. Both ends are inclusive days.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateRange {
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    /// What was typed, for the graph title
+    pub label: String,
+}
+
+impl DateRange {
+    /// `git log` options limiting commits to the range
+    pub fn log_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(since) = self.since {
+            args.push(format!("--since={} 00:00:00", since));
+        }
+        if let Some(until) = self.until {
+            args.push(format!("--until={} 23:59:59", until));
+        }
+        args
+    }
+}
+
+/// Parses `last 2 weeks` (days, weeks, months or years; `last month` means
+/// one), `2024-01-01..2024-02-01` (either end may be left open), a single
+/// day `2024-01-15` or a month `2024-01`
+pub fn parse(text: &str, today: NaiveDate) -> Result<DateRange, String> {
+    let text = text.trim();
+    let label = text.to_string();
+
+    if let Some((since, until)) = text.split_once("..") {
+        let since = (!since.trim().is_empty()).then(|| parse_day(since.trim())).transpose()?;
+        let until = (!until.trim().is_empty()).then(|| parse_day(until.trim())).transpose()?;
+        if since.is_none() && until.is_none() {
+            return Err("give at least one end of the range".to_string());
+        }
+        if let (Some(since), Some(until)) = (since, until)
+            && since > until
+        {
+            return Err(format!("{} is after {}", since, until));
+        }
+        return Ok(DateRange { since, until, label });
+    }
+
+    if let Ok(day) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(DateRange { since: Some(day), until: Some(day), label });
+    }
+    if let Ok(first) = NaiveDate::parse_from_str(&format!("{}-01", text), "%Y-%m-%d") {
+        let last = first
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .ok_or_else(|| format!("month out of range: {}", text))?;
+        return Ok(DateRange { since: Some(first), until: Some(last), label });
+    }
+
+    let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let (count, unit) = match words.as_slice() {
+        ["last", unit] => (1, *unit),
+        ["last", count, unit] | [count, unit] => {
+            let count = count.parse::<u32>().map_err(|_| format!("not a number: {}", count))?;
+            (count, *unit)
+        }
+        _ => return Err(format!("unrecognized date range: {}", text)),
+    };
+    let since = match unit.trim_end_matches('s') {
+        "day" => today.checked_sub_days(Days::new(u64::from(count))),
+        "week" => today.checked_sub_days(Days::new(7 * u64::from(count))),
+        "month" => today.checked_sub_months(Months::new(count)),
+        "year" => count.checked_mul(12).and_then(|months| today.checked_sub_months(Months::new(months))),
+        _ => return Err(format!("unknown unit: {} (days, weeks, months, years)", unit)),
+    };
+    let since = since.ok_or_else(|| format!("date out of range: {}", text))?;
+    Ok(DateRange { since: Some(since), until: None, label })
+}
+
+fn parse_day(text: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|_| format!("not a YYYY-MM-DD date: {}", text))
+}
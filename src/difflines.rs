@@ -0,0 +1,194 @@
+use ratatui::{style::Style, text::Span};
+use std::ops::Range;
+
+/// Old and new file line numbers of each line of a `git show -p` output,
+/// worked out as lines stream in
+#[derive(Debug, Default)]
+pub struct LineNumbers {
+    numbers: Vec<(Option<u32>, Option<u32>)>,
+    old: u32,
+    new: u32,
+    in_hunk: bool,
+    /// Largest number seen, for the gutter width
+    max: u32,
+}
+
+impl LineNumbers {
+    pub fn clear(&mut self) {
+        *self = LineNumbers::default();
+    }
+
+    pub fn push(&mut self, line: &str) {
+        let numbers = if let Some((old, new)) = hunk_start(line) {
+            self.old = old;
+            self.new = new;
+            self.in_hunk = true;
+            (None, None)
+        } else if line.starts_with("diff ") || line.starts_with("@@") {
+            // Next file, or a combined diff of a merge, which has a column per parent
+            self.in_hunk = false;
+            (None, None)
+        } else if !self.in_hunk {
+            (None, None)
+        } else if line.starts_with('+') {
+            self.new += 1;
+            (None, Some(self.new - 1))
+        } else if line.starts_with('-') {
+            self.old += 1;
+            (Some(self.old - 1), None)
+        } else if line.starts_with(' ') || line.is_empty() {
+            self.old += 1;
+            self.new += 1;
+            (Some(self.old - 1), Some(self.new - 1))
+        } else {
+            // `\ No newline at end of file`
+            (None, None)
+        };
+        self.max = self.max.max(numbers.0.unwrap_or(0)).max(numbers.1.unwrap_or(0));
+        self.numbers.push(numbers);
+    }
+
+    pub fn get(&self, index: usize) -> (Option<u32>, Option<u32>) {
+        self.numbers.get(index).copied().unwrap_or_default()
+    }
+
+    /// Digits of the widest number
+    pub fn digits(&self) -> usize {
+        self.max.max(1).ilog10() as usize + 1
+    }
+}
+
+/// First old and new line of a `@@ -12,7 +12,9 @@` hunk header
+fn hunk_start(line: &str) -> Option<(u32, u32)> {
+    let mut words = line.strip_prefix("@@ ")?.split(' ');
+    let old = words.next()?.strip_prefix('-')?;
+    let new = words.next()?.strip_prefix('+')?;
+    let start = |range: &str| range.split(',').next()?.parse().ok();
+    Some((start(old)?, start(new)?))
+}
+
+/// A file of a `git show -p` output: its path before and after the change,
+/// none on the side where it does not exist (added or deleted)
+pub type FilePaths = (Option<String>, Option<String>);
+
+/// The file line `index` belongs to; the first file for the commit message
+/// above them
+pub fn file_at(lines: &[&str], index: usize) -> Option<FilePaths> {
+    let end = index.min(lines.len().checked_sub(1)?);
+    let start = lines[..=end]
+        .iter()
+        .rposition(|line| line.starts_with("diff "))
+        .or_else(|| lines.iter().position(|line| line.starts_with("diff ")))?;
+    file_paths(&lines[start..])
+}
+
+/// Every file of a `git show -p` output, in order
+pub fn files(lines: &[&str]) -> Vec<FilePaths> {
+    (0..lines.len())
+        .filter(|&index| lines[index].starts_with("diff "))
+        .filter_map(|index| file_paths(&lines[index..]))
+        .collect()
+}
+
+/// Paths of the file section starting at `lines[0]`, from its `---` and
+/// `+++` lines, else from the `diff --git a/<old> b/<new>` header (binary
+/// files and mode changes have no others)
+fn file_paths(lines: &[&str]) -> Option<FilePaths> {
+    let side = |path: &str, prefix: &str| path.trim_end().trim_matches('"').strip_prefix(prefix).map(str::to_string);
+    let mut old = None;
+    for line in &lines[1..] {
+        if line.starts_with("diff ") || line.starts_with("@@") {
+            break;
+        }
+        if let Some(path) = line.strip_prefix("--- ") {
+            old = side(path, "a/");
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            return Some((old, side(path, "b/")));
+        }
+    }
+    let (old, new) = lines[0].strip_prefix("diff --git a/")?.split_once(" b/")?;
+    Some((Some(old.to_string()), Some(new.to_string())))
+}
+
+/// Characters in a line's spans
+pub fn char_count(spans: &[Span<'static>]) -> usize {
+    spans.iter().map(|span| span.content.chars().count()).sum()
+}
+
+/// Up to `width` characters of a line's spans from character `start`, styles
+/// kept
+pub fn slice(spans: &[Span<'static>], start: usize, width: usize) -> Vec<Span<'static>> {
+    let mut sliced = Vec::new();
+    let mut skip = start;
+    let mut room = width;
+    for span in spans {
+        if room == 0 {
+            break;
+        }
+        let chars = span.content.chars().count();
+        if skip >= chars {
+            skip -= chars;
+            continue;
+        }
+        let text: String = span.content.chars().skip(skip).take(room).collect();
+        skip = 0;
+        room -= text.chars().count();
+        sliced.push(Span::styled(text, span.style));
+    }
+    sliced
+}
+
+/// Character ranges of `query` in `line`, ignoring case unless the query
+/// has capitals
+pub fn find(line: &str, query: &str) -> Vec<Range<usize>> {
+    let fold = |c: char| match query.chars().any(char::is_uppercase) {
+        true => c,
+        false => c.to_lowercase().next().unwrap_or(c),
+    };
+    let haystack: Vec<char> = line.chars().map(fold).collect();
+    let needle: Vec<char> = query.chars().map(fold).collect();
+    let mut hits = Vec::new();
+    if needle.is_empty() {
+        return hits;
+    }
+    let mut start = 0;
+    while start + needle.len() <= haystack.len() {
+        if haystack[start..start + needle.len()] == needle[..] {
+            hits.push(start..start + needle.len());
+            start += needle.len();
+        } else {
+            start += 1;
+        }
+    }
+    hits
+}
+
+/// A line's spans with the characters in `hits` (in order, as from [`find`])
+/// restyled by `patch`
+pub fn highlight(spans: &[Span<'static>], hits: &[Range<usize>], patch: Style) -> Vec<Span<'static>> {
+    let mut highlighted = Vec::new();
+    let mut position = 0;
+    let mut hits = hits.iter().peekable();
+    for span in spans {
+        let mut run = String::new();
+        let mut in_hit = false;
+        for c in span.content.chars() {
+            while hits.peek().is_some_and(|hit| hit.end <= position) {
+                hits.next();
+            }
+            let hit = hits.peek().is_some_and(|hit| hit.contains(&position));
+            if hit != in_hit && !run.is_empty() {
+                let style = if in_hit { span.style.patch(patch) } else { span.style };
+                highlighted.push(Span::styled(std::mem::take(&mut run), style));
+            }
+            in_hit = hit;
+            run.push(c);
+            position += 1;
+        }
+        if !run.is_empty() {
+            let style = if in_hit { span.style.patch(patch) } else { span.style };
+            highlighted.push(Span::styled(run, style));
+        }
+    }
+    highlighted
+}
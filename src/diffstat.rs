@@ -0,0 +1,40 @@
+use git2::{Delta, DiffFindOptions, Oid, Patch, Repository};
+
+/// Lines a file gained and lost in a commit
+pub struct FileStat {
+    /// The new path, `old => new` for a rename
+    pub path: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+}
+
+/// Per-file line counts of a commit against its first parent (the empty
+/// tree for a root commit), renames detected, in path order. Made with
+/// git2's tree diff rather than by running `git show --stat`.
+pub fn file_stats(repo: &Repository, oid: Oid) -> Result<Vec<FileStat>, git2::Error> {
+    let commit = repo.find_commit(oid)?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?.tree()?),
+    };
+    let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+    let mut stats = Vec::new();
+    for index in 0..diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(&diff, index)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        let path = |file: git2::DiffFile<'_>| file.path().map(|path| path.display().to_string()).unwrap_or_default();
+        let path = match delta.status() {
+            Delta::Renamed => format!("{} => {}", path(delta.old_file()), path(delta.new_file())),
+            Delta::Deleted => path(delta.old_file()),
+            _ => path(delta.new_file()),
+        };
+        let binary = delta.flags().is_binary();
+        let (_, insertions, deletions) = patch.line_stats()?;
+        stats.push(FileStat { path, insertions, deletions, binary });
+    }
+    Ok(stats)
+}
@@ -0,0 +1,45 @@
+use git2::{Repository, Status, StatusOptions};
+
+/// Changed files in the working tree and index, counted like the short
+/// `git status` columns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// Changes in the index
+    pub staged: usize,
+    /// Tracked files changed in the working tree but not staged
+    pub modified: usize,
+    /// Untracked files, an untracked directory counting once
+    pub untracked: usize,
+    pub conflicted: usize,
+}
+
+impl Summary {
+    pub fn of(repo: &Repository) -> Result<Summary, git2::Error> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(false).exclude_submodules(false);
+        let mut summary = Summary::default();
+        for entry in repo.statuses(Some(&mut options))?.iter() {
+            let status = entry.status();
+            if status.is_conflicted() {
+                summary.conflicted += 1;
+                continue;
+            }
+            if status.intersects(
+                Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED | Status::INDEX_RENAMED | Status::INDEX_TYPECHANGE,
+            ) {
+                summary.staged += 1;
+            }
+            if status.intersects(Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_RENAMED | Status::WT_TYPECHANGE) {
+                summary.modified += 1;
+            }
+            if status.is_wt_new() {
+                summary.untracked += 1;
+            }
+        }
+        Ok(summary)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        *self == Summary::default()
+    }
+}
@@ -0,0 +1,85 @@
+use crate::process::ExternalCommand;
+use std::{collections::HashSet, path::Path};
+
+/// A loaded commit, drawn as a node
+pub struct Node<'a> {
+    pub id: &'a str,
+    pub short_id: &'a str,
+    pub summary: &'a str,
+    pub author: &'a str,
+    /// Decorations as `git log` prints them: `HEAD -> main`, `tag: v1.0`,
+    /// `origin/main`
+    pub refs: &'a [String],
+    pub parents: &'a [String],
+}
+
+/// Subjects are cut to this many characters in node labels
+const SUMMARY_WIDTH: usize = 60;
+
+/// The commits as a Graphviz digraph: a node per commit, children above
+/// their parents, and a box beside each decorated commit for its branches
+/// and tags. Parents that are not among `nodes` (past the loaded page or
+/// left out by a filter) end a dashed edge as a dot.
+pub fn graph(title: &str, nodes: &[Node]) -> String {
+    let loaded: HashSet<&str> = nodes.iter().map(|node| node.id).collect();
+    let mut cut = HashSet::new();
+    let mut text = format!("digraph {} {{\n", quote(title));
+    text.push_str(&format!("  graph [label={}, labelloc=t, fontname=\"Helvetica\"];\n", quote(title)));
+    text.push_str("  node [shape=box, style=\"rounded,filled\", fillcolor=\"#f5f5f5\", fontname=\"Helvetica\", fontsize=10];\n");
+    text.push_str("  edge [color=\"#555555\", arrowsize=0.6];\n");
+    for node in nodes {
+        let label = format!("{}  {}\n{}", node.short_id, shorten(node.summary), node.author);
+        text.push_str(&format!("  {} [label={}];\n", quote(node.id), quote(&label)));
+        for parent in node.parents {
+            if loaded.contains(parent.as_str()) {
+                text.push_str(&format!("  {} -> {};\n", quote(node.id), quote(parent)));
+                continue;
+            }
+            if cut.insert(parent.as_str()) {
+                text.push_str(&format!("  {} [shape=point, width=0.08, label=\"\"];\n", quote(parent)));
+            }
+            text.push_str(&format!("  {} -> {} [style=dashed];\n", quote(node.id), quote(parent)));
+        }
+        if node.refs.is_empty() {
+            continue;
+        }
+        let refs_id = format!("refs {}", node.id);
+        let fill = match node.refs {
+            refs if refs.iter().any(|r| r == "HEAD" || r.starts_with("HEAD ->")) => "#ffe082",
+            refs if refs.iter().all(|r| r.starts_with("tag: ")) => "#ffccbc",
+            _ => "#c8e6c9",
+        };
+        text.push_str(&format!(
+            "  {} [label={}, shape=box, style=filled, fillcolor=\"{}\"];\n",
+            quote(&refs_id),
+            quote(&node.refs.join("\n")),
+            fill
+        ));
+        text.push_str(&format!("  {} -> {} [style=dotted, arrowhead=none];\n", quote(&refs_id), quote(node.id)));
+        text.push_str(&format!("  {{ rank=same; {}; {}; }}\n", quote(&refs_id), quote(node.id)));
+    }
+    text.push_str("}\n");
+    text
+}
+
+/// Graphviz rendering the DOT file at `dot` as SVG into `svg`
+pub fn render_svg(dot: &Path, svg: &Path) -> ExternalCommand {
+    ExternalCommand::new("dot")
+        .arg("-Tsvg")
+        .arg("-o")
+        .arg(svg.display().to_string())
+        .arg(dot.display().to_string())
+}
+
+fn shorten(summary: &str) -> String {
+    match summary.char_indices().nth(SUMMARY_WIDTH) {
+        Some((end, _)) => format!("{}…", &summary[..end]),
+        None => summary.to_string(),
+    }
+}
+
+/// A DOT string literal; line breaks become `\n`, centered
+fn quote(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
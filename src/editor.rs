@@ -0,0 +1,75 @@
+use crate::process::ExternalCommand;
+use std::{io, path::Path};
+
+/// Editors that open a file at a line given as `+<line>` before it
+const LINE_ARGUMENT: [&str; 12] = ["vi", "vim", "nvim", "view", "gvim", "nano", "emacs", "emacsclient", "micro", "kak", "joe", "ne"];
+
+/// The editor on `path`, started the way git starts it: through the shell,
+/// so the configured command may carry arguments (`code --wait`)
+pub fn open(editor: &str, path: &Path) -> ExternalCommand {
+    open_at(editor, path, None)
+}
+
+/// [`open`] at `line`, for the editors known to take `+<line>`; others
+/// open the file at the top
+pub fn open_at(editor: &str, path: &Path, line: Option<u32>) -> ExternalCommand {
+    let program = editor.split_whitespace().next().and_then(|program| Path::new(program).file_name());
+    let cmd = ExternalCommand::new("sh").arg("-c").arg(format!("{} \"$@\"", editor)).arg(editor);
+    match line {
+        Some(line) if program.is_some_and(|program| LINE_ARGUMENT.iter().any(|known| program == *known)) => {
+            cmd.arg(format!("+{}", line)).arg(path.display().to_string())
+        }
+        _ => cmd.arg(path.display().to_string()),
+    }
+}
+
+/// The `[editor] open` template (`code --goto {file}:{line}`) run by the
+/// shell, the placeholders standing for its `"$1"` and `"$2"`. Confirmed
+/// before it first runs, as it comes from the config; the script is the
+/// same for every file, so that covers them all.
+pub fn from_template(template: &str, path: &Path, line: Option<u32>) -> ExternalCommand {
+    let script = template.replace("{file}", "\"$1\"").replace("{line}", "\"$2\"");
+    ExternalCommand::new("sh")
+        .arg("-c")
+        .arg(script)
+        .arg("sh")
+        .script_args([path.display().to_string(), line.unwrap_or(1).to_string()])
+        .user_configured()
+}
+
+/// Writes the file a message is edited in: `text`, then `help` as `#`
+/// comment lines, which are left out of the message
+pub fn write_draft(path: &Path, text: &str, help: &[String]) -> io::Result<()> {
+    let mut draft = format!("{}\n", text.trim_end());
+    if !help.is_empty() {
+        draft.push('\n');
+    }
+    for line in help {
+        match line.is_empty() {
+            true => draft.push_str("#\n"),
+            false => draft.push_str(&format!("# {}\n", line)),
+        }
+    }
+    std::fs::write(path, draft)
+}
+
+/// The message saved in the editor: without comment lines and the blank
+/// lines around it. Empty means the edit was given up.
+pub fn read_message(path: &Path) -> io::Result<String> {
+    Ok(clean(&std::fs::read_to_string(path)?))
+}
+
+/// `text` as a message: without comment lines and the blank lines around it
+pub fn clean(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.starts_with('#')).map(str::trim_end).collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// The message is what was prepared for the editor, a template and the
+/// `added` trailer lines, with nothing written into it; git refuses to
+/// commit those
+pub fn is_unedited(message: &str, template: &str, added: &[String]) -> bool {
+    let written: Vec<&str> = message.lines().filter(|line| !added.iter().any(|added| added == line)).collect();
+    let written = written.join("\n");
+    written.trim().is_empty() || written.trim() == template
+}
@@ -0,0 +1,79 @@
+use crate::config::{ConfigError, ConfigSource};
+use std::{fmt, path::PathBuf};
+
+/// Errors that end the program, each with its own exit code so wrapper
+/// scripts can tell them apart.
+#[derive(Debug)]
+pub enum Fatal {
+    /// The TUI itself failed while running
+    Runtime(anyhow::Error),
+    NoRepository { path: PathBuf },
+    CorruptRepository { path: PathBuf, detail: String },
+    TerminalInit(String),
+    Config(ConfigError),
+    /// Bad command-line arguments
+    Usage(String),
+}
+
+impl Fatal {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Fatal::Runtime(_) => 1,
+            Fatal::NoRepository { .. } => 2,
+            Fatal::CorruptRepository { .. } => 3,
+            Fatal::TerminalInit(_) => 4,
+            Fatal::Config(_) => 5,
+            Fatal::Usage(_) => 64,
+        }
+    }
+
+    fn hint(&self) -> Option<String> {
+        match self {
+            Fatal::Runtime(_) | Fatal::Usage(_) => None,
+            Fatal::NoRepository { .. } => Some(
+                "run git-tui from inside a git working tree, or create one with `git init`".to_string(),
+            ),
+            Fatal::CorruptRepository { path, .. } => Some(format!(
+                "check the repository with `git -C {} fsck`",
+                path.display()
+            )),
+            Fatal::TerminalInit(_) => Some(
+                "git-tui needs an interactive terminal; make sure stdout is a TTY and TERM is set"
+                    .to_string(),
+            ),
+            Fatal::Config(e) => Some(match e.source {
+                ConfigSource::File(ref path) => format!(
+                    "fix or remove {} (set GIT_TUI_CONFIG to use another file)",
+                    path.display()
+                ),
+                ConfigSource::Env(ref name) => format!("fix or unset {}", name),
+                ConfigSource::Option(_) => "fix the --set option".to_string(),
+            }),
+        }
+    }
+
+    /// Prints the error and its remediation hint to stderr
+    pub fn report(&self) {
+        eprintln!("git-tui: {}", self);
+        if let Some(hint) = self.hint() {
+            eprintln!("hint: {}", hint);
+        }
+    }
+}
+
+impl fmt::Display for Fatal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fatal::Runtime(e) => write!(f, "error: {:#}", e),
+            Fatal::NoRepository { path } => {
+                write!(f, "not a git repository (or any parent up to /): {}", path.display())
+            }
+            Fatal::CorruptRepository { path, detail } => {
+                write!(f, "cannot read repository at {}: {}", path.display(), detail)
+            }
+            Fatal::TerminalInit(detail) => write!(f, "failed to initialize terminal: {}", detail),
+            Fatal::Config(e) => write!(f, "invalid configuration in {}", e),
+            Fatal::Usage(message) => write!(f, "{}", message.trim_end()),
+        }
+    }
+}
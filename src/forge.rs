@@ -0,0 +1,119 @@
+use crate::process::ExternalCommand;
+
+/// Code hosts whose open review requests can be listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forge {
+    GitHub,
+    GitLab,
+}
+
+impl Forge {
+    pub fn name(self) -> &'static str {
+        match self {
+            Forge::GitHub => "GitHub",
+            Forge::GitLab => "GitLab",
+        }
+    }
+
+    /// What the host calls them, for titles
+    pub fn noun(self) -> &'static str {
+        match self {
+            Forge::GitHub => "pull requests",
+            Forge::GitLab => "merge requests",
+        }
+    }
+
+    /// `#7` or `!7`, as the host writes references
+    pub fn reference(self, number: u64) -> String {
+        match self {
+            Forge::GitHub => format!("#{}", number),
+            Forge::GitLab => format!("!{}", number),
+        }
+    }
+
+    /// Local branch a checked-out request is fetched into
+    pub fn local_branch(self, number: u64) -> String {
+        match self {
+            Forge::GitHub => format!("pr/{}", number),
+            Forge::GitLab => format!("mr/{}", number),
+        }
+    }
+
+    /// Ref the host keeps the request's head commit under, also for forks
+    pub fn head_ref(self, number: u64) -> String {
+        match self {
+            Forge::GitHub => format!("refs/pull/{}/head", number),
+            Forge::GitLab => format!("refs/merge-requests/{}/head", number),
+        }
+    }
+}
+
+/// An open pull or merge request
+#[derive(Debug, Clone)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub author: String,
+    /// Branch the changes are on, in the fork for requests from one
+    pub branch: String,
+    /// Where the branch is when it is in another repository
+    pub fork: Option<String>,
+    /// Full id of the head commit
+    pub sha: String,
+    pub draft: bool,
+    pub url: String,
+    /// Status of the latest CI pipeline for the head commit, where the host reports one
+    pub pipeline: Option<String>,
+}
+
+/// Path of the project a remote URL points at on `host` (`owner/name`, or
+/// `group/subgroup/name` on GitLab): `https://host/owner/name.git`,
+/// `git@host:owner/name` or `ssh://git@host:22/owner/name`
+pub fn project_path(url: &str, host: &str) -> Option<String> {
+    let (authority, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?,
+        // scp-like `user@host:path`
+        None => url.split_once(':')?,
+    };
+    let url_host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    // Without a `:port`
+    let url_host = url_host.split(':').next()?;
+    if !url_host.eq_ignore_ascii_case(host) {
+        return None;
+    }
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    (path.contains('/') && !path.split('/').any(str::is_empty)).then(|| path.to_string())
+}
+
+/// A GET request through `curl`. Headers go in through stdin so tokens never
+/// show up on the command line; the HTTP status is appended to the body.
+pub fn get(url: &str, headers: &[String]) -> ExternalCommand {
+    let headers: String = headers.iter().map(|h| format!("{}\n", h)).collect();
+    ExternalCommand::new("curl")
+        .args(["--silent", "--show-error", "--max-time", "30", "--header", "@-", "--write-out", r"\n%{http_code}"])
+        .rev(url)
+        .input(headers)
+}
+
+pub enum FetchError {
+    /// No answer: curl could not run or reach the host
+    Failed(String),
+    /// HTTP status other than 200, with the body
+    Status(String, String),
+}
+
+/// Runs a [`get`] request, giving the body of a 200 answer
+pub fn fetch(request: &ExternalCommand) -> Result<String, FetchError> {
+    let output = match request.output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => return Err(FetchError::Failed(format!("curl: {}", String::from_utf8_lossy(&output.stderr).trim()))),
+        Err(e) => return Err(FetchError::Failed(format!("Failed to run curl: {}", e))),
+    };
+    let response = String::from_utf8_lossy(&output.stdout);
+    let (body, status) = response.trim_end().rsplit_once('\n').unwrap_or(("", response.trim()));
+    match status {
+        "200" => Ok(body.to_string()),
+        _ => Err(FetchError::Status(status.to_string(), body.to_string())),
+    }
+}
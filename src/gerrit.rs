@@ -0,0 +1,156 @@
+use git2::{BranchType, Repository};
+
+/// A push for review: `HEAD:refs/for/<target>%topic=…,r=…`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewPush {
+    pub target: String,
+    pub topic: Option<String>,
+    pub reviewers: Vec<String>,
+    pub cc: Vec<String>,
+}
+
+impl ReviewPush {
+    /// Parses `main topic=login r=alice@example.com cc=bob`: the target
+    /// branch, then any number of `topic=`, `r=` (reviewer) and `cc=` options
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut words = input.split_whitespace();
+        let target = words.next().ok_or("give the target branch")?;
+        let target = target.strip_prefix("refs/heads/").unwrap_or(target);
+        if target.starts_with('-') || target.contains(['%', ':']) {
+            return Err(format!("not a branch name: {}", target));
+        }
+
+        let mut push = ReviewPush {
+            target: target.to_string(),
+            topic: None,
+            reviewers: Vec::new(),
+            cc: Vec::new(),
+        };
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .ok_or_else(|| format!("expected topic=, r= or cc=, not '{}'", word))?;
+            // Options are separated by commas in the ref name
+            if value.is_empty() || value.contains(',') {
+                return Err(format!("invalid value for {}: '{}'", key, value));
+            }
+            match key {
+                "topic" => push.topic = Some(value.to_string()),
+                "r" | "reviewer" => push.reviewers.push(value.to_string()),
+                "cc" => push.cc.push(value.to_string()),
+                _ => return Err(format!("unknown option '{}' (topic, r, cc)", key)),
+            }
+        }
+        Ok(push)
+    }
+
+    pub fn refspec(&self) -> String {
+        let options: Vec<String> = self
+            .topic
+            .iter()
+            .map(|topic| format!("topic={}", topic))
+            .chain(self.reviewers.iter().map(|r| format!("r={}", r)))
+            .chain(self.cc.iter().map(|cc| format!("cc={}", cc)))
+            .collect();
+        match options.is_empty() {
+            true => format!("HEAD:refs/for/{}", self.target),
+            false => format!("HEAD:refs/for/{}%{}", self.target, options.join(",")),
+        }
+    }
+}
+
+/// The remote changes are pushed to: `gerrit` when there is one (as
+/// `git review` sets up), else the branch's upstream remote, else `origin`
+pub fn remote(repo: &Repository, branch: Option<&str>) -> Option<String> {
+    let remotes = repo.remotes().ok()?;
+    let names: Vec<&str> = remotes.iter().flatten().collect();
+    if names.contains(&"gerrit") {
+        return Some("gerrit".to_string());
+    }
+    let upstream = branch.and_then(|b| upstream(repo, b)).map(|(remote, _)| remote);
+    upstream
+        .filter(|r| names.contains(&r.as_str()))
+        .or_else(|| names.contains(&"origin").then(|| "origin".to_string()))
+        .or_else(|| names.first().map(|r| r.to_string()))
+}
+
+/// Branch to review against: the upstream branch's name when it is on
+/// `remote`, else the branch `<remote>/HEAD` points at
+pub fn default_target(repo: &Repository, branch: Option<&str>, remote: &str) -> Option<String> {
+    if let Some((upstream_remote, name)) = branch.and_then(|b| upstream(repo, b))
+        && upstream_remote == remote
+    {
+        return Some(name);
+    }
+    let head = repo.find_reference(&format!("refs/remotes/{}/HEAD", remote)).ok()?;
+    let target = head.symbolic_target()?;
+    target.strip_prefix(&format!("refs/remotes/{}/", remote)).map(str::to_string)
+}
+
+/// `(remote, branch)` of a local branch's upstream
+fn upstream(repo: &Repository, branch: &str) -> Option<(String, String)> {
+    let upstream = repo.find_branch(branch, BranchType::Local).ok()?.upstream().ok()?;
+    let name = upstream.name().ok()??;
+    let (remote, branch) = name.split_once('/')?;
+    Some((remote.to_string(), branch.to_string()))
+}
+
+/// Change URLs Gerrit reports on a successful push, in `remote:` lines like
+/// `remote:   https://review.example.com/c/project/+/1234 Fix the thing [NEW]`
+pub fn change_urls(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("remote:"))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Why Gerrit refused the push: the `[remote rejected]` reason, such as
+/// `no new changes`
+pub fn rejection(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (_, rest) = line.split_once("[remote rejected]")?;
+        let reason = rest.split_once('(')?.1.trim_end().strip_suffix(')')?;
+        Some(reason.to_string())
+    })
+}
+
+/// `links` plus, unless one is configured for `Change-Id`, a link to the
+/// change on the Gerrit server of the repository's Gerrit remote
+pub fn with_change_link(repo: &Repository, mut links: Vec<(String, String)>) -> Vec<(String, String)> {
+    if !links.iter().any(|(key, _)| key.eq_ignore_ascii_case("Change-Id"))
+        && let Some(host) = server(repo)
+    {
+        links.push(("Change-Id".to_string(), format!("https://{}/q/{{value}}", host)));
+    }
+    links
+}
+
+/// Host of the Gerrit server: that of the remote named `gerrit`, else of one
+/// reached through Gerrit's SSH port 29418
+fn server(repo: &Repository) -> Option<String> {
+    let remotes = repo.remotes().ok()?;
+    let urls: Vec<(bool, String)> = remotes
+        .iter()
+        .flatten()
+        .filter_map(|name| Some((name == "gerrit", repo.find_remote(name).ok()?.url()?.to_string())))
+        .collect();
+    let (_, url) = urls
+        .iter()
+        .find(|(is_gerrit, _)| *is_gerrit)
+        .or_else(|| urls.iter().find(|(_, url)| url.contains(":29418/")))?;
+    url_host(url).map(str::to_string)
+}
+
+/// `review.example.com` of `ssh://user@review.example.com:29418/project`,
+/// `https://review.example.com/a/project` or `user@review.example.com:project`
+fn url_host(url: &str) -> Option<&str> {
+    let authority = match url.split_once("://") {
+        Some((_, rest)) => rest.split('/').next()?,
+        None => url.split_once(':')?.0,
+    };
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    host.split(':').next().filter(|host| !host.is_empty())
+}
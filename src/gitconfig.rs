@@ -0,0 +1,40 @@
+/// Config keys that decide who commits are attributed to, how they are
+/// signed and where they are pushed, as a `git config --get-regexp` pattern
+pub const ATTRIBUTION_KEYS: &str =
+    r"^(user\.(name|email|signingkey)|(commit|tag)\.gpgsign|gpg\.format|remote\..*\.(url|pushurl))$";
+
+/// The value git uses for a config key, and where it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValue {
+    pub key: String,
+    pub value: String,
+    /// `system`, `global`, `local`, `worktree` or `command`
+    pub scope: String,
+    /// File the value was read from, or `command line`
+    pub origin: String,
+}
+
+/// Parses `git config --show-scope --show-origin --get-regexp` output
+/// (`scope<TAB>origin<TAB>key value` lines), keeping the effective value of
+/// each key: the one read last. Keys come out in the order first seen.
+pub fn parse_effective(output: &str) -> Vec<ConfigValue> {
+    let mut values: Vec<ConfigValue> = Vec::new();
+    for line in output.lines() {
+        let mut fields = line.splitn(3, '\t');
+        let (Some(scope), Some(origin), Some(entry)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        let (key, value) = entry.split_once(' ').unwrap_or((entry, ""));
+        let value = ConfigValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            scope: scope.to_string(),
+            origin: origin.strip_prefix("file:").unwrap_or(origin).to_string(),
+        };
+        match values.iter_mut().find(|v| v.key == value.key) {
+            Some(existing) => *existing = value,
+            None => values.push(value),
+        }
+    }
+    values
+}
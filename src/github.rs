@@ -0,0 +1,112 @@
+use crate::forge::{self, FetchError, PullRequest};
+use serde::Deserialize;
+
+/// Where the GitHub API lives: `github.com`, or a GitHub Enterprise host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubSettings {
+    /// Host name remote URLs are matched against
+    pub host: String,
+    pub api_url: String,
+    /// From the config file; `GITHUB_TOKEN` or `GH_TOKEN` when not set there
+    pub token: Option<String>,
+}
+
+impl Default for GitHubSettings {
+    fn default() -> Self {
+        GitHubSettings {
+            host: "github.com".to_string(),
+            api_url: "https://api.github.com".to_string(),
+            token: None,
+        }
+    }
+}
+
+impl GitHubSettings {
+    fn token(&self) -> Option<String> {
+        self.token
+            .clone()
+            .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+            .or_else(|| std::env::var("GH_TOKEN").ok())
+            .filter(|t| !t.is_empty())
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiPull {
+    number: u64,
+    title: String,
+    #[serde(default)]
+    draft: bool,
+    html_url: String,
+    user: ApiUser,
+    head: ApiHead,
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct ApiHead {
+    #[serde(rename = "ref")]
+    branch: String,
+    sha: String,
+    /// Missing when the fork was deleted
+    repo: Option<ApiRepo>,
+    label: String,
+}
+
+#[derive(Deserialize)]
+struct ApiRepo {
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+/// `owner/name` of a GitHub remote URL
+pub fn repository(url: &str, host: &str) -> Option<String> {
+    forge::project_path(url, host).filter(|path| path.matches('/').count() == 1)
+}
+
+/// The open pull requests of `repository` (`owner/name`), newest first
+pub fn list(settings: &GitHubSettings, repository: &str) -> Result<Vec<PullRequest>, String> {
+    let url = format!("{}/repos/{}/pulls?state=open&per_page=100", settings.api_url.trim_end_matches('/'), repository);
+    let mut headers = vec!["Accept: application/vnd.github+json".to_string()];
+    if let Some(token) = settings.token() {
+        headers.push(format!("Authorization: Bearer {}", token));
+    }
+    let body = forge::fetch(&forge::get(&url, &headers)).map_err(|e| match e {
+        FetchError::Failed(message) => message,
+        FetchError::Status(status, body) => match status.as_str() {
+            "401" => "GitHub rejected the token (401)".to_string(),
+            "404" => format!("{} not found on GitHub; private repositories need a token", repository),
+            _ => {
+                let message = serde_json::from_str::<ApiError>(&body).map(|e| e.message).unwrap_or_default();
+                format!("GitHub answered {}: {}", status, message)
+            }
+        },
+    })?;
+    let pulls: Vec<ApiPull> = serde_json::from_str(&body).map_err(|e| format!("Unexpected GitHub response: {}", e))?;
+    Ok(pulls
+        .into_iter()
+        .map(|pull| {
+            let same_repo = pull.head.repo.as_ref().is_some_and(|r| r.full_name.eq_ignore_ascii_case(repository));
+            PullRequest {
+                number: pull.number,
+                title: pull.title,
+                author: pull.user.login,
+                fork: (!same_repo).then_some(pull.head.label),
+                branch: pull.head.branch,
+                sha: pull.head.sha,
+                draft: pull.draft,
+                url: pull.html_url,
+                // Would take a request per pull request
+                pipeline: None,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,122 @@
+use crate::forge::{self, FetchError, PullRequest};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Where the GitLab API lives: `gitlab.com`, or a self-managed host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitLabSettings {
+    /// Host name remote URLs are matched against
+    pub host: String,
+    pub api_url: String,
+    /// From the config file; `GITLAB_TOKEN` when not set there
+    pub token: Option<String>,
+}
+
+impl Default for GitLabSettings {
+    fn default() -> Self {
+        GitLabSettings {
+            host: "gitlab.com".to_string(),
+            api_url: "https://gitlab.com/api/v4".to_string(),
+            token: None,
+        }
+    }
+}
+
+impl GitLabSettings {
+    fn token(&self) -> Option<String> {
+        self.token
+            .clone()
+            .or_else(|| std::env::var("GITLAB_TOKEN").ok())
+            .filter(|t| !t.is_empty())
+    }
+
+    fn headers(&self) -> Vec<String> {
+        self.token().map(|token| format!("PRIVATE-TOKEN: {}", token)).into_iter().collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiMergeRequest {
+    iid: u64,
+    title: String,
+    #[serde(default)]
+    draft: bool,
+    web_url: String,
+    author: ApiUser,
+    source_branch: String,
+    /// Missing while GitLab is still preparing the merge request
+    sha: Option<String>,
+    project_id: u64,
+    source_project_id: u64,
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct ApiPipeline {
+    sha: String,
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    message: serde_json::Value,
+}
+
+/// `group/subgroup/name` of a GitLab remote URL
+pub fn project(url: &str, host: &str) -> Option<String> {
+    forge::project_path(url, host)
+}
+
+/// The open merge requests of `project`, newest first, with the status of
+/// the latest pipeline for each head commit
+pub fn list(settings: &GitLabSettings, project: &str) -> Result<Vec<PullRequest>, String> {
+    let base = format!("{}/projects/{}", settings.api_url.trim_end_matches('/'), project.replace('/', "%2F"));
+    let url = format!("{}/merge_requests?state=opened&per_page=100", base);
+    let body = forge::fetch(&forge::get(&url, &settings.headers())).map_err(|e| match e {
+        FetchError::Failed(message) => message,
+        FetchError::Status(status, body) => match status.as_str() {
+            "401" => "GitLab rejected the token (401)".to_string(),
+            "404" => format!("{} not found on GitLab; private projects need a token", project),
+            _ => {
+                let message = serde_json::from_str::<ApiError>(&body).map(|e| e.message.to_string()).unwrap_or_default();
+                format!("GitLab answered {}: {}", status, message)
+            }
+        },
+    })?;
+    let requests: Vec<ApiMergeRequest> =
+        serde_json::from_str(&body).map_err(|e| format!("Unexpected GitLab response: {}", e))?;
+
+    // Latest first, so the first status seen for a commit is the current one.
+    // Not fatal: listing pipelines may need more access than merge requests.
+    let mut pipelines: HashMap<String, String> = HashMap::new();
+    if !requests.is_empty()
+        && let Ok(body) = forge::fetch(&forge::get(&format!("{}/pipelines?per_page=100", base), &settings.headers()))
+        && let Ok(list) = serde_json::from_str::<Vec<ApiPipeline>>(&body)
+    {
+        for pipeline in list {
+            pipelines.entry(pipeline.sha).or_insert(pipeline.status);
+        }
+    }
+
+    Ok(requests
+        .into_iter()
+        .map(|request| {
+            let sha = request.sha.unwrap_or_default();
+            PullRequest {
+                number: request.iid,
+                title: request.title,
+                author: request.author.username,
+                fork: (request.source_project_id != request.project_id).then(|| format!("fork:{}", request.source_branch)),
+                branch: request.source_branch,
+                pipeline: pipelines.get(&sha).cloned(),
+                sha,
+                draft: request.draft,
+                url: request.web_url,
+            }
+        })
+        .collect())
+}
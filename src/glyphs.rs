@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+/// What a graph node stands for, most specific first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NodeKind {
+    /// Parents not shown (outside the range, page limit or shallow clone)
+    Boundary,
+    Head,
+    /// A stash entry (`refs/stash` and its reflog)
+    Stash,
+    Tagged,
+    Merge,
+    #[default]
+    Commit,
+}
+
+/// Characters drawn for the graph and the branch list. Configured through
+/// the `[glyphs]` table: `set` picks `unicode` (the default) or `ascii`, and
+/// each other key (a field name) replaces a single glyph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyphs {
+    pub commit: char,
+    pub merge: char,
+    pub tagged: char,
+    pub head: char,
+    pub stash: char,
+    pub boundary: char,
+    /// Lane lines, for the `|`, `/`, `\` and `-` of `git log --graph`
+    pub vertical: char,
+    pub slash: char,
+    pub backslash: char,
+    pub horizontal: char,
+    /// Branch list markers: any branch, one the graph includes, the filtered
+    /// one and a marked one
+    pub branch: char,
+    pub included: char,
+    pub filtered: char,
+    pub marked: char,
+    /// Branch group headers
+    pub expanded: char,
+    pub collapsed: char,
+    /// Before the selected row of the branch list and the graph
+    pub selection: char,
+    /// After a pinned commit in the graph
+    pub pin: char,
+    /// Built on the `ascii` set
+    pub ascii: bool,
+}
+
+impl Glyphs {
+    pub const UNICODE: Glyphs = Glyphs {
+        commit: '●',
+        merge: '◆',
+        tagged: '◈',
+        head: '◉',
+        stash: '◇',
+        boundary: '◌',
+        vertical: '│',
+        slash: '╱',
+        backslash: '╲',
+        horizontal: '─',
+        branch: '○',
+        included: '◉',
+        filtered: '●',
+        marked: '✔',
+        expanded: '▾',
+        collapsed: '▸',
+        selection: '▶',
+        pin: '⚑',
+        ascii: false,
+    };
+
+    /// For terminals or fonts without the Unicode shapes
+    pub const ASCII: Glyphs = Glyphs {
+        commit: '*',
+        merge: 'M',
+        tagged: 'T',
+        head: '@',
+        stash: 'S',
+        boundary: 'o',
+        vertical: '|',
+        slash: '/',
+        backslash: '\\',
+        horizontal: '-',
+        branch: 'o',
+        included: '+',
+        filtered: '*',
+        marked: 'x',
+        expanded: 'v',
+        collapsed: '>',
+        selection: '>',
+        pin: '!',
+        ascii: true,
+    };
+
+    pub fn node(&self, kind: NodeKind) -> char {
+        match kind {
+            NodeKind::Boundary => self.boundary,
+            NodeKind::Head => self.head,
+            NodeKind::Stash => self.stash,
+            NodeKind::Tagged => self.tagged,
+            NodeKind::Merge => self.merge,
+            NodeKind::Commit => self.commit,
+        }
+    }
+
+    /// How a lane character from `git log --graph` is drawn
+    pub fn lane(&self, ch: char) -> char {
+        match ch {
+            '|' => self.vertical,
+            '/' => self.slash,
+            '\\' => self.backslash,
+            '-' => self.horizontal,
+            _ => ch,
+        }
+    }
+
+    /// The other base set, without the configured replacements
+    pub fn other_set(&self) -> Glyphs {
+        match self.ascii {
+            true => Glyphs::UNICODE,
+            false => Glyphs::ASCII,
+        }
+    }
+
+    pub fn from_config(entries: &HashMap<String, String>) -> Result<Glyphs, String> {
+        let mut glyphs = match entries.get("set").map(String::as_str) {
+            None | Some("unicode") => Glyphs::UNICODE,
+            Some("ascii") => Glyphs::ASCII,
+            Some(other) => return Err(format!("unknown glyphs.set '{}' (unicode or ascii)", other)),
+        };
+
+        for (name, value) in entries {
+            let slot = match name.as_str() {
+                "set" => continue,
+                "commit" => &mut glyphs.commit,
+                "merge" => &mut glyphs.merge,
+                "tagged" => &mut glyphs.tagged,
+                "head" => &mut glyphs.head,
+                "stash" => &mut glyphs.stash,
+                "boundary" => &mut glyphs.boundary,
+                "vertical" => &mut glyphs.vertical,
+                "slash" => &mut glyphs.slash,
+                "backslash" => &mut glyphs.backslash,
+                "horizontal" => &mut glyphs.horizontal,
+                "branch" => &mut glyphs.branch,
+                "included" => &mut glyphs.included,
+                "filtered" => &mut glyphs.filtered,
+                "marked" => &mut glyphs.marked,
+                "expanded" => &mut glyphs.expanded,
+                "collapsed" => &mut glyphs.collapsed,
+                "selection" => &mut glyphs.selection,
+                "pin" => &mut glyphs.pin,
+                _ => return Err(format!("unknown glyph '{}'", name)),
+            };
+            let mut chars = value.chars();
+            *slot = match (chars.next(), chars.next()) {
+                (Some(c), None) if !c.is_whitespace() && !c.is_control() => c,
+                _ => return Err(format!("glyphs.{} must be a single character, not '{}'", name, value)),
+            };
+        }
+
+        Ok(glyphs)
+    }
+}
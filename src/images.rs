@@ -0,0 +1,123 @@
+use crate::process::ExternalCommand;
+use crate::window;
+use std::path::Path;
+
+/// How the terminal is asked to draw an image in a box of cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// kitty's graphics protocol (kitty, Ghostty); PNG only
+    Kitty,
+    /// iTerm2's inline images (iTerm2, WezTerm); any format the terminal
+    /// decodes
+    Iterm2,
+    /// Sixel graphics, made by `img2sixel` from libsixel
+    Sixel,
+}
+
+impl Protocol {
+    /// The `[diff] images` setting: `auto` picks the protocol of the
+    /// terminal the environment names; sixel support cannot be told that
+    /// way and has to be asked for
+    pub fn from_setting(value: &str) -> Result<Option<Protocol>, String> {
+        match value {
+            "auto" => Ok(detect()),
+            "kitty" => Ok(Some(Protocol::Kitty)),
+            "iterm2" => Ok(Some(Protocol::Iterm2)),
+            "sixel" => Ok(Some(Protocol::Sixel)),
+            "off" => Ok(None),
+            _ => Err(format!("diff.images: unknown value '{}' (auto, kitty, iterm2, sixel or off)", value)),
+        }
+    }
+
+    /// Images stay where they were put until deleted (kitty), rather than
+    /// being cells that drawing text over removes
+    pub fn is_layered(self) -> bool {
+        self == Protocol::Kitty
+    }
+}
+
+/// The protocol of the terminal git-tui runs in, from the variables it
+/// sets. Inside tmux or screen, which pass no graphics through, none.
+fn detect() -> Option<Protocol> {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    if std::env::var_os("TMUX").is_some() || var("TERM").starts_with("screen") {
+        return None;
+    }
+    if var("TERM") == "xterm-kitty" || std::env::var_os("KITTY_WINDOW_ID").is_some() || var("TERM_PROGRAM") == "ghostty" {
+        return Some(Protocol::Kitty);
+    }
+    if matches!(var("TERM_PROGRAM").as_str(), "iTerm.app" | "WezTerm") || var("LC_TERMINAL") == "iTerm2" {
+        return Some(Protocol::Iterm2);
+    }
+    None
+}
+
+/// Files shown as images, by extension
+pub fn is_image(path: &str) -> bool {
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    matches!(extension.as_str(), "png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "ico")
+}
+
+/// Width and height of a PNG, from its header
+pub fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    if data.len() < 24 || &data[..8] != b"\x89PNG\r\n\x1a\n" || &data[12..16] != b"IHDR" {
+        return None;
+    }
+    let number = |bytes: &[u8]| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    Some((number(&data[16..20]), number(&data[20..24])))
+}
+
+/// The largest `(columns, rows)` an image of `pixels` fits in within a box
+/// of `cells`, keeping its shape; `cell` is a cell's size in pixels
+pub fn fit(pixels: (u32, u32), cells: (u16, u16), cell: (u16, u16)) -> (u16, u16) {
+    let (width, height) = (f64::from(pixels.0.max(1)), f64::from(pixels.1.max(1)));
+    let (box_width, box_height) = (f64::from(cells.0) * f64::from(cell.0), f64::from(cells.1) * f64::from(cell.1));
+    // Thumbnails are never drawn larger than the image
+    let scale = (box_width / width).min(box_height / height).min(1.0);
+    let columns = (width * scale / f64::from(cell.0)).ceil() as u16;
+    let rows = (height * scale / f64::from(cell.1)).ceil() as u16;
+    (columns.clamp(1, cells.0), rows.clamp(1, cells.1))
+}
+
+/// kitty placing the PNG `data` as image `id`, scaled to `columns` by
+/// `rows` cells from the cursor; the cursor stays and replies are off
+pub fn kitty(data: &[u8], id: u32, (columns, rows): (u16, u16)) -> String {
+    let encoded = window::base64(data);
+    let chunks: Vec<&str> = encoded.as_bytes().chunks(4096).map(|chunk| std::str::from_utf8(chunk).unwrap_or_default()).collect();
+    let mut text = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        match index {
+            0 => text.push_str(&format!("\x1b_Ga=T,f=100,i={},c={},r={},C=1,q=2,m={};{}\x1b\\", id, columns, rows, more, chunk)),
+            _ => text.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk)),
+        }
+    }
+    text
+}
+
+/// kitty removing image `id` and freeing its data
+pub fn kitty_delete(id: u32) -> String {
+    format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", id)
+}
+
+/// iTerm2 drawing `data` at the cursor within `columns` by `rows` cells,
+/// its shape kept
+pub fn iterm2(data: &[u8], (columns, rows): (u16, u16)) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        data.len(),
+        columns,
+        rows,
+        window::base64(data)
+    )
+}
+
+/// `img2sixel` turning the image on its standard input into sixels `width`
+/// by `height` pixels; without a width, as wide as its shape makes it
+pub fn sixel(data: Vec<u8>, width: Option<u32>, height: u32) -> ExternalCommand {
+    let width = width.map_or_else(|| "auto".to_string(), |width| width.to_string());
+    ExternalCommand::new("img2sixel")
+        .arg(format!("--width={}", width))
+        .arg(format!("--height={}", height))
+        .input(data)
+}
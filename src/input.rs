@@ -0,0 +1,88 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+/// Single-line text field shared by search, prompts and filters
+#[derive(Debug, Clone, Default)]
+pub struct TextInput {
+    value: String,
+    /// Cursor position in characters
+    cursor: usize,
+}
+
+impl TextInput {
+    pub fn with_value(value: &str) -> Self {
+        TextInput {
+            value: value.to_string(),
+            cursor: value.chars().count(),
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.value.clear();
+        self.cursor = 0;
+    }
+
+    /// Applies an editing key; returns false for keys the field does not use
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char(c) => {
+                let at = self.byte_index(self.cursor);
+                self.value.insert(at, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace => self.delete_back(),
+            KeyCode::Delete => {
+                if self.cursor < self.value.chars().count() {
+                    let at = self.byte_index(self.cursor);
+                    self.value.remove(at);
+                }
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.value.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.value.chars().count(),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn delete_back(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let at = self.byte_index(self.cursor);
+            self.value.remove(at);
+        }
+    }
+
+    /// The text with the cursor drawn as a reversed cell
+    pub fn line(&self, style: Style) -> Line<'static> {
+        let at = self.byte_index(self.cursor);
+        let (before, rest) = self.value.split_at(at);
+        let mut rest_chars = rest.chars();
+        let under_cursor = rest_chars.next().map(String::from).unwrap_or_else(|| " ".to_string());
+
+        Line::from(vec![
+            Span::styled(before.to_string(), style),
+            Span::styled(under_cursor, style.add_modifier(Modifier::REVERSED)),
+            Span::styled(rest_chars.collect::<String>(), style),
+        ])
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+}
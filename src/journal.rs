@@ -0,0 +1,117 @@
+use chrono::{DateTime, Local};
+use git2::{Repository, RepositoryState};
+use serde::{Deserialize, Serialize};
+use std::{io, path::PathBuf};
+
+/// Written before a mutating git command starts and removed once it has
+/// finished without leaving the repository part way through, so a crash or a
+/// closed terminal can be noticed and cleaned up on the next start
+const FILE_NAME: &str = "git-tui-state";
+
+/// Operations that are journaled while they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Operation {
+    Am,
+    Rebase,
+    Merge,
+    CherryPick,
+    Revert,
+    Reset,
+}
+
+impl Operation {
+    /// The git subcommand
+    pub fn command(self) -> &'static str {
+        match self {
+            Operation::Am => "am",
+            Operation::Rebase => "rebase",
+            Operation::Merge => "merge",
+            Operation::CherryPick => "cherry-pick",
+            Operation::Revert => "revert",
+            Operation::Reset => "reset",
+        }
+    }
+
+    /// Whether the repository is stopped part way through this operation,
+    /// which `git <command> --continue` or `--abort` then pick up
+    pub fn stopped_in(self, state: RepositoryState) -> bool {
+        match self {
+            Operation::Am => matches!(state, RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase),
+            Operation::Rebase => matches!(
+                state,
+                RepositoryState::Rebase
+                    | RepositoryState::RebaseInteractive
+                    | RepositoryState::RebaseMerge
+                    | RepositoryState::ApplyMailboxOrRebase
+            ),
+            Operation::Merge => state == RepositoryState::Merge,
+            Operation::CherryPick => matches!(state, RepositoryState::CherryPick | RepositoryState::CherryPickSequence),
+            Operation::Revert => matches!(state, RepositoryState::Revert | RepositoryState::RevertSequence),
+            // Nothing to continue: an interrupted reset is only undone
+            Operation::Reset => false,
+        }
+    }
+
+    /// The operation the repository is stopped part way through, if any
+    pub fn in_progress(state: RepositoryState) -> Option<Operation> {
+        [Operation::Am, Operation::Rebase, Operation::Merge, Operation::CherryPick, Operation::Revert]
+            .into_iter()
+            .find(|operation| operation.stopped_in(state))
+    }
+}
+
+/// What was running, and where the repository was before it started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub operation: Operation,
+    /// The command line, as it would be typed
+    pub command: String,
+    pub started: DateTime<Local>,
+    /// Checked-out branch, if any
+    pub branch: Option<String>,
+    /// Commit HEAD pointed at, to go back to
+    pub head: Option<String>,
+}
+
+impl Entry {
+    pub fn new(repo: &Repository, operation: Operation, command: String) -> Self {
+        let head = repo.head().ok();
+        Entry {
+            operation,
+            command,
+            started: Local::now(),
+            branch: head.as_ref().filter(|h| h.is_branch()).and_then(|h| h.shorthand().map(str::to_string)),
+            head: head.and_then(|h| h.target()).map(|id| id.to_string()),
+        }
+    }
+}
+
+/// `<git dir>/git-tui-state`; per worktree, like git's own operation state
+fn path(repo: &Repository) -> PathBuf {
+    repo.path().join(FILE_NAME)
+}
+
+pub fn begin(repo: &Repository, entry: &Entry) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(entry).map_err(io::Error::other)?;
+    std::fs::write(path(repo), json + "\n")
+}
+
+/// Drops the entry unless the operation stopped part way; that is left for
+/// the next start to offer resuming or aborting
+pub fn settle(repo: &Repository, operation: Operation) {
+    if !operation.stopped_in(repo.state()) {
+        clear(repo);
+    }
+}
+
+pub fn clear(repo: &Repository) {
+    let _ = std::fs::remove_file(path(repo));
+}
+
+/// The operation a previous session left behind, if any. An unreadable file
+/// is treated as none rather than blocking startup.
+pub fn load(repo: &Repository) -> Option<Entry> {
+    let text = std::fs::read_to_string(path(repo)).ok()?;
+    serde_json::from_str(&text).ok()
+}
@@ -0,0 +1,938 @@
+use crossterm::event::KeyCode;
+use std::collections::HashMap;
+
+/// Where a key press is being interpreted. Modal contexts (diff, search, help)
+/// swallow every key; the panel contexts fall back to `Global`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+    Global,
+    Branches,
+    Graph,
+    /// A fetch or history load running in the background; checked ahead
+    /// of the panels
+    Task,
+    Diff,
+    Search,
+    Help,
+    Confirm,
+    Menu,
+    Prompt,
+    Submodules,
+    Worktrees,
+    PullRequests,
+    Conflicts,
+    Info,
+    Compare,
+    Stats,
+    Sparse,
+}
+
+impl Context {
+    pub fn title(self) -> &'static str {
+        match self {
+            Context::Global => "Global",
+            Context::Branches => "Branches panel",
+            Context::Graph => "Git Graph panel",
+            Context::Task => "Running task (spinner in the status line)",
+            Context::Diff => "Diff view",
+            Context::Search => "Branch search",
+            Context::Help => "Help",
+            Context::Confirm => "Confirmation prompt",
+            Context::Menu => "Menus (1-9 pick an entry directly)",
+            Context::Prompt => "Text prompt",
+            Context::Submodules => "Submodules view",
+            Context::Worktrees => "Worktrees view",
+            Context::PullRequests => "Pull/merge requests view",
+            Context::Conflicts => "Conflicts view",
+            Context::Info => "Information overlay",
+            Context::Compare => "Branch comparison",
+            Context::Stats => "Statistics",
+            Context::Sparse => "Sparse checkout view",
+        }
+    }
+
+    /// Section name used for this context in the `[keys]` config table
+    pub fn config_name(self) -> &'static str {
+        match self {
+            Context::Global => "global",
+            Context::Branches => "branches",
+            Context::Graph => "graph",
+            Context::Task => "task",
+            Context::Diff => "diff",
+            Context::Search => "search",
+            Context::Help => "help",
+            Context::Confirm => "confirm",
+            Context::Menu => "menu",
+            Context::Prompt => "prompt",
+            Context::Submodules => "submodules",
+            Context::Worktrees => "worktrees",
+            Context::PullRequests => "pull_requests",
+            Context::Conflicts => "conflicts",
+            Context::Info => "info",
+            Context::Compare => "compare",
+            Context::Stats => "stats",
+            Context::Sparse => "sparse",
+        }
+    }
+
+    /// Order in which groups are listed in the help overlay
+    pub fn all() -> [Context; 18] {
+        [
+            Context::Global,
+            Context::Branches,
+            Context::Graph,
+            Context::Task,
+            Context::Diff,
+            Context::Search,
+            Context::Help,
+            Context::Confirm,
+            Context::Menu,
+            Context::Prompt,
+            Context::Submodules,
+            Context::Worktrees,
+            Context::PullRequests,
+            Context::Conflicts,
+            Context::Info,
+            Context::Compare,
+            Context::Stats,
+            Context::Sparse,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    ToggleHelp,
+    SwitchPanel,
+    FocusBranches,
+    FocusGraph,
+    MoveUp,
+    MoveDown,
+    DetailsPageUp,
+    DetailsPageDown,
+    ClearFilter,
+    Refresh,
+    SelectBranch,
+    StartSearch,
+    OpenDiff,
+    CloseDiff,
+    ScrollUp,
+    ScrollDown,
+    ScrollPageUp,
+    ScrollPageDown,
+    ScrollLeft,
+    ScrollRight,
+    ToggleDiffWrap,
+    SearchDiff,
+    NextMatch,
+    PreviousMatch,
+    CancelSearch,
+    SearchNext,
+    SearchDeleteChar,
+    CloseHelp,
+    ToggleMark,
+    ClearMarks,
+    GraphMarked,
+    FetchBranches,
+    DeleteBranches,
+    PreviewMerge,
+    ConfirmYes,
+    ConfirmOverride,
+    ConfirmNo,
+    ConfirmAlways,
+    MenuSelect,
+    MenuCancel,
+    ToggleRefFilter,
+    RefMenu,
+    PromptSubmit,
+    PromptCancel,
+    AuthorDomains,
+    ToggleHideBots,
+    ToggleHideTreeSame,
+    ToggleStashes,
+    ToggleMerges,
+    ToggleFirstParent,
+    CycleHistoryOrder,
+    ToggleMilestones,
+    ToggleRelativeDates,
+    HighlightAuthor,
+    ToggleGlyphSet,
+    ToggleFold,
+    ExportJson,
+    TrailerActions,
+    Checkout,
+    SetUpstream,
+    CycleBranchSort,
+    ToggleBranchGroups,
+    ToggleHideRemotes,
+    ShowChange,
+    GoToParent,
+    GoToMergeParent,
+    GoToChild,
+    ShowSubmodules,
+    CloseSubmodules,
+    SubmoduleInit,
+    SubmoduleUpdate,
+    SubmoduleOpen,
+    Bisect,
+    ShowGitConfig,
+    PickIdentity,
+    CloseInfo,
+    ExportAudit,
+    DateRange,
+    PathFilter,
+    Pickaxe,
+    FilterCommitTypes,
+    Changelog,
+    FormatPatch,
+    ApplyPatch,
+    PushForReview,
+    ShowWorktrees,
+    CloseWorktrees,
+    WorktreeAdd,
+    WorktreeRemove,
+    WorktreeSwitch,
+    ShowPullRequests,
+    ClosePullRequests,
+    PullRequestJump,
+    PullRequestCheckout,
+    PullRequestOpen,
+    ShowConflicts,
+    CloseConflicts,
+    TakeOurs,
+    TakeTheirs,
+    MarkResolved,
+    ContinueOperation,
+    AbortOperation,
+    Undo,
+    ToggleOpLog,
+    CancelTask,
+    SwitchRepository,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PreviousTab,
+    ToggleSplitDiff,
+    TogglePin,
+    ShowPins,
+    CompareBranches,
+    CompareOtherSide,
+    CompareJump,
+    CloseCompare,
+    RevisionRange,
+    RecoverBranch,
+    Commit,
+    OpenFile,
+    OpenFileAtRevision,
+    ExportDot,
+    ShowStats,
+    Contributors,
+    ToggleShortstat,
+    EditBranchDescription,
+    Deepen,
+    CloseStats,
+    ShowSparse,
+    CloseSparse,
+    SparseAdd,
+    SparseRemove,
+    SparseReapply,
+    SparseDisable,
+    LfsDiff,
+    NextHunk,
+    PreviousHunk,
+    ToggleImagePreview,
+}
+
+const ACTION_NAMES: &[(Action, &str)] = &[
+    (Action::Quit, "quit"),
+    (Action::ToggleHelp, "toggle_help"),
+    (Action::SwitchPanel, "switch_panel"),
+    (Action::FocusBranches, "focus_branches"),
+    (Action::FocusGraph, "focus_graph"),
+    (Action::MoveUp, "move_up"),
+    (Action::MoveDown, "move_down"),
+    (Action::DetailsPageUp, "details_page_up"),
+    (Action::DetailsPageDown, "details_page_down"),
+    (Action::ClearFilter, "clear_filter"),
+    (Action::Refresh, "refresh"),
+    (Action::SelectBranch, "select_branch"),
+    (Action::StartSearch, "start_search"),
+    (Action::OpenDiff, "open_diff"),
+    (Action::CloseDiff, "close_diff"),
+    (Action::ScrollUp, "scroll_up"),
+    (Action::ScrollDown, "scroll_down"),
+    (Action::ScrollPageUp, "scroll_page_up"),
+    (Action::ScrollPageDown, "scroll_page_down"),
+    (Action::ScrollLeft, "scroll_left"),
+    (Action::ScrollRight, "scroll_right"),
+    (Action::ToggleDiffWrap, "toggle_diff_wrap"),
+    (Action::SearchDiff, "search_diff"),
+    (Action::NextMatch, "next_match"),
+    (Action::PreviousMatch, "previous_match"),
+    (Action::CancelSearch, "cancel_search"),
+    (Action::SearchNext, "search_next"),
+    (Action::SearchDeleteChar, "search_delete_char"),
+    (Action::CloseHelp, "close_help"),
+    (Action::ToggleMark, "toggle_mark"),
+    (Action::ClearMarks, "clear_marks"),
+    (Action::GraphMarked, "graph_marked"),
+    (Action::FetchBranches, "fetch_branches"),
+    (Action::DeleteBranches, "delete_branches"),
+    (Action::PreviewMerge, "preview_merge"),
+    (Action::ConfirmYes, "confirm_yes"),
+    (Action::ConfirmOverride, "confirm_override"),
+    (Action::ConfirmNo, "confirm_no"),
+    (Action::ConfirmAlways, "confirm_always"),
+    (Action::MenuSelect, "menu_select"),
+    (Action::MenuCancel, "menu_cancel"),
+    (Action::ToggleRefFilter, "toggle_ref_filter"),
+    (Action::RefMenu, "ref_menu"),
+    (Action::PromptSubmit, "prompt_submit"),
+    (Action::PromptCancel, "prompt_cancel"),
+    (Action::AuthorDomains, "author_domains"),
+    (Action::ToggleHideBots, "toggle_hide_bots"),
+    (Action::ToggleHideTreeSame, "toggle_hide_tree_same"),
+    (Action::ToggleStashes, "toggle_stashes"),
+    (Action::ToggleMerges, "toggle_merges"),
+    (Action::ToggleFirstParent, "toggle_first_parent"),
+    (Action::CycleHistoryOrder, "cycle_history_order"),
+    (Action::ToggleMilestones, "toggle_milestones"),
+    (Action::ToggleRelativeDates, "toggle_relative_dates"),
+    (Action::HighlightAuthor, "highlight_author"),
+    (Action::ToggleGlyphSet, "toggle_glyph_set"),
+    (Action::ToggleFold, "toggle_fold"),
+    (Action::ExportJson, "export_json"),
+    (Action::TrailerActions, "trailer_actions"),
+    (Action::Checkout, "checkout"),
+    (Action::SetUpstream, "set_upstream"),
+    (Action::CycleBranchSort, "cycle_branch_sort"),
+    (Action::ToggleBranchGroups, "toggle_branch_groups"),
+    (Action::ToggleHideRemotes, "toggle_hide_remotes"),
+    (Action::ShowChange, "show_change"),
+    (Action::GoToParent, "go_to_parent"),
+    (Action::GoToMergeParent, "go_to_merge_parent"),
+    (Action::GoToChild, "go_to_child"),
+    (Action::ShowSubmodules, "show_submodules"),
+    (Action::CloseSubmodules, "close_submodules"),
+    (Action::SubmoduleInit, "submodule_init"),
+    (Action::SubmoduleUpdate, "submodule_update"),
+    (Action::SubmoduleOpen, "submodule_open"),
+    (Action::Bisect, "bisect"),
+    (Action::ShowGitConfig, "show_git_config"),
+    (Action::PickIdentity, "pick_identity"),
+    (Action::CloseInfo, "close_info"),
+    (Action::ExportAudit, "export_audit"),
+    (Action::DateRange, "date_range"),
+    (Action::PathFilter, "path_filter"),
+    (Action::Pickaxe, "pickaxe"),
+    (Action::FilterCommitTypes, "filter_commit_types"),
+    (Action::Changelog, "changelog"),
+    (Action::FormatPatch, "format_patch"),
+    (Action::ApplyPatch, "apply_patch"),
+    (Action::PushForReview, "push_for_review"),
+    (Action::ShowWorktrees, "show_worktrees"),
+    (Action::CloseWorktrees, "close_worktrees"),
+    (Action::WorktreeAdd, "worktree_add"),
+    (Action::WorktreeRemove, "worktree_remove"),
+    (Action::WorktreeSwitch, "worktree_switch"),
+    (Action::ShowPullRequests, "show_pull_requests"),
+    (Action::ClosePullRequests, "close_pull_requests"),
+    (Action::PullRequestJump, "pull_request_jump"),
+    (Action::PullRequestCheckout, "pull_request_checkout"),
+    (Action::PullRequestOpen, "pull_request_open"),
+    (Action::ShowConflicts, "show_conflicts"),
+    (Action::CloseConflicts, "close_conflicts"),
+    (Action::TakeOurs, "take_ours"),
+    (Action::TakeTheirs, "take_theirs"),
+    (Action::MarkResolved, "mark_resolved"),
+    (Action::ContinueOperation, "continue_operation"),
+    (Action::AbortOperation, "abort_operation"),
+    (Action::Undo, "undo"),
+    (Action::ToggleOpLog, "toggle_operation_log"),
+    (Action::CancelTask, "cancel_task"),
+    (Action::SwitchRepository, "switch_repository"),
+    (Action::NewTab, "new_tab"),
+    (Action::CloseTab, "close_tab"),
+    (Action::NextTab, "next_tab"),
+    (Action::PreviousTab, "previous_tab"),
+    (Action::ToggleSplitDiff, "toggle_split_diff"),
+    (Action::TogglePin, "toggle_pin"),
+    (Action::ShowPins, "show_pins"),
+    (Action::CompareBranches, "compare_branches"),
+    (Action::CompareOtherSide, "compare_other_side"),
+    (Action::CompareJump, "compare_jump"),
+    (Action::CloseCompare, "close_compare"),
+    (Action::RevisionRange, "revision_range"),
+    (Action::RecoverBranch, "recover_branch"),
+    (Action::Commit, "commit"),
+    (Action::OpenFile, "open_file"),
+    (Action::OpenFileAtRevision, "open_file_at_revision"),
+    (Action::ExportDot, "export_dot"),
+    (Action::ShowStats, "show_stats"),
+    (Action::Contributors, "contributors"),
+    (Action::ToggleShortstat, "toggle_shortstat"),
+    (Action::EditBranchDescription, "edit_branch_description"),
+    (Action::Deepen, "deepen"),
+    (Action::CloseStats, "close_stats"),
+    (Action::ShowSparse, "show_sparse_checkout"),
+    (Action::CloseSparse, "close_sparse_checkout"),
+    (Action::SparseAdd, "sparse_add"),
+    (Action::SparseRemove, "sparse_remove"),
+    (Action::SparseReapply, "sparse_reapply"),
+    (Action::SparseDisable, "sparse_disable"),
+    (Action::LfsDiff, "lfs_diff"),
+    (Action::NextHunk, "next_hunk"),
+    (Action::PreviousHunk, "previous_hunk"),
+    (Action::ToggleImagePreview, "toggle_image_preview"),
+];
+
+impl Action {
+    /// Actions that modify the repository (or its remote-tracking refs) and
+    /// are refused in read-only mode
+    pub fn is_mutating(self) -> bool {
+        matches!(
+            self,
+            Action::FetchBranches
+                | Action::DeleteBranches
+                | Action::RefMenu
+                | Action::Checkout
+                | Action::SetUpstream
+                | Action::EditBranchDescription
+                | Action::Deepen
+                | Action::SubmoduleInit
+                | Action::SubmoduleUpdate
+                | Action::WorktreeAdd
+                | Action::WorktreeRemove
+                | Action::SparseAdd
+                | Action::SparseRemove
+                | Action::SparseReapply
+                | Action::SparseDisable
+                | Action::Bisect
+                | Action::ApplyPatch
+                | Action::PushForReview
+                | Action::PullRequestCheckout
+                | Action::TakeOurs
+                | Action::TakeTheirs
+                | Action::MarkResolved
+                | Action::ContinueOperation
+                | Action::AbortOperation
+                | Action::Undo
+                | Action::RecoverBranch
+                | Action::Commit
+        )
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTION_NAMES
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(action, _)| *action)
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::ToggleHelp => "Show this help",
+            Action::SwitchPanel => "Switch panel",
+            Action::FocusBranches => "Focus branches panel",
+            Action::FocusGraph => "Focus git graph panel",
+            Action::MoveUp => "Move selection up",
+            Action::MoveDown => "Move selection down",
+            Action::DetailsPageUp => "Scroll commit details up",
+            Action::DetailsPageDown => "Scroll commit details down",
+            Action::ClearFilter => "Clear branch and trailer filters",
+            Action::Refresh => "Refresh repository data",
+            Action::SelectBranch => "Filter graph by branch (choose relationship mode)",
+            Action::StartSearch => "Search branches",
+            Action::OpenDiff => "Show diff of commit (expands folded bot commits)",
+            Action::CloseDiff => "Close diff",
+            Action::ScrollUp => "Scroll up",
+            Action::ScrollDown => "Scroll down",
+            Action::ScrollPageUp => "Scroll up fast",
+            Action::ScrollPageDown => "Scroll down fast",
+            Action::ScrollLeft => "Scroll left (long lines unwrapped)",
+            Action::ScrollRight => "Scroll right (long lines unwrapped)",
+            Action::ToggleDiffWrap => "Wrap long lines or scroll them sideways",
+            Action::SearchDiff => "Search the diff",
+            Action::NextMatch => "Jump to the next match",
+            Action::PreviousMatch => "Jump to the previous match",
+            Action::CancelSearch => "Cancel search",
+            Action::SearchNext => "Find next match",
+            Action::SearchDeleteChar => "Delete character",
+            Action::CloseHelp => "Close help",
+            Action::ToggleMark => "Mark/unmark branch",
+            Action::ClearMarks => "Clear all marks",
+            Action::GraphMarked => "Graph exactly the marked branches",
+            Action::FetchBranches => "Fetch marked (or selected) branches",
+            Action::DeleteBranches => "Delete marked (or selected) branches",
+            Action::PreviewMerge => "Show whether merging the branch into HEAD would conflict, without merging",
+            Action::ConfirmYes => "Confirm",
+            Action::ConfirmOverride => "Confirm, even when rewriting pushed commits",
+            Action::ConfirmNo => "Cancel",
+            Action::ConfirmAlways => "Confirm and stop asking for this kind of action (saved in the config)",
+            Action::MenuSelect => "Choose entry",
+            Action::MenuCancel => "Close menu",
+            Action::ToggleRefFilter => "Toggle [refs] exclude patterns",
+            Action::RefMenu => "New branch/tag, reset current branch, check out or rebase onto commit",
+            Action::PromptSubmit => "Accept",
+            Action::PromptCancel => "Cancel",
+            Action::AuthorDomains => "Commits per author email domain; filter by domain",
+            Action::ToggleHideBots => "Hide/show bot commits",
+            Action::ToggleHideTreeSame => "Hide/show commits without changes (tree-same as first parent)",
+            Action::ToggleStashes => "Show/hide stash entries in the graph",
+            Action::ToggleMerges => "Hide/show merge commits (git log --no-merges)",
+            Action::ToggleFirstParent => "Follow only first parents (git log --first-parent)",
+            Action::CycleHistoryOrder => "Order the graph topologically, by date or oldest first",
+            Action::ToggleMilestones => "Show only commits with a branch or tag (--simplify-by-decoration)",
+            Action::ToggleRelativeDates => "Switch Commit Details between relative and absolute dates",
+            Action::HighlightAuthor => "Highlight one author's commits in the graph, dimming the rest",
+            Action::ToggleGlyphSet => "Switch between Unicode and ASCII graph and branch glyphs",
+            Action::ToggleFold => "Expand/fold a run of bot commits",
+            Action::ExportJson => "Export branches and graph as JSON",
+            Action::TrailerActions => "Filter by or open the commit's trailers",
+            Action::Checkout => "Check out branch (remote branches detached)",
+            Action::SetUpstream => "Set, change or unset the branch's upstream",
+            Action::CycleBranchSort => "Sort branches by name, last commit date or ahead/behind count",
+            Action::ToggleBranchGroups => "Group branches into local, per-remote and prefix/ folders",
+            Action::ToggleHideRemotes => "Hide/show remote branches in the branch list",
+            Action::ShowChange => "Show all commits with this Change-Id, on any branch",
+            Action::GoToParent => "Select the commit's first parent",
+            Action::GoToMergeParent => "Select one of a merge's other parents",
+            Action::GoToChild => "Select a child of the commit",
+            Action::ShowSubmodules => "Show submodules",
+            Action::CloseSubmodules => "Close submodules",
+            Action::SubmoduleInit => "Initialize submodule",
+            Action::SubmoduleUpdate => "Initialize and update submodule",
+            Action::SubmoduleOpen => "Browse submodule (q returns here)",
+            Action::Bisect => "Bisect: mark commit good/bad/skip, start or end a session",
+            Action::ExportAudit => "Write the audit report (--audit mode)",
+            Action::DateRange => "Limit the graph to a date range (empty clears)",
+            Action::PathFilter => "Only show commits touching these paths or globs (empty clears)",
+            Action::Pickaxe => "Find commits whose diff adds or removes text (git log -S/-G)",
+            Action::FilterCommitTypes => "Show only conventional commits of some types (feat, fix, ...)",
+            Action::Changelog => "Write a Markdown changelog of the view or up to HEAD, or copy it",
+            Action::FormatPatch => "Write the commit (or up to HEAD) as patch files, or copy it",
+            Action::ApplyPatch => "Apply a patch or mailbox file (git am / git apply)",
+            Action::PushForReview => "Push HEAD to Gerrit for review (refs/for/<branch>)",
+            Action::ShowWorktrees => "Show worktrees",
+            Action::ShowGitConfig => "Show identity, signing and remote config in effect here",
+            Action::CloseInfo => "Close",
+            Action::PickIdentity => "Pick the identity commits made in this session use",
+            Action::CloseWorktrees => "Close worktrees",
+            Action::WorktreeAdd => "New worktree from the selected branch or commit",
+            Action::WorktreeRemove => "Remove worktree (prune if its directory is gone)",
+            Action::WorktreeSwitch => "Browse this worktree instead",
+            Action::ShowPullRequests => "Show open GitHub pull requests or GitLab merge requests",
+            Action::ClosePullRequests => "Close pull/merge requests",
+            Action::PullRequestJump => "Select the request's head commit (or source branch) in the graph",
+            Action::PullRequestCheckout => "Fetch the request into pr/<number> (mr/ on GitLab) and check it out",
+            Action::PullRequestOpen => "Open the request in the browser",
+            Action::ShowConflicts => "Show the files a stopped merge, rebase or cherry-pick conflicts on",
+            Action::CloseConflicts => "Close conflicts",
+            Action::TakeOurs => "Resolve the file with our side (HEAD)",
+            Action::TakeTheirs => "Resolve the file with their side (the commit being applied)",
+            Action::MarkResolved => "Mark the file resolved once edited (no conflict markers left)",
+            Action::ContinueOperation => "Continue the operation once every file is resolved",
+            Action::AbortOperation => "Abort the operation, back to where it started",
+            Action::ToggleOpLog => "Show or hide the log of git commands and git2 calls run this session",
+            Action::Undo => "Undo the last checkout, reset, patch or merge git-tui made (asks first)",
+            Action::CancelTask => "Cancel the running fetch or history load",
+            Action::SwitchRepository => "Switch to another repository; each keeps its view while away",
+            Action::NewTab => "Open a tab with a copy of this view (filters, selection, history options)",
+            Action::CloseTab => "Close this tab",
+            Action::NextTab => "Next tab",
+            Action::PreviousTab => "Previous tab",
+            Action::ToggleSplitDiff => "Show the selected commit's diff beside the graph, following the selection",
+            Action::TogglePin => "Pin or unpin the commit (kept across sessions)",
+            Action::ShowPins => "List pinned commits and jump to one",
+            Action::CompareBranches => "Compare the two marked branches, or this one with another ref",
+            Action::CompareOtherSide => "Switch to the other side",
+            Action::CompareJump => "Select the commit in the graph",
+            Action::CloseCompare => "Close the comparison",
+            Action::RevisionRange => "Graph a revision range (A..B, A...B); once set, its diff or patches",
+            Action::RecoverBranch => "Recreate a deleted branch from the reflog or a dangling commit",
+            Action::Commit => "Commit the staged changes, writing the message in $EDITOR",
+            Action::OpenFile => "Open the file at the top of the diff in the editor (working tree copy)",
+            Action::OpenFileAtRevision => "Open the file at the top of the diff as of the diff's commit (temporary copy)",
+            Action::ExportDot => "Export the loaded graph as Graphviz DOT, or SVG (.svg, needs dot)",
+            Action::ShowStats => "Statistics of the loaded commits: authors, activity, busiest files, sizes",
+            Action::CloseStats => "Close the statistics",
+            Action::Contributors => "Contributors with commit counts and last activity; filter by one",
+            Action::ToggleShortstat => "Show/hide the lines each commit adds and removes (+X −Y) in the graph",
+            Action::EditBranchDescription => "Write the branch's description (branch.<name>.description) in $EDITOR",
+            Action::Deepen => "Shallow clone: fetch more history (--deepen) or all of it (--unshallow)",
+            Action::ShowSparse => "Show the sparse checkout: its directories or patterns, files checked out",
+            Action::CloseSparse => "Close the sparse checkout view",
+            Action::SparseAdd => "Add a directory to the sparse checkout (the first one turns it on)",
+            Action::SparseRemove => "Remove the directory or pattern from the sparse checkout",
+            Action::SparseReapply => "Reapply the patterns to the working tree (git sparse-checkout reapply)",
+            Action::SparseDisable => "Turn sparse checkout off, checking out every file",
+            Action::LfsDiff => "Fetch the real content of the LFS file at the top of the diff and diff it",
+            Action::NextHunk => "Scroll to the next hunk (binary files have none and are skipped)",
+            Action::PreviousHunk => "Scroll to the previous hunk",
+            Action::ToggleImagePreview => "Show/hide before and after thumbnails of the image file at the top of the diff",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub context: Context,
+    pub key: KeyCode,
+    pub action: Action,
+}
+
+/// Single source of truth for key handling; the help overlay is rendered from it.
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        use Context::*;
+
+        let table: &[(Context, KeyCode, Action)] = &[
+            (Global, KeyCode::Char('q'), Quit),
+            (Global, KeyCode::Esc, Quit),
+            (Global, KeyCode::Char('?'), ToggleHelp),
+            (Global, KeyCode::Tab, SwitchPanel),
+            (Global, KeyCode::Left, FocusBranches),
+            (Global, KeyCode::Char('h'), FocusBranches),
+            (Global, KeyCode::Right, FocusGraph),
+            (Global, KeyCode::Char('l'), FocusGraph),
+            (Global, KeyCode::Up, MoveUp),
+            (Global, KeyCode::Char('k'), MoveUp),
+            (Global, KeyCode::Down, MoveDown),
+            (Global, KeyCode::Char('j'), MoveDown),
+            (Global, KeyCode::PageUp, DetailsPageUp),
+            (Global, KeyCode::PageDown, DetailsPageDown),
+            (Global, KeyCode::Char('c'), ClearFilter),
+            (Global, KeyCode::Char('C'), ClearFilter),
+            (Global, KeyCode::Char('r'), Refresh),
+            (Global, KeyCode::Char('R'), Refresh),
+            (Global, KeyCode::Char('e'), ToggleRefFilter),
+            (Global, KeyCode::Char('a'), AuthorDomains),
+            (Global, KeyCode::Char('B'), ToggleHideBots),
+            (Global, KeyCode::Char('H'), ToggleHideTreeSame),
+            (Global, KeyCode::Char('Z'), ToggleStashes),
+            (Global, KeyCode::Char('_'), ToggleMerges),
+            (Global, KeyCode::Char('1'), ToggleFirstParent),
+            (Global, KeyCode::Char('Y'), CycleHistoryOrder),
+            (Global, KeyCode::Char('d'), ToggleMilestones),
+            (Global, KeyCode::Char('w'), ToggleRelativeDates),
+            (Global, KeyCode::Char('i'), HighlightAuthor),
+            (Global, KeyCode::Char('V'), ToggleGlyphSet),
+            (Global, KeyCode::Char('E'), ExportJson),
+            (Global, KeyCode::Char('S'), ShowSubmodules),
+            (Global, KeyCode::Char('W'), ShowWorktrees),
+            (Global, KeyCode::Char('A'), ExportAudit),
+            (Global, KeyCode::Char('I'), ShowGitConfig),
+            (Global, KeyCode::Char('U'), PickIdentity),
+            (Global, KeyCode::Char('T'), DateRange),
+            (Global, KeyCode::Char('P'), PathFilter),
+            (Global, KeyCode::Char('F'), Pickaxe),
+            (Global, KeyCode::Char('y'), FilterCommitTypes),
+            (Global, KeyCode::Char('N'), Changelog),
+            (Global, KeyCode::Char('M'), ApplyPatch),
+            (Global, KeyCode::Char('G'), PushForReview),
+            (Global, KeyCode::Char('#'), ShowStats),
+            (Global, KeyCode::Char('&'), Contributors),
+            (Global, KeyCode::Char('%'), ToggleShortstat),
+            (Global, KeyCode::Char('~'), Deepen),
+            (Global, KeyCode::Char('*'), ShowSparse),
+            (Branches, KeyCode::Enter, SelectBranch),
+            (Branches, KeyCode::Char('/'), StartSearch),
+            (Branches, KeyCode::Char(' '), ToggleMark),
+            (Branches, KeyCode::Char('x'), ClearMarks),
+            (Branches, KeyCode::Char('v'), GraphMarked),
+            (Branches, KeyCode::Char('f'), FetchBranches),
+            (Branches, KeyCode::Char('D'), DeleteBranches),
+            (Branches, KeyCode::Char('p'), PreviewMerge),
+            (Branches, KeyCode::Char('o'), Checkout),
+            (Branches, KeyCode::Char('^'), SetUpstream),
+            (Branches, KeyCode::Char('t'), EditBranchDescription),
+            (Branches, KeyCode::Char('s'), CycleBranchSort),
+            (Branches, KeyCode::Char('g'), ToggleBranchGroups),
+            (Branches, KeyCode::Char('L'), ToggleHideRemotes),
+            (Branches, KeyCode::Char('='), CompareBranches),
+            (Branches, KeyCode::Char('b'), RecoverBranch),
+            (Graph, KeyCode::Enter, OpenDiff),
+            (Graph, KeyCode::Char('b'), RefMenu),
+            (Graph, KeyCode::Char('p'), FormatPatch),
+            (Graph, KeyCode::Char('z'), ToggleFold),
+            (Graph, KeyCode::Char('t'), TrailerActions),
+            (Graph, KeyCode::Char('g'), ShowChange),
+            (Graph, KeyCode::Char('['), GoToParent),
+            (Graph, KeyCode::Char('{'), GoToMergeParent),
+            (Graph, KeyCode::Char(']'), GoToChild),
+            (Graph, KeyCode::Char('X'), Bisect),
+            (Graph, KeyCode::Char('m'), TogglePin),
+            (Graph, KeyCode::Char('D'), ExportDot),
+            (Diff, KeyCode::Esc, CloseDiff),
+            (Diff, KeyCode::Char('q'), CloseDiff),
+            (Diff, KeyCode::Up, ScrollUp),
+            (Diff, KeyCode::Char('k'), ScrollUp),
+            (Diff, KeyCode::Down, ScrollDown),
+            (Diff, KeyCode::Char('j'), ScrollDown),
+            (Diff, KeyCode::PageUp, ScrollPageUp),
+            (Diff, KeyCode::PageDown, ScrollPageDown),
+            (Diff, KeyCode::Left, ScrollLeft),
+            (Diff, KeyCode::Char('h'), ScrollLeft),
+            (Diff, KeyCode::Right, ScrollRight),
+            (Diff, KeyCode::Char('l'), ScrollRight),
+            (Diff, KeyCode::Char('w'), ToggleDiffWrap),
+            (Diff, KeyCode::Char('/'), SearchDiff),
+            (Diff, KeyCode::Char('n'), NextMatch),
+            (Diff, KeyCode::Char('N'), PreviousMatch),
+            (Diff, KeyCode::Char(']'), NextHunk),
+            (Diff, KeyCode::Char('['), PreviousHunk),
+            (Diff, KeyCode::Char('i'), ToggleImagePreview),
+            (Diff, KeyCode::Char('o'), OpenFile),
+            (Diff, KeyCode::Char('O'), OpenFileAtRevision),
+            (Diff, KeyCode::Char('L'), LfsDiff),
+            (Search, KeyCode::Esc, CancelSearch),
+            (Search, KeyCode::Enter, SearchNext),
+            (Search, KeyCode::Backspace, SearchDeleteChar),
+            (Help, KeyCode::Esc, CloseHelp),
+            (Help, KeyCode::Char('q'), CloseHelp),
+            (Help, KeyCode::Char('?'), CloseHelp),
+            (Help, KeyCode::Up, ScrollUp),
+            (Help, KeyCode::Char('k'), ScrollUp),
+            (Help, KeyCode::Down, ScrollDown),
+            (Help, KeyCode::Char('j'), ScrollDown),
+            (Confirm, KeyCode::Char('y'), ConfirmYes),
+            (Confirm, KeyCode::Enter, ConfirmYes),
+            (Confirm, KeyCode::Char('!'), ConfirmOverride),
+            (Confirm, KeyCode::Char('a'), ConfirmAlways),
+            (Confirm, KeyCode::Char('n'), ConfirmNo),
+            (Confirm, KeyCode::Esc, ConfirmNo),
+            (Menu, KeyCode::Up, MoveUp),
+            (Menu, KeyCode::Char('k'), MoveUp),
+            (Menu, KeyCode::Down, MoveDown),
+            (Menu, KeyCode::Char('j'), MoveDown),
+            (Menu, KeyCode::Enter, MenuSelect),
+            (Menu, KeyCode::Esc, MenuCancel),
+            (Menu, KeyCode::Char('q'), MenuCancel),
+            (Submodules, KeyCode::Up, MoveUp),
+            (Submodules, KeyCode::Char('k'), MoveUp),
+            (Submodules, KeyCode::Down, MoveDown),
+            (Submodules, KeyCode::Char('j'), MoveDown),
+            (Submodules, KeyCode::Char('i'), SubmoduleInit),
+            (Submodules, KeyCode::Char('u'), SubmoduleUpdate),
+            (Submodules, KeyCode::Enter, SubmoduleOpen),
+            (Submodules, KeyCode::Esc, CloseSubmodules),
+            (Submodules, KeyCode::Char('q'), CloseSubmodules),
+            (Worktrees, KeyCode::Up, MoveUp),
+            (Worktrees, KeyCode::Char('k'), MoveUp),
+            (Worktrees, KeyCode::Down, MoveDown),
+            (Worktrees, KeyCode::Char('j'), MoveDown),
+            (Worktrees, KeyCode::Char('n'), WorktreeAdd),
+            (Worktrees, KeyCode::Char('d'), WorktreeRemove),
+            (Worktrees, KeyCode::Enter, WorktreeSwitch),
+            (Worktrees, KeyCode::Esc, CloseWorktrees),
+            (Worktrees, KeyCode::Char('q'), CloseWorktrees),
+            (Global, KeyCode::Char('O'), ShowPullRequests),
+            (PullRequests, KeyCode::Up, MoveUp),
+            (PullRequests, KeyCode::Char('k'), MoveUp),
+            (PullRequests, KeyCode::Down, MoveDown),
+            (PullRequests, KeyCode::Char('j'), MoveDown),
+            (PullRequests, KeyCode::Enter, PullRequestJump),
+            (PullRequests, KeyCode::Char('c'), PullRequestCheckout),
+            (PullRequests, KeyCode::Char('o'), PullRequestOpen),
+            (PullRequests, KeyCode::Char('r'), ShowPullRequests),
+            (PullRequests, KeyCode::Esc, ClosePullRequests),
+            (PullRequests, KeyCode::Char('q'), ClosePullRequests),
+            (Global, KeyCode::Char('u'), Undo),
+            (Global, KeyCode::Char('J'), ToggleOpLog),
+            (Global, KeyCode::Char('n'), SwitchRepository),
+            (Global, KeyCode::Char('+'), NewTab),
+            (Global, KeyCode::Char('-'), CloseTab),
+            (Global, KeyCode::Char('>'), NextTab),
+            (Global, KeyCode::Char('<'), PreviousTab),
+            (Global, KeyCode::Char('|'), ToggleSplitDiff),
+            (Global, KeyCode::Char('\''), ShowPins),
+            (Global, KeyCode::Char('.'), RevisionRange),
+            (Global, KeyCode::Char('@'), Commit),
+            (Global, KeyCode::Char('K'), ShowConflicts),
+            (Conflicts, KeyCode::Up, MoveUp),
+            (Conflicts, KeyCode::Char('k'), MoveUp),
+            (Conflicts, KeyCode::Down, MoveDown),
+            (Conflicts, KeyCode::Char('j'), MoveDown),
+            (Conflicts, KeyCode::Char('o'), TakeOurs),
+            (Conflicts, KeyCode::Char('t'), TakeTheirs),
+            (Conflicts, KeyCode::Char('a'), MarkResolved),
+            (Conflicts, KeyCode::Char('c'), ContinueOperation),
+            (Conflicts, KeyCode::Char('A'), AbortOperation),
+            (Conflicts, KeyCode::Esc, CloseConflicts),
+            (Conflicts, KeyCode::Char('q'), CloseConflicts),
+            (Task, KeyCode::Esc, CancelTask),
+            (Info, KeyCode::Esc, CloseInfo),
+            (Info, KeyCode::Char('q'), CloseInfo),
+            (Info, KeyCode::Up, ScrollUp),
+            (Info, KeyCode::Char('k'), ScrollUp),
+            (Info, KeyCode::Down, ScrollDown),
+            (Info, KeyCode::Char('j'), ScrollDown),
+            (Compare, KeyCode::Up, MoveUp),
+            (Compare, KeyCode::Char('k'), MoveUp),
+            (Compare, KeyCode::Down, MoveDown),
+            (Compare, KeyCode::Char('j'), MoveDown),
+            (Compare, KeyCode::Tab, CompareOtherSide),
+            (Compare, KeyCode::Left, CompareOtherSide),
+            (Compare, KeyCode::Char('h'), CompareOtherSide),
+            (Compare, KeyCode::Right, CompareOtherSide),
+            (Compare, KeyCode::Char('l'), CompareOtherSide),
+            (Compare, KeyCode::Enter, CompareJump),
+            (Compare, KeyCode::Esc, CloseCompare),
+            (Compare, KeyCode::Char('q'), CloseCompare),
+            (Stats, KeyCode::Esc, CloseStats),
+            (Stats, KeyCode::Char('q'), CloseStats),
+            (Stats, KeyCode::Char('#'), CloseStats),
+            (Sparse, KeyCode::Up, MoveUp),
+            (Sparse, KeyCode::Char('k'), MoveUp),
+            (Sparse, KeyCode::Down, MoveDown),
+            (Sparse, KeyCode::Char('j'), MoveDown),
+            (Sparse, KeyCode::Char('n'), SparseAdd),
+            (Sparse, KeyCode::Char('d'), SparseRemove),
+            (Sparse, KeyCode::Char('r'), SparseReapply),
+            (Sparse, KeyCode::Char('x'), SparseDisable),
+            (Sparse, KeyCode::Esc, CloseSparse),
+            (Sparse, KeyCode::Char('q'), CloseSparse),
+            (Sparse, KeyCode::Char('*'), CloseSparse),
+            (Prompt, KeyCode::Enter, PromptSubmit),
+            (Prompt, KeyCode::Esc, PromptCancel),
+        ];
+
+        Keymap {
+            bindings: table
+                .iter()
+                .map(|&(context, key, action)| Binding { context, key, action })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Default bindings with the `[keys.<context>]` overrides from the config
+    /// applied. An override replaces every default key of that action in that
+    /// context.
+    pub fn with_overrides(
+        overrides: &HashMap<String, HashMap<String, Vec<String>>>,
+    ) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+
+        for (context_name, actions) in overrides {
+            let context = Context::all()
+                .into_iter()
+                .find(|c| c.config_name() == context_name)
+                .ok_or_else(|| format!("unknown key context [keys.{}]", context_name))?;
+
+            for (action_name, keys) in actions {
+                let action = Action::from_name(action_name).ok_or_else(|| {
+                    format!("unknown action '{}' in [keys.{}]", action_name, context_name)
+                })?;
+
+                keymap
+                    .bindings
+                    .retain(|b| !(b.context == context && b.action == action));
+                for key in keys {
+                    let key = parse_key(key).ok_or_else(|| {
+                        format!("invalid key '{}' for {}.{}", key, context_name, action_name)
+                    })?;
+                    keymap.bindings.push(Binding { context, key, action });
+                }
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    /// Resolve a key in `context`, falling back to global bindings for the panels
+    pub fn lookup(&self, context: Context, key: KeyCode) -> Option<Action> {
+        let find = |ctx: Context| {
+            self.bindings
+                .iter()
+                .find(|b| b.context == ctx && b.key == key)
+                .map(|b| b.action)
+        };
+
+        match context {
+            Context::Branches | Context::Graph => find(context).or_else(|| find(Context::Global)),
+            _ => find(context),
+        }
+    }
+
+    /// Bindings of a context grouped by action, in declaration order: `(keys, description)`
+    pub fn describe(&self, context: Context, read_only: bool) -> Vec<(String, &'static str)> {
+        let mut rows: Vec<(Action, Vec<String>)> = Vec::new();
+        for binding in self
+            .bindings
+            .iter()
+            .filter(|b| b.context == context && !(read_only && b.action.is_mutating()))
+        {
+            let label = key_label(binding.key);
+            match rows.iter_mut().find(|(action, _)| *action == binding.action) {
+                Some((_, keys)) => keys.push(label),
+                None => rows.push((binding.action, vec![label])),
+            }
+        }
+
+        rows.into_iter()
+            .map(|(action, keys)| (keys.join("/"), action.description()))
+            .collect()
+    }
+}
+
+pub fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Up => "↑".to_string(),
+        KeyCode::Down => "↓".to_string(),
+        KeyCode::Left => "←".to_string(),
+        KeyCode::Right => "→".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::BackTab => "Shift+Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Inverse of [`key_label`], also accepting ASCII names for the arrow keys
+pub fn parse_key(text: &str) -> Option<KeyCode> {
+    let key = match text {
+        "Space" | "space" => KeyCode::Char(' '),
+        "↑" | "Up" | "up" => KeyCode::Up,
+        "↓" | "Down" | "down" => KeyCode::Down,
+        "←" | "Left" | "left" => KeyCode::Left,
+        "→" | "Right" | "right" => KeyCode::Right,
+        "PgUp" | "PageUp" => KeyCode::PageUp,
+        "PgDn" | "PageDown" => KeyCode::PageDown,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Tab" => KeyCode::Tab,
+        "Shift+Tab" | "BackTab" => KeyCode::BackTab,
+        "Backspace" => KeyCode::Backspace,
+        "Del" | "Delete" => KeyCode::Delete,
+        _ => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                (Some('F'), Some(_)) => KeyCode::F(text[1..].parse().ok()?),
+                _ => return None,
+            }
+        }
+    };
+    Some(key)
+}
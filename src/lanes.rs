@@ -0,0 +1,75 @@
+/// Which lane of the palette each character of `git log --graph` rows is
+/// drawn in, top to bottom: `None` for spaces. A lane takes the next index
+/// when it opens (a branch tip, the second parent of a merge) and keeps it
+/// down to where it closes, however far it shifts left or right meanwhile.
+/// Works on rows turned upside down (`/` and `\` swapped) just the same.
+pub fn assign<'a>(rows: impl IntoIterator<Item = &'a str>) -> Vec<Vec<Option<usize>>> {
+    let mut next = 0;
+    let mut open = || {
+        next += 1;
+        Some(next - 1)
+    };
+    let mut assigned: Vec<Vec<Option<usize>>> = Vec::new();
+    let mut above: &[u8] = &[];
+    for row in rows {
+        let chars = row.as_bytes();
+        let previous = assigned.last().map(Vec::as_slice).unwrap_or_default();
+        let at = |column: Option<usize>| column.and_then(|column| above.get(column)).copied().unwrap_or(b' ');
+        let lane_at = |column: usize| previous.get(column).copied().flatten();
+        let mut lanes: Vec<Option<usize>> = Vec::with_capacity(chars.len());
+        for (column, &ch) in chars.iter().enumerate() {
+            let left = column.checked_sub(1);
+            let lane = match ch {
+                b' ' | b'_' => None,
+                // Straight down, else the end of an edge coming in
+                b'|' | b'*' | b'o' => match (at(Some(column)), at(left), at(Some(column + 1))) {
+                    (b'|' | b'*' | b'o', _, _) => lane_at(column),
+                    (_, b'\\', _) => left.and_then(lane_at),
+                    (_, _, b'/') => lane_at(column + 1),
+                    _ => open(),
+                },
+                // From up left: a lane shifting right, or one opening off
+                // the node there (a merge's other parent)
+                b'\\' => match (at(left), at(column.checked_sub(2))) {
+                    (b'_', _) => left.and_then(lane_at),
+                    (b'|', b'_') => column.checked_sub(2).and_then(lane_at),
+                    // Where an edge meets a lane going on straight down, the
+                    // edge is what carries on sideways
+                    (b'|', b'\\') if left.and_then(|left| chars.get(left)) == Some(&b'|') => {
+                        column.checked_sub(2).and_then(lane_at)
+                    }
+                    (b'\\' | b'|', _) => left.and_then(lane_at),
+                    _ => open(),
+                },
+                // From up right: a lane shifting left or closing into the
+                // node it ends at
+                b'/' => match (at(Some(column + 1)), at(Some(column + 2))) {
+                    (b'_', _) => lane_at(column + 1),
+                    (b'|', b'_') => lane_at(column + 2),
+                    (b'|', b'/') if chars.get(column + 1) == Some(&b'|') => lane_at(column + 2),
+                    (b'/' | b'|' | b'*' | b'o', _) => lane_at(column + 1),
+                    _ => open(),
+                },
+                // An octopus merge's edges, drawn from its node
+                _ => lanes.iter().rev().find_map(|lane| *lane),
+            };
+            lanes.push(lane);
+        }
+        // A `_` carries a lane along the bottom of the row, between the `/`
+        // on its right it comes from (the `\` on its left, upside down) and
+        // the next row
+        let ends_run = |ch: &u8| !matches!(ch, b'_' | b'|');
+        for column in (0..chars.len()).filter(|&column| chars[column] == b'_') {
+            let right = chars[column..].iter().position(ends_run).map(|offset| column + offset);
+            let left = chars[..column].iter().rposition(ends_run);
+            lanes[column] = match (right, left) {
+                (Some(end), _) if chars[end] == b'/' => lanes[end],
+                (_, Some(start)) if chars[start] == b'\\' => lanes[start],
+                _ => open(),
+            };
+        }
+        assigned.push(lanes);
+        above = chars;
+    }
+    assigned
+}
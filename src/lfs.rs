@@ -0,0 +1,55 @@
+use crate::process::ExternalCommand;
+
+/// First lines a Git LFS pointer file starts with; the second is from
+/// before the project was renamed
+const VERSIONS: [&str; 2] = ["version https://git-lfs.github.com/spec/v1", "version https://hawser.github.com/spec/v1"];
+
+/// Pointer files are never larger than this
+const MAX_SIZE: usize = 1024;
+
+/// The object a Git LFS pointer file stands in for
+pub struct Pointer {
+    /// `sha256:<hex>`
+    pub oid: String,
+    /// Bytes of the real content
+    pub size: u64,
+}
+
+impl Pointer {
+    /// The oid shortened for display: `sha256:1a2b3c4d5e6f`
+    pub fn short_oid(&self) -> &str {
+        let end = self.oid.find(':').map_or(0, |colon| colon + 1) + 12;
+        self.oid.get(..end).unwrap_or(&self.oid)
+    }
+}
+
+/// The pointer in a blob's content, if it is one
+pub fn parse(content: &[u8]) -> Option<Pointer> {
+    if content.len() > MAX_SIZE {
+        return None;
+    }
+    let text = std::str::from_utf8(content).ok()?;
+    let mut lines = text.lines();
+    if !VERSIONS.contains(&lines.next()?) {
+        return None;
+    }
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("oid", value)) => oid = Some(value.to_string()),
+            Some(("size", value)) => size = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some(Pointer { oid: oid?, size: size? })
+}
+
+/// A file's pointer files, as stored, before and after a change; none on a
+/// side where it does not exist
+pub type Pointers = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// `git lfs smudge` turning the pointer file `pointer` at `path` into the
+/// real content, downloading it when it is not in the local LFS store
+pub fn smudge(git: ExternalCommand, path: &str, pointer: Vec<u8>) -> ExternalCommand {
+    git.args(["lfs", "smudge", "--"]).arg(path).input(pointer)
+}
@@ -1,109 +1,1171 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use crossterm::{
     event::{self, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use git2::{Repository, BranchType, Oid};
+use git2::{Repository, BranchType, Mailmap, Oid};
+use serde::{Deserialize, Serialize};
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    symbols,
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Sparkline, Tabs, Wrap},
     Frame, Terminal,
 };
 use std::{
-    collections::HashMap,
-    io,
-    path::Path,
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::{ExitCode, Output},
+    time::{Duration, Instant},
 };
 
-#[derive(Debug, Clone)]
+mod ansi;
+mod audit;
+mod bisect;
+mod branchtree;
+mod cache;
+mod changelog;
+mod cli;
+mod commitdiff;
+mod compare;
+mod commitgraph;
+mod config;
+mod contributors;
+mod confirm;
+mod conflicts;
+mod conventional;
+mod daterange;
+mod difflines;
+mod diffstat;
+mod dot;
+mod dirty;
+mod editor;
+mod fatal;
+mod forge;
+mod gerrit;
+mod github;
+mod gitlab;
+mod gitconfig;
+mod glyphs;
+mod images;
+mod input;
+mod journal;
+mod keymap;
+mod lanes;
+mod lfs;
+mod mergepreview;
+mod oplog;
+mod partial;
+mod patches;
+mod pickaxe;
+mod pins;
+mod recover;
+mod repos;
+mod revrange;
+mod sparse;
+mod tags;
+mod stats;
+mod prefetch;
+mod process;
+mod published;
+mod submodules;
+mod task;
+mod theme;
+mod trailers;
+mod undo;
+mod watcher;
+mod window;
+mod worktrees;
+
+use clap::Parser;
+use cli::{Cli, HistoryScope};
+use daterange::DateRange;
+use difflines::LineNumbers;
+use compare::Comparison;
+use conflicts::{Conflict, Side};
+use config::{ConfigWatcher, Identity, Layers, RefFilter, Settings};
+use conventional::TypeFilter;
+use fatal::Fatal;
+use forge::{Forge, PullRequest};
+use github::GitHubSettings;
+use gitlab::GitLabSettings;
+use glyphs::{Glyphs, NodeKind};
+use input::TextInput;
+use journal::Operation;
+use keymap::{Action, Context, Keymap};
+use mergepreview::Preview;
+use pickaxe::Pickaxe;
+use prefetch::Prefetcher;
+use process::{ExternalCommand, LineStream};
+use revrange::RevRange;
+use audit::{AuditEvent, AuditLog};
+use bisect::BisectState;
+use branchtree::{BranchRow, BranchSort};
+use cache::{LruCache, Snapshot, Weigh};
+use changelog::Grouping;
+use submodules::SubmoduleInfo;
+use task::Task;
+use sparse::SparseCheckout;
+use worktrees::WorktreeInfo;
+use theme::Theme;
+use trailers::Trailer;
+use watcher::RepoWatcher;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct GitCommit {
     id: String,
     short_id: String,
     message: String,
     author: String,
+    email: String,
     timestamp: DateTime<Utc>,
     parents: Vec<String>,
     refs: Vec<String>, // Branch and tag references
+    /// Same tree as the first parent (or an empty root commit): changes nothing
+    tree_same: bool,
 }
 
-#[derive(Debug, Clone)]
+impl Weigh for GitCommit {
+    fn weight(&self) -> usize {
+        std::mem::size_of::<GitCommit>()
+            + self.id.len()
+            + self.short_id.len()
+            + self.message.len()
+            + self.author.len()
+            + self.email.len()
+            + self.parents.weight()
+            + self.refs.weight()
+    }
+}
+
+/// What is kept on disk between sessions when `[cache] persist` is on.
+/// Commit metadata is not: reading it back costs about as much as reading
+/// the commits from the object database.
+#[derive(Serialize, Deserialize)]
+struct CachedData {
+    /// Fingerprint of the ref tips both caches were filled for
+    ref_tips: String,
+    descendants: Vec<(String, Vec<String>)>,
+    full_ids: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 struct GitBranch {
     name: String,
     commit_id: String,
     is_head: bool,
     is_remote: bool,
+    /// Pseudo-entry named `HEAD` standing for a detached HEAD
+    is_detached: bool,
+    /// Commits only on this branch and only on its upstream, for local
+    /// branches that have one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ahead_behind: Option<(usize, usize)>,
+    /// Upstream of a local branch (`origin/main`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    upstream: Option<String>,
+    /// Committer time of the tip, for sorting by date
+    #[serde(skip)]
+    time: i64,
+    /// `branch.<name>.description` of a local branch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 struct GraphLine {
     commit_id: String,
     graph_text: String,
+    /// Palette index of the lane each `graph_text` character is on, from
+    /// [`lanes::assign`] once the rows are final
+    lanes: Vec<Option<usize>>,
     commit_text: String,
-    refs_text: String,
+    /// Parents are not shown: cut off by the filter, the page limit or a shallow clone
+    is_boundary: bool,
+    /// Number of bot commits folded into this row (0 for ordinary rows)
+    folded: usize,
+    /// First commit of the bot run this row belongs to, folded or expanded
+    fold_key: Option<String>,
+    /// Other loaded commits with the same Change-Id (cherry-picks, re-uploads)
+    same_change: usize,
+    /// The commit's diff against its first parent is empty
+    tree_same: bool,
+    /// Picks the node glyph (boundary rows are drawn as such regardless)
+    node: NodeKind,
+    /// Lines the commit adds and removes, once counted for the shortstat toggle
+    shortstat: Option<(usize, usize)>,
 }
 
+/// Commits fetched per page; more pages load as the selection reaches the end
+const COMMIT_PAGE_SIZE: usize = 100;
+
+/// Pages of older history loaded at most to reach a commit jumped to
+const JUMP_PAGE_LIMIT: usize = 20;
+
+/// Upper bound on diff lines taken from git per frame, keeping input responsive
+const DIFF_LINES_PER_FRAME: usize = 100_000;
+
 struct App {
     repository: Repository,
     branches: Vec<GitBranch>,
     commits: HashMap<String, GitCommit>,
     graph_lines: Vec<GraphLine>,
+    /// Into `branch_rows`
     selected_branch: usize,
+    /// What the Branches panel lists: branches, and group headers when grouped
+    branch_rows: Vec<BranchRow>,
+    branch_sort: BranchSort,
+    group_branches: bool,
+    /// Leave remote branches out of the Branches panel (not the graph)
+    hide_remotes: bool,
+    /// Keys of the branch groups folded away (see [`BranchRow::Group`])
+    collapsed_groups: HashSet<String>,
     selected_commit: usize,
     branch_list_state: ListState,
     commit_list_state: ListState,
     show_logs: bool,
     current_branch_filter: Option<String>,
-    loading: bool,
+    /// Only the commits of this range (`main..feature`), in place of the
+    /// branch filter
+    rev_range: Option<RevRange>,
+    // Branch multi-select; `exact_refs` graphs exactly these instead of the descendant heuristic
+    marked_branches: Vec<String>,
+    exact_refs: Option<Vec<String>>,
+    pending_confirm: Option<PendingConfirm>,
+    filter_mode: FilterMode,
+    author_filter: Option<AuthorFilter>,
+    /// `Name <email>` whose commits stand out in the graph, the rest dimmed
+    highlight_author: Option<String>,
+    /// Graph rows of the highlighted author's commits
+    author_rows: HashSet<String>,
+    hide_bots: bool,
+    /// Leave out commits that change nothing (see [`GitCommit::tree_same`])
+    hide_tree_same: bool,
+    /// Graph every stash entry, not only branches and tags (all-branches view)
+    show_stashes: bool,
+    /// `--no-merges`: only commits with a single parent
+    hide_merges: bool,
+    /// `--first-parent`: the mainline of each branch, merges standing for
+    /// the work they bring in
+    first_parent: bool,
+    history_order: HistoryOrder,
+    /// `--simplify-by-decoration`: only commits a branch or tag points at
+    milestones_only: bool,
+    /// Commit Details shows "3 days ago" rather than `date_format`
+    relative_dates: bool,
+    /// Graph rows end with the lines the commit adds and removes
+    show_shortstat: bool,
+    /// Those counts per commit, kept across reloads; none for a commit whose
+    /// parent is missing (a shallow clone's boundary)
+    shortstats: HashMap<Oid, Option<(usize, usize)>>,
+    /// Commits of a shallow clone whose parents were not fetched; the graph
+    /// says history is cut off while there are any
+    shallow_boundaries: usize,
+    /// The remote a partial clone fetches the blobs it left out from; diffs
+    /// fetch what they need from it before they are made
+    promisor_remote: Option<String>,
+    /// Confirmations turned off in the config
+    skip_confirm: HashSet<confirm::Kind>,
+    date_format: String,
+    /// `.mailmap` (and `mailmap.file`), read at startup, so people who changed
+    /// name or email show up as one
+    mailmap: Option<Mailmap>,
+    /// Full ids of the stash entries, `stash@{0}` first
+    stashes: Vec<String>,
+    bot_authors: Vec<String>,
+    /// Bot runs (by first commit id) the user unfolded
+    expanded_folds: HashSet<String>,
+    /// Only commits carrying this trailer
+    trailer_filter: Option<Trailer>,
+    trailer_links: Vec<(String, String)>,
+    /// Commit messages are prepared with a `Signed-off-by` trailer
+    signoff: bool,
+    /// Trailers prepared in commit messages, from commands
+    trailer_commands: Vec<config::TrailerCommand>,
+    /// Only commits dated within this range
+    date_range: Option<DateRange>,
+    /// Only commits touching these pathspecs (relative to the top level)
+    path_filter: Vec<String>,
+    /// Only commits whose diff adds or removes this; hits are highlighted in diffs
+    pickaxe: Option<Pickaxe>,
+    /// Only conventional commits of these types
+    type_filter: Option<TypeFilter>,
+    /// Operations taking at least this long end with a desktop notification
+    notify_after: Option<Duration>,
+    /// Last title sent to the terminal
+    window_title: String,
+    menu: Option<Menu>,
+    prompt: Option<Prompt>,
     error_message: Option<String>,
     scroll_offset: u16, // For scrolling commit details
     // Diff viewing
     current_diff: Option<String>,
+    diff_stream: Option<LineStream>,
+    /// Full id of the commit being streamed, to cache its diff once complete
+    diff_stream_commit: Option<String>,
+    /// Files seen so far in the diff being streamed
+    diff_files: usize,
+    /// Lines in `current_diff`, kept up to date as it grows
+    diff_line_count: usize,
+    /// Gutter numbers for each line of `current_diff`
+    diff_numbers: LineNumbers,
+    /// Wrap long diff lines; otherwise they scroll sideways by `diff_hscroll` characters
+    diff_wrap: bool,
+    diff_hscroll: usize,
+    /// Text searched for in the diff, and the line of the match jumped to
+    diff_search: Option<String>,
+    diff_match: Option<usize>,
     show_diff: bool,
     diff_scroll_offset: u16,
+    /// Split layout: the selected commit's diff in a pane right of the graph
+    split_diff: bool,
+    /// Commit the split pane's diff was loaded for
+    split_diff_commit: String,
+    /// `[diff] filter`: the diff is shown as this command prints it
+    diff_filter: Option<String>,
+    /// The filter's output for the diff shown, one styled line per line of
+    /// `current_diff` (which then holds its text)
+    diff_styled: Option<Vec<Line<'static>>>,
+    /// The diff as it was before the filter, to find its files in
+    diff_unfiltered: Option<String>,
+    /// Revisions the diff shown goes from and to, to read its files at
+    diff_revisions: Option<(String, String)>,
+    /// `[editor] open` template for opening files
+    open_command: Option<String>,
+    /// How the terminal draws images, if it can
+    image_protocol: Option<images::Protocol>,
+    /// The diff view shows thumbnails of the image file at its top
+    image_preview: bool,
+    /// Boxes the last frame left for those thumbnails
+    image_slots: Option<ImageSlots>,
+    /// What the thumbnails on the terminal were drawn for, to draw them
+    /// again only when that changes
+    images_shown: Option<String>,
+    // Paged history loading
+    history_scope: HistoryScope,
+    commit_limit: usize,
+    has_more_commits: bool,
+    load_more_pending: bool,
     // Cache for performance
-    descendant_cache: HashMap<String, Vec<String>>,
+    /// Descendant branches per branch, valid for the ref tips in `ref_tips`
+    descendant_cache: LruCache<Vec<String>>,
+    /// Commit metadata by full id, so reloads skip the object database
+    commit_cache: LruCache<GitCommit>,
+    /// Full ids of the abbreviated ids `git log` printed, valid for the ref
+    /// tips in `ref_tips`: with the same tips, an abbreviation git printed as
+    /// unique still names the same commit
+    full_id_cache: LruCache<String>,
+    /// Diffs already shown by full commit id, so showing one again is
+    /// instant. Dropped by a manual refresh.
+    diff_cache: LruCache<String>,
+    /// Fills `diff_cache` with the diffs around the selection
+    prefetcher: Prefetcher,
+    /// Commits either side of the selection whose diffs are prefetched
+    prefetch_distance: usize,
+    /// Selected commit the last prefetch was asked around
+    prefetched_around: String,
+    ref_tips: String,
+    persist_cache: bool,
+    /// Keep git's commit-graph up to date, see [`App::update_commit_graph`]
+    commit_graph: bool,
+    /// Caches gained entries since they were loaded from disk
+    cache_dirty: bool,
     branch_commit_cache: HashMap<String, String>,
+    /// `git verify-tag` verdicts of signed tags shown in Commit Details, by
+    /// tag object
+    tag_verifications: HashMap<Oid, tags::Verification>,
+    /// Commit HEAD points at when it is detached
+    detached_head: Option<String>,
     // Branch search
     search_mode: bool,
-    search_query: String,
+    search_input: TextInput,
+    // Key bindings and help overlay
+    keymap: Keymap,
+    show_help: bool,
+    help_scroll_offset: u16,
+    // Live configuration
+    theme: Theme,
+    glyphs: Glyphs,
+    /// From the config; `glyphs` is the other base set while switched
+    configured_glyphs: Glyphs,
+    hidden_branches: Vec<glob::Pattern>,
+    ref_filter: RefFilter,
+    ref_filter_enabled: bool,
+    config_watcher: ConfigWatcher,
+    toast: Option<Toast>,
+    // Refresh when other processes change the repository
+    repo_watcher: Option<RepoWatcher>,
+    branch_flash: Option<BranchFlash>,
+    read_only: bool,
+    /// Read-only from the command line (`--read-only`, `--audit`), which the
+    /// config cannot turn off
+    forced_read_only: bool,
+    submodules: Option<SubmoduleView>,
+    /// Submodule to open in a nested view once the current frame is done
+    open_nested: Option<PathBuf>,
+    /// Command to hand the terminal to once the current frame is done
+    suspend: Option<Suspend>,
+    /// Path of this repository inside the superproject, for nested views
+    nested_label: Option<String>,
+    worktrees: Option<WorktreeView>,
+    sparse: Option<SparseView>,
+    pull_requests: Option<PullRequestView>,
+    compare: Option<CompareView>,
+    /// Statistics dashboard of the loaded commits
+    stats: Option<stats::Stats>,
+    github: GitHubSettings,
+    gitlab: GitLabSettings,
+    /// `git bisect` session of this worktree, if any
+    bisect: Option<BisectState>,
+    /// Files the commit in Commit Details changed, with their line counts;
+    /// none when they could not be counted
+    details_stat: Option<(Oid, Option<Vec<diffstat::FileStat>>)>,
+    /// Identity picked for commits made in this session; the repository's
+    /// config applies when none is
+    identity: Option<Identity>,
+    identities: Vec<Identity>,
+    /// Operation a previous session left unfinished, while it is being offered
+    interrupted: Option<journal::Entry>,
+    /// Commits and diffs viewed, in `--audit` mode. Handed on to nested and
+    /// switched views so the trail covers the whole session.
+    audit: Option<AuditLog>,
+    /// Worktree or repository to browse instead of this one, once the
+    /// current frame is done
+    switch_repo: Option<PathBuf>,
+    /// Offered by the repository picker, with those next to this one when
+    /// `discover_repos` is set
+    repos: Vec<PathBuf>,
+    discover_repos: bool,
+    info: Option<InfoView>,
+    /// Operation log panel along the bottom
+    show_oplog: bool,
+    conflicts: Option<ConflictView>,
+    /// Conflicted paths at the last refresh, so the conflicts screen only
+    /// opens by itself when they change
+    seen_conflicts: Vec<String>,
+    /// Fetches and history loads running in the background, oldest first
+    tasks: Vec<RunningTask>,
+    /// Changed files as of the last refresh, for the status bar; none
+    /// without a working tree
+    dirty: Option<dirty::Summary>,
+    /// Every tab, in order; the entry of the open one (`tab`) is only
+    /// brought up to date when switching away from it
+    tabs: Vec<TabView>,
+    tab: usize,
+    /// Commits pinned in this repository, oldest first
+    pins: Vec<pins::Pin>,
+}
+
+/// Read-only text overlay
+struct InfoView {
+    title: String,
+    lines: Vec<Line<'static>>,
+    scroll: u16,
+}
+
+/// The conflicts screen of a stopped merge, rebase, cherry-pick or revert
+struct ConflictView {
+    /// None for conflicts left by something with nothing to continue
+    /// (`git stash pop`, `git checkout -m`)
+    operation: Option<Operation>,
+    items: Vec<Conflict>,
+    selected: usize,
+    /// Conflict hunks of the selected file
+    hunks: Vec<(Side, String)>,
+}
+
+/// A background task and what to do once it is done
+struct RunningTask {
+    task: Task,
+    kind: TaskKind,
+}
+
+enum TaskKind {
+    /// The graph, for the view options it was started with
+    Graph { then: AfterGraph },
+    /// Counting the commits of the view, to load the oldest page of them
+    /// oldest first
+    GraphCount { then: AfterGraph },
+    /// `git fetch` per remote, each result named after its remote
+    Fetch { branches: usize, skipped: Vec<String> },
+    /// `git submodule update` of a path
+    Submodule { path: String, done: &'static str },
+    /// Fetching a pull request into a local branch to check out
+    PullRequest { branch: String },
+    /// `git commit-graph write`, which nothing waits for
+    CommitGraph,
+    /// `git fsck` listing dangling commits, to recover a branch from
+    Recover,
+    /// Graphviz rendering an exported graph, the result named after the SVG
+    RenderSvg,
+    /// `git log --numstat` of the loaded commits, for the statistics
+    Stats,
+    /// `git fetch --deepen` or `--unshallow` of a shallow clone
+    Deepen,
+    /// Blobs a partial clone left out, fetched for what waits for them
+    FetchObjects { then: Fetched },
+    /// `git sparse-checkout`, saying `done` once it worked
+    Sparse { done: String },
+    /// `git lfs smudge` of a file's old and new pointer, results named
+    /// after the side
+    LfsDiff { file: difflines::FilePaths },
+    /// `git verify-tag` of a signed tag object
+    VerifyTag { id: Oid },
+    /// `[diff] filter` run on the diff text given
+    FilterDiff { diff: String },
+}
+
+impl TaskKind {
+    /// Fetches and updates run one at a time so they don't race for the
+    /// same refs; history loads and commit-graph writes run alongside
+    fn is_exclusive(&self) -> bool {
+        matches!(self, TaskKind::Fetch { .. } | TaskKind::Submodule { .. } | TaskKind::PullRequest { .. } | TaskKind::Deepen | TaskKind::Sparse { .. })
+    }
+    
+    /// A history load, or the count it starts with
+    fn is_graph(&self) -> bool {
+        matches!(self, TaskKind::Graph { .. } | TaskKind::GraphCount { .. })
+    }
+}
+
+/// Where the selection goes once a history load is done
+enum AfterGraph {
+    /// The first row, for a graph showing something else now
+    Top,
+    /// The commit that was selected, else the row it was on
+    Keep { commit_id: Option<String>, row: usize },
+    /// The first row of the bot run just folded or expanded
+    Fold(String),
+    /// The first commit of the page just loaded, below the rows loaded before
+    NextPage { previous_len: usize },
+    /// A commit jumped to, loading up to `pages` more pages while it may be
+    /// on one of them
+    Jump { commit_id: String, pages: usize },
+}
+
+/// What waits for the missing objects of a partial clone
+enum Fetched {
+    /// The diff of the commit
+    Diff(Oid),
+    /// A file of the diff, opened as of its revision
+    Open { file: difflines::FilePaths, line: Option<u32> },
+}
+
+/// A command that needs the terminal (an editor, `git rebase -i`), run with
+/// the interface suspended once the current frame is done
+struct Suspend {
+    cmd: ExternalCommand,
+    then: Resume,
+}
+
+/// What to do once a [`Suspend`] command has returned
+enum Resume {
+    /// Commit what is staged with the message saved in the file, unless it
+    /// is only the template and the trailer lines it was prepared with
+    Commit { path: PathBuf, before: Option<undo::Position>, template: String, trailers: Vec<String> },
+    /// Tag the commit with the message saved in the file
+    Tag { name: String, commit_id: String, path: PathBuf },
+    /// `git rebase -i` ran, its todo list edited
+    Rebase { onto: String, before: Option<undo::Position> },
+    /// A file was opened to look at; only a failure is reported
+    Open,
+    /// Set the branch's description to what was saved in the file
+    Describe { branch: String, path: PathBuf },
+}
+
+/// The worktrees overlay
+struct WorktreeView {
+    items: Vec<WorktreeInfo>,
+    selected: usize,
+}
+
+/// Where the diff view's image thumbnails go: inside the boxes drawn for
+/// them below the diff
+struct ImageSlots {
+    file: difflines::FilePaths,
+    before: Rect,
+    after: Rect,
+}
+
+/// The sparse checkout overlay, its patterns listed
+struct SparseView {
+    checkout: SparseCheckout,
+    selected: usize,
+}
+
+/// Two refs compared side by side
+struct CompareView {
+    comparison: Comparison,
+    /// Side with the selection, 0 or 1
+    side: usize,
+    /// Selected row on each side
+    selected: [usize; 2],
+}
+
+/// The pull (or merge) requests overlay
+struct PullRequestView {
+    forge: Forge,
+    items: Vec<PullRequest>,
+    selected: usize,
+    /// Remote the requests are fetched from
+    remote: String,
+    /// `owner/name` on GitHub, the project path on GitLab
+    repository: String,
+}
+
+/// The submodules overlay
+struct SubmoduleView {
+    items: Vec<SubmoduleInfo>,
+    selected: usize,
+}
+
+/// Which commits a branch filter shows relative to the chosen branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FilterMode {
+    Only,
+    WithUpstream,
+    WithDescendants,
+    UniqueVsDefault,
+}
+
+impl FilterMode {
+    const ALL: [FilterMode; 4] = [
+        FilterMode::Only,
+        FilterMode::WithUpstream,
+        FilterMode::WithDescendants,
+        FilterMode::UniqueVsDefault,
+    ];
+    
+    fn label(self) -> &'static str {
+        match self {
+            FilterMode::Only => "This branch only",
+            FilterMode::WithUpstream => "This branch + upstream",
+            FilterMode::WithDescendants => "This branch + descendants",
+            FilterMode::UniqueVsDefault => "Commits unique to this branch vs default",
+        }
+    }
+}
+
+/// Order of the graph's commits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum HistoryOrder {
+    /// Children before parents, each branch's commits kept together (what
+    /// `--graph` implies)
+    #[default]
+    Topo,
+    /// Children before parents, otherwise by commit date (`--date-order`)
+    Date,
+    /// Topological, oldest first
+    Reverse,
+}
+
+impl HistoryOrder {
+    fn next(self) -> Self {
+        match self {
+            HistoryOrder::Topo => HistoryOrder::Date,
+            HistoryOrder::Date => HistoryOrder::Reverse,
+            HistoryOrder::Reverse => HistoryOrder::Topo,
+        }
+    }
+    
+    fn label(self) -> &'static str {
+        match self {
+            HistoryOrder::Topo => "topo order",
+            HistoryOrder::Date => "date order",
+            HistoryOrder::Reverse => "oldest first",
+        }
+    }
+}
+
+/// Restricts the graph to some authors
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AuthorFilter {
+    /// Authors whose email is at this domain
+    Domain(String),
+    /// Only commits from bot accounts
+    Bots,
+    /// One contributor, by the name and email the mailmap gives them
+    Person { name: String, email: String },
+}
+
+/// A small pick-one popup
+struct Menu {
+    title: String,
+    items: Vec<String>,
+    selected: usize,
+    kind: MenuKind,
+}
+
+enum MenuKind {
+    /// Choosing the relationship mode for filtering by this branch
+    FilterMode(String),
+    /// Author filter for each entry
+    AuthorFilter(Vec<Option<AuthorFilter>>),
+    /// Actions on the selected commit's trailers
+    Trailers(Vec<TrailerAction>),
+    /// Ref operations on this commit
+    RefAtCommit(String),
+    /// Choosing how to reset the current branch to this commit
+    ResetMode(String),
+    /// Bisect actions for this commit
+    Bisect(String, Vec<BisectAction>),
+    /// Identity for each entry; none for the repository's own config
+    Identity(Vec<Option<Identity>>),
+    /// Patch export for each entry
+    Patches(Vec<PatchAction>),
+    /// Upstream to set for this local branch; none unsets it
+    Upstream(String, Vec<Option<String>>),
+    /// Ways out of an operation a previous session left unfinished
+    Interrupted(Vec<RecoveryAction>),
+    /// Commit to select for each entry (parents or children)
+    JumpTo(Vec<String>),
+    /// Author to highlight for each entry, `None` for no highlight
+    HighlightAuthor(Vec<Option<String>>),
+    /// Commits and grouping of each kind of changelog offered
+    Changelog(Vec<changelog::Source>),
+    /// Repository to switch to for each entry
+    Repository(Vec<PathBuf>),
+    /// Uses of the revision range
+    Range(Vec<RangeAction>),
+    /// Deleted branches and dangling commits to recreate a branch at
+    Recover(Vec<recover::Candidate>),
+    /// Ways to fetch more of a shallow clone's history
+    Deepen(Vec<DeepenAction>),
+    /// Files of the diff to open in the editor, as of its revision with
+    /// `snapshot`
+    OpenFile { files: Vec<difflines::FilePaths>, snapshot: bool },
+    /// LFS files of the diff whose real content to diff
+    LfsDiff(Vec<difflines::FilePaths>),
+}
+
+#[derive(Clone, Copy)]
+enum Relative {
+    FirstParent,
+    /// Second and later parents of a merge
+    MergeParent,
+    Child,
+}
+
+enum RecoveryAction {
+    /// `git <command> --continue`
+    Continue,
+    /// `git <command> --abort`
+    Abort,
+    /// Back to the commit HEAD was at before, keeping local changes
+    Restore(String),
+    Inspect,
+    /// Forget about it and leave the repository as it is
+    Dismiss,
+}
+
+/// What to do with the revision range the graph shows
+enum RangeAction {
+    Diff,
+    /// Ask for a directory and write these commits there, in order
+    Write(Vec<Oid>),
+    Edit,
+    Clear,
+}
+
+/// How much of a shallow clone's missing history to fetch
+enum DeepenAction {
+    By(u32),
+    /// Ask for the number of commits
+    Ask,
+    Unshallow,
+}
+
+enum PatchAction {
+    /// Ask for a directory and write these commits there, in order
+    Write(Vec<Oid>),
+    Copy(Oid),
+}
+
+enum BisectAction {
+    /// `git bisect good|bad|skip`, starting a session first if needed
+    Mark(&'static str),
+    ShowCulprit,
+    Reset,
+}
+
+enum TrailerAction {
+    /// Show only commits carrying the same trailer
+    Filter(Trailer),
+    Open(String),
+}
+
+/// `git reset` modes offered for "reset current branch here"
+const RESET_MODES: [(&str, &str); 3] = [
+    ("--soft", "Soft (keep index and working tree)"),
+    ("--mixed", "Mixed (keep working tree, reset index)"),
+    ("--hard", "Hard (discard all local changes)"),
+];
+
+/// A one-line text prompt
+struct Prompt {
+    title: String,
+    input: TextInput,
+    kind: PromptKind,
+}
+
+enum PromptKind {
+    NewBranch(String),
+    NewTag(String),
+    /// Tag name; the message is then written in the editor
+    NewAnnotatedTag(String),
+    /// Checks out `rev`, detached unless `is_branch`
+    NewWorktree { rev: String, is_branch: bool },
+    AuditReport,
+    /// Empty input clears the range
+    DateRange,
+    /// Space-separated pathspecs; empty input clears the filter
+    PathFilter,
+    /// Empty input ends the search
+    Pickaxe,
+    /// Conventional commit types; empty input clears the filter
+    CommitTypes,
+    /// File to write the changelog to; empty input copies it to the clipboard
+    Changelog(changelog::Source),
+    /// Directory to write these commits' patches to
+    PatchDir(Vec<Oid>),
+    /// Patch file or directory of them to apply
+    ApplyPatch,
+    /// Target branch and options for a push to this Gerrit remote
+    ReviewPush(String),
+    /// Path to write the JSON export to
+    ExportJson,
+    /// Path to write the DOT (or SVG) export to
+    ExportDot,
+    /// Number of commits to deepen a shallow clone by
+    Deepen,
+    /// Directory (or pattern, outside cone mode) to add to the sparse checkout
+    SparseAdd,
+    /// Text to find in the open diff; empty input ends the search
+    DiffSearch,
+    /// Ref to compare this one with
+    CompareWith(String),
+    /// `A..B` or `A...B`; empty input clears the range
+    RevisionRange,
+}
+
+/// Snapshot written by `--export-json` and the export command
+#[derive(Serialize)]
+struct GraphExport<'a> {
+    branches: &'a [GitBranch],
+    /// Loaded commits, newest first
+    commits: Vec<&'a GitCommit>,
+    rows: Vec<GraphRowExport<'a>>,
+}
+
+/// One graph row as displayed; rows without a commit only carry lane lines
+#[derive(Serialize)]
+struct GraphRowExport<'a> {
+    commit_id: Option<&'a str>,
+    graph: &'a str,
+    /// Lane (column) of the commit node
+    lane: Option<usize>,
+    is_boundary: bool,
+    /// Bot commits folded into this row
+    folded: usize,
+}
+
+/// A destructive action waiting for a yes/no answer
+struct PendingConfirm {
+    message: String,
+    action: ConfirmedAction,
+    /// Rewrites commits already on a remote; only the explicit override accepts
+    rewrites_published: bool,
+}
+
+enum ConfirmedAction {
+    DeleteBranches(Vec<String>),
+    Reset { mode: &'static str, commit_id: String },
+    /// `git rebase -i` onto the commit
+    Rebase(String),
+    RemoveWorktree(PathBuf),
+    PruneWorktrees,
+    AbortOperation(Operation),
+    Undo(undo::Record),
+    DisableSparse,
+    /// Resolve a conflicted file with our side (or theirs)
+    TakeSide { path: String, ours: bool, deletes: bool },
+    /// Approve a command line from the config, then go on with `then`
+    RunCommand { command_line: String, then: Approved },
+}
+
+/// What waited for a command from the config to be approved
+enum Approved {
+    Suspend(Box<Suspend>),
+    FilterDiff,
+    /// Preparing a commit message, for its trailer commands
+    Commit,
+}
+
+impl ConfirmedAction {
+    fn kind(&self) -> confirm::Kind {
+        match self {
+            ConfirmedAction::DeleteBranches(_) => confirm::Kind::DeleteBranch,
+            ConfirmedAction::Reset { mode: "--hard", .. } => confirm::Kind::HardReset,
+            ConfirmedAction::Reset { .. } => confirm::Kind::Reset,
+            ConfirmedAction::Rebase(_) => confirm::Kind::Rebase,
+            ConfirmedAction::RemoveWorktree(_) => confirm::Kind::RemoveWorktree,
+            ConfirmedAction::PruneWorktrees => confirm::Kind::PruneWorktrees,
+            ConfirmedAction::AbortOperation(_) => confirm::Kind::AbortOperation,
+            ConfirmedAction::Undo(_) => confirm::Kind::Undo,
+            ConfirmedAction::DisableSparse => confirm::Kind::DisableSparse,
+            ConfirmedAction::TakeSide { .. } => confirm::Kind::TakeSide,
+            ConfirmedAction::RunCommand { .. } => confirm::Kind::RunCommand,
+        }
+    }
+}
+
+/// Short-lived notification shown in the bottom-right corner
+struct Toast {
+    message: String,
+    is_error: bool,
+    shown_at: Instant,
+}
+
+impl Toast {
+    const DURATION: Duration = Duration::from_secs(4);
+}
+
+/// Branches whose tip moved in a refresh, briefly highlighted
+struct BranchFlash {
+    names: HashSet<String>,
+    started: Instant,
+}
+
+impl BranchFlash {
+    const DURATION: Duration = Duration::from_millis(1500);
+}
+
+/// What a tab shows: its filters, history options and selection. The open
+/// tab's live in [`App`] itself; the others are kept here until switched to.
+#[derive(Clone)]
+struct TabView {
+    branch_filter: Option<String>,
+    rev_range: Option<RevRange>,
+    marked_branches: Vec<String>,
+    exact_refs: Option<Vec<String>>,
+    filter_mode: FilterMode,
+    author_filter: Option<AuthorFilter>,
+    highlight_author: Option<String>,
+    hide_bots: bool,
+    hide_tree_same: bool,
+    show_stashes: bool,
+    hide_merges: bool,
+    first_parent: bool,
+    history_order: HistoryOrder,
+    milestones_only: bool,
+    trailer_filter: Option<Trailer>,
+    date_range: Option<DateRange>,
+    path_filter: Vec<String>,
+    pickaxe: Option<Pickaxe>,
+    type_filter: Option<TypeFilter>,
+    ref_filter_enabled: bool,
+    commit_limit: usize,
+    expanded_folds: HashSet<String>,
+    /// Whether the graph had the focus
+    show_logs: bool,
+    /// By name and id, as rows move when refs do
+    selected_branch: Option<String>,
+    selected_commit: Option<String>,
+}
+
+impl TabView {
+    /// The range, the branch filter, the marked branches graphed or `all`
+    fn label(&self) -> String {
+        if let Some(ref range) = self.rev_range {
+            return range.label();
+        }
+        match (&self.exact_refs, &self.branch_filter) {
+            (Some(refs), _) if refs.len() == 1 => refs[0].clone(),
+            (Some(refs), _) => format!("{} branches", refs.len()),
+            (None, Some(branch)) => branch.clone(),
+            (None, None) => "all".to_string(),
+        }
+    }
 }
 
 impl App {
-    fn new<P: AsRef<Path>>(repo_path: P) -> Result<Self> {
+    fn new<P: AsRef<Path>>(repo_path: P, settings: Settings, cli: &Cli) -> Result<Self> {
         let repo = Repository::open(repo_path)?;
+        // Not fatal: without a watcher the view just needs manual refreshes
+        let repo_watcher = RepoWatcher::new(repo.path()).ok();
+        let prefetcher = Prefetcher::start(repo.path().to_path_buf());
+        let config_layers = Layers::new(repo.path(), &cli.set);
+        let trailer_links = gerrit::with_change_link(&repo, settings.trailer_links);
+        let mailmap = repo.mailmap().ok();
         let mut app = App {
             repository: repo,
             branches: Vec::new(),
             commits: HashMap::new(),
             graph_lines: Vec::new(),
             selected_branch: 0,
+            branch_rows: Vec::new(),
+            branch_sort: BranchSort::default(),
+            group_branches: false,
+            hide_remotes: settings.hide_remotes,
+            collapsed_groups: HashSet::new(),
             selected_commit: 0,
             branch_list_state: ListState::default(),
             commit_list_state: ListState::default(),
             show_logs: false,
-            current_branch_filter: None,
-            loading: false,
+            current_branch_filter: cli.branch.clone(),
+            rev_range: None,
+            marked_branches: Vec::new(),
+            exact_refs: None,
+            pending_confirm: None,
+            filter_mode: FilterMode::WithDescendants,
+            author_filter: None,
+            highlight_author: None,
+            author_rows: HashSet::new(),
+            hide_bots: false,
+            hide_tree_same: false,
+            show_stashes: false,
+            hide_merges: false,
+            first_parent: false,
+            history_order: HistoryOrder::default(),
+            milestones_only: false,
+            relative_dates: settings.relative_dates,
+            show_shortstat: false,
+            shallow_boundaries: 0,
+            promisor_remote: None,
+            shortstats: HashMap::new(),
+            skip_confirm: settings.skip_confirm,
+            date_format: settings.date_format.clone(),
+            mailmap,
+            stashes: Vec::new(),
+            bot_authors: settings.bot_authors,
+            expanded_folds: HashSet::new(),
+            trailer_filter: None,
+            date_range: None,
+            path_filter: Vec::new(),
+            pickaxe: None,
+            type_filter: None,
+            trailer_links,
+            signoff: settings.signoff,
+            trailer_commands: settings.trailer_commands,
+            notify_after: settings.notify_after,
+            window_title: String::new(),
+            menu: None,
+            prompt: None,
             error_message: None,
             scroll_offset: 0,
             current_diff: None,
+            diff_stream: None,
+            diff_stream_commit: None,
+            diff_files: 0,
+            diff_line_count: 0,
+            diff_numbers: LineNumbers::default(),
+            diff_wrap: true,
+            diff_hscroll: 0,
+            diff_search: None,
+            diff_match: None,
             show_diff: false,
             diff_scroll_offset: 0,
-            descendant_cache: HashMap::new(),
+            split_diff: settings.split_diff,
+            diff_filter: settings.diff_filter.clone(),
+            diff_styled: None,
+            diff_unfiltered: None,
+            diff_revisions: None,
+            open_command: settings.open_command.clone(),
+            image_protocol: settings.image_protocol,
+            image_preview: false,
+            image_slots: None,
+            images_shown: None,
+            split_diff_commit: String::new(),
+            history_scope: cli.history_scope(),
+            commit_limit: COMMIT_PAGE_SIZE,
+            has_more_commits: false,
+            load_more_pending: false,
+            descendant_cache: LruCache::new(settings.cache_limit / 4),
+            commit_cache: LruCache::new(settings.cache_limit / 2),
+            full_id_cache: LruCache::new(settings.cache_limit / 4),
+            diff_cache: LruCache::new(settings.diff_cache_limit),
+            prefetcher,
+            prefetch_distance: settings.prefetch_distance,
+            prefetched_around: String::new(),
+            ref_tips: String::new(),
+            persist_cache: settings.persist_cache,
+            commit_graph: settings.commit_graph,
+            cache_dirty: false,
             branch_commit_cache: HashMap::new(),
+            tag_verifications: HashMap::new(),
+            detached_head: None,
             search_mode: false,
-            search_query: String::new(),
+            search_input: TextInput::default(),
+            keymap: settings.keymap,
+            show_help: false,
+            help_scroll_offset: 0,
+            theme: settings.theme,
+            configured_glyphs: settings.glyphs.clone(),
+            glyphs: settings.glyphs,
+            hidden_branches: settings.hidden_branches,
+            ref_filter: settings.ref_filter,
+            ref_filter_enabled: true,
+            config_watcher: ConfigWatcher::new(config_layers),
+            toast: None,
+            repo_watcher,
+            branch_flash: None,
+            read_only: cli.read_only || cli.audit || settings.read_only,
+            forced_read_only: cli.read_only || cli.audit,
+            submodules: None,
+            open_nested: None,
+            suspend: None,
+            nested_label: None,
+            worktrees: None,
+            sparse: None,
+            pull_requests: None,
+            compare: None,
+            stats: None,
+            github: settings.github,
+            gitlab: settings.gitlab,
+            switch_repo: None,
+            repos: settings.repos,
+            discover_repos: settings.discover_repos,
+            info: None,
+            show_oplog: false,
+            conflicts: None,
+            seen_conflicts: Vec::new(),
+            tasks: Vec::new(),
+            dirty: None,
+            tabs: Vec::new(),
+            tab: 0,
+            pins: Vec::new(),
+            bisect: None,
+            details_stat: None,
+            audit: cli.audit.then(AuditLog::new),
+            identity: None,
+            identities: settings.identities,
+            interrupted: None,
         };
         
+        app.ref_tips = app.ref_tips_fingerprint();
+        app.pins = pins::load(&app.cache_key_dir());
+        if app.persist_cache {
+            app.load_caches();
+        }
+        if settings.collapse_remotes {
+            let remotes = app.repository.remotes()?;
+            app.collapsed_groups.extend(remotes.iter().flatten().map(|remote| format!("remote:{}", remote)));
+        }
         app.load_branches()?;
         // Don't precompute all relationships - do it lazily
         app.load_graph()?;
         app.branch_list_state.select(Some(0));
         app.commit_list_state.select(Some(0));
+        app.reload_conflicts();
+        app.update_commit_graph();
+        app.reload_dirty();
+        app.tabs = vec![app.tab_view()];
         
         Ok(app)
     }
@@ -111,22 +1173,60 @@ impl App {
     fn load_branches(&mut self) -> Result<()> {
         self.branches.clear();
         self.branch_commit_cache.clear();
+        self.detached_head = None;
+        
+        // A detached HEAD is listed first, as no branch is marked as HEAD then
+        if self.repository.head_detached().unwrap_or(false)
+            && let Some(target) = self.repository.head()?.target()
+        {
+            let commit_id = target.to_string();
+            self.branches.push(GitBranch {
+                name: "HEAD".to_string(),
+                commit_id: commit_id.clone(),
+                is_head: true,
+                is_remote: false,
+                is_detached: true,
+                ahead_behind: None,
+                upstream: None,
+                time: self.commit_time(target),
+                description: None,
+            });
+            self.branch_commit_cache.insert("HEAD".to_string(), commit_id.clone());
+            self.detached_head = Some(commit_id);
+        }
         
         // Load local branches
+        let config = self.repository.config().and_then(|mut config| config.snapshot()).ok();
         let branches = self.repository.branches(Some(BranchType::Local))?;
         for branch_result in branches {
             let (branch, _) = branch_result?;
-            if let Some(name) = branch.name()? {
+            if let Some(name) = branch.name()?
+                && !self.is_branch_hidden(name)
+            {
                 let reference = branch.get();
                 if let Some(target) = reference.target() {
                     let is_head = branch.is_head();
                     let commit_id = target.to_string();
+                    let upstream = branch.upstream().ok();
+                    let ahead_behind = upstream
+                        .as_ref()
+                        .and_then(|upstream| upstream.get().target())
+                        .and_then(|upstream| self.repository.graph_ahead_behind(target, upstream).ok());
+                    let upstream = upstream.and_then(|u| u.name().ok().flatten().map(str::to_string));
                     
                     self.branches.push(GitBranch {
                         name: name.to_string(),
                         commit_id: commit_id.clone(),
                         is_head,
                         is_remote: false,
+                        is_detached: false,
+                        ahead_behind,
+                        upstream,
+                        time: self.commit_time(target),
+                        description: config
+                            .as_ref()
+                            .and_then(|config| config.get_string(&format!("branch.{}.description", name)).ok())
+                            .filter(|description| !description.trim().is_empty()),
                     });
                     
                     // Cache commit ID for quick lookup
@@ -139,7 +1239,9 @@ impl App {
         let remote_branches = self.repository.branches(Some(BranchType::Remote))?;
         for branch_result in remote_branches {
             let (branch, _) = branch_result?;
-            if let Some(name) = branch.name()? {
+            if let Some(name) = branch.name()?
+                && !self.is_branch_hidden(name)
+            {
                 let reference = branch.get();
                 if let Some(target) = reference.target() {
                     let commit_id = target.to_string();
@@ -149,6 +1251,11 @@ impl App {
                         commit_id: commit_id.clone(),
                         is_head: false,
                         is_remote: true,
+                        is_detached: false,
+                        ahead_behind: None,
+                        upstream: None,
+                        time: self.commit_time(target),
+                        description: None,
                     });
                     
                     // Cache commit ID for quick lookup
@@ -157,19 +1264,138 @@ impl App {
             }
         }
         
+        self.sort_branches();
         Ok(())
     }
     
+    fn commit_time(&self, id: Oid) -> i64 {
+        self.repository.find_commit(id).map(|c| c.time().seconds()).unwrap_or(0)
+    }
+    
+    /// Orders the branches by `branch_sort` and lays out the panel's rows.
+    /// The detached HEAD stays first.
+    fn sort_branches(&mut self) {
+        match self.branch_sort {
+            BranchSort::Name => self.branches.sort_by(|a, b| {
+                (!a.is_detached, a.is_remote, &a.name).cmp(&(!b.is_detached, b.is_remote, &b.name))
+            }),
+            BranchSort::Date => self.branches.sort_by(|a, b| {
+                (!a.is_detached, std::cmp::Reverse(a.time), &a.name).cmp(&(!b.is_detached, std::cmp::Reverse(b.time), &b.name))
+            }),
+            // Branches without an upstream last
+            BranchSort::Divergence => self.branches.sort_by_key(|b| {
+                let divergence = b.ahead_behind.map(|(ahead, behind)| ahead + behind);
+                (!b.is_detached, std::cmp::Reverse(divergence), b.is_remote, b.name.clone())
+            }),
+        }
+        self.layout_branch_rows();
+    }
+    
+    fn layout_branch_rows(&mut self) {
+        let items: Vec<branchtree::Item> = self.branches
+            .iter()
+            .map(|b| branchtree::Item { name: &b.name, is_remote: b.is_remote, pinned: b.is_detached })
+            .collect();
+        self.branch_rows = branchtree::rows(&items, self.group_branches, self.hide_remotes, &self.collapsed_groups);
+    }
+    
+    /// The branch on the selected row, if it is not a group header
+    fn selected_git_branch(&self) -> Option<&GitBranch> {
+        match self.branch_rows.get(self.selected_branch) {
+            Some(BranchRow::Branch { index, .. }) => self.branches.get(*index),
+            _ => None,
+        }
+    }
+    
+    fn branch_row(&self, name: &str) -> Option<usize> {
+        self.branch_rows.iter().position(|row| match row {
+            BranchRow::Branch { index, .. } => self.branches[*index].name == name,
+            BranchRow::Group { .. } => false,
+        })
+    }
+    
+    /// Re-lays out the rows, keeping the selection on the same branch or group
+    fn relayout_branches(&mut self, resort: bool) {
+        let selected = self.branch_rows.get(self.selected_branch).map(|row| match row {
+            BranchRow::Branch { index, .. } => Err(self.branches[*index].name.clone()),
+            BranchRow::Group { key, .. } => Ok(key.clone()),
+        });
+        match resort {
+            true => self.sort_branches(),
+            false => self.layout_branch_rows(),
+        }
+        let row = match selected {
+            Some(Err(name)) => self.branch_row(&name),
+            Some(Ok(key)) => self.branch_rows.iter().position(|row| matches!(row, BranchRow::Group { key: k, .. } if *k == key)),
+            None => None,
+        };
+        self.select_branch_row(row.unwrap_or(self.selected_branch));
+    }
+    
+    /// Selects a row, clamped to the list
+    fn select_branch_row(&mut self, row: usize) {
+        self.selected_branch = row.min(self.branch_rows.len().saturating_sub(1));
+        self.branch_list_state.select(Some(self.selected_branch));
+    }
+    
+    fn cycle_branch_sort(&mut self) {
+        self.branch_sort = self.branch_sort.next();
+        self.relayout_branches(true);
+        self.show_toast(format!("Branches sorted by {}", self.branch_sort.label()), false);
+    }
+    
+    fn toggle_branch_groups(&mut self) {
+        self.group_branches = !self.group_branches;
+        self.relayout_branches(false);
+    }
+    
+    fn toggle_hide_remotes(&mut self) {
+        self.hide_remotes = !self.hide_remotes;
+        self.relayout_branches(false);
+        let state = if self.hide_remotes { "hidden" } else { "shown" };
+        self.show_toast(format!("Remote branches {}", state), false);
+    }
+    
+    fn toggle_branch_group(&mut self, key: String) {
+        if !self.collapsed_groups.remove(&key) {
+            self.collapsed_groups.insert(key);
+        }
+        self.relayout_branches(false);
+    }
+    
+    fn is_branch_hidden(&self, name: &str) -> bool {
+        self.hidden_branches.iter().any(|pattern| pattern.matches(name))
+    }
+    
+    /// Base `git` invocation running in the repository's top-level directory
+    fn git_command(&self) -> ExternalCommand {
+        let mut cmd = ExternalCommand::git()
+            .current_dir(self.repository.workdir().unwrap_or(self.repository.path()));
+        if let Some(ref identity) = self.identity {
+            cmd = cmd
+                .env("GIT_AUTHOR_NAME", &identity.name)
+                .env("GIT_AUTHOR_EMAIL", &identity.email)
+                .env("GIT_COMMITTER_NAME", &identity.name)
+                .env("GIT_COMMITTER_EMAIL", &identity.email);
+            if let Some(ref key) = identity.signing_key {
+                // Appended to any `GIT_CONFIG_COUNT` overrides already in the environment
+                let index: usize = std::env::var("GIT_CONFIG_COUNT").ok().and_then(|n| n.parse().ok()).unwrap_or(0);
+                cmd = cmd
+                    .env(&format!("GIT_CONFIG_KEY_{}", index), "user.signingkey")
+                    .env(&format!("GIT_CONFIG_VALUE_{}", index), key)
+                    .env("GIT_CONFIG_COUNT", (index + 1).to_string());
+            }
+        }
+        cmd
+    }
+    
     fn is_ancestor_fast(&self, ancestor_commit: &str, descendant_commit: &str) -> Result<bool> {
         // Use git merge-base to check if ancestor_commit is an ancestor of descendant_commit
-        let mut cmd = std::process::Command::new("git");
-        cmd.arg("merge-base")
-           .arg("--is-ancestor")
-           .arg(ancestor_commit)
-           .arg(descendant_commit)
-           .current_dir(self.repository.path().parent().unwrap_or(self.repository.path()))
-           .stdout(std::process::Stdio::null())
-           .stderr(std::process::Stdio::null());
+        let cmd = self.git_command()
+            .arg("merge-base")
+            .arg("--is-ancestor")
+            .rev(ancestor_commit)
+            .rev(descendant_commit);
         
         match cmd.status() {
             Ok(status) => Ok(status.success()),
@@ -177,263 +1403,792 @@ impl App {
         }
     }
     
+    /// The first graph, loaded before the first frame; in topological
+    /// order, which needs no count first
     fn load_graph(&mut self) -> Result<()> {
-        self.commits.clear();
-        self.graph_lines.clear();
-        
-        // Get git log output with graph using the exact same format as gn function
-        let mut cmd = std::process::Command::new("git");
-        cmd.arg("log")
+        // Loaded now; one still loading in the background would replace it
+        // with the view it was started for
+        self.tasks.retain(|running| !running.kind.is_graph());
+        let cmd = self.graph_command(0)?;
+        self.apply_graph_output(cmd.output())
+    }
+    
+    /// The `git log --graph` command for the current view options, leaving
+    /// out the `skip` newest commits
+    fn graph_command(&mut self, skip: usize) -> Result<ExternalCommand> {
+        // The gn function's format without its `%C(...)` colors: rows are
+        // colored when drawn, and what is parsed has to be plain text
+        let mut cmd = self.git_command()
+           .arg("log")
+           .arg("--no-color")
            .arg("--graph")
            .arg("--abbrev-commit")
            .arg("--decorate")
            .arg("--date=relative")
-           .arg("--format=format:%C(bold cyan)%h%C(reset) - %C(bold green)(%ar)%C(reset) %C(yellow)%s%C(reset) %C(red)- %an%C(reset)%C(bold yellow)%d%C(reset)")
-           .arg("-100"); // Limit to 100 commits for better visibility while maintaining performance
-        
-        // If we have a branch filter, show only related branches with proper graph structure
-        if let Some(ref branch_name) = self.current_branch_filter {
-            // Get descendants from cache or compute on-demand
-            let descendant_branches = if let Some(cached) = self.descendant_cache.get(branch_name) {
-                cached.clone()
-            } else {
-                let descendants = self.compute_descendants_fast(branch_name)?;
-                self.descendant_cache.insert(branch_name.clone(), descendants.clone());
-                descendants
-            };
-            
-            // For master branch or branches with no descendants, don't exclude gerrit refs
-            // as it might exclude all commits
-            if branch_name != "master" && !descendant_branches.is_empty() {
-                // Get all gerrit refs to exclude (like the gn function does)
-                let gerrit_output = std::process::Command::new("git")
-                    .arg("for-each-ref")
-                    .arg("--format=^%(refname:short)")
-                    .arg("refs/remotes/gerrit/")
-                    .current_dir(self.repository.path().parent().unwrap_or(self.repository.path()))
-                    .output();
-                
-                if let Ok(gerrit_out) = gerrit_output {
-                    let gerrit_refs = String::from_utf8_lossy(&gerrit_out.stdout);
-                    for gerrit_ref in gerrit_refs.lines() {
-                        if !gerrit_ref.contains("sunmi") {
-                            cmd.arg(gerrit_ref);
-                        }
-                    }
-                }
+           .arg("--boundary")
+           .arg("--format=format:%h - (%ar) %s - %aN%d");
+        
+        match self.history_scope {
+            HistoryScope::Paged => cmd = cmd.arg(format!("--max-count={}", self.commit_limit)),
+            HistoryScope::MaxCount(n) => cmd = cmd.arg(format!("--max-count={}", n)),
+            HistoryScope::All => {}
+        }
+        if skip > 0 {
+            cmd = cmd.arg(format!("--skip={}", skip));
+        }
+        self.history_filters(cmd)
+    }
+    
+    /// `git log` listing every commit the graph would show, one hash a
+    /// line, to count them
+    fn count_command(&mut self) -> Result<ExternalCommand> {
+        let cmd = self.git_command().args(["log", "--format=%H"]);
+        self.history_filters(cmd)
+    }
+    
+    /// Adds the filters of the current view, then its revisions and paths
+    fn history_filters(&mut self, mut cmd: ExternalCommand) -> Result<ExternalCommand> {
+        match self.author_filter {
+            // Matched against the mapped email, as shown
+            Some(AuthorFilter::Domain(ref domain)) => {
+                cmd = cmd
+                    .arg("--use-mailmap")
+                    .arg("--regexp-ignore-case")
+                    .arg(format!("--author=@{}>", regex_escape(domain)));
             }
-            
-            // Add the base branch
-            cmd.arg(branch_name);
-            
-            // Add all descendant branches
-            for descendant in &descendant_branches {
-                cmd.arg(descendant);
+            // git matches `Name <email>` without the date
+            Some(AuthorFilter::Person { ref name, ref email }) => {
+                cmd = cmd
+                    .arg("--use-mailmap")
+                    .arg(format!("--author=^{} <{}>$", regex_escape(name), regex_escape(email)));
             }
-        } else {
-            cmd.arg("--all");
+            Some(AuthorFilter::Bots) | None => {}
         }
         
-        cmd.current_dir(self.repository.path().parent().unwrap_or(self.repository.path()));
+        if let Some(ref trailer) = self.trailer_filter {
+            cmd = cmd.arg(format!("--grep=^{}: {}$", regex_escape(&trailer.key), regex_escape(&trailer.value)));
+        }
+        if let Some(ref types) = self.type_filter {
+            cmd = cmd.arg(types.log_arg());
+        }
+        // The author, trailer and type patterns are escaped as basic regexes,
+        // whatever grep.patternType says; a `+` in an email would be special
+        // in an extended one
+        let author_pattern = matches!(self.author_filter, Some(AuthorFilter::Domain(_) | AuthorFilter::Person { .. }));
+        let grep_pattern = self.trailer_filter.is_some() || self.type_filter.is_some();
+        if author_pattern || grep_pattern {
+            cmd = cmd.arg("--basic-regexp");
+        }
+        if grep_pattern {
+            // A commit has to match both
+            cmd = cmd.arg("--all-match");
+        }
+        if let Some(ref range) = self.date_range {
+            cmd = cmd.args(range.log_args());
+        }
+        if let Some(ref pickaxe) = self.pickaxe {
+            cmd = cmd.arg(pickaxe.log_arg());
+        }
+        if self.hide_merges {
+            cmd = cmd.arg("--no-merges");
+        }
+        if self.first_parent {
+            cmd = cmd.arg("--first-parent");
+        }
+        if self.history_order == HistoryOrder::Date {
+            cmd = cmd.arg("--date-order");
+        }
+        if self.milestones_only {
+            cmd = cmd.arg("--simplify-by-decoration");
+        }
         
-        let output = match cmd.output() {
-            Ok(output) => output,
-            Err(e) => {
-                eprintln!("Failed to execute git command: {}", e);
-                return Ok(());
-            }
-        };
+        let mut cmd = self.history_revs(cmd)?;
+        // A pathspec rather than filtering a revwalk with tree diffs: git
+        // rewrites parents to the commits kept, so the graph stays connected
+        if !self.path_filter.is_empty() {
+            cmd = cmd.paths(&self.path_filter);
+        }
+        Ok(cmd)
+    }
+    
+    /// Replaces the graph with the output of [`App::graph_command`]. A
+    /// failed `git log` (a filter it rejects, such as an invalid pickaxe
+    /// regex) leaves the graph empty and its error to show in its place.
+    fn apply_graph_output(&mut self, output: Result<Output>) -> Result<()> {
+        self.commits.clear();
+        self.graph_lines.clear();
+        self.stashes = self.stash_ids();
         
+        let output = output.map_err(|e| anyhow::anyhow!("cannot run git log: {}", e))?;
         if !output.status.success() {
-            eprintln!("Git command failed: {}", String::from_utf8_lossy(&output.stderr));
-            return Ok(());
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = match oplog::first_line(&stderr) {
+                line if line.is_empty() => process::failure(output.status, ""),
+                line => line,
+            };
+            return Err(anyhow::anyhow!("git log: {}", reason));
         }
+        self.error_message = None;
         
         let git_output = String::from_utf8_lossy(&output.stdout);
         
+        // Commits whose parents were cut off by a shallow clone
+        let shallow = self.shallow_commits();
+        self.shallow_boundaries = shallow.len();
+        self.promisor_remote = partial::promisor_remote(&self.repository);
+        // The index and untracked-files commits git makes for each stash
+        let stash_parts = match self.show_stashes {
+            true => self.stash_parts(),
+            false => HashSet::new(),
+        };
+        
         // Parse the git log output
+        let mut commit_lines = 0;
+        let mut bot_rows = Vec::new();
         for line in git_output.lines() {
+            // Escapes would hide the commit hash from the parser
+            let line = ansi::strip(line);
+            let line = line.as_ref();
             if line.trim().is_empty() {
                 continue;
             }
             
-            if let Some(commit_info) = self.parse_gn_format_line(line) {
+            if let Some(mut commit_info) = self.parse_gn_format_line(line) {
+                let outside_range = commit_info.is_boundary;
+                // Bot commits are dropped here as git log cannot exclude authors
+                let mut hidden = false;
+                let mut is_bot = false;
                 // Extract commit ID from the line for commit lookup
                 let parts: Vec<&str> = line.split_whitespace().collect();
-                if let Some(commit_short) = parts.iter().find(|p| p.len() >= 7 && p.chars().all(|c| c.is_ascii_hexdigit())) {
-                    if let Ok(oid) = self.find_commit_by_short_id(commit_short) {
-                        if let Ok(commit) = self.repository.find_commit(oid) {
-                            let refs = self.extract_refs_from_line(line);
-                            
-                            let git_commit = GitCommit {
-                                id: commit.id().to_string(),
-                                short_id: commit_short.to_string(),
-                                message: commit.message().unwrap_or("").to_string(), // Full message
-                                author: commit.author().name().unwrap_or("Unknown").to_string(),
-                                timestamp: DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_else(|| Utc::now()),
-                                parents: commit.parents().map(|p| p.id().to_string()).collect(),
-                                refs,
-                            };
-                            
-                            self.commits.insert(git_commit.id.clone(), git_commit);
-                        }
+                if let Some(commit_short) = parts.iter().find(|p| p.len() >= 7 && p.chars().all(|c| c.is_ascii_hexdigit()))
+                    && let Ok(oid) = self.resolve_short_id(commit_short)
+                    && let Some(mut git_commit) = self.commit_metadata(oid)
+                {
+                    git_commit.short_id = commit_short.to_string();
+                    git_commit.refs = self.extract_refs_from_line(line);
+                    
+                    if shallow.contains(&git_commit.id) {
+                        commit_info.is_boundary = true;
+                    }
+                    
+                    commit_info.tree_same = git_commit.tree_same;
+                    commit_info.node = match self.stashes.contains(&git_commit.id) {
+                        true => NodeKind::Stash,
+                        false => node_kind(&git_commit),
+                    };
+                    // Decorated ones stay so hiding never takes a ref off the graph
+                    hidden = !self.shows_author(&git_commit)
+                        || (self.hide_tree_same && git_commit.tree_same && git_commit.refs.is_empty())
+                        || stash_parts.contains(&git_commit.id);
+                    // Decorated commits stay visible so folding never hides a ref
+                    is_bot = git_commit.refs.is_empty()
+                        && self.is_bot(&git_commit.author, &git_commit.email);
+                    if !hidden {
+                        self.commits.insert(git_commit.id.clone(), git_commit);
                     }
                 }
-                self.graph_lines.push(commit_info);
+                // Boundary rows lie outside the requested range, so don't count toward the page
+                if !commit_info.commit_id.is_empty() && !outside_range {
+                    commit_lines += 1;
+                }
+                if !hidden {
+                    self.graph_lines.push(commit_info);
+                    bot_rows.push(is_bot);
+                }
             }
         }
         
-        Ok(())
-    }
-    
-    fn parse_gn_format_line(&self, line: &str) -> Option<GraphLine> {
-        // Parse the gn format: graph + commit_hash - (time) message - author (refs)
-        let mut graph_part = String::new();
-        let mut commit_part = String::new();
-        let mut commit_id = String::new();
-        let mut found_commit = false;
-        
-        for (i, ch) in line.chars().enumerate() {
-            if !found_commit && (ch == '*' || ch.is_ascii_hexdigit()) {
-                // Check if this looks like a commit hash (7+ hex chars)
-                let remaining = &line[i..];
-                if let Some(space_pos) = remaining.find(' ') {
-                    let potential_hash = &remaining[..space_pos];
-                    if potential_hash.len() >= 7 && potential_hash.chars().all(|c| c.is_ascii_hexdigit() || c == '*') {
-                        found_commit = true;
-                        commit_part = line[i..].to_string();
-                        // Extract just the commit hash
-                        if ch.is_ascii_hexdigit() {
-                            commit_id = potential_hash.to_string();
-                        }
-                        break;
+        if self.author_filter != Some(AuthorFilter::Bots) {
+            self.fold_bot_runs(&bot_rows);
+        }
+        self.group_by_change_id();
+        self.mark_author_rows();
+        // git refuses `--reverse` with `--graph`, so the page (the oldest
+        // commits, see `reload_graph_then`) is turned upside down here, edges
+        // mirrored
+        if self.history_order == HistoryOrder::Reverse {
+            self.graph_lines.reverse();
+            for line in &mut self.graph_lines {
+                line.graph_text = line
+                    .graph_text
+                    .chars()
+                    .map(|c| match c {
+                        '/' => '\\',
+                        '\\' => '/',
+                        c => c,
+                    })
+                    .collect();
+            }
+            // A `_` runs along the bottom of its row: upside down, that is
+            // the bottom of the row now above it, where there is room
+            for row in 1..self.graph_lines.len() {
+                let columns: Vec<usize> = self.graph_lines[row].graph_text.match_indices('_').map(|(column, _)| column).collect();
+                for column in columns {
+                    self.graph_lines[row].graph_text.replace_range(column..column + 1, " ");
+                    let above = &mut self.graph_lines[row - 1].graph_text;
+                    if above.as_bytes().get(column) == Some(&b' ') {
+                        above.replace_range(column..column + 1, "_");
                     }
                 }
             }
-            if !found_commit {
-                graph_part.push(ch);
-            }
         }
         
-        // If no commit found, this might be a graph-only line
-        if !found_commit {
-            graph_part = line.to_string();
+        let lanes = lanes::assign(self.graph_lines.iter().map(|line| line.graph_text.as_str()));
+        for (line, lanes) in self.graph_lines.iter_mut().zip(lanes) {
+            line.lanes = lanes;
         }
         
-        Some(GraphLine {
-            graph_text: graph_part,
-            commit_text: commit_part,
-            commit_id,
-            refs_text: String::new(),
-        })
+        // A full page means there may be older history to fetch
+        self.has_more_commits =
+            self.history_scope == HistoryScope::Paged && commit_lines >= self.commit_limit;
+        
+        Ok(())
     }
     
-    fn compute_descendants_fast(&self, base_branch: &str) -> Result<Vec<String>> {
-        let mut descendants = Vec::new();
-        
-        // Get the commit ID of the base branch
-        let base_commit_id = match self.branch_commit_cache.get(base_branch) {
-            Some(id) => id,
-            None => return Ok(descendants),
-        };
-        
-        // Find branches that have the base branch as an ancestor
-        // (i.e., branches that were created FROM the base branch)
-        for branch in &self.branches {
-            if branch.name == base_branch || branch.is_remote {
-                continue; // Skip the base branch itself and remote branches
+    /// Adds the revisions the graph currently shows: the marked branches,
+    /// the branch filter or everything
+    fn history_revs(&mut self, mut cmd: ExternalCommand) -> Result<ExternalCommand> {
+        if let Some(ref range) = self.rev_range {
+            cmd = cmd.rev(range.label());
+        } else if let Some(ref refs) = self.exact_refs {
+            // Manual selection: exactly the marked branches, nothing inferred
+            for r in refs {
+                cmd = cmd.rev(r);
+            }
+        } else if let Some(branch_name) = self.current_branch_filter.clone() {
+            cmd = match self.filter_mode {
+                FilterMode::Only => cmd.rev(&branch_name),
+                FilterMode::WithUpstream => {
+                    cmd = cmd.rev(&branch_name);
+                    if let Some(upstream) = self.upstream_of(&branch_name) {
+                        cmd = cmd.rev(upstream);
+                    }
+                    cmd
+                }
+                FilterMode::UniqueVsDefault => {
+                    cmd = cmd.rev(&branch_name);
+                    if let Some(default) = self.default_branch()
+                        && default != branch_name
+                    {
+                        cmd = cmd.rev(format!("^{}", default));
+                    }
+                    cmd
+                }
+                FilterMode::WithDescendants => self.descendants_log_args(cmd, &branch_name)?,
+            };
+        } else {
+            // `--all` would take in the latest stash on its own
+            if !self.show_stashes {
+                cmd = cmd.arg("--exclude=refs/stash");
+            }
+            cmd = cmd.arg("--all");
+            if self.show_stashes {
+                for id in &self.stashes {
+                    cmd = cmd.rev(id);
+                }
+            }
+        }
+        Ok(cmd)
+    }
+    
+    /// Stash entries from the `refs/stash` reflog, newest first
+    fn stash_ids(&self) -> Vec<String> {
+        let Ok(reflog) = self.repository.reflog("refs/stash") else {
+            return Vec::new();
+        };
+        reflog.iter().map(|entry| entry.id_new().to_string()).collect()
+    }
+    
+    /// Parents of the stash commits other than the commit stashed on
+    fn stash_parts(&self) -> HashSet<String> {
+        self.stashes
+            .iter()
+            .filter_map(|id| self.repository.find_commit(Oid::from_str(id).ok()?).ok())
+            .flat_map(|commit| commit.parent_ids().skip(1).map(|id| id.to_string()).collect::<Vec<_>>())
+            .collect()
+    }
+    
+    /// Collapses runs of consecutive bot commits on the same lane into a
+    /// single row, except runs the user expanded
+    fn fold_bot_runs(&mut self, bot_rows: &[bool]) {
+        let lines = std::mem::take(&mut self.graph_lines);
+        let mut i = 0;
+        while i < lines.len() {
+            let run_end = (i..lines.len())
+                .find(|&j| !bot_rows[j] || lines[j].graph_text != lines[i].graph_text)
+                .unwrap_or(lines.len());
+            if run_end - i < 2 {
+                self.graph_lines.push(lines[i].clone());
+                i += 1;
+                continue;
             }
             
-            if let Some(branch_commit_id) = self.branch_commit_cache.get(&branch.name) {
-                // Check if base_branch is an ancestor of this branch
-                // This means this branch was created FROM the base branch
-                if self.is_ancestor_fast(base_commit_id, branch_commit_id)? {
-                    descendants.push(branch.name.clone());
+            let key = lines[i].commit_id.clone();
+            if self.expanded_folds.contains(&key) {
+                for line in &lines[i..run_end] {
+                    self.graph_lines.push(GraphLine { fold_key: Some(key.clone()), ..line.clone() });
                 }
+            } else {
+                let count = run_end - i;
+                self.graph_lines.push(GraphLine {
+                    commit_text: format!("⤷ {} bot commits", count),
+                    // Still a boundary if the oldest commit of the run is one
+                    is_boundary: lines[run_end - 1].is_boundary,
+                    folded: count,
+                    fold_key: Some(key),
+                    ..lines[i].clone()
+                });
+            }
+            i = run_end;
+        }
+    }
+    
+    /// Counts, for each graph row, the other loaded commits sharing its Change-Id
+    fn group_by_change_id(&mut self) {
+        let mut by_short_id: HashMap<&str, String> = HashMap::new();
+        let mut group_sizes: HashMap<String, usize> = HashMap::new();
+        for commit in self.commits.values() {
+            if let Some(change_id) = trailers::change_id(&commit.message) {
+                *group_sizes.entry(change_id.clone()).or_default() += 1;
+                by_short_id.insert(commit.short_id.as_str(), change_id);
             }
         }
+        for line in &mut self.graph_lines {
+            line.same_change = by_short_id
+                .get(line.commit_id.as_str())
+                .and_then(|change_id| group_sizes.get(change_id))
+                .map_or(0, |size| size - 1);
+        }
+    }
+    
+    /// Commits of the current view sharing `commit`'s Change-Id, excluding itself
+    fn same_change_commits(&self, commit: &GitCommit) -> Vec<&GitCommit> {
+        let Some(change_id) = trailers::change_id(&commit.message) else {
+            return Vec::new();
+        };
+        let mut commits: Vec<&GitCommit> = self.commits
+            .values()
+            .filter(|c| c.id != commit.id && trailers::change_id(&c.message).as_ref() == Some(&change_id))
+            .collect();
+        commits.sort_by_key(|c| std::cmp::Reverse(c.timestamp));
+        commits
+    }
+    
+    /// Graphs every commit with the selected commit's Change-Id across all refs
+    fn show_change(&mut self) {
+        let Some(change_id) = self.get_selected_commit().and_then(|c| trailers::change_id(&c.message)) else {
+            self.show_toast("Commit has no Change-Id", true);
+            return;
+        };
+        self.trailer_filter = Some(Trailer { key: "Change-Id".to_string(), value: change_id });
+        self.set_branch_filter(None);
+    }
+    
+    /// Expands the folded bot run under the selection, or folds the expanded
+    /// run the selection is in
+    fn toggle_fold(&mut self) {
+        let Some(line) = self.graph_lines.get(self.selected_commit) else {
+            return;
+        };
+        let Some(key) = line.fold_key.clone() else {
+            return;
+        };
+        if line.folded > 0 {
+            self.expanded_folds.insert(key.clone());
+        } else {
+            self.expanded_folds.remove(&key);
+        }
+        self.reload_graph_then(AfterGraph::Fold(key));
+    }
+    
+    /// Whether a commit passes the author filter and the hide-bots toggle
+    fn shows_author(&self, commit: &GitCommit) -> bool {
+        let is_bot = self.is_bot(&commit.author, &commit.email);
+        match self.author_filter {
+            Some(AuthorFilter::Bots) => is_bot,
+            _ => !(self.hide_bots && is_bot),
+        }
+    }
+    
+    fn is_bot(&self, name: &str, email: &str) -> bool {
+        let name = name.to_lowercase();
+        let email = email.to_lowercase();
+        self.bot_authors.iter().any(|b| name.contains(b.as_str()) || email.contains(b.as_str()))
+    }
+    
+    /// Commit counts per author email domain over the current view's whole
+    /// history (ignoring the page limit and the author filter), busiest
+    /// first; bot commits are counted separately
+    fn author_domain_stats(&mut self) -> Result<(Vec<(String, usize)>, usize)> {
+        let cmd = self.git_command().arg("log").arg("--format=%aN%x00%aE");
+        let output = self.history_revs(cmd)?.output()?;
+        if !output.status.success() {
+            anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
         
-        Ok(descendants)
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut bots = 0;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let (name, email) = line.split_once('\0').unwrap_or((line, ""));
+            if self.is_bot(name, email) {
+                bots += 1;
+                continue;
+            }
+            let domain = email.rsplit_once('@').map_or("(no domain)", |(_, d)| d);
+            *counts.entry(domain.to_lowercase()).or_default() += 1;
+        }
+        
+        let mut domains: Vec<(String, usize)> = counts.into_iter().collect();
+        domains.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok((domains, bots))
     }
     
-    fn parse_git_log_line(&self, line: &str) -> Option<GraphLine> {
-        // Find where the commit hash starts
+    fn open_deepen_menu(&mut self) {
+        if !self.repository.is_shallow() {
+            self.show_toast("Not a shallow clone: the whole history is here", false);
+            return;
+        }
+        let actions = vec![DeepenAction::By(100), DeepenAction::By(1000), DeepenAction::Ask, DeepenAction::Unshallow];
+        let items = vec![
+            "Deepen by 100 commits".to_string(),
+            "Deepen by 1000 commits".to_string(),
+            "Deepen by…".to_string(),
+            "Fetch the whole history (--unshallow)".to_string(),
+        ];
+        self.menu = Some(Menu {
+            title: format!("Shallow clone ({} boundary commits)", self.shallow_boundaries),
+            items,
+            selected: 0,
+            kind: MenuKind::Deepen(actions),
+        });
+    }
+    
+    /// Fetches `depth` more commits behind the shallow boundary, or the
+    /// rest of the history
+    fn deepen(&mut self, depth: Option<u32>) {
+        if self.task_running() {
+            return;
+        }
+        let cmd = self.git_command().args(["fetch", "--progress"]);
+        let (cmd, label) = match depth {
+            Some(depth) => (cmd.arg(format!("--deepen={}", depth)), format!("Deepening history by {}", depth)),
+            None => (cmd.arg("--unshallow"), "Fetching the whole history".to_string()),
+        };
+        self.start_task(Task::start(label, vec![(String::new(), cmd)]), TaskKind::Deepen);
+    }
+    
+    fn deepened(&mut self, task: &mut Task) {
+        let Some((_, result)) = task.results().pop() else {
+            return;
+        };
+        match result {
+            Ok(output) if output.status.success() => {
+                self.refresh_in_place();
+                let message = match self.repository.is_shallow() {
+                    true => format!("Fetched more history; {} boundary commits left", self.shallow_commits().len()),
+                    false => "Fetched the whole history; the clone is no longer shallow".to_string(),
+                };
+                self.notify_if_slow(task.started, &message);
+                self.show_toast(message, false);
+            }
+            Ok(output) => {
+                let message = format!("git fetch: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+                self.show_toast(message, true);
+            }
+            Err(e) => self.show_toast(format!("Failed to run git fetch: {}", e), true),
+        }
+    }
+    
+    fn shallow_commits(&self) -> HashSet<String> {
+        let shallow_file = watcher::common_dir(self.repository.path()).join("shallow");
+        std::fs::read_to_string(shallow_file)
+            .map(|content| content.lines().map(|l| l.trim().to_string()).collect())
+            .unwrap_or_default()
+    }
+    
+    /// Adds the branch and its descendant branches to a `git log` command
+    fn descendants_log_args(&mut self, mut cmd: ExternalCommand, branch_name: &str) -> Result<ExternalCommand> {
+        // Get descendants from cache or compute on-demand
+        let descendant_branches = if let Some(cached) = self.descendant_cache.get(branch_name) {
+            cached.clone()
+        } else {
+            let descendants = self.compute_descendants_fast(branch_name)?;
+            self.descendant_cache.insert(branch_name.to_string(), descendants.clone());
+            self.cache_dirty = true;
+            descendants
+        };
+        
+        // For the default branch or branches with no descendants, don't exclude
+        // refs as it might exclude all commits
+        let is_default = self.default_branch().as_deref() == Some(branch_name);
+        if !is_default && !descendant_branches.is_empty() {
+            for excluded in self.excluded_refs() {
+                cmd = cmd.rev(format!("^{}", excluded));
+            }
+        }
+        
+        // Add the base branch
+        cmd = cmd.rev(branch_name);
+        
+        // Add all descendant branches
+        for descendant in &descendant_branches {
+            cmd = cmd.rev(descendant);
+        }
+        
+        Ok(cmd)
+    }
+    
+    /// Full names of refs matching the `[refs]` exclude patterns (none while toggled off)
+    fn excluded_refs(&self) -> Vec<String> {
+        if !self.ref_filter_enabled || self.ref_filter.is_empty() {
+            return Vec::new();
+        }
+        let Ok(references) = self.repository.references() else {
+            return Vec::new();
+        };
+        references
+            .flatten()
+            .filter_map(|r| r.name().map(str::to_string))
+            .filter(|name| self.ref_filter.excludes(name))
+            .collect()
+    }
+    
+    /// Upstream of a local branch, e.g. `origin/main`
+    fn upstream_of(&self, branch_name: &str) -> Option<String> {
+        self.repository
+            .find_branch(branch_name, BranchType::Local)
+            .ok()?
+            .upstream()
+            .ok()?
+            .name()
+            .ok()
+            .flatten()
+            .map(str::to_string)
+    }
+    
+    /// The repository's default branch: what `origin/HEAD` points to, else
+    /// a local `main` or `master`
+    fn default_branch(&self) -> Option<String> {
+        if let Ok(reference) = self.repository.find_reference("refs/remotes/origin/HEAD")
+            && let Some(target) = reference.symbolic_target()
+        {
+            return Some(target.trim_start_matches("refs/remotes/").to_string());
+        }
+        ["main", "master"]
+            .into_iter()
+            .find(|name| self.repository.find_branch(name, BranchType::Local).is_ok())
+            .map(str::to_string)
+    }
+    
+    fn parse_gn_format_line(&self, line: &str) -> Option<GraphLine> {
+        // Parse the gn format: graph + commit_hash - (time) message - author (refs)
         let mut graph_part = String::new();
         let mut commit_part = String::new();
+        let mut commit_id = String::new();
         let mut found_commit = false;
         
-        let chars: Vec<char> = line.chars().collect();
-        let mut i = 0;
-        
-        // Extract graph part (everything before the commit hash)
-        while i < chars.len() {
-            let ch = chars[i];
-            if ch.is_ascii_hexdigit() && i + 6 < chars.len() {
+        for (i, ch) in line.char_indices() {
+            if !found_commit && (ch == '*' || ch.is_ascii_hexdigit()) {
                 // Check if this looks like a commit hash (7+ hex chars)
-                let mut is_commit_hash = true;
-                let mut hash_len = 0;
-                for j in i..std::cmp::min(i + 10, chars.len()) {
-                    if chars[j].is_ascii_hexdigit() {
-                        hash_len += 1;
-                    } else if chars[j] == ' ' && hash_len >= 7 {
-                        break;
-                    } else {
-                        is_commit_hash = false;
+                let remaining = &line[i..];
+                if let Some(space_pos) = remaining.find(' ') {
+                    let potential_hash = &remaining[..space_pos];
+                    if potential_hash.len() >= 7 && potential_hash.chars().all(|c| c.is_ascii_hexdigit() || c == '*') {
+                        found_commit = true;
+                        commit_part = line[i..].to_string();
+                        // Extract just the commit hash
+                        if ch.is_ascii_hexdigit() {
+                            commit_id = potential_hash.to_string();
+                        }
                         break;
                     }
                 }
-                
-                if is_commit_hash && hash_len >= 7 {
-                    // Found commit hash, everything from here is commit part
-                    commit_part = chars[i..].iter().collect::<String>();
-                    found_commit = true;
-                    break;
-                }
             }
-            graph_part.push(ch);
-            i += 1;
+            if !found_commit {
+                graph_part.push(ch);
+            }
         }
         
+        // If no commit found, this might be a graph-only line
         if !found_commit {
-            return None;
+            graph_part = line.to_string();
         }
         
-        // Extract refs if any
-        let refs_text = if commit_part.contains('(') && commit_part.contains(')') {
-            let start = commit_part.find('(').unwrap();
-            let end = commit_part.rfind(')').unwrap();
-            commit_part[start..=end].to_string()
-        } else {
-            String::new()
-        };
-        
-        // Extract commit ID for lookup
-        let commit_id = commit_part.split_whitespace().next().unwrap_or("").to_string();
+        // `git log --boundary` draws excluded-parent commits with `o` instead of `*`
+        let is_boundary = found_commit && graph_part.contains('o');
         
         Some(GraphLine {
-            commit_id,
             graph_text: graph_part,
+            lanes: Vec::new(),
             commit_text: commit_part,
-            refs_text,
+            commit_id,
+            is_boundary,
+            folded: 0,
+            fold_key: None,
+            same_change: 0,
+            tree_same: false,
+            node: NodeKind::Commit,
+            shortstat: None,
         })
     }
     
-    fn colorize_graph_text(&self, graph_text: &str) -> Vec<Span<'static>> {
+    fn compute_descendants_fast(&self, base_branch: &str) -> Result<Vec<String>> {
+        let mut descendants = Vec::new();
+        
+        // Get the commit ID of the base branch
+        let base_commit_id = match self.branch_commit_cache.get(base_branch) {
+            Some(id) => id,
+            None => return Ok(descendants),
+        };
+        
+        // Find branches that have the base branch as an ancestor
+        // (i.e., branches that were created FROM the base branch)
+        for branch in &self.branches {
+            if branch.name == base_branch || branch.is_remote {
+                continue; // Skip the base branch itself and remote branches
+            }
+            
+            // Check if base_branch is an ancestor of this branch
+            // This means this branch was created FROM the base branch
+            if self.is_ancestor_fast(base_commit_id, &branch.commit_id)? {
+                descendants.push(branch.name.clone());
+            }
+        }
+        
+        Ok(descendants)
+    }
+    
+    /// One graph row as shown in the graph panel (and by `--print`)
+    fn render_graph_line(&self, line: &GraphLine) -> Line<'static> {
+        // Create colored spans for graph and commit text
+        let mut spans = Vec::new();
+        
+        // Add colored graph part
+        let node = if line.is_boundary { NodeKind::Boundary } else { line.node };
+        spans.extend(self.colorize_graph_text(&line.graph_text, &line.lanes, node));
+        
+        // Add colored commit part
+        let graph_spans = spans.len();
+        if line.folded > 0 || line.tree_same {
+            spans.push(Span::styled(
+                line.commit_text.clone(),
+                Style::default().fg(self.theme.inactive_border).add_modifier(Modifier::ITALIC),
+            ));
+        } else {
+            spans.extend(self.colorize_commit_text(&line.commit_text));
+        }
+        
+        let bisect_mark = self.bisect.as_ref().filter(|_| line.folded == 0).and_then(|b| b.mark(&line.commit_id));
+        if line.folded == 0
+            && !line.commit_id.is_empty()
+            && self.detached_head.as_ref().is_some_and(|id| id.starts_with(&line.commit_id))
+        {
+            // While bisecting, the detached HEAD is the commit being tested
+            let label = match self.bisect {
+                Some(ref state) if state.culprit.is_none() && bisect_mark.is_none() => "  ◀ bisect: testing",
+                _ => "  ◀ detached HEAD",
+            };
+            spans.push(Span::styled(
+                label,
+                Style::default().fg(self.theme.ref_head).add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            ));
+        }
+        
+        match bisect_mark {
+            Some("first bad commit") => spans.push(Span::styled(
+                "  ◀ first bad commit",
+                Style::default().fg(self.theme.error).add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            )),
+            Some(mark) => {
+                let color = match mark {
+                    "bad" => self.theme.error,
+                    "good" => self.theme.branch_local,
+                    _ => self.theme.inactive_border,
+                };
+                spans.push(Span::styled(format!("  bisect: {}", mark), Style::default().fg(color)));
+            }
+            None => {}
+        }
+        
+        if line.node == NodeKind::Stash
+            && let Some(index) = self.stashes.iter().position(|id| id.starts_with(&line.commit_id))
+        {
+            spans.push(Span::styled(
+                format!("  stash@{{{}}}", index),
+                Style::default().fg(self.theme.ref_stash).add_modifier(Modifier::BOLD),
+            ));
+        }
+        
+        if line.folded == 0
+            && !line.commit_id.is_empty()
+            && self.pins.iter().any(|pin| pin.id.starts_with(&line.commit_id))
+        {
+            spans.push(Span::styled(
+                format!("  {} pinned", self.glyphs.pin),
+                Style::default().fg(self.theme.branch_marked).add_modifier(Modifier::BOLD),
+            ));
+        }
+        
+        if line.tree_same && line.folded == 0 {
+            spans.push(Span::styled(
+                "  ∅ no changes",
+                Style::default().fg(self.theme.inactive_border),
+            ));
+        }
+        
+        if let Some((added, removed)) = line.shortstat.filter(|_| self.show_shortstat) {
+            spans.push(Span::styled(format!("  +{}", added), Style::default().fg(self.theme.diff_added)));
+            spans.push(Span::styled(format!(" −{}", removed), Style::default().fg(self.theme.diff_removed)));
+        }
+        
+        if line.same_change > 0 {
+            spans.push(Span::styled(
+                format!("  ⧉{}", line.same_change + 1),
+                Style::default().fg(self.theme.ref_tag),
+            ));
+        }
+        
+        if line.is_boundary {
+            spans.push(Span::styled(
+                "  (parents not shown)",
+                Style::default().fg(self.theme.inactive_border).add_modifier(Modifier::ITALIC),
+            ));
+        }
+        
+        if self.highlight_author.is_some() && !line.commit_id.is_empty() {
+            let patch = match self.author_rows.contains(&line.commit_id) {
+                true => Style::default().add_modifier(Modifier::BOLD),
+                false => Style::default().fg(self.theme.inactive_border).remove_modifier(Modifier::BOLD),
+            };
+            for span in &mut spans[graph_spans..] {
+                span.style = span.style.patch(patch);
+            }
+        }
+        
+        Line::from(spans)
+    }
+    
+    /// Colors the lanes of a row and draws its node (`git log` marks it
+    /// with `*`, or `o` for boundary commits) with the glyph for `node`
+    fn colorize_graph_text(&self, graph_text: &str, lanes: &[Option<usize>], node: NodeKind) -> Vec<Span<'static>> {
         let mut spans = Vec::new();
         let mut current_span = String::new();
         let mut current_color = Color::White;
         
-        for ch in graph_text.chars() {
-            let new_color = match ch {
-                '*' => Color::Red,        // Commit nodes
-                '|' => Color::Green,      // Vertical lines  
-                '/' => Color::Blue,       // Merge lines going up-right
-                '\\' => Color::Cyan,      // Merge lines going down-right
-                '_' => Color::Yellow,     // Horizontal lines
-                '-' => Color::Yellow,     // Horizontal merge lines
-                '+' => Color::Magenta,    // Complex merge points
-                ' ' => Color::White,      // Spaces
-                _ => Color::White,        // Other characters
+        // Graph text is ASCII, so characters are columns
+        for (column, ch) in graph_text.chars().enumerate() {
+            let (ch, new_color) = match ch {
+                '*' | 'o' => (self.glyphs.node(node), match node {
+                    NodeKind::Boundary => Color::DarkGray,
+                    NodeKind::Head => self.theme.ref_head,
+                    NodeKind::Stash => self.theme.ref_stash,
+                    NodeKind::Tagged => self.theme.ref_tag,
+                    NodeKind::Merge => Color::Magenta,
+                    NodeKind::Commit => Color::Red,
+                }),
+                // Not worth a span of their own
+                ' ' => (ch, current_color),
+                _ => (self.glyphs.lane(ch), self.lane_color(lanes.get(column).copied().flatten())),
             };
             
             if new_color != current_color && !current_span.is_empty() {
@@ -452,6 +2207,11 @@ impl App {
         spans
     }
     
+    /// Color of a lane, cycling the theme's palette
+    fn lane_color(&self, lane: Option<usize>) -> Color {
+        self.theme.lanes[lane.unwrap_or_default() % self.theme.lanes.len()]
+    }
+    
     fn colorize_commit_text(&self, commit_text: &str) -> Vec<Span<'static>> {
         let mut spans = Vec::new();
         
@@ -463,6 +2223,9 @@ impl App {
         
         let mut in_refs = false;
         let mut ref_content = String::new();
+        // The subject follows the `(2 hours ago)` group
+        let mut groups_closed = 0;
+        let mut subject_start = false;
         
         for (i, part) in parts.iter().enumerate() {
             if i > 0 {
@@ -471,7 +2234,7 @@ impl App {
             
             // Check if this is a commit hash (7+ hex characters)
             if i == 0 && part.len() >= 7 && part.chars().all(|c| c.is_ascii_hexdigit()) {
-                spans.push(Span::styled(part.to_string(), Style::default().fg(Color::Yellow)));
+                spans.push(Span::styled(part.to_string(), Style::default().fg(self.theme.commit_hash)));
             }
             // Check for references (HEAD, origin, branch names)
             else if part.starts_with('(') {
@@ -481,6 +2244,8 @@ impl App {
                     in_refs = false;
                     spans.push(self.colorize_refs(&ref_content));
                     ref_content.clear();
+                    groups_closed += 1;
+                    subject_start = groups_closed == 1;
                 }
             }
             else if in_refs {
@@ -490,11 +2255,25 @@ impl App {
                     in_refs = false;
                     spans.push(self.colorize_refs(&ref_content));
                     ref_content.clear();
+                    groups_closed += 1;
+                    subject_start = groups_closed == 1;
+                }
+            }
+            else if std::mem::take(&mut subject_start)
+                && let Some(prefix) = conventional::prefix(part)
+            {
+                let mut style = Style::default().fg(conventional_color(prefix.kind, &self.theme));
+                if prefix.breaking {
+                    style = style.add_modifier(Modifier::BOLD);
+                }
+                spans.push(Span::styled(part[..prefix.len].to_string(), style));
+                if prefix.len < part.len() {
+                    spans.push(Span::styled(part[prefix.len..].to_string(), Style::default().fg(self.theme.commit_text)));
                 }
             }
             // Regular commit message
             else {
-                spans.push(Span::styled(part.to_string(), Style::default().fg(Color::White)));
+                spans.push(Span::styled(part.to_string(), Style::default().fg(self.theme.commit_text)));
             }
         }
         
@@ -507,13 +2286,13 @@ impl App {
         
         // Determine color based on ref type
         let color = if inner.contains("HEAD") {
-            Color::Cyan
+            self.theme.ref_head
         } else if inner.contains("origin/") || inner.contains("remote/") {
-            Color::Red
+            self.theme.ref_remote
         } else if inner.contains("tag:") {
-            Color::Yellow
+            self.theme.ref_tag
         } else {
-            Color::Green // Local branches
+            self.theme.ref_local // Local branches
         };
         
         Span::styled(refs_text.to_string(), Style::default().fg(color).add_modifier(Modifier::BOLD))
@@ -521,14 +2300,17 @@ impl App {
     
     fn extract_refs_from_line(&self, line: &str) -> Vec<String> {
         let mut refs = Vec::new();
-        if let Some(start) = line.find('(') {
-            if let Some(end) = line.rfind(')') {
-                let refs_str = &line[start+1..end];
-                for part in refs_str.split(',') {
-                    let part = part.trim();
-                    if part.starts_with("origin/") || !part.contains('/') {
-                        refs.push(part.to_string());
-                    }
+        // The decoration is the trailing "(...)" group; earlier parentheses
+        // belong to the relative date or the subject
+        let line = line.trim_end();
+        if line.ends_with(')')
+            && let Some(start) = line.rfind(" (")
+        {
+            let refs_str = &line[start + 2..line.len() - 1];
+            for part in refs_str.split(',') {
+                let part = part.trim();
+                if part.starts_with("origin/") || !part.contains('/') {
+                    refs.push(part.to_string());
                 }
             }
         }
@@ -536,19 +2318,75 @@ impl App {
     }
 }
 
-fn colorize_diff_line(line: &str) -> Line<'static> {
+/// `3 days ago`, the way git words relative dates
+fn relative_time(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - time).num_seconds();
+    if seconds < 0 {
+        return "in the future".to_string();
+    }
+    let (count, unit) = match seconds {
+        0..90 => (seconds, "second"),
+        90..5_400 => ((seconds + 30) / 60, "minute"),
+        5_400..129_600 => ((seconds + 1_800) / 3_600, "hour"),
+        129_600..1_209_600 => ((seconds + 43_200) / 86_400, "day"),
+        1_209_600..5_184_000 => ((seconds + 302_400) / 604_800, "week"),
+        5_184_000..31_536_000 => ((seconds + 1_296_000) / 2_592_000, "month"),
+        _ => ((seconds + 15_768_000) / 31_536_000, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    format!("{} {}{} ago", count, unit, plural)
+}
+
+/// Lines a commit adds and removes against its first parent (the empty
+/// tree for a root commit), renames detected
+fn shortstat(repo: &Repository, id: Oid) -> Result<(usize, usize), git2::Error> {
+    let commit = repo.find_commit(id)?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?.tree()?),
+    };
+    let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    diff.find_similar(None)?;
+    let stats = diff.stats()?;
+    Ok((stats.insertions(), stats.deletions()))
+}
+
+/// `Name <email>`, telling authors apart for highlighting
+fn author_key(commit: &GitCommit) -> String {
+    format!("{} <{}>", commit.author, commit.email)
+}
+
+/// Abbreviated commit id for messages
+fn short_id(id: &str) -> String {
+    id.chars().take(7).collect()
+}
+
+/// Node kind of a loaded commit, from its decorations and parents
+fn node_kind(commit: &GitCommit) -> NodeKind {
+    if commit.refs.iter().any(|r| r == "HEAD" || r.starts_with("HEAD ->")) {
+        NodeKind::Head
+    } else if commit.refs.iter().any(|r| r.starts_with("tag: ")) {
+        NodeKind::Tagged
+    } else if commit.parents.len() > 1 {
+        NodeKind::Merge
+    } else {
+        NodeKind::Commit
+    }
+}
+
+fn colorize_diff_line(line: &str, theme: &Theme) -> Line<'static> {
     if line.starts_with("+++") || line.starts_with("---") {
         // File headers
         Line::from(Span::styled(line.to_string(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)))
     } else if line.starts_with("@@") {
         // Hunk headers
-        Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)))
+        Line::from(Span::styled(line.to_string(), Style::default().fg(theme.diff_hunk).add_modifier(Modifier::BOLD)))
     } else if line.starts_with("+") {
         // Added lines
-        Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Green)))
+        Line::from(Span::styled(line.to_string(), Style::default().fg(theme.diff_added)))
     } else if line.starts_with("-") {
         // Removed lines
-        Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Red)))
+        Line::from(Span::styled(line.to_string(), Style::default().fg(theme.diff_removed)))
     } else if line.starts_with("commit ") {
         // Commit hash
         Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
@@ -557,17 +2395,131 @@ fn colorize_diff_line(line: &str) -> Line<'static> {
         Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Blue)))
     } else if line.starts_with("diff --git") {
         // Diff headers
-        Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)))
+        Line::from(Span::styled(line.to_string(), Style::default().fg(theme.diff_header).add_modifier(Modifier::BOLD)))
     } else if line.starts_with("index ") {
         // Index line
         Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Gray)))
+    } else if line.starts_with("LFS object") || line.starts_with("Binary file ") || line.starts_with("Submodule ") {
+        // Summaries of Git LFS pointers, binary files and submodule moves
+        Line::from(Span::styled(line.to_string(), Style::default().fg(theme.diff_hunk)))
+    } else if line.starts_with("  > ") || line.starts_with("  < ") {
+        // Commits a submodule gained or lost
+        let color = if line.starts_with("  >") { theme.diff_added } else { theme.diff_removed };
+        Line::from(Span::styled(line.to_string(), Style::default().fg(color)))
     } else {
         // Normal text
         Line::from(Span::styled(line.to_string(), Style::default().fg(Color::White)))
     }
 }
 
+/// An added or removed diff line with the pickaxe hits standing out
+fn highlight_hits(line: &str, hits: &[Range<usize>], theme: &Theme) -> Line<'static> {
+    let color = if line.starts_with('+') { theme.diff_added } else { theme.diff_removed };
+    let style = Style::default().fg(color);
+    let mut spans = Vec::new();
+    let mut end = 0;
+    for hit in hits {
+        spans.push(Span::styled(line[end..hit.start].to_string(), style));
+        spans.push(Span::styled(line[hit.clone()].to_string(), style.add_modifier(Modifier::REVERSED | Modifier::BOLD)));
+        end = hit.end;
+    }
+    spans.push(Span::styled(line[end..].to_string(), style));
+    Line::from(spans)
+}
+
 impl App {
+    /// Fingerprint of every ref and its target; branch relationships stay
+    /// valid while it is unchanged
+    fn ref_tips_fingerprint(&self) -> String {
+        let mut tips: Vec<(String, String)> = self.repository
+            .references()
+            .map(|refs| {
+                refs.flatten()
+                    .filter_map(|r| Some((r.name()?.to_string(), r.target()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        tips.sort();
+        cache::fingerprint(tips.iter().flat_map(|(name, target)| [name.as_bytes(), target.as_bytes()]))
+    }
+    
+    /// Directory the on-disk cache is keyed by, shared by all worktrees
+    fn cache_key_dir(&self) -> PathBuf {
+        watcher::common_dir(self.repository.path())
+    }
+    
+    fn load_caches(&mut self) {
+        let Some(data) = Snapshot::<CachedData>::load(&self.cache_key_dir()) else {
+            return;
+        };
+        if data.ref_tips == self.ref_tips {
+            self.descendant_cache.extend(data.descendants);
+            self.full_id_cache.extend(data.full_ids);
+        }
+    }
+    
+    fn save_caches(&self) {
+        if !self.persist_cache || !self.cache_dirty {
+            return;
+        }
+        let data = CachedData {
+            ref_tips: self.ref_tips.clone(),
+            descendants: self.descendant_cache.to_vec(),
+            full_ids: self.full_id_cache.to_vec(),
+        };
+        // A cache that cannot be written only costs the next start its warmth
+        let _ = Snapshot::save(&self.cache_key_dir(), data);
+    }
+    
+    /// Metadata of a commit, from the cache or the object database. `short_id`
+    /// and `refs` depend on the graph row and are left for the caller to fill.
+    fn commit_metadata(&mut self, oid: Oid) -> Option<GitCommit> {
+        let key = oid.to_string();
+        if let Some(commit) = self.commit_cache.get(&key) {
+            return Some(commit.clone());
+        }
+        let commit = self.repository.find_commit(oid).ok()?;
+        let author = self.author_of(&commit);
+        let git_commit = GitCommit {
+            id: key.clone(),
+            short_id: String::new(),
+            message: commit.message().unwrap_or("").to_string(), // Full message
+            author: author.name().unwrap_or("Unknown").to_string(),
+            email: author.email().unwrap_or("").to_string(),
+            timestamp: DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_else(Utc::now),
+            // parent_ids() does not need the parent objects, which a shallow clone lacks
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+            refs: Vec::new(),
+            tree_same: match commit.parent_count() {
+                0 => commit.tree().is_ok_and(|tree| tree.is_empty()),
+                _ => commit.parent(0).is_ok_and(|parent| parent.tree_id() == commit.tree_id()),
+            },
+        };
+        self.commit_cache.insert(key, git_commit.clone());
+        Some(git_commit)
+    }
+    
+    /// A commit's author as the mailmap has it
+    fn author_of(&self, commit: &git2::Commit<'_>) -> git2::Signature<'static> {
+        self.mailmap
+            .as_ref()
+            .and_then(|mailmap| commit.author_with_mailmap(mailmap).ok())
+            .unwrap_or_else(|| commit.author().to_owned())
+    }
+    
+    /// Full id of an abbreviated commit id from `git log`, through the cache
+    fn resolve_short_id(&mut self, short_id: &str) -> Result<Oid> {
+        if let Some(id) = self.full_id_cache.get(short_id)
+            && let Ok(oid) = Oid::from_str(id)
+        {
+            return Ok(oid);
+        }
+        let oid = self.find_commit_by_short_id(short_id)?;
+        self.full_id_cache.insert(short_id.to_string(), oid.to_string());
+        self.cache_dirty = true;
+        Ok(oid)
+    }
+    
     fn find_commit_by_short_id(&self, short_id: &str) -> Result<Oid> {
         // Try to expand the short ID using git2's built-in functionality
         match self.repository.revparse_single(short_id) {
@@ -575,10 +2527,10 @@ impl App {
                 if let Some(commit) = obj.as_commit() {
                     return Ok(commit.id());
                 }
-                if let Some(tag) = obj.as_tag() {
-                    if let Some(commit) = tag.target()?.as_commit() {
-                        return Ok(commit.id());
-                    }
+                if let Some(tag) = obj.as_tag()
+                    && let Some(commit) = tag.target()?.as_commit()
+                {
+                    return Ok(commit.id());
                 }
                 return Ok(obj.id());
             }
@@ -588,12 +2540,10 @@ impl App {
                 revwalk.push_head().ok(); // Don't fail if HEAD doesn't exist
                 revwalk.set_sorting(git2::Sort::TIME)?;
                 
-                for commit_id in revwalk.take(1000) { // Limit search to recent 1000 commits
-                    if let Ok(commit_id) = commit_id {
-                        let commit_str = commit_id.to_string();
-                        if commit_str.starts_with(short_id) {
-                            return Ok(commit_id);
-                        }
+                for commit_id in revwalk.take(1000).flatten() { // Limit search to recent 1000 commits
+                    let commit_str = commit_id.to_string();
+                    if commit_str.starts_with(short_id) {
+                        return Ok(commit_id);
                     }
                 }
             }
@@ -604,26 +2554,25 @@ impl App {
     
     fn set_branch_filter(&mut self, branch_name: Option<String>) {
         self.current_branch_filter = branch_name;
-        self.loading = true;
-        self.error_message = None;
-        match self.load_graph() {
-            Ok(_) => {
-                self.loading = false;
-            }
-            Err(e) => {
-                self.loading = false;
-                self.error_message = Some(format!("Failed to load graph: {}", e));
-            }
-        }
-        self.commit_list_state.select(Some(0));
-        self.selected_commit = 0;
+        self.exact_refs = None;
+        self.rev_range = None;
+        self.commit_limit = COMMIT_PAGE_SIZE;
+        self.reload_graph();
     }
     
     fn get_included_branches(&self) -> Vec<String> {
-        if let Some(ref base_branch) = self.current_branch_filter {
+        if let Some(ref refs) = self.exact_refs {
+            refs.clone()
+        } else if let Some(ref base_branch) = self.current_branch_filter {
             let mut included = vec![base_branch.clone()];
-            if let Some(descendants) = self.descendant_cache.get(base_branch) {
-                included.extend(descendants.clone());
+            match self.filter_mode {
+                FilterMode::WithDescendants => {
+                    if let Some(descendants) = self.descendant_cache.peek(base_branch) {
+                        included.extend(descendants.clone());
+                    }
+                }
+                FilterMode::WithUpstream => included.extend(self.upstream_of(base_branch)),
+                FilterMode::Only | FilterMode::UniqueVsDefault => {}
             }
             included
         } else {
@@ -631,43 +2580,76 @@ impl App {
         }
     }
     
+    /// Reloads the branches, and the graph in the background keeping the
+    /// selected commit selected
     fn refresh_data(&mut self) -> Result<()> {
-        self.loading = true;
         self.error_message = None;
+        // Cached relationships are stale once a branch tip moved
+        let ref_tips = self.ref_tips_fingerprint();
+        if ref_tips != self.ref_tips {
+            self.descendant_cache.clear();
+            self.full_id_cache.clear();
+            self.ref_tips = ref_tips;
+        }
         
-        match self.load_branches() {
-            Ok(_) => {
-                match self.load_graph() {
-                    Ok(_) => {
-                        self.loading = false;
-                    }
-                    Err(e) => {
-                        self.loading = false;
-                        self.error_message = Some(format!("Failed to load graph: {}", e));
-                    }
-                }
-            }
-            Err(e) => {
-                self.loading = false;
-                self.error_message = Some(format!("Failed to load branches: {}", e));
-            }
+        self.bisect = bisect::state(&self.repository);
+        match oplog::timed("git2: list branches", || self.load_branches()) {
+            Ok(_) => self.reload_graph_in_place(),
+            Err(e) => self.error_message = Some(format!("Failed to load branches: {}", e)),
         }
+        self.reload_conflicts();
+        self.update_commit_graph();
+        self.reload_dirty();
         
         Ok(())
     }
     
-    fn next_branch(&mut self) {
-        if !self.branches.is_empty() {
-            self.selected_branch = (self.selected_branch + 1) % self.branches.len();
-            self.branch_list_state.select(Some(self.selected_branch));
+    /// Reloads after external changes to HEAD, refs or the index (another
+    /// process committing, a background fetch)
+    fn poll_repo_changes(&mut self) {
+        if !self.repo_watcher.as_mut().is_some_and(|w| w.poll()) {
+            return;
         }
+        self.refresh_in_place();
     }
     
-    fn previous_branch(&mut self) {
-        if !self.branches.is_empty() {
-            self.selected_branch = if self.selected_branch == 0 {
-                self.branches.len() - 1
-            } else {
+    /// Refreshes after refs moved, keeping the selected branch and commit
+    /// even when new commits push them down, and flashing the branches whose
+    /// tip moved
+    fn refresh_in_place(&mut self) {
+        let tips: HashMap<String, String> = self.branches
+            .iter()
+            .map(|b| (b.name.clone(), b.commit_id.clone()))
+            .collect();
+        let selected_branch = self.selected_git_branch().map(|b| b.name.clone());
+        
+        let _ = self.refresh_data();
+        
+        let row = selected_branch.and_then(|name| self.branch_row(&name));
+        self.select_branch_row(row.unwrap_or(self.selected_branch));
+        
+        let moved: HashSet<String> = self.branches
+            .iter()
+            .filter(|b| tips.get(&b.name) != Some(&b.commit_id))
+            .map(|b| b.name.clone())
+            .collect();
+        if !moved.is_empty() {
+            self.branch_flash = Some(BranchFlash { names: moved, started: Instant::now() });
+        }
+    }
+    
+    fn next_branch(&mut self) {
+        if !self.branch_rows.is_empty() {
+            self.selected_branch = (self.selected_branch + 1) % self.branch_rows.len();
+            self.branch_list_state.select(Some(self.selected_branch));
+        }
+    }
+    
+    fn previous_branch(&mut self) {
+        if !self.branch_rows.is_empty() {
+            self.selected_branch = if self.selected_branch == 0 {
+                self.branch_rows.len() - 1
+            } else {
                 self.selected_branch - 1
             };
             self.branch_list_state.select(Some(self.selected_branch));
@@ -676,11 +2658,29 @@ impl App {
     
     fn next_commit(&mut self) {
         if !self.graph_lines.is_empty() {
+            if self.selected_commit + 1 == self.graph_lines.len() && self.has_more_commits {
+                // Select the "loading more" row; the next page is fetched after it is drawn
+                self.load_more_pending = true;
+                self.commit_list_state.select(Some(self.graph_lines.len()));
+                return;
+            }
             self.selected_commit = (self.selected_commit + 1) % self.graph_lines.len();
             self.commit_list_state.select(Some(self.selected_commit));
         }
     }
     
+    /// Fetches the next page of history, keeping the current position
+    fn load_more_commits(&mut self) {
+        self.load_more_pending = false;
+        // Moving past the end again while the page loads waits for it
+        if self.tasks.iter().any(|running| running.kind.is_graph()) {
+            return;
+        }
+        self.commit_limit += COMMIT_PAGE_SIZE;
+        let previous_len = self.graph_lines.len();
+        self.reload_graph_then(AfterGraph::NextPage { previous_len });
+    }
+    
     fn previous_commit(&mut self) {
         if !self.graph_lines.is_empty() {
             self.selected_commit = if self.selected_commit == 0 {
@@ -695,367 +2695,6544 @@ impl App {
     fn get_selected_commit(&self) -> Option<&GitCommit> {
         if let Some(line) = self.graph_lines.get(self.selected_commit) {
             // First try to find by exact commit_id match
-            if !line.commit_id.is_empty() {
-                if let Some(commit) = self.commits.values().find(|c| 
+            if !line.commit_id.is_empty()
+                && let Some(commit) = self.commits.values().find(|c| 
                     c.short_id == line.commit_id || 
                     c.id.starts_with(&line.commit_id) || 
                     c.id == line.commit_id
-                ) {
-                    return Some(commit);
-                }
+                )
+            {
+                return Some(commit);
             }
             
             // Fallback: try to extract commit hash from commit_text
             let parts: Vec<&str> = line.commit_text.split_whitespace().collect();
-            if let Some(potential_hash) = parts.first() {
-                if potential_hash.len() >= 7 && potential_hash.chars().all(|c| c.is_ascii_hexdigit()) {
-                    if let Some(commit) = self.commits.values().find(|c| 
-                        c.short_id == *potential_hash || 
-                        c.id.starts_with(potential_hash)
-                    ) {
-                        return Some(commit);
-                    }
-                }
+            if let Some(potential_hash) = parts.first()
+                && potential_hash.len() >= 7
+                && potential_hash.chars().all(|c| c.is_ascii_hexdigit())
+                && let Some(commit) = self.commits.values().find(|c| 
+                    c.short_id == *potential_hash || 
+                    c.id.starts_with(potential_hash)
+                )
+            {
+                return Some(commit);
             }
         }
         None
     }
     
+    /// Asks which relationship mode to use, then filters by the selected
+    /// branch. On a group header, folds or unfolds the group instead.
     fn select_current_branch(&mut self) {
-        if let Some(branch) = self.branches.get(self.selected_branch) {
-            let branch_name = if branch.is_remote {
-                branch.name.clone()
-            } else {
-                branch.name.clone()
-            };
-            self.set_branch_filter(Some(branch_name));
+        if let Some(BranchRow::Group { key, .. }) = self.branch_rows.get(self.selected_branch) {
+            self.toggle_branch_group(key.clone());
+            return;
+        }
+        if let Some(branch) = self.selected_git_branch() {
+            let branch_name = branch.name.clone();
+            self.menu = Some(Menu {
+                title: format!("Filter by {}", branch_name),
+                items: FilterMode::ALL.iter().map(|m| m.label().to_string()).collect(),
+                selected: FilterMode::ALL.iter().position(|m| *m == self.filter_mode).unwrap_or(0),
+                kind: MenuKind::FilterMode(branch_name),
+            });
         }
     }
     
-    fn clear_branch_filter(&mut self) {
-        self.set_branch_filter(None);
-    }
-    
-    fn load_commit_diff(&mut self) {
-        if self.graph_lines.is_empty() || self.selected_commit >= self.graph_lines.len() {
+    fn choose_menu_entry(&mut self, index: usize) {
+        let Some(menu) = self.menu.take() else {
             return;
-        }
-        
-        let selected_line = &self.graph_lines[self.selected_commit];
-        let commit_id = &selected_line.commit_id;
-        
-        if commit_id.is_empty() {
+        };
+        if index >= menu.items.len() {
+            self.menu = Some(menu);
             return;
         }
-        
-        // Run git show command to get diff (no color to avoid ANSI codes)
-        let output = std::process::Command::new("git")
-            .args(&["show", "--no-color", "--format=fuller", "--stat", "-p", commit_id])
-            .current_dir(self.repository.workdir().unwrap_or_else(|| self.repository.path()))
-            .output();
-            
-        match output {
-            Ok(output) => {
-                if output.status.success() {
-                    self.current_diff = Some(String::from_utf8_lossy(&output.stdout).to_string());
-                    self.show_diff = true;
-                    self.diff_scroll_offset = 0;
-                } else {
-                    self.current_diff = Some(format!("Error getting diff: {}", 
-                        String::from_utf8_lossy(&output.stderr)));
-                    self.show_diff = true;
-                    self.diff_scroll_offset = 0;
+        match menu.kind {
+            MenuKind::FilterMode(branch_name) => {
+                self.filter_mode = FilterMode::ALL[index];
+                self.set_branch_filter(Some(branch_name));
+            }
+            MenuKind::AuthorFilter(mut filters) => self.set_author_filter(filters.swap_remove(index)),
+            MenuKind::Trailers(mut actions) => match actions.swap_remove(index) {
+                TrailerAction::Filter(trailer) => {
+                    self.trailer_filter = Some(trailer);
+                    self.commit_limit = COMMIT_PAGE_SIZE;
+                    self.reload_graph();
+                }
+                TrailerAction::Open(url) => self.open_url(&url),
+            },
+            MenuKind::RefAtCommit(commit_id) => match index {
+                0 => self.open_prompt(format!("New branch at {}", commit_id), "", PromptKind::NewBranch(commit_id)),
+                1 => self.open_prompt(format!("New tag at {}", commit_id), "", PromptKind::NewTag(commit_id)),
+                2 => self.open_prompt(format!("New annotated tag at {}", commit_id), "", PromptKind::NewAnnotatedTag(commit_id)),
+                4 => self.checkout(&commit_id, true),
+                5 => {
+                    let mut message = format!(
+                        "Rebase {} interactively onto {}?\n\nThe editor opens on the todo list of the commits after it.",
+                        self.head_label(),
+                        commit_id
+                    );
+                    let warning = self.check_rewrite(&commit_id);
+                    if let Some(ref warning) = warning {
+                        message.push_str("\n\n");
+                        message.push_str(warning);
+                    }
+                    self.confirm(PendingConfirm {
+                        message,
+                        action: ConfirmedAction::Rebase(commit_id),
+                        rewrites_published: warning.is_some(),
+                    });
+                }
+                _ => {
+                    self.menu = Some(Menu {
+                        title: format!("Reset {} to {}", self.head_label(), commit_id),
+                        items: RESET_MODES.iter().map(|(_, label)| label.to_string()).collect(),
+                        selected: 1,
+                        kind: MenuKind::ResetMode(commit_id),
+                    });
                 }
+            },
+            MenuKind::Identity(mut identities) => {
+                self.identity = identities.swap_remove(index);
+                let message = match self.identity {
+                    Some(ref identity) => format!("Committing as {} <{}>", identity.name, identity.email),
+                    None => "Committing with the repository's identity".to_string(),
+                };
+                self.show_toast(message, false);
             }
-            Err(e) => {
-                self.current_diff = Some(format!("Failed to run git show: {}", e));
-                self.show_diff = true;
-                self.diff_scroll_offset = 0;
+            MenuKind::Patches(mut actions) => match actions.swap_remove(index) {
+                PatchAction::Write(ids) => {
+                    let title = format!("Write {} patch(es) to directory", ids.len());
+                    self.open_prompt(title, "patches", PromptKind::PatchDir(ids));
+                }
+                PatchAction::Copy(id) => self.copy_patch(id),
+            },
+            MenuKind::Range(mut actions) => match actions.swap_remove(index) {
+                RangeAction::Diff => self.show_range_diff(),
+                RangeAction::Write(ids) => {
+                    let title = format!("Write {} patch(es) to directory", ids.len());
+                    self.open_prompt(title, "patches", PromptKind::PatchDir(ids));
+                }
+                RangeAction::Edit => {
+                    let current = self.rev_range.as_ref().map(RevRange::label).unwrap_or_default();
+                    self.open_prompt("Revision range (A..B or A...B, empty clears)".to_string(), &current, PromptKind::RevisionRange);
+                }
+                RangeAction::Clear => self.clear_branch_filter(),
+            },
+            MenuKind::Deepen(mut actions) => match actions.swap_remove(index) {
+                DeepenAction::By(depth) => self.deepen(Some(depth)),
+                DeepenAction::Ask => self.open_prompt("Deepen the history by (commits)".to_string(), "", PromptKind::Deepen),
+                DeepenAction::Unshallow => self.deepen(None),
+            },
+            MenuKind::OpenFile { mut files, snapshot } => self.open_file(files.swap_remove(index), None, snapshot),
+            MenuKind::LfsDiff(mut files) => self.fetch_lfs_content(files.swap_remove(index)),
+            MenuKind::Recover(mut candidates) => {
+                let candidate = candidates.swap_remove(index);
+                let title = format!("Recreate branch at {}", short_id(&candidate.id.to_string()));
+                let name = candidate.branch.unwrap_or_default();
+                self.open_prompt(title, &name, PromptKind::NewBranch(candidate.id.to_string()));
+            }
+            MenuKind::Upstream(branch, mut upstreams) => self.set_upstream(&branch, upstreams.swap_remove(index)),
+            MenuKind::JumpTo(mut ids) => {
+                self.show_logs = true;
+                self.jump_to_commit(&ids.swap_remove(index));
+            }
+            MenuKind::HighlightAuthor(mut authors) => {
+                self.highlight_author = authors.swap_remove(index);
+                self.mark_author_rows();
+            }
+            MenuKind::Changelog(mut sources) => {
+                let source = sources.swap_remove(index);
+                let title = format!("Changelog of {}, {}: file (empty copies it)", source.label, source.grouping.label());
+                self.open_prompt(title, "CHANGES.md", PromptKind::Changelog(source));
+            }
+            MenuKind::Repository(mut paths) => {
+                let path = paths.swap_remove(index);
+                if Some(&path) != self.workdir_canonical().as_ref() {
+                    self.switch_repo = Some(path);
+                }
+            }
+            MenuKind::Interrupted(mut actions) => self.recover_interrupted(actions.swap_remove(index)),
+            MenuKind::Bisect(commit_id, mut actions) => match actions.swap_remove(index) {
+                BisectAction::Mark(term) => self.bisect_mark(term, &commit_id),
+                BisectAction::ShowCulprit => {
+                    if let Some(culprit) = self.bisect.as_ref().and_then(|b| b.culprit) {
+                        self.select_commit_row(&culprit.to_string());
+                    }
+                }
+                BisectAction::Reset => self.bisect_reset(),
+            },
+            MenuKind::ResetMode(commit_id) => {
+                let (mode, _) = RESET_MODES[index];
+                let mut message = format!(
+                    "Reset {} to {} ({})?",
+                    self.head_label(),
+                    commit_id,
+                    mode
+                );
+                if mode == "--hard" {
+                    message.push_str("\n\nUncommitted changes will be lost.");
+                }
+                let warning = self.check_rewrite(&commit_id);
+                if let Some(ref warning) = warning {
+                    message.push_str("\n\n");
+                    message.push_str(warning);
+                }
+                self.confirm(PendingConfirm {
+                    message,
+                    action: ConfirmedAction::Reset { mode, commit_id },
+                    rewrites_published: warning.is_some(),
+                });
             }
         }
     }
     
-    fn close_diff(&mut self) {
-        self.show_diff = false;
-        self.current_diff = None;
-        self.diff_scroll_offset = 0;
-    }
-
-    fn get_max_diff_scroll(&self, visible_height: u16) -> u16 {
-        if let Some(ref diff_content) = self.current_diff {
-            let total_lines = diff_content.lines().count();
-            let content_height = (visible_height.saturating_sub(2)) as usize; // Account for borders
-            total_lines.saturating_sub(content_height) as u16
-        } else {
-            0
+    /// Authors of the loaded commits, most commits first, to pick one whose
+    /// commits are highlighted
+    fn open_highlight_menu(&mut self) {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for commit in self.commits.values() {
+            *counts.entry(author_key(commit)).or_default() += 1;
+        }
+        let mut authors: Vec<(String, usize)> = counts.into_iter().collect();
+        authors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        
+        let mut items = vec!["No highlight".to_string()];
+        let mut choices = vec![None];
+        for (author, count) in authors {
+            items.push(format!("{:<50} {:>6}", author, count));
+            choices.push(Some(author));
         }
+        let selected = choices.iter().position(|a| *a == self.highlight_author).unwrap_or(0);
+        self.menu = Some(Menu {
+            title: format!("Highlight an author ({} loaded commits)", self.commits.len()),
+            items,
+            selected,
+            kind: MenuKind::HighlightAuthor(choices),
+        });
     }
-
-    fn clamp_diff_scroll(&mut self, visible_height: u16) {
-        let max_scroll = self.get_max_diff_scroll(visible_height);
-        self.diff_scroll_offset = self.diff_scroll_offset.min(max_scroll);
+    
+    fn mark_author_rows(&mut self) {
+        self.author_rows = match self.highlight_author {
+            Some(ref author) => self
+                .commits
+                .values()
+                .filter(|c| author_key(c) == *author)
+                .map(|c| c.short_id.clone())
+                .collect(),
+            None => HashSet::new(),
+        };
     }
     
-    fn search_branch(&mut self) {
-        if self.search_query.is_empty() {
-            return;
-        }
-        
-        let query_lower = self.search_query.to_lowercase();
-        
-        // Search from current position forward
-        for i in (self.selected_branch + 1)..self.branches.len() {
-            if self.branches[i].name.to_lowercase().contains(&query_lower) {
-                self.selected_branch = i;
-                self.branch_list_state.select(Some(i));
+    /// Opens the author domain breakdown; choosing an entry filters by it
+    fn open_author_menu(&mut self) {
+        let (domains, bots) = match self.author_domain_stats() {
+            Ok(stats) => stats,
+            Err(e) => {
+                self.show_toast(format!("Failed to count authors: {}", e), true);
                 return;
             }
+        };
+        let total = domains.iter().map(|(_, n)| n).sum::<usize>() + bots;
+        let percent = |n: usize| n * 100 / total.max(1);
+        
+        let mut items = vec!["All authors".to_string()];
+        let mut filters = vec![None];
+        for (domain, count) in domains {
+            items.push(format!("{:<30} {:>6}  {:>3}%", domain, count, percent(count)));
+            filters.push(Some(AuthorFilter::Domain(domain)));
+        }
+        if bots > 0 {
+            items.push(format!("{:<30} {:>6}  {:>3}%", "bots", bots, percent(bots)));
+            filters.push(Some(AuthorFilter::Bots));
         }
         
-        // Wrap around: search from beginning to current position
-        for i in 0..=self.selected_branch {
-            if self.branches[i].name.to_lowercase().contains(&query_lower) {
-                self.selected_branch = i;
-                self.branch_list_state.select(Some(i));
+        let selected = filters.iter().position(|f| *f == self.author_filter).unwrap_or(0);
+        self.menu = Some(Menu {
+            title: format!("Author domains ({} commits)", total),
+            items,
+            selected,
+            kind: MenuKind::AuthorFilter(filters),
+        });
+    }
+    
+    /// Everyone with commits in the view's whole history (ignoring the page
+    /// limit and the author filter), to filter the graph by one of them
+    fn open_contributors(&mut self) {
+        let cmd = self.git_command().args(["log", "--use-mailmap", "--format=%aN%x00%aE%x00%at"]);
+        let output = match self.history_revs(cmd).and_then(|cmd| cmd.output()) {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                self.show_toast(format!("Failed to list contributors: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr))), true);
+                return;
+            }
+            Err(e) => {
+                self.show_toast(format!("Failed to list contributors: {}", e), true);
                 return;
             }
+        };
+        let contributors = contributors::tally(&String::from_utf8_lossy(&output.stdout));
+        let total: usize = contributors.iter().map(|c| c.commits).sum();
+        let now = Utc::now();
+        
+        let mut items = vec!["All authors".to_string()];
+        let mut filters = vec![None];
+        for contributor in &contributors {
+            items.push(format!(
+                "{:<24} {:<32} {:>6}  last {}",
+                contributor.name,
+                contributor.email,
+                contributor.commits,
+                relative_time(contributor.last, now),
+            ));
         }
+        filters.extend(contributors.into_iter().map(|c| Some(AuthorFilter::Person { name: c.name, email: c.email })));
+        let selected = filters.iter().position(|f| *f == self.author_filter).unwrap_or(0);
+        self.menu = Some(Menu {
+            title: format!("Contributors ({} with {} commits)", items.len() - 1, total),
+            items,
+            selected,
+            kind: MenuKind::AuthorFilter(filters),
+        });
     }
-}
-
-fn draw_ui(f: &mut Frame, app: &mut App) {
-    // Main layout with help at bottom
-    let main_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Min(0), Constraint::Length(3)]) // Help takes 3 lines at bottom
-        .split(f.area());
     
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(33), Constraint::Percentage(67)]) // Graph takes 2/3
-        .split(main_chunks[0]);
-    
-    let left_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
-        .split(chunks[0]);
+    fn set_author_filter(&mut self, filter: Option<AuthorFilter>) {
+        self.author_filter = filter;
+        self.commit_limit = COMMIT_PAGE_SIZE;
+        self.reload_graph();
+    }
     
-    // Draw branches (top-left)
-    draw_branches(f, app, left_chunks[0]);
+    fn toggle_hide_bots(&mut self) {
+        self.hide_bots = !self.hide_bots;
+        if self.hide_bots && self.author_filter == Some(AuthorFilter::Bots) {
+            self.author_filter = None;
+        }
+        self.reload_graph();
+        let state = if self.hide_bots { "hidden" } else { "shown" };
+        self.show_toast(format!("Bot commits {}", state), false);
+    }
     
-    // Draw commit details (bottom-left)
-    draw_commit_details(f, app, left_chunks[1]);
+    /// Switches between the configured glyphs and the other base set
+    fn toggle_glyph_set(&mut self) {
+        self.glyphs = match self.glyphs == self.configured_glyphs {
+            true => self.configured_glyphs.other_set(),
+            false => self.configured_glyphs.clone(),
+        };
+        let set = if self.glyphs.ascii { "ASCII" } else { "Unicode" };
+        self.show_toast(format!("{} glyphs", set), false);
+    }
     
-    // Draw commits graph (right side)
-    draw_commits(f, app, chunks[1]);
+    fn toggle_stashes(&mut self) {
+        self.show_stashes = !self.show_stashes;
+        self.reload_graph();
+        // The graph is still loading, so not from `self.stashes`
+        let message = match (self.show_stashes, self.stash_ids().len()) {
+            (true, 0) => "No stash entries".to_string(),
+            (true, n) => format!("{} stash entries shown", n),
+            (false, _) => "Stash entries hidden".to_string(),
+        };
+        self.show_toast(message, false);
+    }
     
-    // Draw help at bottom
-    draw_help(f, app, main_chunks[1]);
+    fn cycle_history_order(&mut self) {
+        self.history_order = self.history_order.next();
+        self.reload_graph();
+        self.show_toast(format!("Graph in {}", self.history_order.label()), false);
+    }
     
-    // Draw diff overlay if showing diff
-    if app.show_diff {
-        draw_diff_overlay(f, app);
+    fn toggle_shortstat(&mut self) {
+        self.show_shortstat = !self.show_shortstat;
+        let state = if self.show_shortstat { "shown" } else { "hidden" };
+        self.show_toast(format!("Lines added and removed per commit {}", state), false);
     }
-}
-
-fn draw_branches(f: &mut Frame, app: &mut App, area: Rect) {
-    let included_branches = app.get_included_branches();
     
-    let items: Vec<ListItem> = app.branches
-        .iter()
-        .map(|branch| {
-            let is_current_filter = app.current_branch_filter.as_ref() == Some(&branch.name);
-            let is_included = included_branches.contains(&branch.name);
-            
-            let style = if is_current_filter {
-                Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
-            } else if is_included {
-                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-            } else if branch.is_head {
-                Style::default().fg(Color::Yellow)
-            } else if branch.is_remote {
-                Style::default().fg(Color::Cyan)
-            } else {
-                Style::default().fg(Color::White)
+    /// Counts the lines changed by the commits of `rows` not counted yet,
+    /// for the shortstat toggle. Merges are left out, as in
+    /// `git log --shortstat`.
+    fn load_shortstats(&mut self, rows: Range<usize>) {
+        let mut missing = Vec::new();
+        for index in rows {
+            let Some(line) = self.graph_lines.get(index) else {
+                break;
             };
-            
-            let marker = if is_current_filter { 
-                "● " 
-            } else if is_included { 
-                "◉ " 
-            } else { 
-                "○ " 
+            if line.shortstat.is_some() || line.folded > 0 || line.tree_same || line.commit_id.is_empty() {
+                continue;
+            }
+            let Ok(id) = self.resolve_short_id(&line.commit_id.clone()) else {
+                continue;
             };
-            let head_marker = if branch.is_head { " (HEAD)" } else { "" };
-            let remote_marker = if branch.is_remote { " [remote]" } else { "" };
-            
-            ListItem::new(format!("{}{}{}{}", marker, branch.name, head_marker, remote_marker))
-                .style(style)
-        })
-        .collect();
+            match self.shortstats.get(&id) {
+                Some(counted) => self.graph_lines[index].shortstat = *counted,
+                None if self.commits.get(&id.to_string()).is_some_and(|c| c.parents.len() > 1) => {}
+                None => missing.push((index, id)),
+            }
+        }
+        if missing.is_empty() {
+            return;
+        }
+        let what = format!("git2: shortstat of {} commit(s)", missing.len());
+        let _ = oplog::timed(what, || -> Result<(), git2::Error> {
+            for (index, id) in missing {
+                let counted = shortstat(&self.repository, id).ok();
+                self.shortstats.insert(id, counted);
+                self.graph_lines[index].shortstat = counted;
+            }
+            Ok(())
+        });
+    }
     
-    let title = if app.search_mode {
-        format!("Branches [Search: {}]", app.search_query)
-    } else {
-        "Branches".to_string()
-    };
+    fn toggle_relative_dates(&mut self) {
+        self.relative_dates = !self.relative_dates;
+        let kind = if self.relative_dates { "relative" } else { "absolute" };
+        self.show_toast(format!("Showing {} dates", kind), false);
+    }
     
-    // Highlight the border when this panel is focused
-    let border_style = if !app.show_logs {
-        Style::default().fg(Color::Yellow)  // Active panel: yellow border
-    } else {
-        Style::default().fg(Color::DarkGray)  // Inactive panel: dark gray border
-    };
+    /// A commit time as Commit Details shows it: relative, or in the local
+    /// timezone in the configured format
+    fn format_time(&self, time: DateTime<Utc>) -> String {
+        match self.relative_dates {
+            true => relative_time(time, Utc::now()),
+            false => time.with_timezone(&Local).format(&self.date_format).to_string(),
+        }
+    }
     
-    let list = List::new(items)
-        .block(Block::default()
-            .title(title)
-            .borders(Borders::ALL)
-            .border_style(border_style))
-        .highlight_style(Style::default().bg(Color::DarkGray))
-        .highlight_symbol("▶ ");
+    fn toggle_milestones(&mut self) {
+        self.milestones_only = !self.milestones_only;
+        self.reload_graph();
+        let message = match self.milestones_only {
+            true => "Showing only commits with a branch or tag",
+            false => "Showing all commits",
+        };
+        self.show_toast(message, false);
+    }
     
-    f.render_stateful_widget(list, area, &mut app.branch_list_state);
-}
-
-fn draw_commits(f: &mut Frame, app: &mut App, area: Rect) {
-    if app.loading {
-        let paragraph = Paragraph::new("Loading git graph...")
-            .block(Block::default()
-                .title("Git Graph")
-                .borders(Borders::ALL));
-        f.render_widget(paragraph, area);
-        return;
+    fn toggle_merges(&mut self) {
+        self.hide_merges = !self.hide_merges;
+        self.reload_graph();
+        let state = if self.hide_merges { "hidden" } else { "shown" };
+        self.show_toast(format!("Merge commits {}", state), false);
     }
     
-    if let Some(ref error) = app.error_message {
-        let paragraph = Paragraph::new(format!("Error: {}", error))
-            .block(Block::default()
-                .title("Git Graph - Error")
-                .borders(Borders::ALL))
-            .style(Style::default().fg(Color::Red));
-        f.render_widget(paragraph, area);
-        return;
+    fn toggle_first_parent(&mut self) {
+        self.first_parent = !self.first_parent;
+        self.reload_graph();
+        let message = match self.first_parent {
+            true => "Following first parents only",
+            false => "Following all parents",
+        };
+        self.show_toast(message, false);
     }
     
-    // Pre-compute colored lines to avoid borrowing issues
-    let colored_lines: Vec<Line> = app.graph_lines
-        .iter()
-        .map(|line| {
-            // Create colored spans for graph and commit text
-            let mut spans = Vec::new();
-            
-            // Add colored graph part
-            spans.extend(app.colorize_graph_text(&line.graph_text));
-            
-            // Add colored commit part
-            spans.extend(app.colorize_commit_text(&line.commit_text));
-            
-            // Create a Line from spans
-            Line::from(spans)
-        })
-        .collect();
+    fn toggle_hide_tree_same(&mut self) {
+        self.hide_tree_same = !self.hide_tree_same;
+        self.reload_graph();
+        let state = if self.hide_tree_same { "hidden" } else { "shown" };
+        self.show_toast(format!("Commits without changes {}", state), false);
+    }
     
-    let items: Vec<ListItem> = colored_lines
-        .into_iter()
-        .map(|line| ListItem::new(line))
-        .collect();
+    /// Reloads the graph after a view option changed, starting at the top.
+    /// `git log` runs in the background; the graph on screen stays until it
+    /// is done.
+    fn reload_graph(&mut self) {
+        self.reload_graph_then(AfterGraph::Top);
+    }
     
-    let title = if let Some(ref branch) = app.current_branch_filter {
-        let included_branches = app.get_included_branches();
-        if included_branches.len() > 1 {
-            format!("Git Graph - {} + {} descendants", 
-                    branch, included_branches.len() - 1)
-        } else {
-            format!("Git Graph - {}", branch)
+    /// Loads the graph in the background, the one shown kept until it is
+    /// done; `then` places the selection in the new one
+    fn reload_graph_then(&mut self, then: AfterGraph) {
+        // git refuses `--reverse` with `--graph`: oldest first, the commits
+        // are counted so the newest can be skipped and the page start at
+        // the root
+        if self.history_order == HistoryOrder::Reverse && self.history_scope != HistoryScope::All {
+            match self.count_command() {
+                Ok(cmd) => {
+                    let task = Task::start("Counting history", vec![(String::new(), cmd)]);
+                    self.start_task(task, TaskKind::GraphCount { then });
+                }
+                Err(e) => self.error_message = Some(format!("Failed to load graph: {}", e)),
+            }
+            return;
         }
-    } else {
-        "Git Graph - All branches".to_string()
-    };
-    
-    // Highlight the border when this panel is focused
-    let border_style = if app.show_logs {
-        Style::default().fg(Color::Yellow)  // Active panel: yellow border
-    } else {
-        Style::default().fg(Color::DarkGray)  // Inactive panel: dark gray border
-    };
-    
-    let list = List::new(items)
-        .block(Block::default()
-            .title(title.as_str())
-            .borders(Borders::ALL)
-            .border_style(border_style))
-        .highlight_style(Style::default().bg(Color::DarkGray))
-        .highlight_symbol("▶ ");
+        self.load_graph_page(0, then);
+    }
     
-    f.render_stateful_widget(list, area, &mut app.commit_list_state);
-}
-
-fn draw_commit_details(f: &mut Frame, app: &App, area: Rect) {
-    let content = if let Some(commit) = app.get_selected_commit() {
-        let mut details = format!(
-            "Commit: {}\nShort: {}\nAuthor: {}\nDate: {}\n",
-            commit.id,
-            commit.short_id,
-            commit.author,
-            commit.timestamp.format("%Y-%m-%d %H:%M:%S UTC")
-        );
-        
-        if !commit.parents.is_empty() {
-            details.push_str(&format!("\nParents:\n"));
-            for parent in &commit.parents {
-                details.push_str(&format!("  {}\n", &parent[..8]));
+    fn load_graph_page(&mut self, skip: usize, then: AfterGraph) {
+        match self.graph_command(skip) {
+            Ok(cmd) => {
+                let task = Task::start("Loading history", vec![(String::new(), cmd)]);
+                self.start_task(task, TaskKind::Graph { then });
             }
+            Err(e) => self.error_message = Some(format!("Failed to load graph: {}", e)),
         }
-        
-        // Add full commit message with proper formatting
-        details.push_str(&format!("\nMessage:\n{}", commit.message));
-        
-        details
-    } else {
-        // Debug information to see what's happening
-        let selected_line = app.graph_lines.get(app.selected_commit);
-        let debug_info = if let Some(line) = selected_line {
-            format!("❌ No commit found!\n\nSelected Line:\n• Index: {}\n• Commit ID: '{}'\n• Graph: '{}'\n• Commit Text: '{}'\n\nCommits in HashMap: {}", 
-                    app.selected_commit, line.commit_id, line.graph_text, line.commit_text, app.commits.len())
-        } else {
-            format!("❌ No line at index {} (total: {})", app.selected_commit, app.graph_lines.len())
+    }
+    
+    /// Loads the oldest page of the commits counted. A failed count loads
+    /// from the newest, for `git log --graph` to report what is wrong.
+    fn graph_counted(&mut self, task: &mut Task, then: AfterGraph) {
+        let Some((_, output)) = task.results().pop() else {
+            return;
         };
-        
-        format!("🐛 DEBUG MODE\n\nShow Logs: {}\n{}", app.show_logs, debug_info)
-    };
+        let total = match output {
+            Ok(output) if output.status.success() => output.stdout.split(|&b| b == b'\n').filter(|line| !line.is_empty()).count(),
+            _ => 0,
+        };
+        let page = match self.history_scope {
+            HistoryScope::MaxCount(n) => n,
+            HistoryScope::Paged | HistoryScope::All => self.commit_limit,
+        };
+        self.load_graph_page(total.saturating_sub(page), then);
+    }
+    
+    /// Reloads the graph keeping the selected commit selected
+    fn reload_graph_in_place(&mut self) {
+        let commit_id = self.graph_lines
+            .get(self.selected_commit)
+            .map(|l| l.commit_id.clone())
+            .filter(|id| !id.is_empty());
+        self.reload_graph_then(AfterGraph::Keep { commit_id, row: self.selected_commit });
+    }
+    
+    fn graph_loaded(&mut self, task: &mut Task, then: AfterGraph) {
+        let Some((_, output)) = task.results().pop() else {
+            return;
+        };
+        if let Err(e) = self.apply_graph_output(output) {
+            self.error_message = Some(format!("Failed to load graph: {}", e));
+        }
+        let last = self.graph_lines.len().saturating_sub(1);
+        self.selected_commit = match then {
+            AfterGraph::Top => 0,
+            AfterGraph::Keep { commit_id, row } => commit_id
+                .and_then(|id| self.graph_row(&id))
+                .unwrap_or(row.min(last)),
+            // The run starts at the same row either way
+            AfterGraph::Fold(key) => self.graph_lines
+                .iter()
+                .position(|l| l.fold_key.as_ref() == Some(&key))
+                .unwrap_or(self.selected_commit.min(last)),
+            AfterGraph::NextPage { previous_len } if self.graph_lines.len() > previous_len => previous_len,
+            AfterGraph::NextPage { .. } => self.selected_commit.min(last),
+            AfterGraph::Jump { commit_id, pages } => {
+                self.jump_loaded(commit_id, pages);
+                return;
+            }
+        };
+        self.commit_list_state.select(Some(self.selected_commit));
+    }
+    
+    /// Runs `task` in the background; a history load or diff filter
+    /// replaces one still running, which was started for options or a diff
+    /// that since changed
+    fn start_task(&mut self, task: Task, kind: TaskKind) {
+        if kind.is_graph() {
+            self.tasks.retain(|running| !running.kind.is_graph());
+        }
+        if matches!(kind, TaskKind::FilterDiff { .. }) {
+            self.tasks.retain(|running| !matches!(running.kind, TaskKind::FilterDiff { .. }));
+        }
+        self.tasks.push(RunningTask { task, kind });
+    }
+    
+    /// Whether a fetch or update is already running, saying so if it is
+    fn task_running(&mut self) -> bool {
+        let Some(running) = self.tasks.iter().find(|running| running.kind.is_exclusive()) else {
+            return false;
+        };
+        let message = format!("{} is still running (Esc cancels it)", running.task.label);
+        self.show_toast(message, true);
+        true
+    }
+    
+    /// The open tab as it is now
+    fn tab_view(&self) -> TabView {
+        TabView {
+            branch_filter: self.current_branch_filter.clone(),
+            rev_range: self.rev_range.clone(),
+            marked_branches: self.marked_branches.clone(),
+            exact_refs: self.exact_refs.clone(),
+            filter_mode: self.filter_mode,
+            author_filter: self.author_filter.clone(),
+            highlight_author: self.highlight_author.clone(),
+            hide_bots: self.hide_bots,
+            hide_tree_same: self.hide_tree_same,
+            show_stashes: self.show_stashes,
+            hide_merges: self.hide_merges,
+            first_parent: self.first_parent,
+            history_order: self.history_order,
+            milestones_only: self.milestones_only,
+            trailer_filter: self.trailer_filter.clone(),
+            date_range: self.date_range.clone(),
+            path_filter: self.path_filter.clone(),
+            pickaxe: self.pickaxe.clone(),
+            type_filter: self.type_filter.clone(),
+            ref_filter_enabled: self.ref_filter_enabled,
+            commit_limit: self.commit_limit,
+            expanded_folds: self.expanded_folds.clone(),
+            show_logs: self.show_logs,
+            selected_branch: self.selected_git_branch().map(|b| b.name.clone()),
+            selected_commit: self.graph_lines
+                .get(self.selected_commit)
+                .map(|l| l.commit_id.clone())
+                .filter(|id| !id.is_empty()),
+        }
+    }
+    
+    /// Shows `view` in place of the open tab's view, loading its graph
+    fn restore_tab(&mut self, view: TabView) {
+        self.current_branch_filter = view.branch_filter;
+        self.rev_range = view.rev_range;
+        self.marked_branches = view.marked_branches;
+        self.exact_refs = view.exact_refs;
+        self.filter_mode = view.filter_mode;
+        self.author_filter = view.author_filter;
+        self.highlight_author = view.highlight_author;
+        self.hide_bots = view.hide_bots;
+        self.hide_tree_same = view.hide_tree_same;
+        self.show_stashes = view.show_stashes;
+        self.hide_merges = view.hide_merges;
+        self.first_parent = view.first_parent;
+        self.history_order = view.history_order;
+        self.milestones_only = view.milestones_only;
+        self.trailer_filter = view.trailer_filter;
+        self.date_range = view.date_range;
+        self.path_filter = view.path_filter;
+        self.pickaxe = view.pickaxe;
+        self.type_filter = view.type_filter;
+        self.ref_filter_enabled = view.ref_filter_enabled;
+        self.commit_limit = view.commit_limit;
+        self.expanded_folds = view.expanded_folds;
+        self.show_logs = view.show_logs;
+        
+        self.reload_graph_then(AfterGraph::Keep { commit_id: view.selected_commit, row: 0 });
+        let row = view.selected_branch.and_then(|name| self.branch_row(&name));
+        self.select_branch_row(row.unwrap_or(0));
+    }
+    
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.tab || index >= self.tabs.len() {
+            return;
+        }
+        self.tabs[self.tab] = self.tab_view();
+        self.tab = index;
+        self.restore_tab(self.tabs[index].clone());
+    }
+    
+    /// Opens a tab after this one showing the same view, to change from there
+    fn new_tab(&mut self) {
+        self.tabs[self.tab] = self.tab_view();
+        self.tabs.insert(self.tab + 1, self.tabs[self.tab].clone());
+        self.tab += 1;
+        self.show_toast(format!("Tab {} of {}", self.tab + 1, self.tabs.len()), false);
+    }
+    
+    fn close_tab(&mut self) {
+        if self.tabs.len() < 2 {
+            self.show_toast("Last tab: q quits", true);
+            return;
+        }
+        self.tabs.remove(self.tab);
+        self.tab = self.tab.min(self.tabs.len() - 1);
+        self.restore_tab(self.tabs[self.tab].clone());
+    }
+    
+    /// Labels of the tabs in order, the open one from the live view
+    fn tab_labels(&self) -> Vec<String> {
+        self.tabs
+            .iter()
+            .enumerate()
+            .map(|(index, view)| match index == self.tab {
+                true => self.tab_view().label(),
+                false => view.label(),
+            })
+            .collect()
+    }
+    
+    /// Finishes the background tasks that are done
+    fn poll_tasks(&mut self) {
+        let mut index = 0;
+        while index < self.tasks.len() {
+            if !self.tasks[index].task.poll() {
+                index += 1;
+                continue;
+            }
+            let RunningTask { mut task, kind } = self.tasks.remove(index);
+            match kind {
+                TaskKind::Graph { then } => self.graph_loaded(&mut task, then),
+                TaskKind::GraphCount { then } => self.graph_counted(&mut task, then),
+                TaskKind::Fetch { branches, skipped } => self.fetch_done(&mut task, branches, skipped),
+                TaskKind::Submodule { path, done } => self.submodule_done(&mut task, &path, done),
+                TaskKind::PullRequest { branch } => self.pull_request_fetched(&mut task, &branch),
+                TaskKind::Recover => self.recover_scanned(&mut task),
+                TaskKind::RenderSvg => self.svg_rendered(&mut task),
+                TaskKind::Stats => self.stats_counted(&mut task),
+                TaskKind::Deepen => self.deepened(&mut task),
+                TaskKind::FetchObjects { then } => self.objects_fetched(&mut task, then),
+                TaskKind::Sparse { done } => self.sparse_done(&mut task, &done),
+                TaskKind::LfsDiff { file } => self.lfs_content_fetched(&mut task, file),
+                TaskKind::VerifyTag { id } => self.tag_verified(&mut task, id),
+                TaskKind::FilterDiff { diff } => self.diff_filtered(&mut task, &diff),
+                TaskKind::CommitGraph => {
+                    if let Some((_, Ok(output))) = task.results().pop()
+                        && !output.status.success()
+                    {
+                        let message = format!("Could not write the commit-graph: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+                        self.show_toast(message, true);
+                    }
+                }
+            }
+        }
+    }
+    
+    fn reload_dirty(&mut self) {
+        self.dirty = match self.repository.is_bare() {
+            true => None,
+            false => oplog::timed("git2: status", || dirty::Summary::of(&self.repository)).ok(),
+        };
+    }
+    
+    /// Writes git's commit-graph in the background when it is missing or
+    /// older than the newest pack, so `git log --graph` reads parents from it
+    /// instead of opening every commit. Not in read-only mode, as it writes
+    /// to the object directory.
+    fn update_commit_graph(&mut self) {
+        if !self.commit_graph
+            || self.read_only
+            || self.tasks.iter().any(|running| matches!(running.kind, TaskKind::CommitGraph))
+        {
+            return;
+        }
+        let common_dir = watcher::common_dir(self.repository.path());
+        match commitgraph::state(&self.repository, &common_dir) {
+            commitgraph::State::Missing | commitgraph::State::Stale => {}
+            commitgraph::State::Disabled | commitgraph::State::Fresh => return,
+        }
+        let cmd = self.git_command().args(commitgraph::WRITE_ARGS);
+        self.start_task(Task::start("Writing commit-graph", vec![(String::new(), cmd)]), TaskKind::CommitGraph);
+    }
+    
+    /// Stops every background task; dropping one kills its command
+    fn cancel_tasks(&mut self) {
+        let labels: Vec<String> = self.tasks.drain(..).map(|running| running.task.label.clone()).collect();
+        if !labels.is_empty() {
+            self.show_toast(format!("Cancelled: {}", labels.join(", ")), false);
+        }
+    }
+    
+    /// Offers filtering by, and opening links of, the selected commit's trailers
+    fn open_trailer_menu(&mut self) {
+        let Some(commit) = self.get_selected_commit() else {
+            return;
+        };
+        let trailers = trailers::parse(&commit.message);
+        if trailers.is_empty() {
+            self.show_toast("Commit has no trailers", true);
+            return;
+        }
+        
+        let mut items = Vec::new();
+        let mut actions = Vec::new();
+        for trailer in trailers {
+            if let Some(url) = trailers::link(&trailer, &self.trailer_links) {
+                items.push(format!("Open {}", url));
+                actions.push(TrailerAction::Open(url));
+            }
+            items.push(format!("Filter by {}: {}", trailer.key, trailer.value));
+            actions.push(TrailerAction::Filter(trailer));
+        }
+        self.menu = Some(Menu {
+            title: "Trailers".to_string(),
+            items,
+            selected: 0,
+            kind: MenuKind::Trailers(actions),
+        });
+    }
+    
+    /// Opens a URL with the desktop's default handler
+    fn open_url(&mut self, url: &str) {
+        let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+        match ExternalCommand::new(opener).rev(url).output() {
+            Ok(output) if output.status.success() => self.show_toast(format!("Opened {}", url), false),
+            Ok(output) => self.show_toast(
+                format!("{} failed: {}", opener, String::from_utf8_lossy(&output.stderr).trim()),
+                true,
+            ),
+            Err(e) => self.show_toast(format!("Failed to open {}: {}", url, e), true),
+        }
+    }
+    
+    /// Offers branch, tag and reset operations on the selected commit
+    fn open_ref_menu(&mut self) {
+        let Some(line) = self.graph_lines.get(self.selected_commit) else {
+            return;
+        };
+        if line.commit_id.is_empty() {
+            return;
+        }
+        let commit_id = line.commit_id.clone();
+        self.menu = Some(Menu {
+            title: format!("Commit {}", commit_id),
+            items: vec![
+                "New branch here".to_string(),
+                "New tag here".to_string(),
+                "New annotated tag here (message in the editor)".to_string(),
+                format!("Reset {} here", self.head_label()),
+                "Check out here (detached HEAD)".to_string(),
+                format!("Rebase {} interactively onto here (git rebase -i)", self.head_label()),
+            ],
+            selected: 0,
+            kind: MenuKind::RefAtCommit(commit_id),
+        });
+    }
+    
+    /// Patch export for the selected commit, or the commits from it up to HEAD
+    fn open_patch_menu(&mut self) {
+        let Some(line) = self.graph_lines.get(self.selected_commit) else {
+            return;
+        };
+        if line.commit_id.is_empty() {
+            return;
+        }
+        let commit_id = line.commit_id.clone();
+        let Ok(id) = self.resolve_short_id(&commit_id) else {
+            return;
+        };
+        if self.repository.find_commit(id).is_ok_and(|c| c.parent_count() > 1) {
+            self.show_toast("A merge commit has no single patch", true);
+            return;
+        }
+        
+        let mut items = vec![format!("Write {} as a patch file", commit_id)];
+        let mut actions = vec![PatchAction::Write(vec![id])];
+        // Up to HEAD when the commit is on the current branch
+        let head = self.repository.head().ok().and_then(|h| h.target());
+        if let Some(head) = head
+            && head != id
+            && self.repository.graph_descendant_of(head, id).unwrap_or(false)
+        {
+            let base = self.repository.find_commit(id).ok().and_then(|c| c.parent_id(0).ok());
+            match patches::series(&self.repository, &[head], base.as_slice()) {
+                Ok(ids) if ids.len() > 1 => {
+                    items.push(format!("Write {}^..HEAD as {} patch files (merges left out)", commit_id, ids.len()));
+                    actions.push(PatchAction::Write(ids));
+                }
+                Ok(_) => {}
+                Err(e) => self.show_toast(format!("Cannot list commits up to HEAD: {}", e.message()), true),
+            }
+        }
+        if let Some(range) = self.rev_range.clone()
+            && let Ok(ids) = self.range_patches(&range)
+            && !ids.is_empty()
+        {
+            items.push(format!("Write {} as {} patch files (merges left out)", range.label(), ids.len()));
+            actions.push(PatchAction::Write(ids));
+        }
+        items.push(format!("Copy {} as a patch to the clipboard", commit_id));
+        actions.push(PatchAction::Copy(id));
+        
+        self.menu = Some(Menu {
+            title: "Format patch".to_string(),
+            items,
+            selected: 0,
+            kind: MenuKind::Patches(actions),
+        });
+    }
+    
+    /// Commits of the range that have a patch, oldest first
+    fn range_patches(&self, range: &RevRange) -> Result<Vec<Oid>, git2::Error> {
+        let (tips, hidden) = range.ends(&self.repository)?;
+        patches::series(&self.repository, &tips, &hidden)
+    }
+    
+    /// Asks for a range to graph; with one set, offers its diff and patches
+    fn open_range_menu(&mut self) {
+        let Some(range) = self.rev_range.clone() else {
+            let initial = match self.marked_branches.as_slice() {
+                [a, b] => format!("{}..{}", a, b),
+                _ => String::new(),
+            };
+            self.open_prompt("Revision range (A..B or A...B)".to_string(), &initial, PromptKind::RevisionRange);
+            return;
+        };
+        let label = range.label();
+        let mut items = vec![format!("Show the diff of {}", label)];
+        let mut actions = vec![RangeAction::Diff];
+        match self.range_patches(&range) {
+            Ok(ids) if !ids.is_empty() => {
+                items.push(format!("Write {} as {} patch files (merges left out)", label, ids.len()));
+                actions.push(RangeAction::Write(ids));
+            }
+            Ok(_) => {}
+            Err(e) => self.show_toast(format!("Cannot list the commits of {}: {}", label, e.message()), true),
+        }
+        items.push("Change the range".to_string());
+        actions.push(RangeAction::Edit);
+        items.push("Clear the range".to_string());
+        actions.push(RangeAction::Clear);
+        self.menu = Some(Menu {
+            title: format!("Range {}", label),
+            items,
+            selected: 0,
+            kind: MenuKind::Range(actions),
+        });
+    }
+    
+    /// Graphs only the range typed; empty clears it
+    fn set_rev_range(&mut self, text: &str) -> Result<(), String> {
+        if text.is_empty() {
+            self.clear_branch_filter();
+            return Ok(());
+        }
+        let range = RevRange::parse(text)?;
+        range.ends(&self.repository).map_err(|e| format!("{}: {}", range.label(), e.message()))?;
+        self.current_branch_filter = None;
+        self.exact_refs = None;
+        self.rev_range = Some(range);
+        self.commit_limit = COMMIT_PAGE_SIZE;
+        self.reload_graph();
+        Ok(())
+    }
+    
+    /// `git diff A..B` (or `A...B`, from the merge base), shown as it is made
+    fn show_range_diff(&mut self) {
+        let Some(ref range) = self.rev_range else {
+            return;
+        };
+        let cmd = self.git_command().args(["diff", "--no-color"]).rev(range.label());
+        let revisions = (range.from.clone(), range.to.clone());
+        self.clear_diff_view();
+        self.diff_revisions = Some(revisions);
+        // The split pane has to go back to the selected commit afterwards
+        self.split_diff_commit.clear();
+        self.show_diff = true;
+        match cmd.stream() {
+            Ok(stream) => self.diff_stream = Some(stream),
+            Err(e) => self.append_diff_lines(vec![format!("Failed to run git diff: {}", e)]),
+        }
+    }
+    
+    /// Changelogs of the commits in the view, or of those after the selected
+    /// one up to HEAD, by conventional type or by author
+    fn open_changelog_menu(&mut self) {
+        // Merges, boundary commits (outside the range) and folded bot runs are left out
+        let rows: Vec<String> = self
+            .graph_lines
+            .iter()
+            .filter(|line| !line.commit_id.is_empty() && !line.is_boundary && line.folded == 0)
+            .map(|line| line.commit_id.clone())
+            .collect();
+        let mut view = Vec::new();
+        for row in rows {
+            if let Ok(id) = self.resolve_short_id(&row)
+                && self.repository.find_commit(id).is_ok_and(|c| c.parent_count() <= 1)
+            {
+                view.push(id);
+            }
+        }
+        let mut scopes = Vec::new();
+        if !view.is_empty() {
+            scopes.push((view, "the current view".to_string()));
+        }
+        let head = self.repository.head().ok().and_then(|h| h.target());
+        let selected = self.get_selected_commit().and_then(|c| Oid::from_str(&c.id).ok());
+        if let (Some(head), Some(selected)) = (head, selected)
+            && head != selected
+            && self.repository.graph_descendant_of(head, selected).unwrap_or(false)
+            && let Ok(mut ids) = patches::series(&self.repository, &[head], &[selected])
+        {
+            ids.reverse();
+            scopes.push((ids, format!("{}..HEAD", short_id(&selected.to_string()))));
+        }
+        if scopes.is_empty() {
+            self.show_toast("No commits for a changelog", true);
+            return;
+        }
+        
+        let mut items = Vec::new();
+        let mut sources = Vec::new();
+        for (ids, label) in scopes {
+            for grouping in [Grouping::Type, Grouping::Author] {
+                items.push(format!("{} ({} commits), {}", label, ids.len(), grouping.label()));
+                sources.push(changelog::Source { ids: ids.clone(), label: label.clone(), grouping });
+            }
+        }
+        self.menu = Some(Menu {
+            title: "Changelog".to_string(),
+            items,
+            selected: 0,
+            kind: MenuKind::Changelog(sources),
+        });
+    }
+    
+    /// Writes the changelog to `path` (relative to the top level), or copies
+    /// it to the clipboard when no path is given
+    fn write_changelog(&mut self, source: &changelog::Source, path: &str) -> Result<(), String> {
+        let entries: Vec<changelog::Entry> = source
+            .ids
+            .iter()
+            .filter_map(|id| self.repository.find_commit(*id).ok())
+            .map(|commit| changelog::Entry {
+                short_id: short_id(&commit.id().to_string()),
+                subject: commit.summary().unwrap_or("").to_string(),
+                author: self.author_of(&commit).name().unwrap_or("").to_string(),
+            })
+            .collect();
+        let text = changelog::markdown(&source.label, &entries, source.grouping);
+        if path.is_empty() {
+            window::copy_to_clipboard(text.as_bytes()).map_err(|e| format!("Failed to copy: {}", e))?;
+            self.show_toast(format!("Copied the changelog ({} commits) to the clipboard", entries.len()), false);
+            return Ok(());
+        }
+        let path = self.repository.workdir().unwrap_or_else(|| self.repository.path()).join(path);
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.show_toast(format!("Wrote the changelog ({} commits) to {}", entries.len(), path.display()), false);
+        Ok(())
+    }
+    
+    /// Writes `NNNN-subject.patch` files into `dir` (relative to the top level)
+    fn write_patches(&mut self, ids: &[Oid], dir: &str) -> Result<(), String> {
+        let dir = self.repository.workdir().unwrap_or_else(|| self.repository.path()).join(dir);
+        let written = patches::write_series(&self.repository, ids, &dir)?;
+        self.show_toast(format!("Wrote {} patch(es) to {}", written.len(), dir.display()), false);
+        Ok(())
+    }
+    
+    /// Applies a patch file, or every `.patch` file in a directory: mailboxes
+    /// are committed with `git am`, plain diffs applied to the index and
+    /// working tree with `git apply`. Failures are shown in a popup.
+    fn apply_patches(&mut self, path: &str) -> Result<(), String> {
+        let path = match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+            _ => self.repository.workdir().unwrap_or_else(|| self.repository.path()).join(path),
+        };
+        let files = patches::patch_files(&path)?;
+        let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        let mailbox = files.iter().all(|f| patches::is_mailbox(Path::new(f)));
+        
+        let started = Instant::now();
+        let cmd = if mailbox {
+            self.git_command().args(["am", "--3way"])
+        } else {
+            // Applies what it can and leaves the rest in .rej files
+            self.git_command().args(["apply", "--index", "--reject"])
+        };
+        let cmd = cmd.paths(&files);
+        let before = undo::Position::of(&self.repository);
+        if mailbox {
+            self.journal_begin(Operation::Am, &cmd)?;
+        }
+        let output = cmd.output().map_err(|e| format!("Failed to apply: {}", e));
+        if mailbox {
+            journal::settle(&self.repository, Operation::Am);
+            undo::record(&self.repository, cmd.display(), before, "--keep");
+        }
+        let output = output?;
+        let report = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+        
+        if output.status.success() {
+            self.refresh_in_place();
+            let message = format!("Applied {} patch(es)", files.len());
+            self.notify_if_slow(started, &message);
+            self.show_toast(message, false);
+            return Ok(());
+        }
+        
+        let (title, outcome) = if mailbox {
+            // Leave the repository as it was rather than mid-`git am`
+            let in_progress = matches!(
+                self.repository.state(),
+                git2::RepositoryState::ApplyMailbox | git2::RepositoryState::ApplyMailboxOrRebase
+            );
+            let aborted = !in_progress || self.git_command().args(["am", "--abort"]).status().is_ok_and(|s| s.success());
+            let outcome = if aborted {
+                "git am stopped; nothing was applied."
+            } else {
+                "git am stopped and could not be aborted; run `git am --abort` or resolve and `git am --continue`."
+            };
+            journal::settle(&self.repository, Operation::Am);
+            ("Patch did not apply", outcome)
+        } else {
+            ("Patch applied with rejects", "Hunks that did not apply were saved as .rej files next to their files.")
+        };
+        self.refresh_in_place();
+        self.show_command_report(title, outcome, &report);
+        Ok(())
+    }
+    
+    /// Asks for the branch to push HEAD for review against, with options
+    fn open_review_push(&mut self) {
+        let branch = self.repository
+            .head()
+            .ok()
+            .filter(|h| h.is_branch())
+            .and_then(|h| h.shorthand().map(str::to_string));
+        let Some(remote) = gerrit::remote(&self.repository, branch.as_deref()) else {
+            self.show_toast("No remote to push to", true);
+            return;
+        };
+        let target = gerrit::default_target(&self.repository, branch.as_deref(), &remote).unwrap_or_default();
+        let title = format!("Push {} for review to {} (branch topic=… r=… cc=…)", self.head_label(), remote);
+        self.open_prompt(title, &target, PromptKind::ReviewPush(remote));
+    }
+    
+    /// `git push <remote> HEAD:refs/for/<target>%…`, reporting the change URL
+    fn push_for_review(&mut self, remote: &str, input: &str) -> Result<(), String> {
+        let push = gerrit::ReviewPush::parse(input)?;
+        let started = Instant::now();
+        let output = self.git_command()
+            .arg("push")
+            .rev(remote)
+            .arg(push.refspec())
+            .output()
+            .map_err(|e| format!("Failed to push: {}", e))?;
+        // git and Gerrit report on stderr
+        let report = format!("{}{}", String::from_utf8_lossy(&output.stderr), String::from_utf8_lossy(&output.stdout));
+        
+        if !output.status.success() {
+            return Err(match gerrit::rejection(&report) {
+                Some(reason) => format!("Push rejected: {}", reason),
+                None => report.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("Push failed").trim().to_string(),
+            });
+        }
+        
+        let urls = gerrit::change_urls(&report);
+        let message = match urls.as_slice() {
+            [] => format!("Pushed for review to {}", push.target),
+            [url] => format!("Change: {}", url),
+            [first, rest @ ..] => format!("Changes: {} and {} more", first, rest.len()),
+        };
+        self.notify_if_slow(started, &message);
+        self.show_toast(message, false);
+        Ok(())
+    }
+    
+    /// Keeps the conflicts screen up to date, opening it when the index
+    /// gained conflicts other than the ones last seen
+    fn reload_conflicts(&mut self) {
+        let items = conflicts::list(&self.repository).unwrap_or_default();
+        let paths: Vec<String> = items.iter().map(|c| c.path.clone()).collect();
+        if self.conflicts.is_some() || (!paths.is_empty() && paths != self.seen_conflicts) {
+            self.show_conflicts(items);
+        }
+        self.seen_conflicts = paths;
+    }
+    
+    fn open_conflicts(&mut self) {
+        let items = match conflicts::list(&self.repository) {
+            Ok(items) => items,
+            Err(e) => {
+                self.show_toast(format!("Failed to read the index: {}", e), true);
+                return;
+            }
+        };
+        if items.is_empty() && Operation::in_progress(self.repository.state()).is_none() {
+            self.show_toast("No conflicts", false);
+            return;
+        }
+        self.show_conflicts(items);
+    }
+    
+    /// Opens or updates the screen, keeping the selected file where it can
+    fn show_conflicts(&mut self, items: Vec<Conflict>) {
+        let selected = match self.conflicts {
+            Some(ref view) => view.items
+                .get(view.selected)
+                .and_then(|old| items.iter().position(|c| c.path == old.path))
+                .unwrap_or(view.selected.min(items.len().saturating_sub(1))),
+            None => 0,
+        };
+        self.conflicts = Some(ConflictView {
+            operation: Operation::in_progress(self.repository.state()),
+            items,
+            selected,
+            hunks: Vec::new(),
+        });
+        self.load_conflict_hunks();
+    }
+    
+    fn load_conflict_hunks(&mut self) {
+        let workdir = self.repository.workdir().unwrap_or_else(|| self.repository.path()).to_path_buf();
+        if let Some(ref mut view) = self.conflicts {
+            view.hunks = view.items
+                .get(view.selected)
+                .map(|c| conflicts::hunks(&workdir, &c.path))
+                .unwrap_or_default();
+        }
+    }
+    
+    fn selected_conflict(&self) -> Option<&Conflict> {
+        self.conflicts.as_ref().and_then(|view| view.items.get(view.selected))
+    }
+    
+    /// Asks before resolving the selected file with one side's version, or
+    /// by deleting it when that side deleted it: either replaces what is in
+    /// the working tree, hand edits included
+    fn request_take_side(&mut self, ours: bool) {
+        let Some(conflict) = self.selected_conflict().cloned() else {
+            return;
+        };
+        let side = if ours { "our" } else { "their" };
+        let deletes = conflict.deletes(ours);
+        let workdir = self.repository.workdir().unwrap_or_else(|| self.repository.path());
+        let edited = match workdir.join(&conflict.path).exists() && !conflicts::has_markers(workdir, &conflict.path) {
+            true => "\nIt has no conflict markers left: it may have been resolved by hand.",
+            false => "",
+        };
+        let message = match deletes {
+            true => format!("Delete {}, as {} side did?{}", conflict.path, side, edited),
+            false => format!("Replace {} with {} side's version?{}", conflict.path, side, edited),
+        };
+        self.confirm(PendingConfirm {
+            message,
+            action: ConfirmedAction::TakeSide { path: conflict.path, ours, deletes },
+            rewrites_published: false,
+        });
+    }
+
+    fn take_side(&mut self, path: &str, ours: bool, deletes: bool) {
+        let (side, flag) = if ours { ("our", "--ours") } else { ("their", "--theirs") };
+        let result = if deletes {
+            self.git_command().args(["rm", "--quiet"]).paths([path]).output()
+        } else {
+            match self.git_command().args(["checkout", flag]).paths([path]).output() {
+                Ok(output) if output.status.success() => self.git_command().arg("add").paths([path]).output(),
+                other => other,
+            }
+        };
+        self.finish_resolving(result, format!("Took {} side of {}", side, path));
+    }
+    
+    /// Stages a file resolved by hand, unless conflict markers are left in it
+    fn mark_resolved(&mut self) {
+        let Some(conflict) = self.selected_conflict().cloned() else {
+            return;
+        };
+        let workdir = self.repository.workdir().unwrap_or_else(|| self.repository.path());
+        if conflicts::has_markers(workdir, &conflict.path) {
+            self.show_toast(format!("{} still has conflict markers", conflict.path), true);
+            return;
+        }
+        let result = match workdir.join(&conflict.path).exists() {
+            true => self.git_command().arg("add").paths([&conflict.path]).output(),
+            false => self.git_command().args(["rm", "--quiet"]).paths([&conflict.path]).output(),
+        };
+        self.finish_resolving(result, format!("Marked {} resolved", conflict.path));
+    }
+    
+    fn finish_resolving(&mut self, result: Result<std::process::Output>, done: String) {
+        match result {
+            Ok(output) if output.status.success() => self.show_toast(done, false),
+            Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Err(e) => self.show_toast(format!("git failed: {}", e), true),
+        }
+        self.refresh_in_place();
+    }
+    
+    /// `git <command> --continue` once nothing is left conflicted; a rebase
+    /// stopping again on the next commit brings the screen back up
+    fn continue_operation(&mut self) {
+        let Some(operation) = self.conflicts.as_ref().and_then(|view| view.operation) else {
+            self.show_toast("No merge, rebase, cherry-pick or revert to continue", true);
+            return;
+        };
+        let remaining = self.conflicts.as_ref().map_or(0, |view| view.items.len());
+        if remaining > 0 {
+            self.show_toast(format!("{} file(s) still conflicted", remaining), true);
+            return;
+        }
+        // No editor can be shown here; the prepared message is kept
+        let cmd = self.git_command()
+            .args([operation.command(), "--continue"])
+            .env("GIT_EDITOR", "true");
+        self.run_operation_command(operation, cmd, format!("Continued git {}", operation.command()));
+    }
+    
+    fn request_abort_operation(&mut self) {
+        let Some(operation) = self.conflicts.as_ref().and_then(|view| view.operation) else {
+            self.show_toast("No merge, rebase, cherry-pick or revert to abort", true);
+            return;
+        };
+        self.confirm(PendingConfirm {
+            message: format!(
+                "Abort git {}?\nThe branch goes back to where it was before, dropping any resolutions made.",
+                operation.command()
+            ),
+            action: ConfirmedAction::AbortOperation(operation),
+            rewrites_published: false,
+        });
+    }
+    
+    fn run_operation_command(&mut self, operation: Operation, cmd: ExternalCommand, done: String) {
+        let before = undo::Position::of(&self.repository);
+        let result = cmd.output();
+        journal::settle(&self.repository, operation);
+        match result {
+            Ok(output) if output.status.success() => {
+                undo::record(&self.repository, cmd.display(), before, "--keep");
+                self.show_toast(done, false);
+                if !operation.stopped_in(self.repository.state()) {
+                    self.conflicts = None;
+                }
+            }
+            Ok(output) => {
+                let report = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+                self.show_command_report("Conflicts", &format!("{} failed", cmd.display()), &report);
+            }
+            Err(e) => self.show_toast(format!("{}: {}", cmd.display(), e), true),
+        }
+        self.refresh_in_place();
+    }
+    
+    /// Shows what merging the selected branch into HEAD would do, conflicts
+    /// included, without touching the working tree
+    fn preview_merge(&mut self) {
+        let Some(branch) = self.selected_git_branch() else {
+            return;
+        };
+        let name = branch.name.clone();
+        let reference = if branch.is_remote { format!("refs/remotes/{}", name) } else { format!("refs/heads/{}", name) };
+        let theirs = self.repository.refname_to_id(&reference);
+        let ours = self.repository.head().ok().and_then(|h| h.target());
+        let (Ok(theirs), Some(ours)) = (theirs, ours) else {
+            self.show_toast(format!("Cannot resolve {} or HEAD", name), true);
+            return;
+        };
+        let call = format!("git2: merge {} into {} in memory", short_id(&theirs.to_string()), short_id(&ours.to_string()));
+        let preview = match oplog::timed(call, || mergepreview::preview(&self.repository, ours, theirs)) {
+            Ok(preview) => preview,
+            Err(e) => {
+                self.show_toast(format!("Merge preview failed: {}", e.message()), true);
+                return;
+            }
+        };
+        
+        let head = self.head_label();
+        let (outcome, color) = match preview {
+            Preview::UpToDate => (format!("{} already contains {}; nothing to merge.", head, name), self.theme.commit_text),
+            Preview::FastForward(count) => (
+                format!("{} would fast-forward by {} commit(s) to {}.", head, count, name),
+                self.theme.diff_added,
+            ),
+            Preview::Clean(files) => (
+                format!("{} merges into {} without conflicts, changing {} file(s).", name, head, files),
+                self.theme.diff_added,
+            ),
+            Preview::Conflicts(ref conflicts) => (
+                format!("Merging {} into {} would conflict in {} file(s):", name, head, conflicts.len()),
+                self.theme.error,
+            ),
+        };
+        let mut lines = vec![Line::from(Span::styled(outcome, Style::default().fg(color).add_modifier(Modifier::BOLD)))];
+        if let Preview::Conflicts(conflicts) = preview {
+            lines.push(Line::from(""));
+            let width = conflicts.iter().map(|c| c.kind.len()).max().unwrap_or(0);
+            for conflict in conflicts {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<width$}  ", conflict.kind, width = width), Style::default().fg(self.theme.error)),
+                    Span::raw(conflict.path),
+                ]));
+            }
+        }
+        self.info = Some(InfoView {
+            title: format!("Merge preview: {} into {}", name, head),
+            lines,
+            scroll: 0,
+        });
+    }
+    
+    /// Output of a failed command in the info popup, errors highlighted
+    fn show_command_report(&mut self, title: &str, outcome: &str, output: &str) {
+        let mut lines = vec![
+            Line::from(Span::styled(outcome.to_string(), Style::default().add_modifier(Modifier::BOLD))),
+            Line::from(""),
+        ];
+        for line in output.lines() {
+            let is_error = line.starts_with("error") || line.starts_with("Rejected") || line.starts_with("Patch failed");
+            let color = if is_error { self.theme.error } else { self.theme.commit_text };
+            lines.push(Line::from(Span::styled(line.to_string(), Style::default().fg(color))));
+        }
+        self.info = Some(InfoView {
+            title: title.to_string(),
+            lines,
+            scroll: 0,
+        });
+    }
+    
+    fn copy_patch(&mut self, id: Oid) {
+        let result = self.repository
+            .find_commit(id)
+            .and_then(|commit| patches::email(&self.repository, &commit, 1, 1));
+        match result {
+            Ok(text) => match window::copy_to_clipboard(&text) {
+                Ok(()) => self.show_toast(format!("Copied patch ({} bytes) to the clipboard", text.len()), false),
+                Err(e) => self.show_toast(format!("Failed to copy: {}", e), true),
+            },
+            Err(e) => self.show_toast(format!("Failed to create patch: {}", e.message()), true),
+        }
+    }
+    
+    fn open_bisect_menu(&mut self) {
+        let Some(line) = self.graph_lines.get(self.selected_commit) else {
+            return;
+        };
+        if line.commit_id.is_empty() {
+            return;
+        }
+        let commit_id = line.commit_id.clone();
+        let mut actions = Vec::new();
+        let mut items = Vec::new();
+        match self.bisect {
+            None => {
+                items.push(format!("Start bisect: {} is bad", commit_id));
+                actions.push(BisectAction::Mark("bad"));
+                items.push(format!("Start bisect: {} is good", commit_id));
+                actions.push(BisectAction::Mark("good"));
+            }
+            Some(ref state) => {
+                if state.culprit.is_none() {
+                    for (term, label) in [("good", "Mark good"), ("bad", "Mark bad"), ("skip", "Skip (cannot be tested)")] {
+                        items.push(format!("{} {}", label, commit_id));
+                        actions.push(BisectAction::Mark(term));
+                    }
+                } else {
+                    items.push("Go to the first bad commit".to_string());
+                    actions.push(BisectAction::ShowCulprit);
+                }
+                items.push("End bisect (git bisect reset)".to_string());
+                actions.push(BisectAction::Reset);
+            }
+        }
+        self.menu = Some(Menu {
+            title: "Bisect".to_string(),
+            items,
+            selected: 0,
+            kind: MenuKind::Bisect(commit_id, actions),
+        });
+    }
+    
+    /// Marks a commit for `git bisect`, which then checks out the next
+    /// commit to test or reports the first bad one
+    fn bisect_mark(&mut self, term: &str, commit_id: &str) {
+        if self.bisect.is_none() {
+            match self.git_command().args(["bisect", "start"]).output() {
+                Ok(output) if output.status.success() => {}
+                Ok(output) => {
+                    self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true);
+                    return;
+                }
+                Err(e) => {
+                    self.show_toast(format!("git bisect failed: {}", e), true);
+                    return;
+                }
+            }
+        }
+        
+        let output = match self.git_command().arg("bisect").arg(term).rev(commit_id).output() {
+            Ok(output) => output,
+            Err(e) => {
+                self.show_toast(format!("git bisect failed: {}", e), true);
+                return;
+            }
+        };
+        let _ = self.refresh_data();
+        if !output.status.success() {
+            self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true);
+            return;
+        }
+        
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if let Some(culprit) = self.bisect.as_ref().and_then(|b| b.culprit) {
+            self.select_commit_row(&culprit.to_string());
+            let subject = self.repository.find_commit(culprit).ok().and_then(|c| c.summary().map(str::to_string));
+            self.show_toast(
+                format!("First bad commit: {} {}", &culprit.to_string()[..7], subject.unwrap_or_default()),
+                false,
+            );
+        } else {
+            // Follow the commit git checked out for testing
+            if let Some(ref head) = self.detached_head.clone() {
+                self.select_commit_row(head);
+            }
+            let status = stdout.lines().find(|l| !l.trim().is_empty()).unwrap_or("Marked");
+            self.show_toast(status.to_string(), false);
+        }
+    }
+    
+    fn bisect_reset(&mut self) {
+        match self.git_command().args(["bisect", "reset"]).output() {
+            Ok(output) if output.status.success() => {
+                let _ = self.refresh_data();
+                self.show_toast("Bisect ended", false);
+            }
+            Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Err(e) => self.show_toast(format!("git bisect failed: {}", e), true),
+        }
+    }
+    
+    /// Selects the graph row of a commit, when it is loaded, and in the
+    /// graph still loading once it is done
+    fn select_commit_row(&mut self, commit_id: &str) {
+        if let Some(index) = self.graph_row(commit_id) {
+            self.selected_commit = index;
+            self.commit_list_state.select(Some(index));
+        }
+        for running in &mut self.tasks {
+            if let TaskKind::Graph { ref mut then } | TaskKind::GraphCount { ref mut then } = running.kind {
+                *then = AfterGraph::Keep { commit_id: Some(commit_id.to_string()), row: self.selected_commit };
+            }
+        }
+    }
+    
+    /// Selects the selected commit's first parent, another parent of a merge
+    /// or a child, asking which when there are several
+    fn go_to_relative(&mut self, relative: Relative) {
+        let Some(commit) = self.get_selected_commit() else {
+            return;
+        };
+        let ids: Vec<String> = match relative {
+            Relative::FirstParent => commit.parents.iter().take(1).cloned().collect(),
+            Relative::MergeParent => commit.parents.iter().skip(1).cloned().collect(),
+            // Children are newer, so among the loaded commits if they are in the view
+            Relative::Child => {
+                let mut children: Vec<&GitCommit> = self.commits
+                    .values()
+                    .filter(|c| c.parents.contains(&commit.id))
+                    .collect();
+                children.sort_by_key(|c| self.graph_row(&c.id));
+                children.into_iter().map(|c| c.id.clone()).collect()
+            }
+        };
+        let noun = match relative {
+            Relative::FirstParent => "parent",
+            Relative::MergeParent => "other merge parent",
+            Relative::Child => "child",
+        };
+        match ids.len() {
+            0 => self.show_toast(format!("Commit has no {} in the graph", noun), true),
+            1 => self.jump_to_commit(&ids[0]),
+            _ => {
+                let items = ids
+                    .iter()
+                    .map(|id| {
+                        let summary = Oid::from_str(id)
+                            .ok()
+                            .and_then(|oid| self.commit_metadata(oid))
+                            .map(|c| commit_summary(&c.message))
+                            .unwrap_or_default();
+                        format!("{} {}", short_id(id), summary)
+                    })
+                    .collect();
+                self.menu = Some(Menu {
+                    title: format!("Go to {}", noun),
+                    items,
+                    selected: 0,
+                    kind: MenuKind::JumpTo(ids),
+                });
+            }
+        }
+    }
+    
+    fn toggle_pin(&mut self) {
+        let Some((id, summary)) = self.get_selected_commit().map(|c| (c.id.clone(), commit_summary(&c.message))) else {
+            return;
+        };
+        match pins::toggle(&self.cache_key_dir(), &id, &summary) {
+            Ok(pins) => {
+                self.pins = pins;
+                let message = match self.pins.iter().any(|pin| pin.id == id) {
+                    true => format!("Pinned {} (' lists pins)", short_id(&id)),
+                    false => format!("Unpinned {}", short_id(&id)),
+                };
+                self.show_toast(message, false);
+            }
+            Err(e) => self.show_toast(format!("Could not save pins: {}", e), true),
+        }
+    }
+    
+    /// The pins, newest first, to jump to one
+    fn open_pins_menu(&mut self) {
+        if self.pins.is_empty() {
+            self.show_toast("No pinned commits (m in the graph pins one)", true);
+            return;
+        }
+        let pins: Vec<&pins::Pin> = self.pins.iter().rev().collect();
+        let items = pins
+            .iter()
+            .map(|pin| format!("{} {}  · {}", short_id(&pin.id), pin.summary, pin.pinned.format("%Y-%m-%d")))
+            .collect();
+        self.menu = Some(Menu {
+            title: "Pinned commits".to_string(),
+            items,
+            selected: 0,
+            kind: MenuKind::JumpTo(pins.iter().map(|pin| pin.id.clone()).collect()),
+        });
+    }
+    
+    /// Selects a commit, loading older pages of history while it may be on
+    /// one of them
+    fn jump_to_commit(&mut self, commit_id: &str) {
+        self.jump_loaded(commit_id.to_string(), JUMP_PAGE_LIMIT);
+    }
+    
+    /// Selects the commit jumped to if the graph has it, else loads the next
+    /// page in the background while `pages` are left
+    fn jump_loaded(&mut self, commit_id: String, pages: usize) {
+        if self.graph_row(&commit_id).is_none() && self.has_more_commits && pages > 0 {
+            self.commit_limit += COMMIT_PAGE_SIZE;
+            self.reload_graph_then(AfterGraph::Jump { commit_id, pages: pages - 1 });
+            return;
+        }
+        match self.graph_row(&commit_id) {
+            Some(_) => self.select_commit_row(&commit_id),
+            None => self.show_toast(format!("{} is not in the graph (filtered or folded out)", short_id(&commit_id)), true),
+        }
+    }
+    
+    /// Checks out the selected branch; remote branches are checked out detached
+    fn checkout_selected_branch(&mut self) {
+        let Some(branch) = self.selected_git_branch() else {
+            return;
+        };
+        if branch.is_head {
+            self.show_toast("Already checked out", false);
+            return;
+        }
+        let (name, detach) = (branch.name.clone(), branch.is_remote);
+        self.checkout(&name, detach);
+    }
+    
+    /// Picks the selected local branch's upstream from the remote branches
+    fn open_upstream_menu(&mut self) {
+        let Some(branch) = self.selected_git_branch().filter(|b| !b.is_remote && !b.is_detached) else {
+            self.show_toast("Select a local branch to set its upstream", true);
+            return;
+        };
+        let (name, current) = (branch.name.clone(), branch.upstream.clone());
+        
+        // Every remote branch, including hidden ones, but not `origin/HEAD`
+        let remotes: Result<Vec<String>, git2::Error> = self.repository.branches(Some(BranchType::Remote)).map(|branches| {
+            branches
+                .flatten()
+                .filter(|(b, _)| b.get().kind() != Some(git2::ReferenceType::Symbolic))
+                .filter_map(|(b, _)| b.name().ok().flatten().map(str::to_string))
+                .collect()
+        });
+        let mut remotes = match remotes {
+            Ok(remotes) => remotes,
+            Err(e) => {
+                self.show_toast(format!("Failed to list remote branches: {}", e), true);
+                return;
+            }
+        };
+        if remotes.is_empty() {
+            self.show_toast("No remote branches to track", true);
+            return;
+        }
+        remotes.sort();
+        
+        let mut upstreams: Vec<Option<String>> = remotes.into_iter().map(Some).collect();
+        let mut items: Vec<String> = upstreams.iter().flatten().cloned().collect();
+        if current.is_some() {
+            upstreams.push(None);
+            items.push("Unset upstream".to_string());
+        }
+        // Start at the current upstream, else at the same name on a remote
+        let selected = upstreams
+            .iter()
+            .position(|u| u.is_some() && *u == current)
+            .or_else(|| items.iter().position(|i| i.split_once('/').is_some_and(|(_, b)| b == name)))
+            .unwrap_or(0);
+        self.menu = Some(Menu {
+            title: format!("Upstream of {}", name),
+            items,
+            selected,
+            kind: MenuKind::Upstream(name, upstreams),
+        });
+    }
+    
+    fn set_upstream(&mut self, branch: &str, upstream: Option<String>) {
+        let cmd = match upstream {
+            Some(ref upstream) => self.git_command().arg("branch").arg(format!("--set-upstream-to={}", upstream)),
+            None => self.git_command().args(["branch", "--unset-upstream"]),
+        };
+        match cmd.rev(branch).output() {
+            Ok(output) if output.status.success() => {
+                self.refresh_in_place();
+                let message = match upstream {
+                    Some(upstream) => format!("{} now tracks {}", branch, upstream),
+                    None => format!("{} no longer tracks a branch", branch),
+                };
+                self.show_toast(message, false);
+            }
+            Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Err(e) => self.show_toast(format!("Failed to set the upstream of {}: {}", branch, e), true),
+        }
+    }
+    
+    fn checkout(&mut self, rev: &str, detach: bool) {
+        let mut cmd = self.git_command().arg("checkout");
+        if detach {
+            cmd = cmd.arg("--detach");
+        }
+        let cmd = cmd.rev(rev);
+        let before = undo::Position::of(&self.repository);
+        match cmd.output() {
+            Ok(output) if output.status.success() => {
+                undo::record(&self.repository, cmd.display(), before, "--keep");
+                let _ = self.refresh_data();
+                self.select_branch_row(self.selected_branch);
+                let message = if detach {
+                    format!("HEAD detached at {}", rev)
+                } else {
+                    format!("Switched to {}", rev)
+                };
+                self.show_toast(message, false);
+            }
+            Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Err(e) => self.show_toast(format!("Failed to check out {}: {}", rev, e), true),
+        }
+    }
+    
+    fn open_prompt(&mut self, title: String, initial: &str, kind: PromptKind) {
+        self.prompt = Some(Prompt {
+            title,
+            input: TextInput::with_value(initial),
+            kind,
+        });
+    }
+    
+    fn submit_prompt(&mut self) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        let value = prompt.input.value().trim().to_string();
+        if value.is_empty() && !matches!(prompt.kind, PromptKind::DateRange | PromptKind::PathFilter | PromptKind::Pickaxe | PromptKind::CommitTypes | PromptKind::Changelog(_) | PromptKind::DiffSearch | PromptKind::RevisionRange) {
+            self.prompt = Some(prompt);
+            return;
+        }
+        let result = match prompt.kind {
+            PromptKind::NewBranch(ref commit_id) => self.create_ref("branch", &value, commit_id),
+            PromptKind::NewTag(ref commit_id) => self.create_ref("tag", &value, commit_id),
+            PromptKind::NewAnnotatedTag(ref commit_id) => self.annotate_tag(&value, commit_id),
+            PromptKind::ExportJson => self.write_export(&value),
+            PromptKind::ExportDot => self.write_dot_export(&value),
+            PromptKind::NewWorktree { ref rev, is_branch } => self.add_worktree(&value, rev, is_branch),
+            PromptKind::AuditReport => self.write_audit_report(Path::new(&value)),
+            PromptKind::DateRange => self.set_date_range(&value),
+            PromptKind::Pickaxe => self.set_pickaxe(&value),
+            PromptKind::CommitTypes => self.set_type_filter(&value),
+            PromptKind::Changelog(ref source) => self.write_changelog(source, &value),
+            PromptKind::PatchDir(ref ids) => self.write_patches(ids, &value),
+            PromptKind::ApplyPatch => self.apply_patches(&value),
+            PromptKind::ReviewPush(ref remote) => self.push_for_review(remote, &value),
+            PromptKind::DiffSearch => self.search_diff(value),
+            PromptKind::CompareWith(ref name) => self.compare_refs([name.as_str(), value.as_str()]),
+            PromptKind::RevisionRange => self.set_rev_range(&value),
+            PromptKind::Deepen => match value.trim().parse::<u32>() {
+                Ok(depth) if depth > 0 => {
+                    self.deepen(Some(depth));
+                    Ok(())
+                }
+                _ => Err(format!("'{}' is not a number of commits", value.trim())),
+            },
+            PromptKind::SparseAdd => self.add_sparse(&value),
+            PromptKind::PathFilter => {
+                self.path_filter = value.split_whitespace().map(str::to_string).collect();
+                self.commit_limit = COMMIT_PAGE_SIZE;
+                self.reload_graph();
+                Ok(())
+            }
+        };
+        if let Err(message) = result {
+            // Keep the prompt open so the input can be corrected
+            self.prompt = Some(prompt);
+            self.show_toast(message, true);
+        }
+    }
+    
+    /// `git branch` or `git tag` named `name` at `commit_id`
+    fn create_ref(&mut self, kind: &str, name: &str, commit_id: &str) -> Result<(), String> {
+        let output = self.git_command()
+            .arg(kind)
+            .rev(name)
+            .rev(commit_id)
+            .output()
+            .map_err(|e| format!("Failed to create {}: {}", kind, e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        let _ = self.refresh_data();
+        self.show_toast(format!("Created {} {} at {}", kind, name, commit_id), false);
+        Ok(())
+    }
+    
+    /// The editor git would start: `GIT_EDITOR`, `core.editor`, `VISUAL`,
+    /// `EDITOR`, else `vi`
+    fn editor(&self) -> Result<String, String> {
+        let output = self.git_command()
+            .args(["var", "GIT_EDITOR"])
+            .output()
+            .map_err(|e| format!("Cannot find the editor: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+    
+    /// Hands the terminal to the editor on `path` in the git directory,
+    /// holding `text` and the `help` comments; `then` takes the message
+    fn edit_message(&mut self, path: PathBuf, text: &str, help: &[String], then: Resume) {
+        let editor = match self.editor() {
+            Ok(editor) => editor,
+            Err(e) => {
+                self.show_toast(e, true);
+                return;
+            }
+        };
+        if let Err(e) = editor::write_draft(&path, text, help) {
+            self.show_toast(format!("{}: {}", path.display(), e), true);
+            return;
+        }
+        self.suspend = Some(Suspend { cmd: editor::open(&editor, &path), then });
+    }
+    
+    /// `commit.template`'s content, read the way git reads it: a relative
+    /// path from the top-level directory. None when it is not set.
+    fn commit_template(&self) -> Result<Option<String>, String> {
+        let config = self.repository.config().and_then(|mut config| config.snapshot()).map_err(|e| e.message().to_string())?;
+        let Ok(path) = config.get_path("commit.template") else {
+            return Ok(None);
+        };
+        let path = self.repository.workdir().unwrap_or(self.repository.path()).join(path);
+        std::fs::read_to_string(&path)
+            .map(Some)
+            .map_err(|e| format!("Cannot read commit.template {}: {}", path.display(), e))
+    }
+    
+    /// The `Key: value` lines of the trailers commit messages are prepared
+    /// with: `Signed-off-by` for the committer, then those of `[[trailers.add]]`
+    fn prepared_trailers(&self) -> Result<Vec<String>, String> {
+        let mut lines = Vec::new();
+        if self.signoff {
+            let output = self.git_command()
+                .args(["var", "GIT_COMMITTER_IDENT"])
+                .output()
+                .map_err(|e| format!("Cannot sign off: {}", e))?;
+            if !output.status.success() {
+                return Err(format!("Cannot sign off: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr))));
+            }
+            // `Name <email> 1760600000 +0200`
+            let ident = String::from_utf8_lossy(&output.stdout);
+            let ident = ident.rfind('>').map_or(ident.trim(), |end| &ident[..=end]);
+            lines.push(format!("Signed-off-by: {}", ident));
+        }
+        for trailer in &self.trailer_commands {
+            let output = self.trailer_command(trailer)
+                .output()
+                .map_err(|e| format!("[[trailers.add]] {}: {}", trailer.key, e))?;
+            let value = oplog::first_line(&String::from_utf8_lossy(&output.stdout));
+            if !output.status.success() || value.trim().is_empty() {
+                let reason = process::failure(output.status, &String::from_utf8_lossy(&output.stderr));
+                return Err(format!("[[trailers.add]] {} printed no value: {}", trailer.key, reason));
+            }
+            lines.push(format!("{}: {}", trailer.key, value.trim()));
+        }
+        Ok(lines)
+    }
+    
+    /// A `[[trailers.add]]` command, run by the shell in the top-level
+    /// directory; confirmed before it first runs, as it comes from the config
+    fn trailer_command(&self, trailer: &config::TrailerCommand) -> ExternalCommand {
+        ExternalCommand::new("sh")
+            .arg("-c")
+            .arg(&trailer.command)
+            .user_configured()
+            .current_dir(self.repository.workdir().unwrap_or_else(|| self.repository.path()))
+    }
+    
+    /// Commits what is staged, the message written in the editor. A merge
+    /// being concluded starts from git's prepared message, anything else
+    /// from `commit.template`; both get the configured trailers.
+    fn commit_staged(&mut self) {
+        let output = match self.git_command().args(["diff", "--cached", "--name-status"]).output() {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true);
+                return;
+            }
+            Err(e) => {
+                self.show_toast(format!("Failed to list staged changes: {}", e), true);
+                return;
+            }
+        };
+        let staged = String::from_utf8_lossy(&output.stdout).into_owned();
+        let merging = self.repository.state() == git2::RepositoryState::Merge;
+        if staged.trim().is_empty() && !merging {
+            self.show_toast("Nothing staged to commit", true);
+            return;
+        }
+        
+        let merge_message = std::fs::read_to_string(self.repository.path().join("MERGE_MSG")).ok();
+        let template = match merge_message {
+            Some(_) => None,
+            None => match self.commit_template() {
+                Ok(template) => template,
+                Err(e) => {
+                    self.show_toast(e, true);
+                    return;
+                }
+            },
+        };
+        // The commit starts over once a trailer command is approved
+        let unapproved = self.trailer_commands.iter().find_map(|trailer| self.trailer_command(trailer).needs_confirmation());
+        if let Some(needed) = unapproved {
+            self.confirm_command(needed, Approved::Commit);
+            return;
+        }
+        let trailers = match self.prepared_trailers() {
+            Ok(trailers) => trailers,
+            Err(e) => {
+                self.show_toast(e, true);
+                return;
+            }
+        };
+        let prepared = merge_message.as_deref().or(template.as_deref()).unwrap_or_default();
+        let prepared = prepared.lines().filter(|line| !line.starts_with('#')).collect::<Vec<_>>().join("\n");
+        let prepared = trailers::append(&prepared, &trailers);
+        let mut help = vec![
+            "Write the commit message. Lines starting with '#' are left out,".to_string(),
+            "and an empty message commits nothing.".to_string(),
+            String::new(),
+            format!("On {}, to be committed:", self.head_label()),
+        ];
+        help.extend(staged.lines().map(|line| format!("    {}", line.replace('\t', "  "))));
+        let path = self.repository.path().join("COMMIT_EDITMSG");
+        let before = undo::Position::of(&self.repository);
+        // A merge's message may be committed as git prepared it
+        let (template, trailers) = match merge_message {
+            Some(_) => (String::new(), Vec::new()),
+            None => (editor::clean(&template.unwrap_or_default()), trailers),
+        };
+        self.edit_message(path.clone(), &prepared, &help, Resume::Commit { path, before, template, trailers });
+    }
+    
+    /// Asks for the annotated tag's message in the editor
+    fn annotate_tag(&mut self, name: &str, commit_id: &str) -> Result<(), String> {
+        if self.repository.find_reference(&format!("refs/tags/{}", name)).is_ok() {
+            return Err(format!("Tag {} already exists", name));
+        }
+        let help = vec![
+            format!("Write the message for tag {} at {}.", name, commit_id),
+            "Lines starting with '#' are left out, and an empty message tags nothing.".to_string(),
+        ];
+        let path = self.repository.path().join("TAG_EDITMSG");
+        let then = Resume::Tag { name: name.to_string(), commit_id: commit_id.to_string(), path: path.clone() };
+        self.edit_message(path, "", &help, then);
+        Ok(())
+    }
+    
+    /// Edits the selected local branch's description in the editor, like
+    /// `git branch --edit-description`
+    fn edit_branch_description(&mut self) {
+        let Some(branch) = self.selected_git_branch().filter(|b| !b.is_remote && !b.is_detached) else {
+            self.show_toast("Select a local branch to describe it", true);
+            return;
+        };
+        let (name, current) = (branch.name.clone(), branch.description.clone().unwrap_or_default());
+        let help = vec![
+            format!("Describe branch {}: what it is for, shown in the Branches panel", name),
+            "and used by `git format-patch --cover-from-description` and".to_string(),
+            "`git request-pull`. Lines starting with '#' are left out, and an".to_string(),
+            "empty description removes it.".to_string(),
+        ];
+        let path = self.repository.path().join("EDIT_DESCRIPTION");
+        self.edit_message(path.clone(), &current, &help, Resume::Describe { branch: name, path });
+    }
+    
+    /// `git rebase -i` of HEAD onto the commit, its todo list edited in the
+    /// editor git starts
+    fn rebase_interactive(&mut self, commit_id: String) {
+        let cmd = self.git_command().args(["rebase", "--interactive"]).rev(&commit_id);
+        if let Err(e) = self.journal_begin(Operation::Rebase, &cmd) {
+            self.show_toast(e, true);
+            return;
+        }
+        let before = undo::Position::of(&self.repository);
+        self.suspend = Some(Suspend { cmd, then: Resume::Rebase { onto: commit_id, before } });
+    }
+    
+    /// Picks up after a command run with the interface suspended
+    fn resumed(&mut self, cmd: &ExternalCommand, then: Resume, result: Result<Output>) {
+        let output = match result {
+            Ok(output) => output,
+            Err(e) => {
+                if matches!(then, Resume::Rebase { .. }) {
+                    journal::settle(&self.repository, Operation::Rebase);
+                }
+                self.show_toast(format!("{}: {}", cmd.display(), e), true);
+                return;
+            }
+        };
+        let failure = || match oplog::first_line(&String::from_utf8_lossy(&output.stderr)) {
+            line if line.is_empty() => process::failure(output.status, ""),
+            line => line,
+        };
+        match then {
+            Resume::Commit { path, before, template, trailers } => {
+                let Some(message) = self.saved_message(&path, &output, "commit") else {
+                    return;
+                };
+                if (!template.is_empty() || !trailers.is_empty()) && editor::is_unedited(&message, &template, &trailers) {
+                    self.show_toast("The message was left as prepared: no commit made", true);
+                    return;
+                }
+                let cmd = self.git_command().args(["commit", "--cleanup=whitespace", "--file=-"]).input(message);
+                match cmd.output() {
+                    Ok(output) if output.status.success() => {
+                        undo::record(&self.repository, cmd.display(), before, "--soft");
+                        self.refresh_in_place();
+                        let head = self.repository.head().ok().and_then(|h| h.target()).map(|id| short_id(&id.to_string()));
+                        self.show_toast(format!("Committed {}", head.unwrap_or_default()), false);
+                    }
+                    Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+                    Err(e) => self.show_toast(format!("Failed to commit: {}", e), true),
+                }
+            }
+            Resume::Tag { name, commit_id, path } => {
+                let Some(message) = self.saved_message(&path, &output, "tag") else {
+                    return;
+                };
+                let cmd = self.git_command()
+                    .args(["tag", "--annotate", "--cleanup=whitespace", "--file=-"])
+                    .rev(&name)
+                    .rev(&commit_id)
+                    .input(message);
+                match cmd.output() {
+                    Ok(output) if output.status.success() => {
+                        let _ = self.refresh_data();
+                        self.show_toast(format!("Created annotated tag {} at {}", name, commit_id), false);
+                    }
+                    Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+                    Err(e) => self.show_toast(format!("Failed to tag: {}", e), true),
+                }
+            }
+            Resume::Describe { branch, path } => {
+                if !output.status.success() {
+                    self.show_toast(format!("Editor failed ({}); description left as it was", failure()), true);
+                    return;
+                }
+                let description = match editor::read_message(&path) {
+                    Ok(description) => description,
+                    Err(e) => {
+                        self.show_toast(format!("{}: {}", path.display(), e), true);
+                        return;
+                    }
+                };
+                let key = format!("branch.{}.description", branch);
+                let saved = oplog::timed(format!("git2: set {}", key), || {
+                    let mut config = self.repository.config()?.open_level(git2::ConfigLevel::Local)?;
+                    match description.is_empty() {
+                        true => match config.remove(&key) {
+                            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(()),
+                            result => result,
+                        },
+                        false => config.set_str(&key, &format!("{}\n", description)),
+                    }
+                });
+                match saved {
+                    Ok(()) => {
+                        let _ = self.refresh_data();
+                        let done = if description.is_empty() { "Removed the description of" } else { "Described" };
+                        self.show_toast(format!("{} {}", done, branch), false);
+                    }
+                    Err(e) => self.show_toast(format!("Failed to set {}: {}", key, e.message()), true),
+                }
+            }
+            Resume::Open => {
+                if !output.status.success() {
+                    self.show_toast(format!("{}: {}", cmd.display(), failure()), true);
+                }
+            }
+            Resume::Rebase { onto, before } => {
+                journal::settle(&self.repository, Operation::Rebase);
+                undo::record(&self.repository, cmd.display(), before, "--keep");
+                if output.status.success() {
+                    self.show_toast(format!("Rebased {} onto {}", self.head_label(), onto), false);
+                } else if !Operation::Rebase.stopped_in(self.repository.state()) {
+                    self.show_toast(format!("git rebase -i: {}", failure()), true);
+                }
+                // Stopped for an edit or on conflicts: the conflicts screen
+                // comes up with the refresh
+                self.refresh_in_place();
+            }
+        }
+    }
+    
+    /// Opens the file the diff view is in, at the line at the top of the
+    /// view; with `snapshot`, as of the diff's revision. With a `[diff]
+    /// filter` the view cannot be traced back to a file, so the diff's
+    /// files are listed instead.
+    fn open_diff_file(&mut self, snapshot: bool) {
+        if let Some(ref unfiltered) = self.diff_unfiltered {
+            let mut files = difflines::files(&unfiltered.lines().collect::<Vec<_>>());
+            match files.len() {
+                0 => self.show_toast("No files in this diff", true),
+                1 => self.open_file(files.remove(0), None, snapshot),
+                _ => {
+                    let items = files.iter().map(|(old, new)| new.clone().or(old.clone()).unwrap_or_default()).collect();
+                    self.menu = Some(Menu {
+                        title: "Open in the editor".to_string(),
+                        items,
+                        selected: 0,
+                        kind: MenuKind::OpenFile { files, snapshot },
+                    });
+                }
+            }
+            return;
+        }
+        let Some(ref diff) = self.current_diff else {
+            return;
+        };
+        let lines: Vec<&str> = diff.lines().collect();
+        let top = self.diff_scroll_offset as usize;
+        let Some(file) = difflines::file_at(&lines, top) else {
+            self.show_toast("No file at this point of the diff", true);
+            return;
+        };
+        // The first numbered line from the top, in the same file
+        let numbers = (top..lines.len())
+            .take_while(|&index| index == top || !lines[index].starts_with("diff "))
+            .map(|index| self.diff_numbers.get(index))
+            .find(|numbers| *numbers != (None, None));
+        let line = numbers.and_then(|(old, new)| match file.1 {
+            Some(_) => new.or(old),
+            None => old,
+        });
+        self.open_file(file, line, snapshot);
+    }
+    
+    fn open_file(&mut self, (old, new): difflines::FilePaths, line: Option<u32>, snapshot: bool) {
+        if snapshot
+            && let Some(remote) = self.promisor_remote.clone()
+            && let Some(blob) = self.missing_revision_blob(&old, &new)
+        {
+            self.fetch_objects(&remote, &[blob], Fetched::Open { file: (old, new), line });
+            return;
+        }
+        let path = if snapshot {
+            match self.revision_copy(old, new) {
+                Ok(path) => path,
+                Err(e) => {
+                    self.show_toast(e, true);
+                    return;
+                }
+            }
+        } else {
+            let Some(path) = new else {
+                self.show_toast(format!("{} was deleted; O opens it as it was", old.unwrap_or_default()), true);
+                return;
+            };
+            let full = self.repository.workdir().unwrap_or_else(|| self.repository.path()).join(&path);
+            if !full.exists() {
+                self.show_toast(format!("{} is not in the working tree; O opens it as of the commit", path), true);
+                return;
+            }
+            full
+        };
+        let cmd = match self.open_command {
+            Some(ref template) => editor::from_template(template, &path, line),
+            None => match self.editor() {
+                Ok(editor) => editor::open_at(&editor, &path, line),
+                Err(e) => {
+                    self.show_toast(e, true);
+                    return;
+                }
+            },
+        };
+        self.suspend = Some(Suspend { cmd, then: Resume::Open });
+    }
+    
+    /// Diffs the real content of the LFS file at the top of the view. With a
+    /// `[diff] filter` the view cannot be traced back to a file, so the
+    /// diff's LFS files are listed instead.
+    fn diff_lfs_file(&mut self) {
+        if let Some(ref unfiltered) = self.diff_unfiltered {
+            let files = difflines::files(&unfiltered.lines().collect::<Vec<_>>());
+            let mut files: Vec<difflines::FilePaths> = files.into_iter().filter(|file| self.lfs_pointers(file).is_some()).collect();
+            match files.len() {
+                0 => self.show_toast("No LFS files in this diff", true),
+                1 => self.fetch_lfs_content(files.remove(0)),
+                _ => {
+                    let items = files.iter().map(|(old, new)| new.clone().or(old.clone()).unwrap_or_default()).collect();
+                    self.menu = Some(Menu {
+                        title: "Diff the LFS content of".to_string(),
+                        items,
+                        selected: 0,
+                        kind: MenuKind::LfsDiff(files),
+                    });
+                }
+            }
+            return;
+        }
+        let Some(ref diff) = self.current_diff else {
+            return;
+        };
+        let lines: Vec<&str> = diff.lines().collect();
+        match difflines::file_at(&lines, self.diff_scroll_offset as usize) {
+            Some(file) if self.lfs_pointers(&file).is_some() => self.fetch_lfs_content(file),
+            _ => self.show_toast("No LFS file at this point of the diff", true),
+        }
+    }
+    
+    /// The pointer files of an LFS file of the diff, before and after it,
+    /// none on a side where it does not exist; None when it is not one
+    fn lfs_pointers(&self, (old, new): &difflines::FilePaths) -> Option<lfs::Pointers> {
+        let (before, after) = self.diff_revisions.as_ref()?;
+        let pointer = |rev: &str, path: &Option<String>| -> Option<Vec<u8>> {
+            let content = self.revision_content(rev, path.as_deref()?)?;
+            lfs::parse(&content).map(|_| content)
+        };
+        match (pointer(before, old), pointer(after, new)) {
+            (None, None) => None,
+            pointers => Some(pointers),
+        }
+    }
+    
+    /// What `oid`'s diff goes from: its first parent, or the empty tree for
+    /// a root commit, as in [`commitdiff::render`]
+    fn parent_revision(&self, oid: Oid) -> String {
+        match self.repository.find_commit(oid).map(|commit| commit.parent_count()) {
+            Ok(0) => Oid::hash_object(git2::ObjectType::Tree, &[]).map_or_else(|_| format!("{}^", oid), |empty| empty.to_string()),
+            _ => format!("{}^", oid),
+        }
+    }
+    
+    /// A file's content as of `rev`
+    fn revision_content(&self, rev: &str, path: &str) -> Option<Vec<u8>> {
+        let tree = self.repository.revparse_single(rev).and_then(|object| object.peel_to_tree()).ok()?;
+        let entry = tree.get_path(Path::new(path)).ok()?;
+        self.repository.find_blob(entry.id()).ok().map(|blob| blob.content().to_vec())
+    }
+    
+    /// Puts the thumbnails the last frame left room for on the terminal.
+    /// Only when they change: once drawn they stay until the screen under
+    /// them is.
+    fn place_images<B: ratatui::backend::Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        // Anything drawn over the diff hides them
+        let shown = self.key_context() == Context::Diff;
+        let key = self.image_slots.as_ref().filter(|_| shown).map(|slots| {
+            format!("{:?} {:?} {:?} {:?}", self.diff_revisions, slots.file, slots.before, slots.after)
+        });
+        let Some(protocol) = self.image_protocol else {
+            return Ok(());
+        };
+        if key == self.images_shown {
+            return Ok(());
+        }
+        let mut out = io::stdout();
+        if self.images_shown.take().is_some() {
+            match protocol.is_layered() {
+                true => write!(out, "{}{}", images::kitty_delete(1), images::kitty_delete(2))?,
+                // Drawn into the cells, which only a full redraw paints over
+                false => {
+                    terminal.clear()?;
+                    terminal.draw(|f| draw_ui(f, self))?;
+                }
+            }
+        }
+        let mut failed = None;
+        let slots = self.image_slots.as_ref().filter(|_| shown);
+        if let (Some(slots), Some((before, after))) = (slots, self.diff_revisions.as_ref()) {
+            let sides = [(1, before, &slots.file.0, slots.before), (2, after, &slots.file.1, slots.after)];
+            for (id, rev, path, area) in sides {
+                let Some(content) = path.as_deref().and_then(|path| self.revision_content(rev, path)) else {
+                    continue;
+                };
+                if let Err(e) = draw_image(&mut out, protocol, id, &content, area) {
+                    failed = Some(e);
+                }
+            }
+        }
+        if let Some(e) = failed {
+            self.show_toast(format!("Could not show the image: {}", e), true);
+        }
+        out.flush()?;
+        self.images_shown = key;
+        Ok(())
+    }
+    
+    /// Has `git lfs smudge` turn the file's pointers into the real content
+    /// in the background, downloading what is not in the local LFS store
+    fn fetch_lfs_content(&mut self, file: difflines::FilePaths) {
+        if self.tasks.iter().any(|running| matches!(running.kind, TaskKind::LfsDiff { .. })) {
+            self.show_toast("Already fetching LFS content", true);
+            return;
+        }
+        let Some((old, new)) = self.lfs_pointers(&file) else {
+            return;
+        };
+        let mut commands = Vec::new();
+        if let (Some(pointer), Some(path)) = (old, file.0.as_deref()) {
+            commands.push(("old".to_string(), lfs::smudge(self.git_command(), path, pointer)));
+        }
+        if let (Some(pointer), Some(path)) = (new, file.1.as_deref()) {
+            commands.push(("new".to_string(), lfs::smudge(self.git_command(), path, pointer)));
+        }
+        let path = file.1.clone().or(file.0.clone()).unwrap_or_default();
+        self.start_task(Task::start(format!("Fetching LFS content of {}", path), commands), TaskKind::LfsDiff { file });
+    }
+    
+    /// Shows the diff of the real content in place of the commit's, while
+    /// the diff view is still open on it
+    fn lfs_content_fetched(&mut self, task: &mut Task, (old_path, new_path): difflines::FilePaths) {
+        let (mut old, mut new) = (Vec::new(), Vec::new());
+        for (side, result) in task.results() {
+            let content = match result {
+                Ok(output) if output.status.success() => output.stdout,
+                Ok(output) => {
+                    let message = format!("git lfs smudge: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+                    self.show_toast(message, true);
+                    return;
+                }
+                Err(e) => {
+                    self.show_toast(format!("Failed to run git lfs: {}", e), true);
+                    return;
+                }
+            };
+            match side.as_str() {
+                "old" => old = content,
+                _ => new = content,
+            }
+        }
+        if !self.show_diff && !self.split_diff {
+            return;
+        }
+        let patch = commitdiff::buffers(&old, old_path.as_deref(), &new, new_path.as_deref());
+        let path = new_path.or(old_path).unwrap_or_default();
+        let mut lines = vec![format!("LFS content of {} (Esc, then Enter: back to the commit)", path), String::new()];
+        match patch {
+            Ok(text) if text.is_empty() => lines.push("The content is the same".to_string()),
+            Ok(text) => lines.extend(text.lines().map(str::to_string)),
+            Err(e) => lines.push(format!("Error diffing the content: {}", e.message())),
+        }
+        self.clear_diff_view();
+        self.append_diff_lines(lines);
+        self.notify_if_slow(task.started, &format!("Fetched the LFS content of {}", path));
+    }
+    
+    /// The blob [`revision_copy`](Self::revision_copy) would read, when a
+    /// partial clone left it out
+    fn missing_revision_blob(&self, old: &Option<String>, new: &Option<String>) -> Option<Oid> {
+        let (before, after) = self.diff_revisions.as_ref()?;
+        let (rev, path) = match (new, old) {
+            (Some(path), _) => (after, path),
+            (None, Some(path)) => (before, path),
+            (None, None) => return None,
+        };
+        let tree = self.repository.revparse_single(rev).and_then(|object| object.peel_to_tree()).ok()?;
+        let id = tree.get_path(Path::new(path)).ok()?.id();
+        partial::missing(&self.repository, [id]).ok()?.pop()
+    }
+    
+    /// Fetches blobs a partial clone left out in the background, for
+    /// `then` to go on with once they are here. A diff's fetch replaces one
+    /// still running for a diff the selection has moved away from.
+    fn fetch_objects(&mut self, remote: &str, ids: &[Oid], then: Fetched) {
+        if let Fetched::Diff(oid) = then {
+            let same = |running: &RunningTask| matches!(running.kind, TaskKind::FetchObjects { then: Fetched::Diff(id) } if id == oid);
+            if self.tasks.iter().any(same) {
+                return;
+            }
+            self.tasks.retain(|running| !matches!(running.kind, TaskKind::FetchObjects { then: Fetched::Diff(_) }));
+        }
+        let label = format!("Fetching {} missing blob(s) from {}", ids.len(), remote);
+        let cmd = partial::fetch(self.git_command(), remote, ids);
+        self.start_task(Task::start(label, vec![(String::new(), cmd)]), TaskKind::FetchObjects { then });
+    }
+    
+    fn objects_fetched(&mut self, task: &mut Task, then: Fetched) {
+        let error = match task.results().pop().map(|(_, result)| result) {
+            Some(Ok(output)) if output.status.success() => None,
+            Some(Ok(output)) => Some(format!("git fetch: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)))),
+            Some(Err(e)) => Some(format!("Failed to run git fetch: {}", e)),
+            None => return,
+        };
+        match then {
+            Fetched::Diff(oid) => {
+                // Only while the diff still waits for it
+                let selected = self.graph_lines.get(self.selected_commit).map(|line| line.commit_id.clone());
+                let selected = selected.and_then(|id| self.resolve_short_id(&id).ok());
+                let shown = self.diff_revisions.as_ref().is_some_and(|(_, after)| *after == oid.to_string());
+                if selected != Some(oid) || !shown || !(self.show_diff || self.split_diff) {
+                    return;
+                }
+                match error {
+                    None => {
+                        self.load_selected_diff();
+                    }
+                    Some(error) => {
+                        self.clear_diff_view();
+                        self.append_diff_lines(vec![format!("Could not fetch the blobs of this commit: {}", error)]);
+                    }
+                }
+            }
+            Fetched::Open { file, line } => match error {
+                None => self.open_file(file, line, true),
+                Some(error) => self.show_toast(error, true),
+            },
+        }
+    }
+    
+    /// Writes the file as of the diff's revision (as of before it, when the
+    /// diff deletes it) under the temporary directory, read-only
+    fn revision_copy(&self, old: Option<String>, new: Option<String>) -> Result<PathBuf, String> {
+        let Some((ref before, ref after)) = self.diff_revisions else {
+            return Err("This diff has no revision to read files at".to_string());
+        };
+        let (rev, path) = match (new, old) {
+            (Some(path), _) => (after, path),
+            (None, Some(path)) => (before, path),
+            (None, None) => return Err("No file at this point of the diff".to_string()),
+        };
+        let blob = self.repository
+            .revparse_single(&format!("{}:{}", rev, path))
+            .and_then(|object| object.peel_to_blob())
+            .map_err(|e| format!("{}:{}: {}", rev, path, e.message()))?;
+        let commit = self.repository.revparse_single(rev).map_or_else(|_| rev.clone(), |object| short_id(&object.id().to_string()));
+        let target = std::env::temp_dir().join(format!("git-tui-{}", commit)).join(&path);
+        let write = || -> io::Result<()> {
+            if let Some(dir) = target.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            // A copy left read-only by an earlier open
+            let _ = std::fs::remove_file(&target);
+            std::fs::write(&target, blob.content())?;
+            let mut permissions = std::fs::metadata(&target)?.permissions();
+            permissions.set_readonly(true);
+            std::fs::set_permissions(&target, permissions)
+        };
+        write().map_err(|e| format!("{}: {}", target.display(), e))?;
+        Ok(target)
+    }
+    
+    /// The message saved in the editor, unless it was left empty or the
+    /// editor failed, which is said instead
+    fn saved_message(&mut self, path: &Path, output: &Output, what: &str) -> Option<String> {
+        if !output.status.success() {
+            let message = format!("The editor failed ({}); no {} made", process::failure(output.status, ""), what);
+            self.show_toast(message, true);
+            return None;
+        }
+        match editor::read_message(path) {
+            Ok(message) if message.is_empty() => {
+                self.show_toast(format!("Empty message: no {} made", what), true);
+                None
+            }
+            Ok(message) => Some(message),
+            Err(e) => {
+                self.show_toast(format!("{}: {}", path.display(), e), true);
+                None
+            }
+        }
+    }
+    
+    /// Writes the JSON export to `path`, relative to the working directory
+    fn write_export(&mut self, path: &str) -> Result<(), String> {
+        let path = self.repository.workdir().unwrap_or_else(|| self.repository.path()).join(path);
+        let json = self.export_json().map_err(|e| format!("Export failed: {}", e))?;
+        std::fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.show_toast(format!("Exported graph to {}", path.display()), false);
+        Ok(())
+    }
+    
+    /// Counts the lines each file of the selected commit gained and lost,
+    /// for Commit Details, when the selection moved to another commit
+    fn count_selected_files(&mut self) {
+        let Some(id) = self.get_selected_commit().and_then(|commit| Oid::from_str(&commit.id).ok()) else {
+            return;
+        };
+        if self.details_stat.as_ref().is_some_and(|(counted, _)| *counted == id) {
+            return;
+        }
+        let stats = oplog::timed("git2: diffstat", || diffstat::file_stats(&self.repository, id)).ok();
+        self.details_stat = Some((id, stats));
+    }
+    
+    /// Writes the DOT export to `path`, relative to the working directory.
+    /// For an `.svg` path the DOT file is written beside it and rendered by
+    /// Graphviz in the background.
+    fn write_dot_export(&mut self, path: &str) -> Result<(), String> {
+        let path = self.repository.workdir().unwrap_or_else(|| self.repository.path()).join(path);
+        let svg = path.extension().is_some_and(|ext| ext == "svg");
+        let dot_path = if svg { path.with_extension("dot") } else { path.clone() };
+        std::fs::write(&dot_path, self.export_dot()).map_err(|e| format!("{}: {}", dot_path.display(), e))?;
+        if !svg {
+            self.show_toast(format!("Exported graph to {}", path.display()), false);
+            return Ok(());
+        }
+        let task = Task::start("Rendering SVG", vec![(path.display().to_string(), dot::render_svg(&dot_path, &path))]);
+        self.start_task(task, TaskKind::RenderSvg);
+        Ok(())
+    }
+    
+    fn svg_rendered(&mut self, task: &mut Task) {
+        let Some((path, result)) = task.results().pop() else {
+            return;
+        };
+        match result {
+            Ok(output) if output.status.success() => self.show_toast(format!("Exported graph to {}", path), false),
+            Ok(output) => {
+                let message = format!("dot: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+                self.show_toast(message, true);
+            }
+            Err(e) => self.show_toast(format!("Wrote the DOT file, but Graphviz could not render it ({}): {}", path, e), true),
+        }
+    }
+    
+    /// Starts counting the lines and files the loaded commits change, which
+    /// the statistics open with once done
+    fn count_changes(&mut self) {
+        if self.commits.is_empty() {
+            self.show_toast("No commits loaded", true);
+            return;
+        }
+        if self.tasks.iter().any(|running| matches!(running.kind, TaskKind::Stats)) {
+            return;
+        }
+        let ids: String = self.commits.keys().map(|id| format!("{}\n", id)).collect();
+        let cmd = self
+            .git_command()
+            .args(["log", "--no-walk=unsorted", "--stdin", "--no-renames", "--numstat", "--format=%x00%H"])
+            .input(ids);
+        self.start_task(Task::start("Counting changes", vec![(String::new(), cmd)]), TaskKind::Stats);
+    }
+    
+    /// Opens the statistics; without the line counts when git failed
+    fn stats_counted(&mut self, task: &mut Task) {
+        let numstat = match task.results().pop().map(|(_, result)| result) {
+            Some(Ok(output)) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+            Some(Ok(output)) => {
+                let message = format!("git log --numstat: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+                self.show_toast(message, true);
+                String::new()
+            }
+            Some(Err(e)) => {
+                self.show_toast(format!("Failed to run git log: {}", e), true);
+                String::new()
+            }
+            None => String::new(),
+        };
+        let commits: Vec<stats::Commit> = self.commits
+            .values()
+            .map(|commit| stats::Commit {
+                id: &commit.id,
+                author: &commit.author,
+                time: commit.timestamp,
+                is_merge: commit.parents.len() > 1,
+            })
+            .collect();
+        self.stats = stats::compute(&commits, &numstat);
+    }
+    
+    /// The annotated tags pointing at the selected commit, in the order of
+    /// its refs
+    fn selected_annotations(&self) -> Vec<tags::Annotation> {
+        let Some(commit) = self.get_selected_commit() else {
+            return Vec::new();
+        };
+        commit.refs
+            .iter()
+            .filter_map(|r| r.strip_prefix("tag: "))
+            .filter_map(|name| tags::annotation(&self.repository, name))
+            .collect()
+    }
+    
+    /// Starts checking the signatures of the selected commit's signed tags
+    /// that were not checked yet, for Commit Details to show
+    fn verify_selected_tags(&mut self) {
+        for annotation in self.selected_annotations() {
+            if !annotation.signed || self.tag_verifications.contains_key(&annotation.id) {
+                continue;
+            }
+            self.tag_verifications.insert(annotation.id, tags::Verification::Checking);
+            let task = Task::start(
+                format!("Verifying tag {}", annotation.name),
+                vec![(annotation.name, tags::verify(self.git_command(), annotation.id))],
+            );
+            self.start_task(task, TaskKind::VerifyTag { id: annotation.id });
+        }
+    }
+    
+    fn tag_verified(&mut self, task: &mut Task, id: Oid) {
+        let verification = match task.results().pop().map(|(_, result)| result) {
+            Some(Ok(output)) => tags::parse_verification(output.status.success(), &String::from_utf8_lossy(&output.stderr)),
+            Some(Err(e)) => tags::Verification::Bad(format!("cannot run git verify-tag: {}", e)),
+            None => return,
+        };
+        self.tag_verifications.insert(id, verification);
+    }
+    
+    /// Records the selected commit in the audit trail, once per selection
+    fn audit_selected_commit(&mut self) {
+        if self.audit.is_none() {
+            return;
+        }
+        let Some((id, summary)) = self.get_selected_commit().map(|c| (c.id.clone(), commit_summary(&c.message))) else {
+            return;
+        };
+        self.audit_record(AuditEvent::Commit, &id, &summary);
+    }
+    
+    fn audit_record(&mut self, event: AuditEvent, commit_id: &str, summary: &str) {
+        let repository = self.repository.workdir().unwrap_or_else(|| self.repository.path()).to_path_buf();
+        if let Some(ref mut audit) = self.audit {
+            audit.record(event, &repository, commit_id, summary);
+        }
+    }
+    
+    /// Writes the audit report to `path`, relative to the directory git-tui was
+    /// started in rather than the repository, which is not written to
+    fn write_audit_report(&mut self, path: &Path) -> Result<(), String> {
+        let Some(ref audit) = self.audit else {
+            return Err("Not in audit mode".to_string());
+        };
+        audit.write_report(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let count = audit.len();
+        self.show_toast(format!("Wrote audit report ({} entries) to {}", count, path.display()), false);
+        Ok(())
+    }
+    
+    /// Branches, loaded commits and graph rows as pretty-printed JSON
+    fn export_json(&self) -> serde_json::Result<String> {
+        let full_ids: HashMap<&str, &str> = self.commits
+            .values()
+            .map(|c| (c.short_id.as_str(), c.id.as_str()))
+            .collect();
+        
+        let mut commits: Vec<&GitCommit> = self.commits.values().collect();
+        commits.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.id.cmp(&b.id)));
+        
+        let rows = self.graph_lines
+            .iter()
+            .map(|line| GraphRowExport {
+                commit_id: full_ids.get(line.commit_id.as_str()).copied(),
+                graph: &line.graph_text,
+                // Lanes are two columns wide
+                lane: line.graph_text.find(['*', 'o']).map(|col| col / 2),
+                is_boundary: line.is_boundary,
+                folded: line.folded,
+            })
+            .collect();
+        
+        serde_json::to_string_pretty(&GraphExport {
+            branches: &self.branches,
+            commits,
+            rows,
+        })
+    }
+    
+    /// Loaded commits, newest first, as a Graphviz digraph named after the
+    /// repository
+    fn export_dot(&self) -> String {
+        let mut commits: Vec<&GitCommit> = self.commits.values().collect();
+        commits.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.id.cmp(&b.id)));
+        let nodes: Vec<dot::Node> = commits
+            .iter()
+            .map(|commit| dot::Node {
+                id: &commit.id,
+                short_id: &commit.short_id,
+                summary: commit.message.lines().next().unwrap_or_default().trim(),
+                author: &commit.author,
+                refs: &commit.refs,
+                parents: &commit.parents,
+            })
+            .collect();
+        dot::graph(&self.repo_name(), &nodes)
+    }
+    
+    fn reset_current_branch(&mut self, mode: &str, commit_id: &str) {
+        let cmd = self.git_command().arg("reset").arg(mode).rev(commit_id);
+        if let Err(e) = self.journal_begin(Operation::Reset, &cmd) {
+            self.show_toast(e, true);
+            return;
+        }
+        let before = undo::Position::of(&self.repository);
+        let result = cmd.output();
+        journal::settle(&self.repository, Operation::Reset);
+        match result {
+            Ok(output) if output.status.success() => {
+                // A hard reset's discarded changes are gone either way
+                let undo_mode = if mode == "--hard" { "--keep" } else { mode };
+                undo::record(&self.repository, cmd.display(), before, undo_mode);
+                let _ = self.refresh_data();
+                self.show_toast(format!("Reset {} to {}", self.head_label(), commit_id), false);
+            }
+            Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Err(e) => self.show_toast(format!("Failed to reset: {}", e), true),
+        }
+    }
+    
+    /// Offers to take HEAD back to before the last command git-tui ran that
+    /// moved it, spelling out what changes
+    fn request_undo(&mut self) {
+        let record = match undo::last(&self.repository) {
+            Ok(record) => record,
+            Err(undo::Blocked::Nothing) => {
+                self.show_toast("Nothing to undo", false);
+                return;
+            }
+            Err(undo::Blocked::MovedSince(message)) => {
+                self.show_toast(format!("HEAD was moved outside git-tui since ({}); nothing to undo", message), true);
+                return;
+            }
+        };
+        let position = |p: &undo::Position| match p.branch {
+            Some(ref branch) => format!("{} at {}", branch, short_id(&p.head)),
+            None => format!("detached at {}", short_id(&p.head)),
+        };
+        let mut message = format!(
+            "Undo {} ({})?\n\nHEAD: {} -> {}",
+            record.command,
+            relative_time(record.time.with_timezone(&Utc), Utc::now()),
+            position(&record.after),
+            position(&record.before),
+        );
+        let mut warning = None;
+        // Switching back leaves every branch where it is
+        if record.before.branch == record.after.branch
+            && let (Ok(before), Ok(after)) = (Oid::from_str(&record.before.head), Oid::from_str(&record.after.head))
+        {
+            let label = record.after.branch.as_deref().unwrap_or("HEAD");
+            message.push_str(&self.undo_commit_list(&format!("Leaving {}", label), after, before));
+            message.push_str(&self.undo_commit_list(&format!("Back on {}", label), before, after));
+            warning = published::check(&self.repository, before, after).ok().and_then(|check| check.warning());
+        }
+        message.push_str(&format!("\n\nRuns: {}", self.undo_command(&record).display()));
+        if let Some(ref warning) = warning {
+            message.push_str("\n\n");
+            message.push_str(warning);
+        }
+        self.confirm(PendingConfirm {
+            message,
+            action: ConfirmedAction::Undo(record),
+            rewrites_published: warning.is_some(),
+        });
+    }
+    
+    /// Commits reachable from `tip` but not `base`, a few of them listed
+    fn undo_commit_list(&self, title: &str, tip: Oid, base: Oid) -> String {
+        const SHOWN: usize = 5;
+        let Ok(mut walk) = self.repository.revwalk() else {
+            return String::new();
+        };
+        if walk.push(tip).is_err() || walk.hide(base).is_err() {
+            return String::new();
+        }
+        let ids: Vec<Oid> = walk.flatten().collect();
+        if ids.is_empty() {
+            return String::new();
+        }
+        let mut list = format!("\n\n{} ({}):", title, ids.len());
+        for id in ids.iter().take(SHOWN) {
+            let summary = self.repository.find_commit(*id).ok().and_then(|c| c.summary().map(str::to_string));
+            list.push_str(&format!("\n  {} {}", short_id(&id.to_string()), summary.unwrap_or_default()));
+        }
+        if ids.len() > SHOWN {
+            list.push_str(&format!("\n  ... and {} more", ids.len() - SHOWN));
+        }
+        list
+    }
+    
+    /// Checks the branch out again when the command switched branches,
+    /// otherwise resets back to where HEAD was
+    fn undo_command(&self, record: &undo::Record) -> ExternalCommand {
+        if record.before.branch == record.after.branch {
+            return self.git_command().arg("reset").arg(&record.reset_mode).rev(&record.before.head);
+        }
+        match record.before.branch {
+            Some(ref branch) => self.git_command().arg("checkout").rev(branch),
+            None => self.git_command().args(["checkout", "--detach"]).rev(&record.before.head),
+        }
+    }
+    
+    fn undo(&mut self, record: &undo::Record) {
+        let cmd = self.undo_command(record);
+        match cmd.output() {
+            Ok(output) if output.status.success() => {
+                undo::undone(&self.repository);
+                self.refresh_in_place();
+                self.show_toast(format!("Undid {}", record.command), false);
+            }
+            Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Err(e) => self.show_toast(format!("{}: {}", cmd.display(), e), true),
+        }
+    }
+    
+    /// Records `cmd` as running, so a session that dies before it finishes
+    /// is noticed on the next start
+    fn journal_begin(&self, operation: Operation, cmd: &ExternalCommand) -> Result<(), String> {
+        let entry = journal::Entry::new(&self.repository, operation, cmd.display());
+        journal::begin(&self.repository, &entry).map_err(|e| format!("Cannot record the operation in the git directory: {}", e))
+    }
+    
+    /// Offers to resume, abort or inspect an operation a previous session
+    /// started but did not see through (it crashed or its terminal closed)
+    fn offer_interrupted_operation(&mut self) {
+        self.interrupted = journal::load(&self.repository);
+        if self.interrupted.is_some() {
+            self.open_interrupted_menu();
+        }
+    }
+    
+    fn open_interrupted_menu(&mut self) {
+        let Some(ref entry) = self.interrupted else {
+            return;
+        };
+        let command = entry.operation.command();
+        let head = self.repository.head().ok().and_then(|h| h.target()).map(|id| id.to_string());
+        
+        let mut actions = Vec::new();
+        let mut items = Vec::new();
+        if !self.read_only {
+            if entry.operation.stopped_in(self.repository.state()) {
+                actions.push(RecoveryAction::Continue);
+                items.push(format!("Resume (git {} --continue)", command));
+                actions.push(RecoveryAction::Abort);
+                items.push(format!("Abort (git {} --abort)", command));
+            } else if let Some(ref before) = entry.head
+                && head.as_ref() != Some(before)
+            {
+                items.push(format!("Go back to {} (git reset --keep)", short_id(before)));
+                actions.push(RecoveryAction::Restore(before.clone()));
+            }
+        }
+        actions.push(RecoveryAction::Inspect);
+        items.push("Inspect".to_string());
+        actions.push(RecoveryAction::Dismiss);
+        items.push("Dismiss and leave the repository as it is".to_string());
+        
+        self.menu = Some(Menu {
+            title: format!("Interrupted: git {} at {}", command, entry.started.format("%Y-%m-%d %H:%M")),
+            items,
+            selected: 0,
+            kind: MenuKind::Interrupted(actions),
+        });
+    }
+    
+    fn recover_interrupted(&mut self, action: RecoveryAction) {
+        let Some(entry) = self.interrupted.clone() else {
+            return;
+        };
+        let command = entry.operation.command();
+        let (cmd, done) = match action {
+            RecoveryAction::Inspect => {
+                self.inspect_interrupted(&entry);
+                return;
+            }
+            RecoveryAction::Dismiss => {
+                journal::clear(&self.repository);
+                self.interrupted = None;
+                return;
+            }
+            // No editor can be shown here; the prepared message is kept
+            RecoveryAction::Continue => (
+                self.git_command().args([command, "--continue"]).env("GIT_EDITOR", "true"),
+                format!("Resumed git {}", command),
+            ),
+            RecoveryAction::Abort => (self.git_command().args([command, "--abort"]), format!("Aborted git {}", command)),
+            RecoveryAction::Restore(ref head) => (
+                self.git_command().args(["reset", "--keep"]).rev(head),
+                format!("Back at {}", short_id(head)),
+            ),
+        };
+        
+        self.interrupted = None;
+        match cmd.output() {
+            Ok(output) if output.status.success() => {
+                journal::clear(&self.repository);
+                self.show_toast(done, false);
+            }
+            Ok(output) => {
+                let report = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+                let outcome = format!("{} failed; the operation is offered again on the next start.", cmd.display());
+                self.show_command_report("Interrupted operation", &outcome, &report);
+            }
+            Err(e) => self.show_toast(format!("{}: {}", cmd.display(), e), true),
+        }
+        self.refresh_in_place();
+    }
+    
+    /// What was running, where the repository was and where it is now
+    fn inspect_interrupted(&mut self, entry: &journal::Entry) {
+        let head_now = self.repository.head().ok().and_then(|h| h.target()).map(|id| id.to_string());
+        let stopped = entry.operation.stopped_in(self.repository.state());
+        let label = Style::default().fg(self.theme.commit_hash);
+        let field = |name: &str, value: String| {
+            Line::from(vec![Span::styled(format!("{:<13}", name), label), Span::raw(value)])
+        };
+        let state = if stopped {
+            format!("stopped part way through git {}", entry.operation.command())
+        } else {
+            "no operation in progress".to_string()
+        };
+        
+        let mut lines = vec![
+            field("Command:", entry.command.clone()),
+            field("Started:", entry.started.format("%Y-%m-%d %H:%M:%S").to_string()),
+            field("Branch:", entry.branch.clone().unwrap_or_else(|| "(detached)".to_string())),
+            field("HEAD before:", entry.head.as_deref().map_or("(none)".to_string(), short_id)),
+            field("HEAD now:", head_now.as_deref().map_or("(none)".to_string(), short_id)),
+            field("State:", state),
+            Line::from(""),
+        ];
+        if let Ok(output) = self.git_command().arg("status").output() {
+            lines.extend(String::from_utf8_lossy(&output.stdout).lines().map(|l| Line::from(l.to_string())));
+        }
+        
+        self.info = Some(InfoView {
+            title: "Interrupted operation (Esc to choose what to do)".to_string(),
+            lines,
+            scroll: 0,
+        });
+    }
+    
+    /// Name of the checked-out branch, or "HEAD" when detached
+    fn head_label(&self) -> String {
+        self.repository
+            .head()
+            .ok()
+            .filter(|h| h.is_branch())
+            .and_then(|h| h.shorthand().map(str::to_string))
+            .unwrap_or_else(|| "HEAD".to_string())
+    }
+    
+    /// Applies a date range typed at the prompt; empty clears it
+    fn set_date_range(&mut self, text: &str) -> Result<(), String> {
+        self.date_range = if text.is_empty() {
+            None
+        } else {
+            Some(daterange::parse(text, Local::now().date_naive())?)
+        };
+        self.commit_limit = COMMIT_PAGE_SIZE;
+        self.reload_graph();
+        Ok(())
+    }
+    
+    /// Starts a pickaxe search typed at the prompt; empty ends it
+    fn set_pickaxe(&mut self, text: &str) -> Result<(), String> {
+        let pickaxe = if text.is_empty() { None } else { Some(Pickaxe::parse(text)?) };
+        // git compiles `-G` patterns itself, and rejects some the highlighting
+        // accepts (`(?i)`)
+        if let Some(cmd) = pickaxe.as_ref().and_then(|pickaxe| pickaxe.check(self.git_command())) {
+            let output = cmd.output().map_err(|e| format!("Cannot check the regex: {}", e))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let reason = oplog::first_line(&stderr);
+                return Err(reason.strip_prefix("fatal: ").unwrap_or(&reason).to_string());
+            }
+        }
+        self.pickaxe = pickaxe;
+        self.commit_limit = COMMIT_PAGE_SIZE;
+        self.reload_graph();
+        Ok(())
+    }
+    
+    /// Shows only commits of the conventional types typed at the prompt;
+    /// empty shows all again
+    fn set_type_filter(&mut self, text: &str) -> Result<(), String> {
+        let filter = TypeFilter::parse(text)?;
+        self.type_filter = (!filter.types.is_empty()).then_some(filter);
+        self.commit_limit = COMMIT_PAGE_SIZE;
+        self.reload_graph();
+        Ok(())
+    }
+    
+    fn clear_branch_filter(&mut self) {
+        self.trailer_filter = None;
+        self.set_branch_filter(None);
+    }
+    
+    fn toggle_branch_mark(&mut self) {
+        if let Some(branch) = self.selected_git_branch() {
+            let name = branch.name.clone();
+            if let Some(pos) = self.marked_branches.iter().position(|b| *b == name) {
+                self.marked_branches.remove(pos);
+            } else {
+                self.marked_branches.push(name);
+            }
+        }
+        self.next_branch();
+    }
+    
+    /// Marked branches, or the selected one when nothing is marked
+    fn branch_targets(&self) -> Vec<String> {
+        if !self.marked_branches.is_empty() {
+            self.marked_branches.clone()
+        } else {
+            self.selected_git_branch()
+                .map(|b| vec![b.name.clone()])
+                .unwrap_or_default()
+        }
+    }
+    
+    fn graph_marked_branches(&mut self) {
+        if self.marked_branches.is_empty() {
+            self.show_toast("No branches marked (Space to mark)", true);
+            return;
+        }
+        let refs = self.marked_branches.clone();
+        self.set_branch_filter(None);
+        self.exact_refs = Some(refs);
+        self.reload_graph();
+    }
+    
+    /// Fetches each branch from its remote: remote-tracking branches directly,
+    /// local branches through their configured upstream
+    fn fetch_branches(&mut self, names: &[String]) {
+        if self.task_running() {
+            return;
+        }
+        let mut by_remote: Vec<(String, Vec<String>)> = Vec::new();
+        let mut skipped = Vec::new();
+        
+        for name in names {
+            let Some(branch) = self.branches.iter().find(|b| b.name == *name) else {
+                continue;
+            };
+            let remote_ref = if branch.is_remote {
+                Some(name.clone())
+            } else {
+                self.repository
+                    .find_branch(name, BranchType::Local)
+                    .ok()
+                    .and_then(|b| b.upstream().ok())
+                    .and_then(|u| u.name().ok().flatten().map(str::to_string))
+            };
+            match remote_ref.as_deref().and_then(|r| r.split_once('/')) {
+                Some((remote, branch_name)) => {
+                    match by_remote.iter_mut().find(|(r, _)| r == remote) {
+                        Some((_, branches)) => branches.push(branch_name.to_string()),
+                        None => by_remote.push((remote.to_string(), vec![branch_name.to_string()])),
+                    }
+                }
+                None => skipped.push(name.clone()),
+            }
+        }
+        
+        if by_remote.is_empty() {
+            self.show_toast(format!("Nothing to fetch; no upstream: {}", skipped.join(", ")), true);
+            return;
+        }
+        
+        let label = format!("Fetching {}", by_remote.iter().map(|(r, _)| r.as_str()).collect::<Vec<_>>().join(", "));
+        let branches = by_remote.iter().map(|(_, b)| b.len()).sum();
+        let commands = by_remote
+            .into_iter()
+            .map(|(remote, branches)| {
+                let cmd = self.git_command()
+                    .arg("fetch")
+                    .arg("--progress")
+                    .rev(&remote)
+                    .args(branches.iter().map(|b| format!("refs/heads/{}", b)));
+                (remote, cmd)
+            })
+            .collect();
+        self.start_task(Task::start(label, commands), TaskKind::Fetch { branches, skipped });
+    }
+    
+    fn fetch_done(&mut self, task: &mut Task, branches: usize, skipped: Vec<String>) {
+        let failures: Vec<String> = task
+            .results()
+            .into_iter()
+            .filter_map(|(remote, result)| match result {
+                Ok(output) if output.status.success() => None,
+                Ok(output) => Some(format!("{}: {}", remote, String::from_utf8_lossy(&output.stderr).trim())),
+                Err(e) => Some(format!("{}: {}", remote, e)),
+            })
+            .collect();
+        
+        self.refresh_in_place();
+        
+        let mut message = format!("Fetched {} branch(es)", branches);
+        if !skipped.is_empty() {
+            message.push_str(&format!("; no upstream: {}", skipped.join(", ")));
+        }
+        if !failures.is_empty() {
+            message.push_str(&format!("; failed: {}", failures.join("; ")));
+        }
+        self.notify_if_slow(task.started, &message);
+        self.show_toast(message, !failures.is_empty());
+    }
+    
+    fn request_delete_branches(&mut self, mut names: Vec<String>) {
+        // The detached HEAD entry is not a branch
+        names.retain(|n| !self.branches.iter().any(|b| b.name == *n && b.is_detached));
+        if names.is_empty() {
+            return;
+        }
+        let has_remote = names.iter().any(|n| self.branches.iter().any(|b| b.name == *n && b.is_remote));
+        let mut message = format!("Delete {} branch(es)?\n\n{}", names.len(), names.join("\n"));
+        if has_remote {
+            message.push_str("\n\nRemote branches are only removed locally (remote-tracking refs).");
+        }
+        self.confirm(PendingConfirm {
+            message,
+            action: ConfirmedAction::DeleteBranches(names),
+            rewrites_published: false,
+        });
+    }
+    
+    /// Deletes branches with `git branch -d`, which refuses unmerged branches
+    fn delete_branches(&mut self, names: &[String]) {
+        let mut failures = Vec::new();
+        let mut deleted = 0;
+        
+        for name in names {
+            let is_remote = self.branches.iter().any(|b| b.name == *name && b.is_remote);
+            let mut cmd = self.git_command().arg("branch").arg("-d");
+            if is_remote {
+                cmd = cmd.arg("-r");
+            }
+            match cmd.rev(name).output() {
+                Ok(output) if output.status.success() => deleted += 1,
+                Ok(output) => failures.push(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+                Err(e) => failures.push(format!("{}: {}", name, e)),
+            }
+        }
+        
+        self.marked_branches.retain(|b| !names.contains(b));
+        if let Some(ref mut refs) = self.exact_refs {
+            refs.retain(|b| !names.contains(b));
+        }
+        let _ = self.refresh_data();
+        self.select_branch_row(self.selected_branch);
+        
+        if failures.is_empty() {
+            self.show_toast(format!("Deleted {} branch(es)", deleted), false);
+        } else {
+            self.show_toast(format!("Deleted {}; {}", deleted, failures.join("; ")), true);
+        }
+    }
+    
+    /// Warning when moving the current branch to `commit_id` drops commits
+    /// a remote-tracking branch already has
+    fn check_rewrite(&mut self, commit_id: &str) -> Option<String> {
+        let base = self.resolve_short_id(commit_id).ok()?;
+        let head = self.repository.head().ok()?.target()?;
+        match published::check(&self.repository, base, head) {
+            Ok(check) => check.warning(),
+            Err(e) => Some(format!("Could not check for pushed commits: {}", e)),
+        }
+    }
+    
+    /// Asks before going ahead, unless this kind of confirmation is turned
+    /// off and no pushed commits would be rewritten
+    fn confirm(&mut self, confirm: PendingConfirm) {
+        if !confirm.rewrites_published && self.skip_confirm.contains(&confirm.action.kind()) {
+            self.perform_confirmed(confirm.action);
+        } else {
+            self.pending_confirm = Some(confirm);
+        }
+    }
+    
+    /// `overriding` is the explicit answer required when published history
+    /// would be rewritten
+    fn resolve_confirm(&mut self, accepted: bool, overriding: bool) {
+        let Some(confirm) = self.pending_confirm.take() else {
+            return;
+        };
+        if !accepted {
+            return;
+        }
+        if confirm.rewrites_published && !overriding {
+            self.pending_confirm = Some(confirm);
+            self.show_toast("Commits are already pushed: press ! to rewrite anyway", true);
+            return;
+        }
+        self.perform_confirmed(confirm.action);
+    }
+    
+    /// Confirms and stops asking for this kind of action, by adding it to
+    /// `[confirm] skip` in the config file
+    fn confirm_always(&mut self) {
+        let Some(ref confirm) = self.pending_confirm else {
+            return;
+        };
+        if confirm.rewrites_published {
+            self.show_toast("Commits are already pushed: press ! to rewrite anyway", true);
+            return;
+        }
+        let kind = confirm.action.kind();
+        if !kind.skippable() {
+            self.show_toast("Commands from the config are always confirmed before their first run", true);
+            return;
+        }
+        let saved = match config::config_path() {
+            Some(path) => confirm::skip_in_config(&path, kind),
+            None => Err("no config directory (HOME is not set)".to_string()),
+        };
+        match saved {
+            Ok(()) => {
+                self.skip_confirm.insert(kind);
+                // The action's own outcome replaces this one
+                self.show_toast(format!("No longer asking before: {} (confirm.skip in the config)", kind.label()), false);
+                self.resolve_confirm(true, false);
+            }
+            Err(e) => self.show_toast(format!("Could not save to the config: {}", e), true),
+        }
+    }
+    
+    fn perform_confirmed(&mut self, action: ConfirmedAction) {
+        match action {
+            ConfirmedAction::DeleteBranches(names) => self.delete_branches(&names),
+            ConfirmedAction::Reset { mode, commit_id } => self.reset_current_branch(mode, &commit_id),
+            ConfirmedAction::Rebase(commit_id) => self.rebase_interactive(commit_id),
+            ConfirmedAction::RemoveWorktree(path) => self.worktree_command(&["remove", "--"], Some(&path), "Removed worktree"),
+            ConfirmedAction::PruneWorktrees => self.worktree_command(&["prune"], None, "Pruned stale worktrees"),
+            ConfirmedAction::DisableSparse => {
+                self.sparse_command(&["disable"], Vec::new(), "Sparse checkout is off; every file is checked out".to_string())
+            }
+            ConfirmedAction::Undo(record) => self.undo(&record),
+            ConfirmedAction::TakeSide { path, ours, deletes } => self.take_side(&path, ours, deletes),
+            ConfirmedAction::AbortOperation(operation) => {
+                let cmd = self.git_command().args([operation.command(), "--abort"]);
+                self.run_operation_command(operation, cmd, format!("Aborted git {}", operation.command()));
+            }
+            ConfirmedAction::RunCommand { command_line, then } => {
+                process::approve(&command_line);
+                match then {
+                    Approved::Suspend(suspend) => self.suspend = Some(*suspend),
+                    Approved::FilterDiff => self.filter_diff(),
+                    Approved::Commit => self.commit_staged(),
+                }
+            }
+        }
+    }
+    
+    /// Shows a command from the config before it first runs; `then` goes
+    /// ahead with it once approved
+    fn confirm_command(&mut self, needed: process::NeedsConfirmation, then: Approved) {
+        let message = format!(
+            "Run this command from the config?\n\n{}\n\nOnce approved, it runs without asking until git-tui exits.",
+            needed.command_line
+        );
+        self.confirm(PendingConfirm {
+            message,
+            action: ConfirmedAction::RunCommand { command_line: needed.command_line, then },
+            rewrites_published: false,
+        });
+    }
+    
+    fn load_commit_diff(&mut self) {
+        if self.load_selected_diff() {
+            self.show_diff = true;
+        }
+    }
+    
+    /// Empties the diff view for a diff about to be loaded
+    fn clear_diff_view(&mut self) {
+        self.diff_scroll_offset = 0;
+        self.diff_hscroll = 0;
+        self.diff_files = 0;
+        self.diff_numbers.clear();
+        self.diff_match = None;
+        self.diff_stream = None;
+        self.current_diff = Some(String::new());
+        self.diff_styled = None;
+        self.diff_unfiltered = None;
+        self.diff_line_count = 0;
+    }
+    
+    /// Keeps the split layout's pane on the selected commit's diff; rows
+    /// without a commit leave the last one shown
+    fn follow_diff(&mut self) {
+        if !self.split_diff || self.show_diff {
+            return;
+        }
+        let Some(selected) = self.graph_lines.get(self.selected_commit).map(|l| l.commit_id.clone()) else {
+            return;
+        };
+        if selected.is_empty() || selected == self.split_diff_commit {
+            return;
+        }
+        self.split_diff_commit = selected;
+        self.load_selected_diff();
+    }
+    
+    fn toggle_split_diff(&mut self) {
+        self.split_diff = !self.split_diff;
+        self.split_diff_commit.clear();
+        if !self.split_diff {
+            self.close_diff();
+        }
+    }
+    
+    /// Loads the selected commit's diff into `current_diff`, from the cache,
+    /// git2 or a `git show` stream; false on a row without a commit
+    fn load_selected_diff(&mut self) -> bool {
+        if self.graph_lines.is_empty() || self.selected_commit >= self.graph_lines.len() {
+            return false;
+        }
+        
+        let selected_line = &self.graph_lines[self.selected_commit];
+        let commit_id = &selected_line.commit_id;
+        
+        if commit_id.is_empty() {
+            return false;
+        }
+        
+        if self.audit.is_some()
+            && let Some((id, summary)) = self.get_selected_commit().map(|c| (c.id.clone(), commit_summary(&c.message)))
+        {
+            self.audit_record(AuditEvent::Diff, &id, &summary);
+        }
+        let commit_id = self.graph_lines[self.selected_commit].commit_id.clone();
+        self.clear_diff_view();
+        
+        let oid = self.resolve_short_id(&commit_id).ok();
+        let key = oid.map(|oid| oid.to_string());
+        self.diff_revisions = oid.map(|oid| (self.parent_revision(oid), oid.to_string()));
+        if let Some(text) = key.as_deref().and_then(|key| self.diff_cache.get(key)).cloned() {
+            self.append_diff_lines(text.lines().map(str::to_string).collect());
+            self.filter_diff();
+            return true;
+        }
+        
+        // git2 cannot fetch what a partial clone left out: that is fetched
+        // first, and a diff that cannot even be listed is left to git show
+        let mut in_process = true;
+        if let Some(oid) = oid
+            && let Some(remote) = self.promisor_remote.clone()
+        {
+            match partial::missing_blobs(&self.repository, oid) {
+                Ok(missing) if !missing.is_empty() => {
+                    self.fetch_objects(&remote, &missing, Fetched::Diff(oid));
+                    let message = format!("Fetching {} missing blob(s) of this commit from {} (partial clone)…", missing.len(), remote);
+                    self.append_diff_lines(vec![message]);
+                    return true;
+                }
+                Ok(_) => {}
+                Err(_) => in_process = false,
+            }
+        }
+        
+        // Made in-process; a merge's combined diff only git can make
+        let rendered = oid.filter(|_| in_process).and_then(|oid| {
+            oplog::timed(format!("git2: diff {}", short_id(&commit_id)), || {
+                commitdiff::render(&self.repository, oid, self.mailmap.as_ref()).transpose()
+            })
+            .transpose()
+        });
+        match rendered {
+            Some(Ok(text)) => {
+                self.append_diff_lines(text.lines().map(str::to_string).collect());
+                if let Some(key) = key {
+                    self.diff_cache.insert(key, text);
+                }
+                self.filter_diff();
+                return true;
+            }
+            Some(Err(e)) => {
+                self.append_diff_lines(vec![format!("Error getting diff: {}", e)]);
+                return true;
+            }
+            None => {}
+        }
+        
+        // Stream git show so large diffs can be read while they are generated
+        // (no color to avoid ANSI codes; no --stat, which would need the whole diff first)
+        let stream = ExternalCommand::git()
+            .args(["show", "--no-color", "--format=fuller", "-p"])
+            .rev(&commit_id)
+            .current_dir(self.repository.workdir().unwrap_or_else(|| self.repository.path()))
+            .stream();
+        match stream {
+            Ok(stream) => {
+                self.diff_stream = Some(stream);
+                self.diff_stream_commit = key;
+            }
+            Err(e) => {
+                self.current_diff = Some(format!("Failed to run git show: {}", e));
+                self.diff_line_count = 1;
+                self.diff_numbers.push("");
+            }
+        }
+        true
+    }
+    
+    /// Caches the diffs the prefetcher made, and once the selection moved
+    /// asks it for the ones around the new selection, nearest first
+    fn prefetch_diffs(&mut self) {
+        for (key, text) in self.prefetcher.finished() {
+            self.diff_cache.insert(key, text);
+        }
+        let Some(selected) = self.graph_lines.get(self.selected_commit).map(|l| l.commit_id.clone()) else {
+            return;
+        };
+        if self.prefetch_distance == 0 || selected.is_empty() || selected == self.prefetched_around {
+            return;
+        }
+        self.prefetched_around = selected.clone();
+        
+        let nearest = |lines: &mut dyn Iterator<Item = &GraphLine>| -> Vec<String> {
+            lines
+                .filter(|line| !line.commit_id.is_empty())
+                .take(self.prefetch_distance)
+                .map(|line| line.commit_id.clone())
+                .collect()
+        };
+        let below = nearest(&mut self.graph_lines[self.selected_commit + 1..].iter());
+        let above = nearest(&mut self.graph_lines[..self.selected_commit].iter().rev());
+        let mut wanted = vec![selected];
+        for step in 0..self.prefetch_distance {
+            wanted.extend(below.get(step).cloned());
+            wanted.extend(above.get(step).cloned());
+        }
+        
+        let ids: Vec<Oid> = wanted.iter().filter_map(|short| self.resolve_short_id(short).ok()).collect();
+        let ids: Vec<Oid> = ids.into_iter().filter(|oid| self.diff_cache.peek(&oid.to_string()).is_none()).collect();
+        if !ids.is_empty() {
+            self.prefetcher.request(ids);
+        }
+    }
+    
+    /// Adds lines to the end of the diff shown, counting files and line numbers
+    fn append_diff_lines(&mut self, lines: Vec<String>) {
+        let diff = self.current_diff.get_or_insert_with(String::new);
+        self.diff_line_count += lines.len();
+        for line in lines {
+            if line.starts_with("diff --git ") {
+                self.diff_files += 1;
+            }
+            self.diff_numbers.push(&line);
+            diff.push_str(&line);
+            diff.push('\n');
+        }
+    }
+    
+    /// Appends diff output produced since the last frame
+    fn poll_diff_stream(&mut self) {
+        let Some(ref mut stream) = self.diff_stream else {
+            return;
+        };
+        let lines = stream.drain(DIFF_LINES_PER_FRAME);
+        let finished = stream.finished().cloned();
+        self.append_diff_lines(lines);
+        
+        let diff = self.current_diff.get_or_insert_with(String::new);
+        match finished {
+            Some(Err(errors)) => {
+                let message = format!("Error getting diff: {}", errors);
+                for line in message.lines() {
+                    self.diff_numbers.push(line);
+                }
+                diff.push_str(&message);
+                self.diff_line_count = diff.lines().count();
+                self.diff_stream = None;
+            }
+            Some(Ok(())) => {
+                self.diff_stream = None;
+                if let Some(key) = self.diff_stream_commit.take() {
+                    self.diff_cache.insert(key, diff.clone());
+                }
+                self.filter_diff();
+            }
+            None => {}
+        }
+    }
+    
+    fn close_diff(&mut self) {
+        self.show_diff = false;
+        self.diff_scroll_offset = 0;
+        self.diff_search = None;
+        self.diff_match = None;
+        // The split pane goes on showing it
+        if !self.split_diff {
+            // Dropping the stream stops git if it is still producing output
+            self.diff_stream = None;
+            self.current_diff = None;
+            self.diff_styled = None;
+            self.diff_unfiltered = None;
+        }
+    }
+    
+    /// Runs `[diff] filter` on the finished diff in the background, the
+    /// plain diff shown until it is done
+    fn filter_diff(&mut self) {
+        let (Some(filter), Some(diff)) = (self.diff_filter.as_deref(), self.current_diff.as_deref()) else {
+            return;
+        };
+        if diff.is_empty() {
+            return;
+        }
+        let cmd = ExternalCommand::new("sh")
+            .arg("-c")
+            .arg(filter)
+            .user_configured()
+            .current_dir(self.repository.workdir().unwrap_or_else(|| self.repository.path()))
+            .input(format!("{}\n", diff));
+        if let Some(needed) = cmd.needs_confirmation() {
+            self.confirm_command(needed, Approved::FilterDiff);
+            return;
+        }
+        let diff = diff.to_string();
+        let task = Task::start("Filtering the diff", vec![(String::new(), cmd)]);
+        self.start_task(task, TaskKind::FilterDiff { diff });
+    }
+    
+    /// Shows the diff as `[diff] filter` printed it, colors kept, unless
+    /// another diff is shown by now. Search and scrolling go by its text.
+    /// The plain diff stays when the filter fails.
+    fn diff_filtered(&mut self, task: &mut Task, diff: &str) {
+        if self.current_diff.as_deref() != Some(diff) {
+            return;
+        }
+        let output = match task.results().pop().map(|(_, result)| result) {
+            Some(Ok(output)) if output.status.success() => output,
+            Some(Ok(output)) => {
+                let message = format!("[diff] filter failed: {}", process::failure(output.status, &String::from_utf8_lossy(&output.stderr)));
+                self.show_toast(message, true);
+                return;
+            }
+            Some(Err(e)) => {
+                self.show_toast(format!("[diff] filter failed: {}", e), true);
+                return;
+            }
+            None => return,
+        };
+        let text = String::from_utf8_lossy(&output.stdout);
+        let plain = ansi::strip(&text).replace('\t', "    ");
+        self.diff_styled = Some(text.lines().map(ansi::line).collect());
+        self.diff_line_count = plain.lines().count();
+        self.diff_unfiltered = self.current_diff.replace(plain);
+        self.diff_numbers.clear();
+        self.diff_match = None;
+    }
+    
+    /// Starts a search in the diff from the top of the view; an empty query
+    /// ends it
+    fn search_diff(&mut self, query: String) -> Result<(), String> {
+        if query.is_empty() {
+            self.diff_search = None;
+            self.diff_match = None;
+            return Ok(());
+        }
+        self.diff_search = Some(query.clone());
+        self.diff_match = None;
+        match self.jump_to_diff_match(true) {
+            true => Ok(()),
+            false => Err(format!("'{}' is not in the diff", query)),
+        }
+    }
+    
+    /// Moves to the next (or previous) line matching the diff search,
+    /// wrapping around, and scrolls it into view. The search starts at the
+    /// top of the view until a match was jumped to.
+    fn jump_to_diff_match(&mut self, forward: bool) -> bool {
+        let (Some(query), Some(diff)) = (self.diff_search.as_ref(), self.current_diff.as_ref()) else {
+            return false;
+        };
+        let lines: Vec<&str> = diff.lines().collect();
+        if lines.is_empty() {
+            return false;
+        }
+        let count = lines.len();
+        // The line searched from is only skipped once it holds the current match
+        let (start, first) = match self.diff_match {
+            Some(current) => (current, 1),
+            None => ((self.diff_scroll_offset as usize).min(count - 1), 0),
+        };
+        let Some(found) = (first..count + first)
+            .map(|step| match forward {
+                true => (start + step) % count,
+                false => (start + count - step % count) % count,
+            })
+            .find(|&index| !difflines::find(&lines[index].replace('\t', "    "), query).is_empty())
+        else {
+            return false;
+        };
+        self.diff_match = Some(found);
+        
+        // Leave some context above the match when it is off screen
+        let visible = diff_popup_height().saturating_sub(2) as usize;
+        let offset = self.diff_scroll_offset as usize;
+        if found < offset || found >= offset + visible {
+            self.diff_scroll_offset = found.saturating_sub(visible / 3) as u16;
+            self.clamp_diff_scroll(diff_popup_height());
+        }
+        true
+    }
+
+    /// Scrolls the next (or previous) `@@` hunk header to the top of the
+    /// view; false when there is none that way
+    fn jump_to_hunk(&mut self, forward: bool) -> bool {
+        let Some(ref diff) = self.current_diff else {
+            return false;
+        };
+        let offset = self.diff_scroll_offset as usize;
+        let mut hunks = diff.lines().enumerate().filter(|(_, line)| line.starts_with("@@")).map(|(index, _)| index);
+        let found = match forward {
+            true => hunks.find(|&index| index > offset),
+            false => hunks.take_while(|&index| index < offset).last(),
+        };
+        let Some(found) = found else {
+            return false;
+        };
+        let before = self.diff_scroll_offset;
+        self.diff_scroll_offset = found as u16;
+        self.clamp_diff_scroll(diff_popup_height());
+        // At the end of the diff the last hunks cannot reach the top
+        self.diff_scroll_offset != before
+    }
+    
+    fn get_max_diff_scroll(&self, visible_height: u16) -> u16 {
+        if self.current_diff.is_some() {
+            let total_lines = self.diff_line_count;
+            let content_height = (visible_height.saturating_sub(2)) as usize; // Account for borders
+            total_lines.saturating_sub(content_height) as u16
+        } else {
+            0
+        }
+    }
+
+    fn clamp_diff_scroll(&mut self, visible_height: u16) {
+        let max_scroll = self.get_max_diff_scroll(visible_height);
+        self.diff_scroll_offset = self.diff_scroll_offset.min(max_scroll);
+    }
+    
+    fn search_branch(&mut self) {
+        if self.search_input.is_empty() {
+            return;
+        }
+        
+        let query_lower = self.search_input.value().to_lowercase();
+        
+        // Search from current position forward, wrapping around
+        let rows = self.branch_rows.len();
+        for i in (1..=rows).map(|step| (self.selected_branch + step) % rows) {
+            if let BranchRow::Branch { index, .. } = self.branch_rows[i]
+                && self.branches[index].name.to_lowercase().contains(&query_lower)
+            {
+                self.select_branch_row(i);
+                return;
+            }
+        }
+        
+        // Not on screen: unfold the groups of a match in a folded one
+        let Some(branch) = self.branches
+            .iter()
+            .filter(|b| !(b.is_remote && self.hide_remotes))
+            .find(|b| b.name.to_lowercase().contains(&query_lower))
+        else {
+            return;
+        };
+        let name = branch.name.clone();
+        for key in branchtree::group_keys(&name, branch.is_remote) {
+            self.collapsed_groups.remove(&key);
+        }
+        self.layout_branch_rows();
+        if let Some(row) = self.branch_row(&name) {
+            self.select_branch_row(row);
+        }
+    }
+    
+    fn open_submodules(&mut self) {
+        match submodules::list(&self.repository) {
+            Ok(items) if items.is_empty() => self.show_toast("Repository has no submodules", false),
+            Ok(items) => self.submodules = Some(SubmoduleView { items, selected: 0 }),
+            Err(e) => self.show_toast(format!("Failed to list submodules: {}", e.message()), true),
+        }
+    }
+    
+    fn selected_submodule(&self) -> Option<&SubmoduleInfo> {
+        self.submodules.as_ref().and_then(|view| view.items.get(view.selected))
+    }
+    
+    /// Runs `git submodule <args> -- <path>` on the selected submodule
+    fn submodule_command(&mut self, args: &[&str], done: &'static str) {
+        let Some(path) = self.selected_submodule().map(|s| s.path.display().to_string()) else {
+            return;
+        };
+        if self.task_running() {
+            return;
+        }
+        let cmd = self.git_command().arg("submodule").args(args).arg("--").arg(&path);
+        let task = Task::start(format!("Updating {}", path), vec![(path.clone(), cmd)]);
+        self.start_task(task, TaskKind::Submodule { path, done });
+    }
+    
+    fn submodule_done(&mut self, task: &mut Task, path: &str, done: &str) {
+        let (message, is_error) = match task.results().pop().map(|(_, result)| result) {
+            Some(Ok(output)) if output.status.success() => (format!("{} {}", done, path), false),
+            Some(Ok(output)) => (String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Some(Err(e)) => (format!("git submodule failed: {}", e), true),
+            None => return,
+        };
+        self.notify_if_slow(task.started, &message);
+        self.show_toast(message, is_error);
+        
+        // Statuses changed; keep the selection
+        let selected = self.submodules.as_ref().map_or(0, |v| v.selected);
+        if let Ok(items) = submodules::list(&self.repository) {
+            let selected = selected.min(items.len().saturating_sub(1));
+            self.submodules = Some(SubmoduleView { items, selected });
+        }
+        let _ = self.refresh_data();
+    }
+    
+    /// Queues the selected submodule to be browsed in a nested view
+    fn open_selected_submodule(&mut self) {
+        let Some(submodule) = self.selected_submodule() else {
+            return;
+        };
+        if !submodule.is_checked_out() {
+            self.show_toast("Submodule is not checked out (u: init and update)", true);
+            return;
+        }
+        let workdir = self.repository.workdir().unwrap_or_else(|| self.repository.path());
+        self.open_nested = Some(workdir.join(&submodule.path));
+    }
+    
+    fn open_worktrees(&mut self) {
+        match self.list_worktrees() {
+            Ok(items) => {
+                // Start on the worktree being browsed; keep the selection on a refresh
+                let selected = match self.worktrees {
+                    Some(ref view) => view.selected.min(items.len().saturating_sub(1)),
+                    None => items.iter().position(|w| self.is_current_worktree(w)).unwrap_or(0),
+                };
+                self.worktrees = Some(WorktreeView { items, selected });
+            }
+            Err(e) => self.show_toast(format!("Failed to list worktrees: {}", e), true),
+        }
+    }
+    
+    /// The remote on GitHub or GitLab, with the host and project path:
+    /// `origin` when it is on either, else the first remote that is
+    fn forge_remote(&self) -> Option<(Forge, String, String)> {
+        let remotes = self.repository.remotes().ok()?;
+        let mut names: Vec<&str> = remotes.iter().flatten().collect();
+        names.sort_by_key(|name| *name != "origin");
+        names.into_iter().find_map(|name| {
+            let remote = self.repository.find_remote(name).ok()?;
+            let url = remote.url()?;
+            let (forge, project) = match github::repository(url, &self.github.host) {
+                Some(repository) => (Forge::GitHub, repository),
+                None => (Forge::GitLab, gitlab::project(url, &self.gitlab.host)?),
+            };
+            Some((forge, name.to_string(), project))
+        })
+    }
+    
+    /// Lists the open pull or merge requests, or reloads the list when it is open
+    fn open_pull_requests(&mut self) {
+        let Some((forge, remote, repository)) = self.forge_remote() else {
+            let message = format!(
+                "No remote on {} or {} (github.host and gitlab.host set other hosts)",
+                self.github.host, self.gitlab.host
+            );
+            self.show_toast(message, true);
+            return;
+        };
+        let started = Instant::now();
+        let result = match forge {
+            Forge::GitHub => github::list(&self.github, &repository),
+            Forge::GitLab => gitlab::list(&self.gitlab, &repository),
+        };
+        match result {
+            Ok(items) => {
+                self.notify_if_slow(started, &format!("{} {} loaded", forge.name(), forge.noun()));
+                if items.is_empty() {
+                    self.show_toast(format!("No open {} on {}", forge.noun(), repository), false);
+                }
+                let selected = self.pull_requests.as_ref().map_or(0, |view| view.selected);
+                self.pull_requests = Some(PullRequestView {
+                    forge,
+                    selected: selected.min(items.len().saturating_sub(1)),
+                    items,
+                    remote,
+                    repository,
+                });
+            }
+            Err(e) => self.show_toast(e, true),
+        }
+    }
+    
+    fn selected_pull_request(&self) -> Option<&PullRequest> {
+        self.pull_requests.as_ref().and_then(|view| view.items.get(view.selected))
+    }
+    
+    /// Graph row of a commit, by full id
+    fn graph_row(&self, commit_id: &str) -> Option<usize> {
+        self.graph_lines
+            .iter()
+            .position(|l| !l.commit_id.is_empty() && commit_id.starts_with(&l.commit_id))
+    }
+    
+    /// Selects the request's head commit in the graph, or failing that the
+    /// tip of its source branch as last fetched
+    fn jump_to_pull_request(&mut self) {
+        let (Some(pr), Some(view)) = (self.selected_pull_request(), self.pull_requests.as_ref()) else {
+            return;
+        };
+        let reference = view.forge.reference(pr.number);
+        let source_branch = format!("{}/{}", view.remote, pr.branch);
+        let fetched_tip = pr.fork.is_none()
+            .then(|| self.branch_commit_cache.get(&source_branch).cloned())
+            .flatten()
+            .filter(|id| self.graph_row(id).is_some());
+        
+        let target = if self.graph_row(&pr.sha).is_some() {
+            pr.sha.clone()
+        } else if let Some(tip) = fetched_tip {
+            self.show_toast(format!("{} has newer commits than {} (f: fetch)", reference, source_branch), false);
+            tip
+        } else {
+            self.show_toast(format!("{} ({}) is not in the graph (c: check it out)", reference, short_id(&pr.sha)), true);
+            return;
+        };
+        self.pull_requests = None;
+        self.select_commit_row(&target);
+        self.show_logs = true;
+    }
+    
+    /// Compares the two marked branches, or asks what to compare the
+    /// selected one with: the checked-out branch, or for that one the
+    /// default branch
+    fn start_compare(&mut self) {
+        if let [a, b] = self.marked_branches.as_slice() {
+            let names = [a.clone(), b.clone()];
+            if let Err(message) = self.compare_refs([&names[0], &names[1]]) {
+                self.show_toast(message, true);
+            }
+            return;
+        }
+        let Some(branch) = self.selected_git_branch() else {
+            return;
+        };
+        let name = branch.name.clone();
+        let head = self.head_label();
+        let other = match name == head {
+            true => self.default_branch().filter(|default| *default != name).unwrap_or_default(),
+            false => head,
+        };
+        self.open_prompt(format!("Compare {} with (branch, tag or commit)", name), &other, PromptKind::CompareWith(name));
+    }
+    
+    fn compare_refs(&mut self, names: [&str; 2]) -> Result<(), String> {
+        let call = format!("git2: compare {}...{}", names[0], names[1]);
+        let comparison = oplog::timed(call, || compare::compare(&self.repository, names, self.mailmap.as_ref()))
+            .map_err(|e| format!("Cannot compare {} with {}: {}", names[0], names[1], e.message()))?;
+        // Starting on the side that has something
+        let side = match comparison.sides[0].commits.is_empty() {
+            true => 1,
+            false => 0,
+        };
+        self.compare = Some(CompareView { comparison, side, selected: [0, 0] });
+        Ok(())
+    }
+    
+    /// Selects the commit in the graph, closing the comparison once it is there
+    fn jump_to_compared_commit(&mut self) {
+        let Some(id) = self.compare.as_ref().and_then(|view| {
+            view.comparison.sides[view.side].commits.get(view.selected[view.side]).map(|entry| entry.id.to_string())
+        }) else {
+            return;
+        };
+        self.jump_to_commit(&id);
+        if self.graph_row(&id).is_some() {
+            self.compare = None;
+            self.show_logs = true;
+        }
+    }
+    
+    /// Fetches the request's head into `pr/<number>` (`mr/` on GitLab),
+    /// fast-forwarding it if it exists, and checks that out
+    fn checkout_pull_request(&mut self) {
+        let (Some(pr), Some(view)) = (self.selected_pull_request(), self.pull_requests.as_ref()) else {
+            return;
+        };
+        let branch = view.forge.local_branch(pr.number);
+        let cmd = self.git_command()
+            .arg("fetch")
+            .arg("--progress")
+            .rev(&view.remote)
+            .arg(format!("{}:refs/heads/{}", view.forge.head_ref(pr.number), branch));
+        if self.task_running() {
+            return;
+        }
+        let task = Task::start(format!("Fetching {}", branch), vec![(branch.clone(), cmd)]);
+        self.start_task(task, TaskKind::PullRequest { branch });
+    }
+    
+    fn pull_request_fetched(&mut self, task: &mut Task, branch: &str) {
+        match task.results().pop().map(|(_, result)| result) {
+            Some(Ok(output)) if output.status.success() => {
+                self.notify_if_slow(task.started, &format!("Fetched {}", branch));
+                self.pull_requests = None;
+                self.checkout(branch, false);
+            }
+            Some(Ok(output)) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Some(Err(e)) => self.show_toast(format!("Failed to fetch {}: {}", branch, e), true),
+            None => {}
+        }
+    }
+    
+    /// Runs `git fsck` in the background for dangling commits; the HEAD
+    /// reflog's deleted branches are added once it is done
+    fn scan_for_lost_branches(&mut self) {
+        if self.tasks.iter().any(|running| matches!(running.kind, TaskKind::Recover)) {
+            return;
+        }
+        let cmd = self.git_command().args(["fsck", "--no-progress"]);
+        self.start_task(Task::start("Looking for lost commits", vec![(String::new(), cmd)]), TaskKind::Recover);
+    }
+    
+    /// Offers the deleted branches and dangling commits found to recreate a
+    /// branch at. A failed fsck still leaves the reflog's branches.
+    fn recover_scanned(&mut self, task: &mut Task) {
+        let dangling = match task.results().pop().map(|(_, result)| result) {
+            Some(Ok(output)) => {
+                if !output.status.success() {
+                    let message = format!("git fsck: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+                    self.show_toast(message, true);
+                }
+                recover::dangling_commits(&self.repository, &String::from_utf8_lossy(&output.stdout))
+            }
+            Some(Err(e)) => {
+                self.show_toast(format!("Failed to run git fsck: {}", e), true);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+        let mut candidates = match oplog::timed("git2: reflog", || recover::deleted_branches(&self.repository)) {
+            Ok(candidates) => candidates,
+            Err(e) => {
+                self.show_toast(format!("Cannot read the HEAD reflog: {}", e.message()), true);
+                Vec::new()
+            }
+        };
+        candidates.extend(dangling);
+        if candidates.is_empty() {
+            self.show_toast("Nothing to recover: no deleted branch in the reflog and no dangling commit", false);
+            return;
+        }
+        let now = Utc::now();
+        let items = candidates
+            .iter()
+            .map(|candidate| {
+                let name = candidate.branch.as_deref().unwrap_or("dangling");
+                let id = short_id(&candidate.id.to_string());
+                format!("{} at {} {} ({})", name, id, candidate.summary, relative_time(candidate.time, now))
+            })
+            .collect();
+        self.menu = Some(Menu {
+            title: "Recover a branch".to_string(),
+            items,
+            selected: 0,
+            kind: MenuKind::Recover(candidates),
+        });
+    }
+    
+    fn list_worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        let output = self.git_command().args(["worktree", "list", "--porcelain"]).output()?;
+        if !output.status.success() {
+            anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(worktrees::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+    
+    fn is_current_worktree(&self, worktree: &WorktreeInfo) -> bool {
+        self.repository.workdir().is_some_and(|dir| dir == worktree.path)
+    }
+    
+    fn selected_worktree(&self) -> Option<&WorktreeInfo> {
+        self.worktrees.as_ref().and_then(|view| view.items.get(view.selected))
+    }
+    
+    /// Asks for the path of a new worktree checking out the selected branch
+    /// (local branches) or commit (graph rows and other branches)
+    fn request_add_worktree(&mut self) {
+        let target = if self.show_logs {
+            self.graph_lines
+                .get(self.selected_commit)
+                .filter(|line| !line.commit_id.is_empty())
+                .map(|line| (line.commit_id.clone(), false))
+        } else {
+            self.selected_git_branch().map(|branch| {
+                if branch.is_remote || branch.is_detached {
+                    (branch.commit_id[..branch.commit_id.len().min(7)].to_string(), false)
+                } else {
+                    (branch.name.clone(), true)
+                }
+            })
+        };
+        let Some((rev, is_branch)) = target else {
+            return;
+        };
+        let suggestion = format!("../{}-{}", self.repo_name(), rev.replace('/', "-"));
+        let what = if is_branch { "branch" } else { "commit" };
+        self.open_prompt(
+            format!(" New worktree for {} {} (path) ", what, rev),
+            &suggestion,
+            PromptKind::NewWorktree { rev, is_branch },
+        );
+    }
+    
+    /// `git worktree add` at `path`, relative to the working directory
+    fn add_worktree(&mut self, path: &str, rev: &str, is_branch: bool) -> Result<(), String> {
+        let workdir = self.repository.workdir().unwrap_or_else(|| self.repository.path());
+        let path = workdir.join(path);
+        let output = self.git_command()
+            .args(["worktree", "add"])
+            .args((!is_branch).then_some("--detach"))
+            .arg("--")
+            .arg(path.to_string_lossy())
+            .rev(rev)
+            .output()
+            .map_err(|e| format!("Failed to add worktree: {}", e))?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+        }
+        let _ = self.refresh_data();
+        self.show_toast(format!("Created worktree {} at {}", path.display(), rev), false);
+        if self.worktrees.is_some() {
+            self.open_worktrees();
+        }
+        Ok(())
+    }
+    
+    /// Removes the selected worktree, or prunes the stale ones when its
+    /// directory is already gone
+    fn request_remove_worktree(&mut self) {
+        let Some(worktree) = self.selected_worktree() else {
+            return;
+        };
+        let is_main = self.worktrees.as_ref().is_some_and(|view| view.selected == 0);
+        if is_main {
+            self.show_toast("The main worktree cannot be removed", true);
+            return;
+        }
+        if self.is_current_worktree(worktree) {
+            self.show_toast("Switch to another worktree before removing this one", true);
+            return;
+        }
+        self.confirm(if worktree.prunable {
+            PendingConfirm {
+                message: format!(
+                    "Prune stale worktrees?
+
+{}
+
+Their directories no longer exist; every such entry is pruned.",
+                    worktree.path.display()
+                ),
+                action: ConfirmedAction::PruneWorktrees,
+                rewrites_published: false,
+            }
+        } else {
+            PendingConfirm {
+                message: format!(
+                    "Remove worktree?
+
+{} ({})
+
+git refuses if it has uncommitted changes or is locked.",
+                    worktree.path.display(),
+                    worktree.label()
+                ),
+                action: ConfirmedAction::RemoveWorktree(worktree.path.clone()),
+                rewrites_published: false,
+            }
+        });
+    }
+    
+    fn worktree_command(&mut self, args: &[&str], path: Option<&Path>, done: &str) {
+        let result = self.git_command()
+            .arg("worktree")
+            .args(args)
+            .args(path.map(|p| p.to_string_lossy()))
+            .output();
+        match result {
+            Ok(output) if output.status.success() => self.show_toast(done, false),
+            Ok(output) => self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true),
+            Err(e) => self.show_toast(format!("git worktree failed: {}", e), true),
+        }
+        let _ = self.refresh_data();
+        if self.worktrees.is_some() {
+            self.open_worktrees();
+        }
+    }
+    
+    /// Opens the sparse checkout view, or brings it up to date
+    fn open_sparse(&mut self) {
+        if self.repository.is_bare() {
+            self.show_toast("A bare repository has no working tree to make sparse", true);
+            return;
+        }
+        match self.read_sparse() {
+            Ok(checkout) => {
+                let selected = self.sparse.as_ref().map_or(0, |view| view.selected);
+                let selected = selected.min(checkout.patterns.len().saturating_sub(1));
+                self.sparse = Some(SparseView { checkout, selected });
+            }
+            Err(e) => self.show_toast(format!("Failed to read the sparse checkout: {}", e), true),
+        }
+    }
+    
+    fn read_sparse(&self) -> Result<SparseCheckout> {
+        // Exits with 1 when neither setting is there
+        let output = self.git_command()
+            .args(["config", "--type=bool", "--get-regexp", r"^core\.sparsecheckout"])
+            .output()?;
+        let (enabled, cone) = sparse::parse_config(&String::from_utf8_lossy(&output.stdout));
+        let patterns = match enabled {
+            true => {
+                let output = self.git_command().args(["sparse-checkout", "list"]).output()?;
+                if !output.status.success() {
+                    anyhow::bail!("{}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+                }
+                sparse::parse_list(&String::from_utf8_lossy(&output.stdout))
+            }
+            false => Vec::new(),
+        };
+        let (files, skipped) = sparse::index_counts(&self.repository)?;
+        Ok(SparseCheckout { enabled, cone, patterns, files, skipped })
+    }
+    
+    fn request_add_sparse(&mut self) {
+        let Some(ref view) = self.sparse else {
+            return;
+        };
+        let title = match (view.checkout.enabled, view.checkout.cone) {
+            (false, _) => "Check out only this directory (turns sparse checkout on)",
+            (true, true) => "Add a directory to the sparse checkout",
+            (true, false) => "Add a pattern to the sparse checkout",
+        };
+        self.open_prompt(title.to_string(), "", PromptKind::SparseAdd);
+    }
+    
+    /// Adds `value` to the patterns; with sparse checkout off, sets it up in
+    /// cone mode with that directory alone
+    fn add_sparse(&mut self, value: &str) -> Result<(), String> {
+        let Some(ref view) = self.sparse else {
+            return Ok(());
+        };
+        let checkout = &view.checkout;
+        let pattern = match !checkout.enabled || checkout.cone {
+            true => value.trim().trim_matches('/'),
+            false => value.trim(),
+        };
+        if pattern.is_empty() {
+            return Err("No directory given".to_string());
+        }
+        if checkout.patterns.iter().any(|p| p == pattern) {
+            return Err(format!("{} is already in the sparse checkout", pattern));
+        }
+        let args: &[&str] = match checkout.enabled {
+            true => &["add", "--stdin"],
+            false => &["set", "--cone", "--stdin"],
+        };
+        let done = format!("Added {} to the sparse checkout", pattern);
+        self.sparse_command(args, vec![pattern.to_string()], done);
+        Ok(())
+    }
+    
+    /// Takes the selected pattern out: the others are set again, in the
+    /// same mode
+    fn remove_sparse_pattern(&mut self) {
+        let Some(ref view) = self.sparse else {
+            return;
+        };
+        let checkout = &view.checkout;
+        let Some(pattern) = checkout.patterns.get(view.selected).cloned() else {
+            return;
+        };
+        // No patterns at all would leave out every file
+        if !checkout.cone && checkout.patterns.len() == 1 {
+            self.show_toast("The last pattern cannot be removed (x turns sparse checkout off)", true);
+            return;
+        }
+        let rest = checkout.patterns.iter().filter(|p| **p != pattern).cloned().collect();
+        let mode = if checkout.cone { "--cone" } else { "--no-cone" };
+        let done = format!("Removed {} from the sparse checkout", pattern);
+        self.sparse_command(&["set", mode, "--stdin"], rest, done);
+    }
+    
+    fn reapply_sparse(&mut self) {
+        if !self.sparse.as_ref().is_some_and(|view| view.checkout.enabled) {
+            self.show_toast("Sparse checkout is off", true);
+            return;
+        }
+        self.sparse_command(&["reapply"], Vec::new(), "Reapplied the sparse checkout".to_string());
+    }
+    
+    fn request_disable_sparse(&mut self) {
+        let Some(ref view) = self.sparse else {
+            return;
+        };
+        if !view.checkout.enabled {
+            self.show_toast("Sparse checkout is already off", true);
+            return;
+        }
+        let message = format!(
+            "Turn sparse checkout off?
+
+All {} files of the index are checked out, the {} left out now among them.",
+            view.checkout.files, view.checkout.skipped
+        );
+        self.confirm(PendingConfirm {
+            message,
+            action: ConfirmedAction::DisableSparse,
+            rewrites_published: false,
+        });
+    }
+    
+    /// Runs `git sparse-checkout <args>` in the background with `patterns`
+    /// on its standard input
+    fn sparse_command(&mut self, args: &[&str], patterns: Vec<String>, done: String) {
+        if self.task_running() {
+            return;
+        }
+        let input: String = patterns.iter().map(|pattern| format!("{}\n", pattern)).collect();
+        let cmd = self.git_command().arg("sparse-checkout").args(args).input(input);
+        let task = Task::start("Updating the sparse checkout", vec![(String::new(), cmd)]);
+        self.start_task(task, TaskKind::Sparse { done });
+    }
+    
+    fn sparse_done(&mut self, task: &mut Task, done: &str) {
+        let (message, is_error) = match task.results().pop().map(|(_, result)| result) {
+            Some(Ok(output)) if output.status.success() => (done.to_string(), false),
+            Some(Ok(output)) => {
+                let message = format!("git sparse-checkout: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+                (message, true)
+            }
+            Some(Err(e)) => (format!("Failed to run git sparse-checkout: {}", e), true),
+            None => return,
+        };
+        self.notify_if_slow(task.started, &message);
+        self.show_toast(message, is_error);
+        self.reload_dirty();
+        if self.sparse.is_some() {
+            self.open_sparse();
+        }
+    }
+    
+    /// Queues the selected worktree to replace this one in the whole view
+    fn switch_to_selected_worktree(&mut self) {
+        let Some(worktree) = self.selected_worktree() else {
+            return;
+        };
+        if self.is_current_worktree(worktree) {
+            self.worktrees = None;
+        } else if worktree.is_bare {
+            self.show_toast("A bare repository has no working tree to browse", true);
+        } else if worktree.prunable {
+            self.show_toast("Worktree directory no longer exists (d: prune)", true);
+        } else {
+            self.switch_repo = Some(worktree.path.clone());
+        }
+    }
+    
+    fn open_identity_menu(&mut self) {
+        if self.identities.is_empty() {
+            self.show_toast("No identities configured ([[identities]] in config.toml)", true);
+            return;
+        }
+        let repo_email = self.git_command()
+            .args(["config", "user.email"])
+            .output()
+            .ok()
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .filter(|email| !email.is_empty())
+            .unwrap_or_else(|| "not set".to_string());
+        
+        let mut items = vec![format!("Repository config ({})", repo_email)];
+        let mut identities = vec![None];
+        for identity in &self.identities {
+            let signing = if identity.signing_key.is_some() { "  [signing key]" } else { "" };
+            items.push(format!("{} <{}>{}", identity.name, identity.email, signing));
+            identities.push(Some(identity.clone()));
+        }
+        let selected = identities.iter().position(|i| *i == self.identity).unwrap_or(0);
+        self.menu = Some(Menu {
+            title: "Commit as".to_string(),
+            items,
+            selected,
+            kind: MenuKind::Identity(identities),
+        });
+    }
+    
+    /// Shows who commits made here are attributed to, how they are signed and
+    /// where they are pushed, as git resolves it for this worktree (including
+    /// `includeIf` sections and `config.worktree`), with the file each value
+    /// comes from
+    fn show_git_config(&mut self) {
+        let output = self.git_command()
+            .args(["config", "--show-scope", "--show-origin", "--get-regexp"])
+            .arg(gitconfig::ATTRIBUTION_KEYS)
+            .output();
+        let values = match output {
+            // Exit status 1 only means no key matched
+            Ok(output) if output.status.success() || output.status.code() == Some(1) => {
+                gitconfig::parse_effective(&String::from_utf8_lossy(&output.stdout))
+            }
+            Ok(output) => {
+                self.show_toast(String::from_utf8_lossy(&output.stderr).trim().to_string(), true);
+                return;
+            }
+            Err(e) => {
+                self.show_toast(format!("git config failed: {}", e), true);
+                return;
+            }
+        };
+        
+        let key_width = values.iter().map(|v| v.key.len()).max().unwrap_or(0).max("user.email".len());
+        let mut lines = Vec::new();
+        if let Some(ref identity) = self.identity {
+            lines.push(Line::from(Span::styled(
+                format!("Session identity {} <{}> overrides user.name and user.email", identity.name, identity.email),
+                Style::default().fg(self.theme.ref_head),
+            )));
+            lines.push(Line::from(""));
+        }
+        for required in ["user.name", "user.email"] {
+            if !values.iter().any(|v| v.key == required) {
+                lines.push(Line::from(Span::styled(
+                    format!("{:<width$}  not set: git will refuse to commit", required, width = key_width),
+                    Style::default().fg(self.theme.error),
+                )));
+            }
+        }
+        for value in &values {
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<width$}  ", value.key, width = key_width), Style::default().fg(self.theme.commit_hash)),
+                Span::raw(value.value.clone()),
+            ]));
+            lines.push(Line::from(Span::styled(
+                format!("{:<width$}  {} · {}", "", value.scope, value.origin, width = key_width),
+                Style::default().fg(self.theme.inactive_border),
+            )));
+        }
+        let workdir = self.repository.workdir().unwrap_or_else(|| self.repository.path());
+        self.info = Some(InfoView {
+            title: format!("Git config for {}", workdir.display()),
+            lines,
+            scroll: 0,
+        });
+    }
+    
+    /// Sends a desktop notification for an operation that ran long enough
+    /// for the user to have switched to another window
+    fn notify_if_slow(&self, started: Instant, message: &str) {
+        if self.notify_after.is_some_and(|after| started.elapsed() >= after) {
+            let _ = window::notify(&format!("git-tui: {}", self.repo_name()), message);
+        }
+    }
+    
+    /// Directory name of the working tree (or of the bare repository)
+    fn repo_name(&self) -> String {
+        let dir = self.repository.workdir().unwrap_or_else(|| self.repository.path());
+        dir.file_name().map_or_else(|| dir.display().to_string(), |n| n.to_string_lossy().into_owned())
+    }
+    
+    /// Keeps the terminal title at `git-tui: <repo> (<branch>)`
+    /// The checked-out branch, or where HEAD is detached
+    fn head_description(&self) -> String {
+        match self.detached_head {
+            Some(ref id) => format!("detached at {}", &id[..id.len().min(7)]),
+            None => self.head_label(),
+        }
+    }
+    
+    /// Where the repository is, with the home directory as `~`
+    fn repo_path_label(&self) -> String {
+        home_relative(self.repository.workdir().unwrap_or_else(|| self.repository.path()))
+    }
+    
+    fn workdir_canonical(&self) -> Option<PathBuf> {
+        self.repository.workdir().unwrap_or_else(|| self.repository.path()).canonicalize().ok()
+    }
+    
+    /// Offers the configured repositories and the ones next to this one
+    fn open_repository_menu(&mut self) {
+        let current = self.workdir_canonical();
+        let workdir = self.repository.workdir().unwrap_or_else(|| self.repository.path());
+        let mut paths = repos::candidates(workdir, &self.repos, self.discover_repos);
+        if let Some(ref current) = current
+            && !paths.contains(current)
+        {
+            paths.insert(0, current.clone());
+        }
+        if paths.len() < 2 {
+            self.show_toast("No other repositories ([repos] paths in config.toml)", true);
+            return;
+        }
+        let items = paths
+            .iter()
+            .map(|path| {
+                let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+                let current = if Some(path) == current.as_ref() { "  (current)" } else { "" };
+                format!("{}  {}{}", name, home_relative(path), current)
+            })
+            .collect();
+        let selected = paths.iter().position(|path| Some(path) == current.as_ref()).unwrap_or(0);
+        self.menu = Some(Menu {
+            title: "Switch repository".to_string(),
+            items,
+            selected,
+            kind: MenuKind::Repository(paths),
+        });
+    }
+    
+    fn update_window_title(&mut self) {
+        let title = format!("git-tui: {} ({})", self.repo_name(), self.head_description());
+        if title != self.window_title {
+            let _ = window::set_title(&title);
+            self.window_title = title;
+        }
+    }
+    
+    fn show_toast(&mut self, message: impl Into<String>, is_error: bool) {
+        self.toast = Some(Toast {
+            message: message.into(),
+            is_error,
+            shown_at: Instant::now(),
+        });
+    }
+    
+    /// Picks up config file edits; invalid configs are reported and the
+    /// current settings stay in effect
+    fn poll_config(&mut self) {
+        match self.config_watcher.poll() {
+            Some(Ok(settings)) => {
+                let filters_changed = settings.hidden_branches != self.hidden_branches
+                    || settings.ref_filter != self.ref_filter
+                    || settings.bot_authors != self.bot_authors;
+                self.keymap = settings.keymap;
+                self.theme = settings.theme;
+                self.configured_glyphs = settings.glyphs.clone();
+                self.glyphs = settings.glyphs;
+                self.hidden_branches = settings.hidden_branches;
+                self.ref_filter = settings.ref_filter;
+                self.bot_authors = settings.bot_authors;
+                self.trailer_links = gerrit::with_change_link(&self.repository, settings.trailer_links);
+                self.signoff = settings.signoff;
+                self.trailer_commands = settings.trailer_commands;
+                self.diff_filter = settings.diff_filter;
+                self.open_command = settings.open_command;
+                self.date_format = settings.date_format;
+                self.skip_confirm = settings.skip_confirm;
+                self.read_only = self.forced_read_only || settings.read_only;
+                self.notify_after = settings.notify_after;
+                self.descendant_cache.set_limit(settings.cache_limit / 4);
+                self.commit_cache.set_limit(settings.cache_limit / 2);
+                self.full_id_cache.set_limit(settings.cache_limit / 4);
+                self.diff_cache.set_limit(settings.diff_cache_limit);
+                self.prefetch_distance = settings.prefetch_distance;
+                self.persist_cache = settings.persist_cache;
+                self.commit_graph = settings.commit_graph;
+                self.identities = settings.identities;
+                self.repos = settings.repos;
+                self.discover_repos = settings.discover_repos;
+                self.github = settings.github;
+                self.gitlab = settings.gitlab;
+                if filters_changed {
+                    let _ = self.refresh_data();
+                    self.select_branch_row(self.selected_branch);
+                }
+                self.show_toast("Config reloaded", false);
+            }
+            Some(Err(e)) => self.show_toast(format!("Config not applied: {}", e), true),
+            None => {}
+        }
+        
+        if self.toast.as_ref().is_some_and(|t| t.shown_at.elapsed() >= Toast::DURATION) {
+            self.toast = None;
+        }
+    }
+    
+    fn key_context(&self) -> Context {
+        if self.show_help {
+            Context::Help
+        } else if self.pending_confirm.is_some() {
+            Context::Confirm
+        } else if self.prompt.is_some() {
+            Context::Prompt
+        } else if self.menu.is_some() {
+            Context::Menu
+        } else if self.submodules.is_some() {
+            Context::Submodules
+        } else if self.worktrees.is_some() {
+            Context::Worktrees
+        } else if self.sparse.is_some() {
+            Context::Sparse
+        } else if self.pull_requests.is_some() {
+            Context::PullRequests
+        } else if self.compare.is_some() {
+            Context::Compare
+        } else if self.stats.is_some() {
+            Context::Stats
+        } else if self.info.is_some() {
+            Context::Info
+        } else if self.conflicts.is_some() {
+            Context::Conflicts
+        } else if self.search_mode {
+            Context::Search
+        } else if self.show_diff {
+            Context::Diff
+        } else if self.show_logs {
+            Context::Graph
+        } else {
+            Context::Branches
+        }
+    }
+    
+    /// Apply a resolved key action; returns true when the app should exit
+    fn perform_action(&mut self, action: Action) -> bool {
+        if self.read_only && action.is_mutating() {
+            self.show_toast("Read-only mode: operation disabled", true);
+            return false;
+        }
+        
+        match action {
+            Action::Quit => return true,
+            Action::ToggleHelp => {
+                self.show_help = true;
+                self.help_scroll_offset = 0;
+            }
+            Action::CloseHelp => {
+                self.show_help = false;
+            }
+            Action::StartSearch => {
+                self.search_mode = true;
+                self.search_input.clear();
+            }
+            Action::MoveUp if self.menu.is_some() => {
+                if let Some(ref mut menu) = self.menu {
+                    menu.selected = menu.selected.checked_sub(1).unwrap_or(menu.items.len().saturating_sub(1));
+                }
+            }
+            Action::MoveDown if self.menu.is_some() => {
+                if let Some(ref mut menu) = self.menu {
+                    menu.selected = (menu.selected + 1) % menu.items.len().max(1);
+                }
+            }
+            Action::MoveUp if self.submodules.is_some() => {
+                if let Some(ref mut view) = self.submodules {
+                    view.selected = view.selected.saturating_sub(1);
+                }
+            }
+            Action::MoveDown if self.submodules.is_some() => {
+                if let Some(ref mut view) = self.submodules {
+                    view.selected = (view.selected + 1).min(view.items.len().saturating_sub(1));
+                }
+            }
+            Action::MoveUp if self.worktrees.is_some() => {
+                if let Some(ref mut view) = self.worktrees {
+                    view.selected = view.selected.saturating_sub(1);
+                }
+            }
+            Action::MoveDown if self.worktrees.is_some() => {
+                if let Some(ref mut view) = self.worktrees {
+                    view.selected = (view.selected + 1).min(view.items.len().saturating_sub(1));
+                }
+            }
+            Action::MoveUp if self.sparse.is_some() => {
+                if let Some(ref mut view) = self.sparse {
+                    view.selected = view.selected.saturating_sub(1);
+                }
+            }
+            Action::MoveDown if self.sparse.is_some() => {
+                if let Some(ref mut view) = self.sparse {
+                    view.selected = (view.selected + 1).min(view.checkout.patterns.len().saturating_sub(1));
+                }
+            }
+            Action::MoveUp if self.pull_requests.is_some() => {
+                if let Some(ref mut view) = self.pull_requests {
+                    view.selected = view.selected.saturating_sub(1);
+                }
+            }
+            Action::MoveDown if self.pull_requests.is_some() => {
+                if let Some(ref mut view) = self.pull_requests {
+                    view.selected = (view.selected + 1).min(view.items.len().saturating_sub(1));
+                }
+            }
+            Action::MoveUp if self.compare.is_some() => {
+                if let Some(ref mut view) = self.compare {
+                    view.selected[view.side] = view.selected[view.side].saturating_sub(1);
+                }
+            }
+            Action::MoveDown if self.compare.is_some() => {
+                if let Some(ref mut view) = self.compare {
+                    let count = view.comparison.sides[view.side].commits.len();
+                    view.selected[view.side] = (view.selected[view.side] + 1).min(count.saturating_sub(1));
+                }
+            }
+            Action::MoveUp if self.conflicts.is_some() => {
+                if let Some(ref mut view) = self.conflicts {
+                    view.selected = view.selected.saturating_sub(1);
+                }
+                self.load_conflict_hunks();
+            }
+            Action::MoveDown if self.conflicts.is_some() => {
+                if let Some(ref mut view) = self.conflicts {
+                    view.selected = (view.selected + 1).min(view.items.len().saturating_sub(1));
+                }
+                self.load_conflict_hunks();
+            }
+            Action::MoveUp => {
+                if self.show_logs {
+                    self.previous_commit();
+                } else {
+                    self.previous_branch();
+                }
+                self.scroll_offset = 0; // Reset scroll when changing commits
+            }
+            Action::MoveDown => {
+                if self.show_logs {
+                    self.next_commit();
+                } else {
+                    self.next_branch();
+                }
+                self.scroll_offset = 0; // Reset scroll when changing commits
+            }
+            Action::FocusBranches => {
+                // Switch to branch panel if currently in logs
+                if self.show_logs {
+                    self.show_logs = false;
+                    if !self.branches.is_empty() {
+                        self.branch_list_state.select(Some(self.selected_branch));
+                    }
+                }
+            }
+            Action::FocusGraph => {
+                // Switch to git graph panel if currently in branches
+                if !self.show_logs {
+                    self.show_logs = true;
+                    if !self.graph_lines.is_empty() {
+                        self.commit_list_state.select(Some(self.selected_commit));
+                    }
+                }
+            }
+            Action::DetailsPageUp => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(5);
+            }
+            Action::DetailsPageDown => {
+                self.scroll_offset += 5;
+            }
+            Action::SwitchPanel => {
+                self.show_logs = !self.show_logs;
+                // Ensure the commit list state is properly initialized when switching to logs view
+                if self.show_logs && !self.graph_lines.is_empty() {
+                    self.commit_list_state.select(Some(self.selected_commit));
+                }
+                // Ensure the branch list state is properly initialized when switching to branch view
+                if !self.show_logs && !self.branches.is_empty() {
+                    self.branch_list_state.select(Some(self.selected_branch));
+                }
+            }
+            Action::SelectBranch => self.select_current_branch(),
+            Action::OpenDiff if self.graph_lines.get(self.selected_commit).is_some_and(|l| l.folded > 0) => {
+                self.toggle_fold();
+            }
+            // The split pane already shows the selected commit's diff
+            Action::OpenDiff if self.split_diff && self.current_diff.is_some() => self.show_diff = true,
+            Action::OpenDiff => self.load_commit_diff(),
+            Action::ToggleSplitDiff => self.toggle_split_diff(),
+            Action::TogglePin => self.toggle_pin(),
+            Action::ShowPins => self.open_pins_menu(),
+            Action::ToggleFold => self.toggle_fold(),
+            Action::TrailerActions => self.open_trailer_menu(),
+            Action::Checkout => self.checkout_selected_branch(),
+            Action::SetUpstream => self.open_upstream_menu(),
+            Action::EditBranchDescription => self.edit_branch_description(),
+            Action::Deepen => self.open_deepen_menu(),
+            Action::CycleBranchSort => self.cycle_branch_sort(),
+            Action::ToggleBranchGroups => self.toggle_branch_groups(),
+            Action::ToggleHideRemotes => self.toggle_hide_remotes(),
+            Action::ShowChange => self.show_change(),
+            Action::GoToParent => self.go_to_relative(Relative::FirstParent),
+            Action::GoToMergeParent => self.go_to_relative(Relative::MergeParent),
+            Action::GoToChild => self.go_to_relative(Relative::Child),
+            Action::ShowSubmodules => self.open_submodules(),
+            Action::CloseSubmodules => self.submodules = None,
+            Action::SubmoduleInit => self.submodule_command(&["init"], "Initialized"),
+            Action::SubmoduleUpdate => self.submodule_command(&["update", "--init", "--progress"], "Updated"),
+            Action::SubmoduleOpen => self.open_selected_submodule(),
+            Action::Bisect => self.open_bisect_menu(),
+            Action::ShowGitConfig => self.show_git_config(),
+            Action::PickIdentity => self.open_identity_menu(),
+            Action::CloseInfo => {
+                self.info = None;
+                if self.interrupted.is_some() {
+                    self.open_interrupted_menu();
+                }
+            }
+            Action::ShowWorktrees => self.open_worktrees(),
+            Action::CloseWorktrees => self.worktrees = None,
+            Action::WorktreeAdd => self.request_add_worktree(),
+            Action::WorktreeRemove => self.request_remove_worktree(),
+            Action::WorktreeSwitch => self.switch_to_selected_worktree(),
+            Action::ShowSparse => self.open_sparse(),
+            Action::CloseSparse => self.sparse = None,
+            Action::SparseAdd => self.request_add_sparse(),
+            Action::SparseRemove => self.remove_sparse_pattern(),
+            Action::SparseReapply => self.reapply_sparse(),
+            Action::SparseDisable => self.request_disable_sparse(),
+            Action::ShowPullRequests => self.open_pull_requests(),
+            Action::ClosePullRequests => self.pull_requests = None,
+            Action::PullRequestJump => self.jump_to_pull_request(),
+            Action::CompareBranches => self.start_compare(),
+            Action::CompareOtherSide => {
+                if let Some(ref mut view) = self.compare {
+                    view.side = 1 - view.side;
+                }
+            }
+            Action::CompareJump => self.jump_to_compared_commit(),
+            Action::CloseCompare => self.compare = None,
+            Action::ShowStats => self.count_changes(),
+            Action::Contributors => self.open_contributors(),
+            Action::CloseStats => self.stats = None,
+            Action::RevisionRange => self.open_range_menu(),
+            Action::RecoverBranch => self.scan_for_lost_branches(),
+            Action::Commit => self.commit_staged(),
+            Action::OpenFile => self.open_diff_file(false),
+            Action::OpenFileAtRevision => self.open_diff_file(true),
+            Action::LfsDiff => self.diff_lfs_file(),
+            Action::PullRequestCheckout => self.checkout_pull_request(),
+            Action::PullRequestOpen => {
+                if let Some(url) = self.selected_pull_request().map(|pr| pr.url.clone()) {
+                    self.open_url(&url);
+                }
+            }
+            Action::Undo => self.request_undo(),
+            Action::ToggleOpLog => self.show_oplog = !self.show_oplog,
+            Action::CancelTask => self.cancel_tasks(),
+            Action::SwitchRepository => self.open_repository_menu(),
+            Action::NewTab => self.new_tab(),
+            Action::CloseTab => self.close_tab(),
+            Action::NextTab => self.switch_tab((self.tab + 1) % self.tabs.len()),
+            Action::PreviousTab => self.switch_tab(self.tab.checked_sub(1).unwrap_or(self.tabs.len() - 1)),
+            Action::ShowConflicts => self.open_conflicts(),
+            Action::CloseConflicts => self.conflicts = None,
+            Action::TakeOurs => self.request_take_side(true),
+            Action::TakeTheirs => self.request_take_side(false),
+            Action::MarkResolved => self.mark_resolved(),
+            Action::ContinueOperation => self.continue_operation(),
+            Action::AbortOperation => self.request_abort_operation(),
+            Action::ExportAudit => {
+                if self.audit.is_some() {
+                    let name = format!("git-tui-audit-{}.txt", Utc::now().format("%Y%m%d-%H%M%S"));
+                    self.open_prompt("Write audit report to".to_string(), &name, PromptKind::AuditReport);
+                } else {
+                    self.show_toast("Not in audit mode (start git-tui with --audit)", true);
+                }
+            }
+            Action::ExportJson => {
+                self.open_prompt("Export graph as JSON to".to_string(), "git-tui-graph.json", PromptKind::ExportJson);
+            }
+            Action::ExportDot => {
+                self.open_prompt("Export graph as DOT (or .svg) to".to_string(), "git-tui-graph.dot", PromptKind::ExportDot);
+            }
+            Action::DateRange => {
+                let current = self.date_range.as_ref().map(|r| r.label.clone()).unwrap_or_default();
+                self.open_prompt(
+                    "Date range (\"last 2 weeks\", \"2024-01-01..2024-02-01\", \"2024-03\")".to_string(),
+                    &current,
+                    PromptKind::DateRange,
+                );
+            }
+            Action::FormatPatch => self.open_patch_menu(),
+            Action::Changelog => self.open_changelog_menu(),
+            Action::PushForReview => self.open_review_push(),
+            Action::ApplyPatch => {
+                self.open_prompt("Apply patch file or directory of .patch files".to_string(), "patches", PromptKind::ApplyPatch);
+            }
+            Action::Pickaxe => {
+                let current = match self.pickaxe {
+                    Some(ref p) if p.is_regex => format!("/{}/", p.pattern),
+                    Some(ref p) => p.pattern.clone(),
+                    None => String::new(),
+                };
+                self.open_prompt(
+                    "Commits adding or removing text (/regex/ for a regex)".to_string(),
+                    &current,
+                    PromptKind::Pickaxe,
+                );
+            }
+            Action::FilterCommitTypes => {
+                let current = self.type_filter.as_ref().map(TypeFilter::label).unwrap_or_default();
+                self.open_prompt(
+                    format!("Commit types ({})", conventional::TYPES.join(" ")),
+                    &current,
+                    PromptKind::CommitTypes,
+                );
+            }
+            Action::PathFilter => {
+                let current = self.path_filter.join(" ");
+                self.open_prompt("Paths (space-separated, globs like *.rs allowed)".to_string(), &current, PromptKind::PathFilter);
+            }
+            Action::ClearFilter => self.clear_branch_filter(),
+            Action::Refresh => {
+                // Diffs of a commit only change with the mailmap or replace
+                // refs, which an explicit refresh should pick up too
+                self.diff_cache.clear();
+                self.split_diff_commit.clear();
+                let _ = self.refresh_data();
+            }
+            Action::CloseDiff => self.close_diff(),
+            Action::ScrollUp if self.info.is_some() => {
+                if let Some(ref mut info) = self.info {
+                    info.scroll = info.scroll.saturating_sub(1);
+                }
+            }
+            Action::ScrollDown if self.info.is_some() => {
+                // Clamped against the content height when the overlay is drawn
+                if let Some(ref mut info) = self.info {
+                    info.scroll += 1;
+                }
+            }
+            Action::ScrollUp => {
+                if self.show_help {
+                    self.help_scroll_offset = self.help_scroll_offset.saturating_sub(1);
+                } else {
+                    self.diff_scroll_offset = self.diff_scroll_offset.saturating_sub(1);
+                }
+            }
+            Action::ScrollDown => {
+                if self.show_help {
+                    // Clamped against the content height when the overlay is drawn
+                    self.help_scroll_offset += 1;
+                } else {
+                    let max_scroll = self.get_max_diff_scroll(diff_popup_height());
+                    if self.diff_scroll_offset < max_scroll {
+                        self.diff_scroll_offset += 1;
+                    }
+                }
+            }
+            Action::ScrollPageUp => {
+                self.diff_scroll_offset = self.diff_scroll_offset.saturating_sub(10);
+            }
+            Action::ScrollPageDown => {
+                let max_scroll = self.get_max_diff_scroll(diff_popup_height());
+                self.diff_scroll_offset = (self.diff_scroll_offset + 10).min(max_scroll);
+            }
+            Action::ScrollLeft => self.diff_hscroll = self.diff_hscroll.saturating_sub(8),
+            Action::ScrollRight => {
+                if !self.diff_wrap {
+                    self.diff_hscroll += 8;
+                }
+            }
+            Action::ToggleDiffWrap => {
+                self.diff_wrap = !self.diff_wrap;
+                self.diff_hscroll = 0;
+            }
+            Action::SearchDiff => {
+                let query = self.diff_search.clone().unwrap_or_default();
+                self.open_prompt("Search diff (empty to stop)".to_string(), &query, PromptKind::DiffSearch);
+            }
+            Action::NextMatch | Action::PreviousMatch => {
+                if self.diff_search.is_none() {
+                    self.show_toast("No search; / starts one", true);
+                } else if !self.jump_to_diff_match(action == Action::NextMatch) {
+                    self.show_toast("No match", true);
+                }
+            }
+            Action::ToggleImagePreview => match self.image_protocol {
+                Some(_) => self.image_preview = !self.image_preview,
+                None => self.show_toast("This terminal shows no images; [diff] images sets the protocol (kitty, iterm2, sixel)", true),
+            },
+            Action::NextHunk | Action::PreviousHunk => {
+                if !self.jump_to_hunk(action == Action::NextHunk) {
+                    self.show_toast("No more hunks", false);
+                }
+            }
+            Action::ToggleMark => self.toggle_branch_mark(),
+            Action::ClearMarks => self.marked_branches.clear(),
+            Action::GraphMarked => self.graph_marked_branches(),
+            Action::FetchBranches => {
+                let targets = self.branch_targets();
+                self.fetch_branches(&targets);
+            }
+            Action::PreviewMerge => self.preview_merge(),
+            Action::DeleteBranches => {
+                let targets = self.branch_targets();
+                self.request_delete_branches(targets);
+            }
+            Action::ConfirmYes => self.resolve_confirm(true, false),
+            Action::ConfirmOverride => self.resolve_confirm(true, true),
+            Action::ConfirmNo => self.resolve_confirm(false, false),
+            Action::ConfirmAlways => self.confirm_always(),
+            Action::MenuSelect => {
+                let index = self.menu.as_ref().map_or(0, |m| m.selected);
+                self.choose_menu_entry(index);
+            }
+            Action::MenuCancel => {
+                // Asked again on the next start
+                if let Some(Menu { kind: MenuKind::Interrupted(_), .. }) = self.menu.take() {
+                    self.interrupted = None;
+                }
+            }
+            Action::RefMenu => self.open_ref_menu(),
+            Action::AuthorDomains => self.open_author_menu(),
+            Action::ToggleHideBots => self.toggle_hide_bots(),
+            Action::ToggleHideTreeSame => self.toggle_hide_tree_same(),
+            Action::ToggleMerges => self.toggle_merges(),
+            Action::CycleHistoryOrder => self.cycle_history_order(),
+            Action::ToggleMilestones => self.toggle_milestones(),
+            Action::ToggleRelativeDates => self.toggle_relative_dates(),
+            Action::ToggleShortstat => self.toggle_shortstat(),
+            Action::HighlightAuthor => self.open_highlight_menu(),
+            Action::ToggleFirstParent => self.toggle_first_parent(),
+            Action::ToggleStashes => self.toggle_stashes(),
+            Action::ToggleGlyphSet => self.toggle_glyph_set(),
+            Action::PromptSubmit => self.submit_prompt(),
+            Action::PromptCancel => self.prompt = None,
+            Action::ToggleRefFilter => {
+                if self.ref_filter.is_empty() {
+                    self.show_toast("No [refs] exclude patterns configured", true);
+                } else {
+                    self.ref_filter_enabled = !self.ref_filter_enabled;
+                    self.reload_graph_in_place();
+                    let state = if self.ref_filter_enabled { "on" } else { "off" };
+                    self.show_toast(format!("Ref exclude patterns {}", state), false);
+                }
+            }
+            // Text-entry actions are handled directly by the search input
+            Action::CancelSearch | Action::SearchNext | Action::SearchDeleteChar => {}
+        }
+        false
+    }
+}
+
+/// First line of a commit message
+fn commit_summary(message: &str) -> String {
+    message.lines().next().unwrap_or_default().trim().to_string()
+}
+
+/// Escapes the characters special in git's basic regular expressions
+fn regex_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '.' | '[' | ']' | '*' | '^' | '$') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Height of the diff overlay (90% of terminal height), used to bound scrolling
+fn diff_popup_height() -> u16 {
+    let terminal_height = crossterm::terminal::size().unwrap_or((80, 24)).1;
+    terminal_height * 9 / 10
+}
+
+fn draw_ui(f: &mut Frame, app: &mut App) {
+    app.image_slots = None;
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length((app.tabs.len() > 1) as u16), Constraint::Min(0), Constraint::Length(1)])
+        .split(f.area());
+    let mut main_area = rows[1];
+    draw_status_bar(f, app, rows[2]);
+    if app.tabs.len() > 1 {
+        draw_tab_bar(f, app, rows[0]);
+    }
+    if app.show_oplog {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length((main_area.height / 3).clamp(3, 12))])
+            .split(main_area);
+        main_area = rows[0];
+        draw_oplog(f, app, rows[1]);
+    }
+    
+    let chunks = match app.split_diff {
+        true => Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(22), Constraint::Percentage(38), Constraint::Percentage(40)])
+            .split(main_area),
+        false => Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(33), Constraint::Percentage(67)]) // Graph takes 2/3
+            .split(main_area),
+    };
+    
+    let left_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(chunks[0]);
+    
+    // Draw branches (top-left)
+    draw_branches(f, app, left_chunks[0]);
+    
+    // Draw commit details (bottom-left)
+    draw_commit_details(f, app, left_chunks[1]);
+    
+    // Draw commits graph (right side)
+    draw_commits(f, app, chunks[1]);
+    
+    if app.split_diff {
+        draw_diff_pane(f, app, chunks[2]);
+    }
+    
+    // Draw diff overlay if showing diff
+    if app.show_diff {
+        draw_diff_overlay(f, app);
+    }
+    
+    // Help overlay sits on top of everything else
+    if app.show_help {
+        draw_help_overlay(f, app);
+    }
+    
+    if app.submodules.is_some() {
+        draw_submodules(f, app);
+    }
+    
+    if app.worktrees.is_some() {
+        draw_worktrees(f, app);
+    }
+    
+    if app.sparse.is_some() {
+        draw_sparse(f, app);
+    }
+    
+    if app.pull_requests.is_some() {
+        draw_pull_requests(f, app);
+    }
+    
+    if app.compare.is_some() {
+        draw_compare(f, app);
+    }
+    
+    if app.stats.is_some() {
+        draw_stats(f, app);
+    }
+    
+    if app.conflicts.is_some() {
+        draw_conflicts(f, app);
+    }
+    
+    if app.info.is_some() {
+        draw_info(f, app);
+    }
+    
+    if app.menu.is_some() {
+        draw_menu(f, app);
+    }
+    
+    if app.prompt.is_some() {
+        draw_prompt(f, app);
+    }
+    
+    if app.pending_confirm.is_some() {
+        draw_confirm(f, app);
+    }
+    
+    if app.toast.is_some() {
+        draw_toast(f, app);
+    }
+}
+
+fn draw_branches(f: &mut Frame, app: &mut App, area: Rect) {
+    if app.branch_flash.as_ref().is_some_and(|flash| flash.started.elapsed() >= BranchFlash::DURATION) {
+        app.branch_flash = None;
+    }
+    let included_branches = app.get_included_branches();
+    
+    let items: Vec<ListItem> = app.branch_rows
+        .iter()
+        .map(|row| {
+            let (branch, depth, label_start) = match *row {
+                BranchRow::Branch { index, depth, label_start } => (&app.branches[index], depth, label_start),
+                BranchRow::Group { ref label, depth, count, collapsed, .. } => {
+                    let arrow = if collapsed { app.glyphs.collapsed } else { app.glyphs.expanded };
+                    let style = Style::default().fg(app.theme.inactive_border).add_modifier(Modifier::BOLD);
+                    return ListItem::new(format!("{}{} {} ({})", "  ".repeat(depth), arrow, label, count)).style(style);
+                }
+            };
+            let indent = "  ".repeat(depth);
+            let is_current_filter = app.current_branch_filter.as_ref() == Some(&branch.name);
+            let is_included = included_branches.contains(&branch.name);
+            let is_marked = app.marked_branches.contains(&branch.name);
+            
+            let style = if is_marked {
+                Style::default().fg(app.theme.branch_marked).add_modifier(Modifier::BOLD)
+            } else if is_current_filter {
+                Style::default().fg(app.theme.branch_filter).add_modifier(Modifier::BOLD)
+            } else if is_included {
+                Style::default().fg(app.theme.branch_included).add_modifier(Modifier::BOLD)
+            } else if branch.is_head {
+                Style::default().fg(app.theme.branch_head)
+            } else if branch.is_remote {
+                Style::default().fg(app.theme.branch_remote)
+            } else {
+                Style::default().fg(app.theme.branch_local)
+            };
+            
+            // Tip just moved (a fetch or another process)
+            let style = match app.branch_flash {
+                Some(ref flash) if flash.names.contains(&branch.name) => style.add_modifier(Modifier::REVERSED),
+                _ => style,
+            };
+            
+            let marker = if is_marked {
+                app.glyphs.marked
+            } else if is_current_filter { 
+                app.glyphs.filtered
+            } else if is_included { 
+                app.glyphs.included
+            } else { 
+                app.glyphs.branch
+            };
+            let marker = format!("{} ", marker);
+            if branch.is_detached {
+                let short = &branch.commit_id[..branch.commit_id.len().min(7)];
+                return ListItem::new(format!("{}{}(HEAD detached at {})", indent, marker, short))
+                    .style(style.add_modifier(Modifier::ITALIC));
+            }
+            let head_marker = if branch.is_head { " (HEAD)" } else { "" };
+            let name = &branch.name[label_start..];
+            
+            let mut spans = vec![Span::raw(format!("{}{}{}{}", indent, marker, name, head_marker))];
+            if let Some(ref upstream) = branch.upstream {
+                spans.push(Span::styled(format!(" → {}", upstream), Style::default().fg(app.theme.inactive_border)));
+            }
+            // What needs pushing (↑) or pulling (↓); nothing when in sync
+            if let Some((ahead, behind)) = branch.ahead_behind {
+                if ahead > 0 {
+                    spans.push(Span::styled(format!(" ↑{}", ahead), Style::default().fg(app.theme.diff_added)));
+                }
+                if behind > 0 {
+                    spans.push(Span::styled(format!(" ↓{}", behind), Style::default().fg(app.theme.diff_removed)));
+                }
+            }
+            ListItem::new(Line::from(spans)).style(style)
+        })
+        .collect();
+    
+    let title = if app.search_mode {
+        format!("Branches [Search: {}]", app.search_input.value())
+    } else if let Some(ref label) = app.nested_label {
+        format!("Branches [submodule {}]", label)
+    } else if !app.marked_branches.is_empty() {
+        format!("Branches [{} marked]", app.marked_branches.len())
+    } else if app.branch_sort != BranchSort::Name || app.hide_remotes {
+        let mut notes = Vec::new();
+        if app.branch_sort != BranchSort::Name {
+            notes.push(format!("by {}", app.branch_sort.label()));
+        }
+        if app.hide_remotes {
+            notes.push("remotes hidden".to_string());
+        }
+        format!("Branches [{}]", notes.join(", "))
+    } else {
+        "Branches".to_string()
+    };
+    
+    // Highlight the border when this panel is focused
+    let border_style = if !app.show_logs {
+        Style::default().fg(app.theme.active_border)  // Active panel
+    } else {
+        Style::default().fg(app.theme.inactive_border)  // Inactive panel
+    };
+    
+    // The selected branch's description, its first line along the bottom
+    let description = app.selected_git_branch().and_then(|branch| branch.description.as_deref()).map(|description| {
+        let mut lines = description.lines().filter(|line| !line.trim().is_empty());
+        let first = lines.next().unwrap_or_default().trim();
+        match lines.count() {
+            0 => format!(" {} ", first),
+            more => format!(" {} (+{} more) ", first, more),
+        }
+    });
+    
+    let selection = format!("{} ", app.glyphs.selection);
+    let total = items.len();
+    let list = List::new(items)
+        .block(Block::default()
+            .title(title)
+            .title_bottom(Line::styled(description.unwrap_or_default(), Style::default().fg(app.theme.commit_text)))
+            .borders(Borders::ALL)
+            .border_style(border_style))
+        .highlight_style(Style::default().bg(app.theme.selection_bg))
+        .highlight_symbol(&selection);
+    
+    f.render_stateful_widget(list, area, &mut app.branch_list_state);
+    draw_scrollbar(f, area, total, app.branch_list_state.offset(), border_style);
+}
+
+fn draw_commits(f: &mut Frame, app: &mut App, area: Rect) {
+    if let Some(ref error) = app.error_message {
+        let paragraph = Paragraph::new(format!("Error: {}", error))
+            .block(Block::default()
+                .title("Git Graph - Error")
+                .borders(Borders::ALL))
+            .style(Style::default().fg(app.theme.error))
+            .wrap(Wrap { trim: false });
+        f.render_widget(paragraph, area);
+        return;
+    }
+    
+    // The banner takes the top row of a shallow clone's graph
+    let area = match app.shallow_boundaries {
+        0 => area,
+        count => {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(area);
+            let mut banner = format!(" Shallow clone: history is cut off below {} {} commit(s)", count, app.glyphs.boundary);
+            if !app.read_only {
+                banner.push_str(" · ~: fetch more ");
+            }
+            f.render_widget(
+                Paragraph::new(banner).style(Style::default().fg(app.theme.ref_tag).add_modifier(Modifier::REVERSED)),
+                rows[0],
+            );
+            rows[1]
+        }
+    };
+    
+    if app.show_shortstat {
+        let first = app.commit_list_state.offset();
+        app.load_shortstats(first..first + area.height as usize);
+        app.load_shortstats(app.selected_commit..app.selected_commit + 1);
+    }
+    
+    // Pre-compute colored lines to avoid borrowing issues
+    let colored_lines: Vec<Line> = app.graph_lines
+        .iter()
+        .map(|line| app.render_graph_line(line))
+        .collect();
+    
+    let mut items: Vec<ListItem> = colored_lines
+        .into_iter()
+        .map(ListItem::new)
+        .collect();
+    
+    if app.has_more_commits {
+        items.push(ListItem::new(Span::styled(
+            "  loading more…",
+            Style::default().fg(app.theme.inactive_border).add_modifier(Modifier::ITALIC),
+        )));
+    }
+    
+    let title = if let Some(ref range) = app.rev_range {
+        match range.symmetric {
+            true => format!("Git Graph - {} (on either side, not both)", range.label()),
+            false => format!("Git Graph - {}", range.label()),
+        }
+    } else if let Some(ref refs) = app.exact_refs {
+        format!("Git Graph - {} selected branches", refs.len())
+    } else if let Some(ref branch) = app.current_branch_filter {
+        match app.filter_mode {
+            FilterMode::Only => format!("Git Graph - {} only", branch),
+            FilterMode::WithUpstream => match app.upstream_of(branch) {
+                Some(upstream) => format!("Git Graph - {} + upstream {}", branch, upstream),
+                None => format!("Git Graph - {} (no upstream)", branch),
+            },
+            FilterMode::WithDescendants => {
+                let included_branches = app.get_included_branches();
+                if included_branches.len() > 1 {
+                    format!("Git Graph - {} + {} descendants", 
+                            branch, included_branches.len() - 1)
+                } else {
+                    format!("Git Graph - {}", branch)
+                }
+            }
+            FilterMode::UniqueVsDefault => match app.default_branch() {
+                Some(ref default) if default != branch => {
+                    format!("Git Graph - commits on {} not on {}", branch, default)
+                }
+                _ => format!("Git Graph - {} (no other default branch)", branch),
+            },
+        }
+    } else {
+        "Git Graph - All branches".to_string()
+    };
+    // Filters are listed in the status bar
+    let mut title = title;
+    if app.show_stashes && app.current_branch_filter.is_none() && app.exact_refs.is_none() {
+        title.push_str(" [stashes]");
+    }
+    title.push_str(&format!(" [{}]", app.history_order.label()));
+    if let Some(ref author) = app.highlight_author {
+        title.push_str(&format!(" [highlight: {}]", author));
+    }
+    if let Some(ref audit) = app.audit {
+        title.push_str(&format!(" [audit: {} recorded]", audit.len()));
+    }
+    match app.bisect {
+        Some(ref state) if state.culprit.is_some() => title.push_str(" [bisect done]"),
+        Some(_) => title.push_str(" [bisecting]"),
+        None => {}
+    }
+    
+    // Highlight the border when this panel is focused
+    let border_style = if app.show_logs {
+        Style::default().fg(app.theme.active_border)  // Active panel
+    } else {
+        Style::default().fg(app.theme.inactive_border)  // Inactive panel
+    };
+    
+    let selection = format!("{} ", app.glyphs.selection);
+    let total = items.len();
+    let list = List::new(items)
+        .block(Block::default()
+            .title(title.as_str())
+            .title_bottom(match (app.read_only, app.identity.as_ref()) {
+                (true, _) => " ?: help · read-only ".to_string(),
+                (false, Some(identity)) => format!(" ?: help · committing as {} <{}> ", identity.name, identity.email),
+                (false, None) => " ?: help ".to_string(),
+            })
+            .borders(Borders::ALL)
+            .border_style(border_style))
+        .highlight_style(Style::default().bg(app.theme.selection_bg))
+        .highlight_symbol(&selection);
+    
+    f.render_stateful_widget(list, area, &mut app.commit_list_state);
+    draw_scrollbar(f, area, total, app.commit_list_state.offset(), border_style);
+}
+
+/// A path with the home directory as `~`
+fn home_relative(path: &Path) -> String {
+    // git2 gives working trees with a trailing slash
+    let path: PathBuf = path.components().collect();
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    match home.as_deref().and_then(|home| path.strip_prefix(home).ok()) {
+        Some(relative) => format!("~/{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Spinner, label and newest progress line of a background task
+fn task_status(task: &Task, ascii: bool) -> String {
+    match task.progress() {
+        progress if progress.is_empty() => format!("{} {}  Esc: cancel ", task.spinner(ascii), task.label),
+        progress => format!("{} {}: {}  Esc: cancel ", task.spinner(ascii), task.label, progress),
+    }
+}
+
+/// The view options that leave commits out of the graph, for the status bar
+fn filter_labels(app: &App) -> Vec<String> {
+    let mut labels = Vec::new();
+    match app.author_filter {
+        Some(AuthorFilter::Domain(ref domain)) => labels.push(format!("@{}", domain)),
+        Some(AuthorFilter::Bots) => labels.push("bots only".to_string()),
+        Some(AuthorFilter::Person { ref name, .. }) => labels.push(format!("by {}", name)),
+        None if app.hide_bots => labels.push("bots hidden".to_string()),
+        None => {}
+    }
+    if app.hide_tree_same {
+        labels.push("empty commits hidden".to_string());
+    }
+    if app.milestones_only {
+        labels.push("milestones only".to_string());
+    }
+    if app.first_parent {
+        labels.push("first parent".to_string());
+    }
+    if app.hide_merges {
+        labels.push("no merges".to_string());
+    }
+    if let Some(ref trailer) = app.trailer_filter {
+        labels.push(format!("{}: {}", trailer.key, trailer.value));
+    }
+    if let Some(ref range) = app.date_range {
+        labels.push(range.label.clone());
+    }
+    if let Some(ref pickaxe) = app.pickaxe {
+        labels.push(pickaxe.label());
+    }
+    if let Some(ref types) = app.type_filter {
+        labels.push(types.label());
+    }
+    if !app.path_filter.is_empty() {
+        labels.push(format!("-- {}", app.path_filter.join(" ")));
+    }
+    if !app.ref_filter_enabled {
+        labels.push("ref excludes off".to_string());
+    }
+    labels
+}
+
+/// The line along the top while there are several tabs
+fn draw_tab_bar(f: &mut Frame, app: &App, area: Rect) {
+    let titles: Vec<Line> = app
+        .tab_labels()
+        .into_iter()
+        .enumerate()
+        .map(|(index, label)| Line::from(format!("{} {}", index + 1, label)))
+        .collect();
+    let tabs = Tabs::new(titles)
+        .select(app.tab)
+        .style(Style::default().fg(app.theme.inactive_border))
+        .highlight_style(Style::default().fg(app.theme.commit_text).add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .divider("│");
+    f.render_widget(tabs, area);
+}
+
+/// The line along the bottom: repository, HEAD, changed files and filters,
+/// with a running task on the right
+fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
+    let dim = Style::default().fg(app.theme.inactive_border);
+    let mut spans = vec![
+        Span::styled(format!(" {} ", app.repo_name()), Style::default().fg(app.theme.commit_text).add_modifier(Modifier::BOLD)),
+        Span::styled(app.repo_path_label(), dim),
+        Span::styled(" │ ", dim),
+        Span::styled(app.head_description(), Style::default().fg(app.theme.branch_head)),
+        Span::styled(" │ ", dim),
+    ];
+    match app.dirty {
+        Some(ref dirty) if dirty.is_clean() => spans.push(Span::styled("clean", Style::default().fg(app.theme.diff_added))),
+        Some(ref dirty) => {
+            let counts = [
+                (dirty.staged, "staged", app.theme.diff_added),
+                (dirty.modified, "modified", app.theme.diff_hunk),
+                (dirty.untracked, "untracked", app.theme.inactive_border),
+                (dirty.conflicted, "conflicted", app.theme.error),
+            ];
+            let parts: Vec<Span> = counts
+                .into_iter()
+                .filter(|(count, _, _)| *count > 0)
+                .map(|(count, what, color)| Span::styled(format!("{} {}", count, what), Style::default().fg(color)))
+                .collect();
+            for (i, part) in parts.into_iter().enumerate() {
+                if i > 0 {
+                    spans.push(Span::styled(", ", dim));
+                }
+                spans.push(part);
+            }
+        }
+        None => spans.push(Span::styled("no working tree", dim)),
+    }
+    let filters = filter_labels(app);
+    if !filters.is_empty() {
+        spans.push(Span::styled(" │ ", dim));
+        spans.push(Span::styled(filters.join(" · "), Style::default().fg(app.theme.branch_filter)));
+    }
+    
+    let task = app.tasks.last().map(|running| task_status(&running.task, app.glyphs.ascii));
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(task.as_ref().map_or(0, |t| t.chars().count() as u16 + 1)),
+        ])
+        .split(area);
+    f.render_widget(Paragraph::new(Line::from(spans)), columns[0]);
+    if let Some(task) = task {
+        f.render_widget(
+            Paragraph::new(Span::styled(task, Style::default().fg(app.theme.diff_hunk))).alignment(Alignment::Right),
+            columns[1],
+        );
+    }
+}
+
+/// Draws a scrollbar over the right border of a bordered `area` showing
+/// `total` rows from `first`, when they don't all fit
+fn draw_scrollbar(f: &mut Frame, area: Rect, total: usize, first: usize, style: Style) {
+    let visible = area.height.saturating_sub(2) as usize;
+    if total <= visible {
+        return;
+    }
+    let mut state = ScrollbarState::new(total - visible)
+        .position(first)
+        .viewport_content_length(visible);
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None)
+        .track_symbol(Some(symbols::line::VERTICAL))
+        .style(style);
+    f.render_stateful_widget(scrollbar, area.inner(Margin { vertical: 1, horizontal: 0 }), &mut state);
+}
+
+fn draw_commit_details(f: &mut Frame, app: &App, area: Rect) {
+    let content: Vec<Line> = if let Some(commit) = app.get_selected_commit() {
+        let mut lines = vec![
+            Line::from(format!("Commit: {}", commit.id)),
+            Line::from(format!("Short: {}", commit.short_id)),
+            Line::from(format!("Author: {} <{}>", commit.author, commit.email)),
+            Line::from(format!("Date: {}", app.format_time(commit.timestamp))),
+        ];
+        
+        if !commit.refs.is_empty() {
+            lines.push(Line::from(format!("Refs: {}", commit.refs.join(", "))));
+        }
+        
+        for annotation in app.selected_annotations() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!("Tag {}:", annotation.name),
+                Style::default().fg(app.theme.ref_tag).add_modifier(Modifier::BOLD),
+            )));
+            if let Some(tagger) = annotation.tagger {
+                lines.push(Line::from(format!("  Tagger: {}", tagger)));
+            }
+            if let Some(time) = annotation.time {
+                lines.push(Line::from(format!("  Date: {}", app.format_time(time))));
+            }
+            let (signature, color) = match app.tag_verifications.get(&annotation.id) {
+                _ if !annotation.signed => ("not signed".to_string(), Color::Gray),
+                Some(verification @ tags::Verification::Good(_)) => (verification.describe(), app.theme.diff_added),
+                Some(verification @ tags::Verification::Bad(_)) => (verification.describe(), app.theme.diff_removed),
+                Some(verification) => (verification.describe(), app.theme.diff_hunk),
+                None => (tags::Verification::Checking.describe(), Color::Gray),
+            };
+            lines.push(Line::from(vec![Span::raw("  Signature: "), Span::styled(signature, Style::default().fg(color))]));
+            lines.extend(annotation.message.lines().map(|line| Line::from(format!("  {}", line))));
+        }
+        
+        let same_change = app.same_change_commits(commit);
+        if !same_change.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from("⧉ Same Change-Id (g: all branches):"));
+            for other in same_change {
+                let refs = if other.refs.is_empty() { String::new() } else { format!(" ({})", other.refs.join(", ")) };
+                lines.push(Line::from(format!("  {}{}", other.short_id, refs)));
+            }
+        }
+        
+        let trailers = trailers::parse(&commit.message);
+        if !trailers.is_empty() {
+            let width = trailers.iter().map(|t| t.key.len()).max().unwrap_or(0);
+            lines.push(Line::from(""));
+            lines.push(Line::from("Trailers (t: actions):"));
+            for trailer in &trailers {
+                let key_style = Style::default().fg(trailer_color(&trailer.key, &app.theme)).add_modifier(Modifier::BOLD);
+                // Values that open somewhere with `t`
+                let value_style = match trailers::link(trailer, &app.trailer_links) {
+                    Some(_) => Style::default().fg(app.theme.overlay_border).add_modifier(Modifier::UNDERLINED),
+                    None => Style::default(),
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {:<width$}", trailer.key, width = width), key_style),
+                    Span::raw("  "),
+                    Span::styled(trailer.value.clone(), value_style),
+                ]));
+            }
+        }
+        
+        if app.graph_lines.get(app.selected_commit).is_some_and(|l| l.is_boundary) {
+            lines.push(Line::from("◌ Boundary commit: its parents are outside the current view"));
+        }
+        
+        if let Some(line) = app.graph_lines.get(app.selected_commit)
+            && line.folded > 0
+        {
+            lines.push(Line::from(format!(
+                "⤷ {} bot commits folded, showing the newest (Enter or z expands)",
+                line.folded
+            )));
+        }
+        
+        if !commit.parents.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from("Parents:"));
+            for parent in &commit.parents {
+                lines.push(Line::from(format!("  {}", &parent[..8])));
+            }
+        }
+        
+        // Add full commit message with proper formatting
+        lines.push(Line::from(""));
+        lines.push(Line::from("Message:"));
+        lines.extend(commit.message.lines().map(|line| Line::from(line.to_string())));
+        
+        if let Some((id, Some(files))) = &app.details_stat
+            && id.to_string() == commit.id
+        {
+            lines.extend(diffstat_lines(files, commit.parents.len() > 1, &app.theme));
+        }
+        
+        lines
+    } else {
+        // Debug information to see what's happening
+        let selected_line = app.graph_lines.get(app.selected_commit);
+        let debug_info = if let Some(line) = selected_line {
+            format!("❌ No commit found!\n\nSelected Line:\n• Index: {}\n• Commit ID: '{}'\n• Graph: '{}'\n• Commit Text: '{}'\n\nCommits in HashMap: {}", 
+                    app.selected_commit, line.commit_id, line.graph_text, line.commit_text, app.commits.len())
+        } else {
+            format!("❌ No line at index {} (total: {})", app.selected_commit, app.graph_lines.len())
+        };
+        
+        format!("🐛 DEBUG MODE\n\nShow Logs: {}\n{}", app.show_logs, debug_info)
+            .lines()
+            .map(|line| Line::from(line.to_string()))
+            .collect()
+    };
     
     // Commit details panel has a neutral style (always dark gray since it's not directly navigable)
     let paragraph = Paragraph::new(content)
         .block(Block::default()
             .title("Commit Details")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::DarkGray)))
+            .border_style(Style::default().fg(app.theme.inactive_border)))
         .wrap(Wrap { trim: true })
         .scroll((app.scroll_offset, 0)); // Add scrolling capability
     
-    f.render_widget(paragraph, area);
+    f.render_widget(paragraph, area);
+}
+
+/// Commit Details' `Changes:` section, after the message as in `git show
+/// --stat`: the totals, then each file with a bar of `+` and `-` scaled to
+/// the most changed file and its count. The bar comes first, as the
+/// details pane trims leading spaces.
+fn diffstat_lines(stats: &[diffstat::FileStat], merge: bool, theme: &Theme) -> Vec<Line<'static>> {
+    const BAR_WIDTH: usize = 12;
+    const MAX_FILES: usize = 50;
+    let insertions: usize = stats.iter().map(|stat| stat.insertions).sum();
+    let deletions: usize = stats.iter().map(|stat| stat.deletions).sum();
+    let against = if merge { " against the first parent" } else { "" };
+    let plural = if stats.len() == 1 { "" } else { "s" };
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::raw(format!("Changes{}: {} file{}, ", against, stats.len(), plural)),
+            Span::styled(format!("+{}", insertions), Style::default().fg(theme.diff_added)),
+            Span::raw(" "),
+            Span::styled(format!("-{}", deletions), Style::default().fg(theme.diff_removed)),
+        ]),
+    ];
+    let most = stats.iter().map(|stat| stat.insertions + stat.deletions).max().unwrap_or(0).max(1);
+    let width = stats.iter().take(MAX_FILES).map(|stat| (stat.insertions + stat.deletions).to_string().len()).max().unwrap_or(1);
+    for stat in stats.iter().take(MAX_FILES) {
+        let changed = stat.insertions + stat.deletions;
+        // Binary files have no lines to count
+        let count = match stat.binary {
+            true => format!(" {:>width$} {}", "", stat.path, width = width),
+            false => format!(" {:>width$} {}", changed, stat.path, width = width),
+        };
+        if stat.binary || changed == 0 {
+            let label = if stat.binary { "binary" } else { "·" };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<BAR_WIDTH$}", label), Style::default().fg(Color::Gray)),
+                Span::raw(count),
+            ]));
+            continue;
+        }
+        // At least one mark for a side with any change, however small
+        let scale = |count: usize| match count {
+            0 => 0,
+            _ => (count * BAR_WIDTH).div_ceil(most).max(1),
+        };
+        let (mut plus, mut minus) = (scale(stat.insertions), scale(stat.deletions));
+        while plus + minus > BAR_WIDTH {
+            if plus > minus { plus -= 1 } else { minus -= 1 }
+        }
+        lines.push(Line::from(vec![
+            Span::styled("+".repeat(plus), Style::default().fg(theme.diff_added)),
+            Span::styled("-".repeat(minus), Style::default().fg(theme.diff_removed)),
+            Span::raw(" ".repeat(BAR_WIDTH - plus - minus)),
+            Span::raw(count),
+        ]));
+    }
+    if stats.len() > MAX_FILES {
+        lines.push(Line::from(format!("  … and {} more files", stats.len() - MAX_FILES)));
+    }
+    lines
+}
+
+/// Color of a conventional commit type in the graph: features and fixes
+/// stand out from maintenance
+fn conventional_color(kind: &str, theme: &Theme) -> Color {
+    match kind {
+        "feat" => theme.diff_added,
+        "fix" => theme.diff_removed,
+        "revert" => theme.diff_header,
+        _ => theme.diff_hunk,
+    }
+}
+
+/// Color of a trailer's key: sign-offs, review and test credits, co-authors
+/// and Gerrit's Change-Id each stand out
+fn trailer_color(key: &str, theme: &Theme) -> Color {
+    match key.to_ascii_lowercase().as_str() {
+        "signed-off-by" => theme.ref_local,
+        "reviewed-by" | "acked-by" | "tested-by" | "reported-by" | "suggested-by" => theme.branch_remote,
+        "co-authored-by" | "co-developed-by" => theme.branch_marked,
+        "change-id" => theme.commit_hash,
+        _ => theme.diff_header,
+    }
+}
+
+fn centered_rect(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn draw_help_overlay(f: &mut Frame, app: &mut App) {
+    let popup_area = centered_rect(f.area(), 70, 80);
+    
+    let mut lines = Vec::new();
+    for context in Context::all() {
+        let rows = app.keymap.describe(context, app.read_only);
+        if rows.is_empty() {
+            continue;
+        }
+        if !lines.is_empty() {
+            lines.push(Line::from(""));
+        }
+        lines.push(Line::from(Span::styled(
+            context.title().to_string(),
+            Style::default().fg(app.theme.active_border).add_modifier(Modifier::BOLD),
+        )));
+        for (keys, description) in rows {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<22}", keys), Style::default().fg(app.theme.overlay_border)),
+                Span::styled(description.to_string(), Style::default().fg(Color::White)),
+            ]));
+        }
+    }
+    
+    // Clamp scroll so the last binding stays reachable but not past the end
+    let content_height = popup_area.height.saturating_sub(2) as usize;
+    let max_scroll = lines.len().saturating_sub(content_height) as u16;
+    app.help_scroll_offset = app.help_scroll_offset.min(max_scroll);
+    
+    f.render_widget(Clear, popup_area);
+    
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default()
+            .title(" Key Bindings ")
+            .title_bottom(" Esc/q/?: close  ↑/↓: scroll ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.overlay_border)))
+        .scroll((app.help_scroll_offset, 0));
+    
+    f.render_widget(paragraph, popup_area);
+}
+
+fn draw_info(f: &mut Frame, app: &mut App) {
+    let Some(ref mut info) = app.info else {
+        return;
+    };
+    let popup_area = centered_rect(f.area(), 70, 60);
+    
+    let content_height = popup_area.height.saturating_sub(2) as usize;
+    let max_scroll = info.lines.len().saturating_sub(content_height) as u16;
+    info.scroll = info.scroll.min(max_scroll);
+    
+    f.render_widget(Clear, popup_area);
+    f.render_widget(
+        Paragraph::new(info.lines.clone())
+            .block(Block::default()
+                .title(format!(" {} ", info.title))
+                .title_bottom(" Esc/q: close  ↑/↓: scroll ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.overlay_border)))
+            .scroll((info.scroll, 0)),
+        popup_area,
+    );
+}
+
+fn draw_submodules(f: &mut Frame, app: &App) {
+    let Some(ref view) = app.submodules else {
+        return;
+    };
+    
+    let short = |oid: Option<Oid>| oid.map_or_else(|| "-------".to_string(), |id| id.to_string()[..7].to_string());
+    let path_width = view.items.iter().map(|s| s.path.display().to_string().chars().count()).max().unwrap_or(0);
+    let items: Vec<ListItem> = view.items
+        .iter()
+        .map(|submodule| {
+            let color = match submodule.state() {
+                "clean" => app.theme.branch_local,
+                "uninitialized" | "not checked out" => app.theme.inactive_border,
+                _ => app.theme.error,
+            };
+            let mut state = submodule.state().to_string();
+            if submodule.dirty && state != "dirty" {
+                state.push_str(", dirty");
+            }
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}  ", submodule.path.display(), width = path_width),
+                    Style::default().fg(color),
+                ),
+                Span::styled(short(submodule.recorded), Style::default().fg(app.theme.commit_hash)),
+                Span::raw(" → "),
+                Span::styled(short(submodule.checked_out), Style::default().fg(app.theme.commit_hash)),
+                Span::styled(format!("  {}", state), Style::default().fg(color)),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(view.selected));
+    
+    let popup_area = centered_rect(f.area(), 70, 60);
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(
+        List::new(items)
+            .block(Block::default()
+                .title(" Submodules (recorded → checked out) ")
+                .title_bottom(match app.read_only {
+                    true => " Enter: open  Esc: close ",
+                    false => " i: init  u: update  Enter: open  Esc: close ",
+                })
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.overlay_border)))
+            .highlight_style(Style::default().bg(app.theme.selection_bg))
+            .highlight_symbol("▶ "),
+        popup_area,
+        &mut state,
+    );
+}
+
+fn draw_worktrees(f: &mut Frame, app: &App) {
+    let Some(ref view) = app.worktrees else {
+        return;
+    };
+    
+    let path_width = view.items.iter().map(|w| w.path.display().to_string().chars().count()).max().unwrap_or(0);
+    let items: Vec<ListItem> = view.items
+        .iter()
+        .map(|worktree| {
+            let mut notes = Vec::new();
+            if app.is_current_worktree(worktree) {
+                notes.push("current");
+            }
+            if worktree.locked {
+                notes.push("locked");
+            }
+            if worktree.prunable {
+                notes.push("stale");
+            }
+            let color = if worktree.prunable { app.theme.inactive_border } else { app.theme.branch_local };
+            ListItem::new(Line::from(vec![
+                Span::styled(
+                    format!("{:<width$}  ", worktree.path.display(), width = path_width),
+                    Style::default().fg(color),
+                ),
+                Span::styled(worktree.label(), Style::default().fg(app.theme.commit_hash)),
+                Span::styled(
+                    if notes.is_empty() { String::new() } else { format!("  [{}]", notes.join(", ")) },
+                    Style::default().fg(app.theme.inactive_border),
+                ),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(view.selected));
+    
+    let popup_area = centered_rect(f.area(), 70, 60);
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(
+        List::new(items)
+            .block(Block::default()
+                .title(" Worktrees ")
+                .title_bottom(match app.read_only {
+                    true => " Enter: switch  Esc: close ",
+                    false => " n: new from selection  d: remove  Enter: switch  Esc: close ",
+                })
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.overlay_border)))
+            .highlight_style(Style::default().bg(app.theme.selection_bg))
+            .highlight_symbol("▶ "),
+        popup_area,
+        &mut state,
+    );
+}
+
+fn draw_sparse(f: &mut Frame, app: &App) {
+    let Some(ref view) = app.sparse else {
+        return;
+    };
+    
+    let checkout = &view.checkout;
+    let items: Vec<ListItem> = match checkout.enabled {
+        true if checkout.patterns.is_empty() => vec![ListItem::new(Span::styled(
+            "No directories: only the files at the top level are checked out",
+            Style::default().fg(app.theme.inactive_border),
+        ))],
+        true => checkout.patterns
+            .iter()
+            .map(|pattern| ListItem::new(Span::styled(pattern.clone(), Style::default().fg(app.theme.branch_local))))
+            .collect(),
+        false => vec![ListItem::new(Span::styled(
+            "Sparse checkout is off: the whole tree is checked out",
+            Style::default().fg(app.theme.inactive_border),
+        ))],
+    };
+    let mut state = ListState::default();
+    if checkout.enabled && !checkout.patterns.is_empty() {
+        state.select(Some(view.selected));
+    }
+    
+    let popup_area = centered_rect(f.area(), 60, 60);
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(
+        List::new(items)
+            .block(Block::default()
+                .title(format!(" Sparse checkout ({}) ", checkout.summary()))
+                .title_bottom(match (app.read_only, checkout.enabled) {
+                    (true, _) => " Esc: close ",
+                    (false, true) => " n: add  d: remove  r: reapply  x: turn off  Esc: close ",
+                    (false, false) => " n: check out only a directory  Esc: close ",
+                })
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.overlay_border)))
+            .highlight_style(Style::default().bg(app.theme.selection_bg))
+            .highlight_symbol("▶ "),
+        popup_area,
+        &mut state,
+    );
+}
+
+/// The two sides of a comparison in columns, the merge base below
+fn draw_compare(f: &mut Frame, app: &App) {
+    let Some(ref view) = app.compare else {
+        return;
+    };
+    let [ref a, ref b] = view.comparison.sides;
+    let popup_area = centered_rect(f.area(), 90, 80);
+    f.render_widget(Clear, popup_area);
+    let outer = Block::default()
+        .title(format!(" Compare {}...{} ", a.name, b.name))
+        .title_bottom(" Tab/←/→: other side  Enter: select in graph  Esc: close ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.overlay_border));
+    let inner = outer.inner(popup_area);
+    f.render_widget(outer, popup_area);
+    
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[0]);
+    for (index, side) in view.comparison.sides.iter().enumerate() {
+        let items: Vec<ListItem> = side.commits
+            .iter()
+            .map(|entry| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(short_id(&entry.id.to_string()), Style::default().fg(app.theme.commit_hash)),
+                    Span::raw(" "),
+                    Span::styled(entry.summary.clone(), Style::default().fg(app.theme.commit_text)),
+                    Span::styled(format!(" - {}", entry.author), Style::default().fg(app.theme.inactive_border)),
+                ]))
+            })
+            .collect();
+        let title = match side.commits.len() {
+            0 => format!(" Only on {}: nothing ", side.name),
+            count => format!(" Only on {}: {} commit(s) ", side.name, count),
+        };
+        let border = match index == view.side {
+            true => app.theme.active_border,
+            false => app.theme.inactive_border,
+        };
+        let mut state = ListState::default();
+        state.select((index == view.side && !side.commits.is_empty()).then_some(view.selected[index]));
+        f.render_stateful_widget(
+            List::new(items)
+                .block(Block::default().title(title).borders(Borders::ALL).border_style(Style::default().fg(border)))
+                .highlight_style(Style::default().bg(app.theme.selection_bg))
+                .highlight_symbol("▶ "),
+            columns[index],
+            &mut state,
+        );
+    }
+    
+    let base = match view.comparison.merge_base {
+        Some(ref base) => Line::from(vec![
+            Span::styled(" Merge base: ", Style::default().fg(app.theme.inactive_border)),
+            Span::styled(short_id(&base.id.to_string()), Style::default().fg(app.theme.commit_hash)),
+            Span::raw(" "),
+            Span::styled(base.summary.clone(), Style::default().fg(app.theme.commit_text)),
+            Span::styled(format!(" - {}", base.author), Style::default().fg(app.theme.inactive_border)),
+        ]),
+        None => Line::styled(" No merge base: the two share no history", Style::default().fg(app.theme.error)),
+    };
+    f.render_widget(Paragraph::new(base), rows[1]);
+}
+
+/// Shades of the activity heatmap, from no commits to the busiest days
+const HEAT: [&str; 5] = ["·", "░", "▒", "▓", "█"];
+
+fn draw_stats(f: &mut Frame, app: &App) {
+    let Some(ref stats) = app.stats else {
+        return;
+    };
+    let popup_area = centered_rect(f.area(), 90, 85);
+    f.render_widget(Clear, popup_area);
+    let outer = Block::default()
+        .title(format!(" Statistics of the {} loaded commits ", stats.commits))
+        .title_bottom(" Esc/q: close ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.overlay_border));
+    let inner = outer.inner(popup_area);
+    f.render_widget(outer, popup_area);
+    
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Length(9), Constraint::Length(5), Constraint::Min(0)])
+        .split(inner);
+    let last_day = stats.first_day + chrono::Days::new(stats.days.len() as u64 - 1);
+    let mut summary = vec![Line::from(format!(
+        " {} commits by {} author(s), weeks of {} to {}",
+        stats.commits,
+        stats.authors.len(),
+        stats.first_day.format("%Y-%m-%d"),
+        last_day.format("%Y-%m-%d"),
+    ))];
+    summary.push(match stats.average_size() {
+        Some((added, removed, files)) => Line::from(vec![
+            Span::raw(" Average commit (merges left out): "),
+            Span::styled(format!("+{:.0}", added), Style::default().fg(app.theme.diff_added)),
+            Span::raw(" "),
+            Span::styled(format!("-{:.0}", removed), Style::default().fg(app.theme.diff_removed)),
+            Span::raw(format!(" lines in {:.1} file(s)", files)),
+        ]),
+        None => Line::from(" Only merges: no commit sizes"),
+    });
+    f.render_widget(Paragraph::new(summary), rows[0]);
+    
+    // Weeks as columns, Monday to Sunday down; the newest weeks that fit
+    let heat_block = Block::default()
+        .title(" Commits per day ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.inactive_border));
+    let heat_width = heat_block.inner(rows[1]).width.saturating_sub(4) as usize / 2;
+    let week_count = stats.days.len() / 7;
+    let first_week = week_count.saturating_sub(heat_width);
+    let busiest = stats.days.iter().copied().max().unwrap_or(0).max(1);
+    let heat_lines: Vec<Line> = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+        .iter()
+        .enumerate()
+        .map(|(weekday, name)| {
+            let mut spans = vec![Span::styled(format!("{} ", name), Style::default().fg(app.theme.inactive_border))];
+            for week in first_week..week_count {
+                let count = stats.days[week * 7 + weekday];
+                let shade = match count {
+                    0 => 0,
+                    count => 1 + ((count * 4 - 1) / busiest).min(3) as usize,
+                };
+                let color = if count == 0 { app.theme.inactive_border } else { app.theme.diff_added };
+                spans.push(Span::styled(format!("{} ", HEAT[shade]), Style::default().fg(color)));
+            }
+            Line::from(spans)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(heat_lines).block(heat_block), rows[1]);
+    
+    let weeks = stats.weeks();
+    let spark_block = Block::default()
+        .title(format!(" Commits per week (most: {}) ", weeks.iter().max().unwrap_or(&0)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.inactive_border));
+    let spark_width = spark_block.inner(rows[2]).width as usize;
+    f.render_widget(
+        Sparkline::default()
+            .block(spark_block)
+            .data(&weeks[weeks.len().saturating_sub(spark_width)..])
+            .style(Style::default().fg(app.theme.diff_added)),
+        rows[2],
+    );
+    
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[3]);
+    let charts = [
+        (" Commits per author ", &stats.authors, app.theme.ref_local),
+        (" Busiest files (commits changing them) ", &stats.files, app.theme.ref_remote),
+    ];
+    for ((title, counts, color), area) in charts.into_iter().zip(columns.iter()) {
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.inactive_border));
+        let shown = block.inner(*area).height as usize;
+        let bars: Vec<Bar> = counts
+            .iter()
+            .take(shown)
+            .map(|(name, count)| {
+                Bar::default()
+                    .value(*count)
+                    .label(Line::from(truncate_label(name, 28)))
+                    .text_value(count.to_string())
+            })
+            .collect();
+        f.render_widget(
+            BarChart::default()
+                .block(block)
+                .direction(Direction::Horizontal)
+                .bar_width(1)
+                .bar_gap(0)
+                .bar_style(Style::default().fg(color))
+                .value_style(Style::default().fg(Color::Black).bg(color))
+                .data(BarGroup::default().bars(&bars)),
+            *area,
+        );
+    }
+}
+
+/// `name` cut to `width` characters from the left, where paths differ least
+fn truncate_label(name: &str, width: usize) -> String {
+    let count = name.chars().count();
+    match count > width {
+        true => format!("…{}", name.chars().skip(count - width + 1).collect::<String>()),
+        false => name.to_string(),
+    }
+}
+
+/// Newest commands and git2 calls at the bottom
+fn draw_oplog(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .title(" Operation log ")
+        .title_bottom(" J: hide ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.inactive_border));
+    let inner = block.inner(area);
+    let dim = Style::default().fg(app.theme.inactive_border);
+    let lines: Vec<Line> = oplog::latest(inner.height as usize)
+        .into_iter()
+        .map(|entry| {
+            let duration = match entry.duration {
+                Some(d) if d.as_millis() < 1000 => format!("{}ms", d.as_millis()),
+                Some(d) => format!("{:.1}s", d.as_secs_f64()),
+                None => "...".to_string(),
+            };
+            // Short status up front so long command lines cannot push it out of view
+            let (status, color, why) = match entry.outcome {
+                oplog::Outcome::Running => ("run", app.theme.diff_hunk, String::new()),
+                oplog::Outcome::Ok => ("ok", app.theme.diff_added, String::new()),
+                oplog::Outcome::Failed(why) => ("fail", app.theme.error, format!("  {}", why)),
+                oplog::Outcome::Cancelled => ("stop", app.theme.inactive_border, String::new()),
+            };
+            Line::from(vec![
+                Span::styled(format!("{} {:>7} ", entry.started.format("%H:%M:%S"), duration), dim),
+                Span::styled(format!("{:<5}", status), Style::default().fg(color)),
+                Span::styled(entry.what, Style::default().fg(app.theme.commit_text)),
+                Span::styled(why, Style::default().fg(app.theme.error)),
+            ])
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn draw_conflicts(f: &mut Frame, app: &App) {
+    let Some(ref view) = app.conflicts else {
+        return;
+    };
+    
+    let popup_area = centered_rect(f.area(), 80, 70);
+    f.render_widget(Clear, popup_area);
+    let title = match view.operation {
+        Some(operation) => format!(" Conflicts: git {} ", operation.command()),
+        None => " Conflicts ".to_string(),
+    };
+    let hint = match view.operation {
+        _ if app.read_only => " read-only  Esc: close ",
+        Some(_) => " o: ours  t: theirs  a: mark resolved  c: continue  A: abort  Esc: close ",
+        None => " o: ours  t: theirs  a: mark resolved  Esc: close ",
+    };
+    let block = Block::default()
+        .title(title)
+        .title_bottom(hint)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.overlay_border));
+    let inner = block.inner(popup_area);
+    f.render_widget(block, popup_area);
+    
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(inner);
+    
+    let kind_width = view.items.iter().map(|c| c.kind.len()).max().unwrap_or(0);
+    let items: Vec<ListItem> = view.items
+        .iter()
+        .map(|conflict| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<width$}  ", conflict.kind, width = kind_width), Style::default().fg(app.theme.error)),
+                Span::styled(conflict.path.clone(), Style::default().fg(app.theme.commit_text)),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(view.selected));
+    f.render_stateful_widget(
+        List::new(items)
+            .block(Block::default().borders(Borders::RIGHT).border_style(Style::default().fg(app.theme.inactive_border)))
+            .highlight_style(Style::default().bg(app.theme.selection_bg))
+            .highlight_symbol("▶ "),
+        columns[0],
+        &mut state,
+    );
+    
+    let dim = Style::default().fg(app.theme.inactive_border);
+    let lines: Vec<Line> = if view.items.is_empty() {
+        let next = match view.operation {
+            Some(_) => "All conflicts resolved: c to continue, A to abort",
+            None => "All conflicts resolved",
+        };
+        vec![Line::from(Span::styled(next, dim))]
+    } else if view.hunks.is_empty() {
+        vec![Line::from(Span::styled("No conflict markers in the working tree file (o/t to pick a side)", dim))]
+    } else {
+        view.hunks
+            .iter()
+            .map(|(side, text)| {
+                let style = match side {
+                    Side::Marker => Style::default().fg(app.theme.diff_hunk),
+                    Side::Ours => Style::default().fg(app.theme.diff_added),
+                    Side::Base => Style::default().fg(app.theme.diff_header),
+                    Side::Theirs => Style::default().fg(app.theme.diff_removed),
+                };
+                Line::from(Span::styled(text.clone(), style))
+            })
+            .collect()
+    };
+    f.render_widget(Paragraph::new(lines), columns[1].inner(Margin { horizontal: 1, vertical: 0 }));
+}
+
+fn draw_pull_requests(f: &mut Frame, app: &App) {
+    let Some(ref view) = app.pull_requests else {
+        return;
+    };
+    
+    let number_width = view.items.iter().map(|pr| view.forge.reference(pr.number).len()).max().unwrap_or(0);
+    let items: Vec<ListItem> = view.items
+        .iter()
+        .map(|pr| {
+            // Whether the head commit is loaded, so Enter can jump to it
+            let (marker, marker_color) = match app.graph_row(&pr.sha) {
+                Some(_) => ("● ", app.theme.branch_head),
+                None => ("○ ", app.theme.inactive_border),
+            };
+            let mut spans = vec![
+                Span::styled(marker, Style::default().fg(marker_color)),
+                Span::styled(
+                    format!("{:<width$}  ", view.forge.reference(pr.number), width = number_width),
+                    Style::default().fg(app.theme.commit_hash),
+                ),
+                Span::styled(pr.title.clone(), Style::default().fg(app.theme.commit_text)),
+                Span::styled(format!("  {}", pr.author), Style::default().fg(app.theme.inactive_border)),
+                Span::styled(
+                    format!("  {}", pr.fork.as_deref().unwrap_or(&pr.branch)),
+                    Style::default().fg(app.theme.branch_remote),
+                ),
+            ];
+            if pr.draft {
+                spans.push(Span::styled("  [draft]", Style::default().fg(app.theme.inactive_border)));
+            }
+            if let Some(ref status) = pr.pipeline {
+                let color = match status.as_str() {
+                    "success" => app.theme.diff_added,
+                    "failed" => app.theme.diff_removed,
+                    "running" | "pending" | "created" | "preparing" | "waiting_for_resource" => app.theme.diff_hunk,
+                    _ => app.theme.inactive_border,
+                };
+                spans.push(Span::styled(format!("  pipeline: {}", status), Style::default().fg(color)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(view.selected));
+    
+    let popup_area = centered_rect(f.area(), 80, 60);
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(
+        List::new(items)
+            .block(Block::default()
+                .title(format!(" {} {}: {} ({} open) ", view.forge.name(), view.forge.noun(), view.repository, view.items.len()))
+                .title_bottom(match app.read_only {
+                    true => " Enter: show in graph  o: open  r: reload  Esc: close ",
+                    false => " Enter: show in graph  c: check out  o: open  r: reload  Esc: close ",
+                })
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.overlay_border)))
+            .highlight_style(Style::default().bg(app.theme.selection_bg))
+            .highlight_symbol("▶ "),
+        popup_area,
+        &mut state,
+    );
+}
+
+fn draw_menu(f: &mut Frame, app: &App) {
+    let Some(ref menu) = app.menu else {
+        return;
+    };
+    
+    let area = f.area();
+    let longest = menu.items.iter().map(|i| i.chars().count()).max().unwrap_or(0);
+    let width = ((longest.max(menu.title.chars().count()) + 10) as u16).min(area.width);
+    let height = (menu.items.len() as u16 + 2).min(area.height);
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    
+    let items: Vec<ListItem> = menu.items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| ListItem::new(format!("{}. {}", i + 1, item)))
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(menu.selected));
+    
+    f.render_widget(Clear, popup_area);
+    f.render_stateful_widget(
+        List::new(items)
+            .block(Block::default()
+                .title(format!(" {} ", menu.title))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.overlay_border)))
+            .highlight_style(Style::default().bg(app.theme.selection_bg))
+            .highlight_symbol("▶ "),
+        popup_area,
+        &mut state,
+    );
+}
+
+fn draw_prompt(f: &mut Frame, app: &App) {
+    let Some(ref prompt) = app.prompt else {
+        return;
+    };
+    
+    let area = f.area();
+    let width = (area.width / 2).max(40.min(area.width));
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(3) / 2,
+        width,
+        height: 3.min(area.height),
+    };
+    
+    f.render_widget(Clear, popup_area);
+    f.render_widget(
+        Paragraph::new(prompt.input.line(Style::default().fg(Color::White)))
+            .block(Block::default()
+                .title(format!(" {} ", prompt.title))
+                .title_bottom(" Enter: accept  Esc: cancel ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.overlay_border))),
+        popup_area,
+    );
 }
 
-fn draw_help(f: &mut Frame, app: &App, area: Rect) {
-    let help_text = if app.show_diff {
-        "Esc/q: close diff  ↑/↓/j/k: scroll  PgUp/PgDn: scroll fast"
-    } else if app.search_mode {
-        "Esc: cancel search  Enter: find next  Backspace: delete char  Type to search"
-    } else if app.show_logs {
-        "Tab/h/l: switch panel  c: clear filter  r: refresh  q: quit  |  ↑/↓/j/k: navigate  PgUp/PgDn: scroll  Enter: diff"
+fn draw_confirm(f: &mut Frame, app: &App) {
+    let Some(ref confirm) = app.pending_confirm else {
+        return;
+    };
+    
+    let area = f.area();
+    let height = (confirm.message.lines().count() as u16 + 4).min(area.height);
+    let keys = if confirm.rewrites_published {
+        " !: rewrite anyway  n/Esc: no "
+    } else if !confirm.action.kind().skippable() {
+        " y/Enter: yes  n/Esc: no "
     } else {
-        "Tab/h/l: switch panel  c: clear filter  r: refresh  q: quit  |  ↑/↓/j/k: navigate  Enter: select  /: search"
+        " y/Enter: yes  a: yes, don't ask again  n/Esc: no "
+    };
+    let width = (area.width * 6 / 10).max(30.min(area.width));
+    let popup_area = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
     };
     
-    let help = Paragraph::new(help_text)
-        .block(Block::default()
-            .title("Help")
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::DarkGray)))
-        .style(Style::default().fg(Color::Gray));
+    f.render_widget(Clear, popup_area);
+    f.render_widget(
+        Paragraph::new(confirm.message.as_str())
+            .block(Block::default()
+                .title(format!(" Confirm {} ", confirm.action.kind().label()))
+                .title_bottom(keys)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(app.theme.error)))
+            .wrap(Wrap { trim: false }),
+        popup_area,
+    );
+}
+
+fn draw_toast(f: &mut Frame, app: &App) {
+    let Some(ref toast) = app.toast else {
+        return;
+    };
+    
+    let area = f.area();
+    let message_width = toast.message.chars().count() as u16;
+    let width = (message_width + 4).clamp(20, 60).min(area.width);
+    let inner_width = width.saturating_sub(2).max(1);
+    let height = (message_width.div_ceil(inner_width) + 2).min(area.height);
+    let toast_area = Rect {
+        x: area.width.saturating_sub(width + 1),
+        y: area.height.saturating_sub(height + 1),
+        width,
+        height,
+    };
+    
+    let color = if toast.is_error { app.theme.error } else { app.theme.overlay_border };
+    f.render_widget(Clear, toast_area);
+    f.render_widget(
+        Paragraph::new(toast.message.as_str())
+            .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(color)))
+            .style(Style::default().fg(color))
+            .wrap(Wrap { trim: true }),
+        toast_area,
+    );
+}
+
+/// The diff's lines from the scroll position on, styled and numbered, as
+/// many as fit inside the borders of `area`
+fn diff_text_lines(app: &App, area: Rect) -> Vec<Line<'static>> {
+    let Some(ref diff_content) = app.current_diff else {
+        return Vec::new();
+    };
+    // Account for borders
+    let height = area.height.saturating_sub(2) as usize;
+    let digits = app.diff_numbers.digits();
+    // Old and new numbers, then a separator; a filter prints its own
+    let filtered = app.diff_styled.as_ref();
+    let gutter_width = if filtered.is_some() { 0 } else { digits * 2 + 3 };
+    let text_width = (area.width.saturating_sub(2) as usize).saturating_sub(gutter_width).max(1);
+    let gutter_style = Style::default().fg(app.theme.inactive_border);
+    let number = |n: Option<u32>| n.map(|n| format!("{:>1$}", n, digits)).unwrap_or_else(|| " ".repeat(digits));
     
-    f.render_widget(help, area);
+    // Wrapped lines continue under the text, leaving the gutter blank
+    let mut styled_lines = Vec::new();
+    for (index, line) in diff_content.lines().enumerate().skip(app.diff_scroll_offset as usize) {
+        if styled_lines.len() >= height {
+            break;
+        }
+        // Tabs would throw the columns off
+        let line = line.replace('\t', "    ");
+        let hits = app.pickaxe.as_ref().map(|p| p.hits(&line)).unwrap_or_default();
+        let mut styled = match filtered.and_then(|rows| rows.get(index)) {
+            Some(row) => row.clone(),
+            None if hits.is_empty() => colorize_diff_line(&line, &app.theme),
+            None => highlight_hits(&line, &hits, &app.theme),
+        };
+        if let Some(ref query) = app.diff_search {
+            let found = difflines::find(&line, query);
+            if !found.is_empty() {
+                // The match jumped to stands out from the others
+                let patch = match app.diff_match == Some(index) {
+                    true => Style::default().fg(Color::Black).bg(app.theme.active_border),
+                    false => Style::default().add_modifier(Modifier::REVERSED),
+                };
+                styled = Line::from(difflines::highlight(&styled.spans, &found, patch));
+            }
+        }
+        
+        let (old, new) = app.diff_numbers.get(index);
+        let mut gutter = Span::styled(format!("{} {} │", number(old), number(new)), gutter_style);
+        let (mut start, end) = match app.diff_wrap {
+            true => (0, difflines::char_count(&styled.spans).max(1)),
+            false => (app.diff_hscroll, app.diff_hscroll + 1),
+        };
+        while start < end && styled_lines.len() < height {
+            let mut spans = match filtered {
+                Some(_) => Vec::new(),
+                None => vec![gutter.clone()],
+            };
+            spans.extend(difflines::slice(&styled.spans, start, text_width));
+            styled_lines.push(Line::from(spans));
+            gutter = Span::styled(format!("{:>1$}", "│", gutter_width), gutter_style);
+            start += text_width;
+        }
+    }
+    styled_lines
+}
+
+/// The split layout's pane right of the graph, following the selection
+fn draw_diff_pane(f: &mut Frame, app: &App, area: Rect) {
+    let title = match (app.diff_stream.is_some(), app.diff_files) {
+        (true, files) => format!(" Diff loading… {} file(s) so far ", files),
+        (false, 0) => " Diff ".to_string(),
+        (false, 1) => " Diff · 1 file ".to_string(),
+        (false, files) => format!(" Diff · {} files ", files),
+    };
+    let block = Block::default()
+        .title(title)
+        .title_bottom(" Enter: scroll and search ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.inactive_border));
+    f.render_widget(Paragraph::new(diff_text_lines(app, area)).block(block), area);
+}
+
+/// With image previews on and an image file at the top of the diff, boxes
+/// for its thumbnails before and after the change across the bottom of
+/// `area`, which the terminal draws into once the frame is done. The rest
+/// of `area` is left for the diff.
+fn draw_image_slots(f: &mut Frame, app: &mut App, area: Rect) -> Rect {
+    let Some(protocol) = app.image_protocol.filter(|_| app.image_preview) else {
+        return area;
+    };
+    let Some(file) = app.current_diff.as_deref().and_then(|diff| {
+        difflines::file_at(&diff.lines().collect::<Vec<_>>(), app.diff_scroll_offset as usize)
+    }) else {
+        return area;
+    };
+    let path = file.1.clone().or(file.0.clone()).unwrap_or_default();
+    if !images::is_image(&path) {
+        return area;
+    }
+    let height = area.height * 2 / 5;
+    let preview = Rect { y: area.bottom() - height, height, ..area };
+    let halves = Layout::horizontal([Constraint::Percentage(50); 2]).split(preview);
+    let mut inner = [Rect::default(); 2];
+    for (index, (title, side)) in [(" Before ", &file.0), (" After ", &file.1)].into_iter().enumerate() {
+        let block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(app.theme.overlay_border));
+        inner[index] = block.inner(halves[index]);
+        let note = match side {
+            None => "(no file on this side)",
+            Some(path) if protocol == images::Protocol::Kitty && !path.to_lowercase().ends_with(".png") => "kitty shows PNG images only",
+            Some(_) => "",
+        };
+        f.render_widget(Paragraph::new(note).style(Style::default().fg(app.theme.inactive_border)).block(block), halves[index]);
+    }
+    app.image_slots = Some(ImageSlots { file, before: inner[0], after: inner[1] });
+    Rect { height: area.height - height, ..area }
+}
+
+/// `content` in `area` of the screen, in its shape; kitty takes PNGs only,
+/// which the preview box says for other images
+fn draw_image(out: &mut impl Write, protocol: images::Protocol, id: u32, content: &[u8], area: Rect) -> Result<()> {
+    let size = images::png_size(content);
+    // Pixels per cell, where the terminal tells them
+    let cell = match crossterm::terminal::window_size() {
+        Ok(window) if window.width > 0 && window.columns > 0 => (window.width / window.columns, window.height / window.rows.max(1)),
+        _ => (8, 16),
+    };
+    execute!(out, crossterm::cursor::MoveTo(area.x, area.y))?;
+    match protocol {
+        images::Protocol::Kitty => {
+            if let Some(pixels) = size {
+                write!(out, "{}", images::kitty(content, id, images::fit(pixels, (area.width, area.height), cell)))?;
+            }
+        }
+        images::Protocol::Iterm2 => write!(out, "{}", images::iterm2(content, (area.width, area.height)))?,
+        images::Protocol::Sixel => {
+            let (columns, rows) = size.map_or((area.width, area.height), |pixels| images::fit(pixels, (area.width, area.height), cell));
+            let width = size.map(|_| u32::from(columns) * u32::from(cell.0));
+            let output = images::sixel(content.to_vec(), width, u32::from(rows) * u32::from(cell.1)).output()?;
+            if !output.status.success() {
+                anyhow::bail!("img2sixel: {}", oplog::first_line(&String::from_utf8_lossy(&output.stderr)));
+            }
+            out.write_all(&output.stdout)?;
+        }
+    }
+    Ok(())
 }
 
 fn draw_diff_overlay(f: &mut Frame, app: &mut App) {
@@ -1073,38 +9250,47 @@ fn draw_diff_overlay(f: &mut Frame, app: &mut App) {
     
     // Clear only the popup area  
     f.render_widget(Clear, popup_area);
+    let diff_area = draw_image_slots(f, app, popup_area);
     
-    if let Some(ref diff_content) = app.current_diff {
-        let lines: Vec<&str> = diff_content.lines().collect();
-        let visible_lines: Vec<&str> = lines
-            .iter()
-            .skip(app.diff_scroll_offset as usize)
-            .take((popup_area.height.saturating_sub(2)) as usize) // Account for borders
-            .copied()
-            .collect();
-        
-        // Create colorized spans for diff content
-        let mut styled_lines = Vec::new();
-        for line in visible_lines {
-            styled_lines.push(colorize_diff_line(line));
-        }
-        
-        let paragraph = Paragraph::new(styled_lines)
+    if app.current_diff.is_some() {
+        let paragraph = Paragraph::new(diff_text_lines(app, diff_area))
             .block(Block::default()
-                .title(format!(" Diff (line {}/{}) ", 
-                    app.diff_scroll_offset + 1, 
-                    lines.len().max(1)))
+                .title(if app.diff_stream.is_some() {
+                    format!(" Diff (line {}/{}) loading… {} file(s) so far ",
+                        app.diff_scroll_offset + 1,
+                        app.diff_line_count.max(1),
+                        app.diff_files)
+                } else {
+                    format!(" Diff (line {}/{}) ",
+                        app.diff_scroll_offset + 1,
+                        app.diff_line_count.max(1))
+                })
+                .title_bottom(match app.diff_search {
+                    Some(ref query) => format!(" /{} · n/N: next/previous match ", query),
+                    None => " /: search ".to_string(),
+                })
+                .title_bottom(match (app.diff_wrap, app.diff_hscroll) {
+                    (true, _) => " w: unwrap ".to_string(),
+                    (false, 0) => " w: wrap · ←/→ scroll ".to_string(),
+                    (false, column) => format!(" w: wrap · ←/→ scroll · column {} ", column + 1),
+                })
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)))
-            .wrap(Wrap { trim: false });
+                .border_style(Style::default().fg(app.theme.overlay_border)));
         
-        f.render_widget(paragraph, popup_area);
+        f.render_widget(paragraph, diff_area);
+        draw_scrollbar(
+            f,
+            diff_area,
+            app.diff_line_count,
+            app.diff_scroll_offset as usize,
+            Style::default().fg(app.theme.overlay_border),
+        );
     } else {
         let paragraph = Paragraph::new("Loading diff...")
             .block(Block::default()
                 .title(" Diff ")
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)))
+                .border_style(Style::default().fg(app.theme.overlay_border)))
             .style(Style::default().fg(Color::Gray));
         
         f.render_widget(paragraph, popup_area);
@@ -1112,197 +9298,374 @@ fn draw_diff_overlay(f: &mut Frame, app: &mut App) {
 }
 
 fn handle_events(app: &mut App) -> Result<bool> {
-    if event::poll(std::time::Duration::from_millis(50))? { // Reduced timeout for faster response
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                // Handle search mode
-                if app.search_mode {
-                    match key.code {
-                        KeyCode::Esc => {
-                            app.search_mode = false;
-                            app.search_query.clear();
-                            return Ok(false);
-                        }
-                        KeyCode::Enter => {
-                            app.search_branch();
-                            return Ok(false);
-                        }
-                        KeyCode::Backspace => {
-                            app.search_query.pop();
-                            return Ok(false);
-                        }
-                        KeyCode::Char(c) => {
-                            app.search_query.push(c);
-                            return Ok(false);
-                        }
-                        _ => {}
-                    }
-                    return Ok(false);
-                }
-                
-                // Handle diff view separately
-                if app.show_diff {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => {
-                            app.close_diff();
-                            return Ok(false);
-                        }
-                        KeyCode::Up | KeyCode::Char('k') => {
-                            if app.diff_scroll_offset > 0 {
-                                app.diff_scroll_offset -= 1;
-                            }
-                        }
-                        KeyCode::Down | KeyCode::Char('j') => {
-                            // Calculate current popup height (90% of terminal height)
-                            let terminal_height = crossterm::terminal::size().unwrap_or((80, 24)).1;
-                            let popup_height = terminal_height * 9 / 10;
-                            let max_scroll = app.get_max_diff_scroll(popup_height);
-                            if app.diff_scroll_offset < max_scroll {
-                                app.diff_scroll_offset += 1;
-                            }
-                        }
-                        KeyCode::PageUp => {
-                            if app.diff_scroll_offset >= 10 {
-                                app.diff_scroll_offset -= 10;
-                            } else {
-                                app.diff_scroll_offset = 0;
-                            }
-                        }
-                        KeyCode::PageDown => {
-                            // Calculate current popup height (90% of terminal height)
-                            let terminal_height = crossterm::terminal::size().unwrap_or((80, 24)).1;
-                            let popup_height = terminal_height * 9 / 10;
-                            let max_scroll = app.get_max_diff_scroll(popup_height);
-                            app.diff_scroll_offset = (app.diff_scroll_offset + 10).min(max_scroll);
-                        }
-                        _ => {}
-                    }
-                    return Ok(false);
+    if event::poll(std::time::Duration::from_millis(50))? // Reduced timeout for faster response
+        && let Event::Key(key) = event::read()?
+        && key.kind == KeyEventKind::Press
+    {
+        let context = app.key_context();
+        
+        // A running task takes its cancel key ahead of the panels
+        if matches!(context, Context::Branches | Context::Graph)
+            && !app.tasks.is_empty()
+            && app.keymap.lookup(Context::Task, key.code) == Some(Action::CancelTask)
+        {
+            app.cancel_tasks();
+            return Ok(false);
+        }
+        
+        let action = app.keymap.lookup(context, key.code);
+        
+        // Handle search mode: unbound keys edit the query
+        if context == Context::Search {
+            match action {
+                Some(Action::CancelSearch) => {
+                    app.search_mode = false;
+                    app.search_input.clear();
                 }
-                
-                match key.code {
-                    KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
-                    KeyCode::Char('/') => {
-                        // Enter search mode only when in branch panel
-                        if !app.show_logs {
-                            app.search_mode = true;
-                            app.search_query.clear();
-                        }
-                    }
-                    KeyCode::Up | KeyCode::Char('k') => {
-                        if app.show_logs {
-                            app.previous_commit();
-                        } else {
-                            app.previous_branch();
-                        }
-                        app.scroll_offset = 0; // Reset scroll when changing commits
-                    }
-                    KeyCode::Down | KeyCode::Char('j') => {
-                        if app.show_logs {
-                            app.next_commit();
-                        } else {
-                            app.next_branch();
-                        }
-                        app.scroll_offset = 0; // Reset scroll when changing commits
-                    }
-                    KeyCode::Left | KeyCode::Char('h') => {
-                        // Switch to branch panel if currently in logs
-                        if app.show_logs {
-                            app.show_logs = false;
-                            if !app.branches.is_empty() {
-                                app.branch_list_state.select(Some(app.selected_branch));
-                            }
-                        }
-                    }
-                    KeyCode::Right | KeyCode::Char('l') => {
-                        // Switch to git graph panel if currently in branches
-                        if !app.show_logs {
-                            app.show_logs = true;
-                            if !app.graph_lines.is_empty() {
-                                app.commit_list_state.select(Some(app.selected_commit));
-                            }
-                        }
-                    }
-                    KeyCode::PageUp => {
-                        if app.scroll_offset > 5 {
-                            app.scroll_offset -= 5;
-                        } else {
-                            app.scroll_offset = 0;
-                        }
-                    }
-                    KeyCode::PageDown => {
-                        app.scroll_offset += 5;
-                    }
-                    KeyCode::Tab => {
-                        app.show_logs = !app.show_logs;
-                        // Ensure the commit list state is properly initialized when switching to logs view
-                        if app.show_logs && !app.graph_lines.is_empty() {
-                            app.commit_list_state.select(Some(app.selected_commit));
-                        }
-                        // Ensure the branch list state is properly initialized when switching to branch view
-                        if !app.show_logs && !app.branches.is_empty() {
-                            app.branch_list_state.select(Some(app.selected_branch));
-                        }
-                    }
-                    KeyCode::Enter => {
-                        if !app.show_logs {
-                            // Branches 패널에서 Enter: 브랜치 선택
-                            app.select_current_branch();
-                        } else {
-                            // Git Graph 패널에서 Enter: diff 보기
-                            app.load_commit_diff();
-                        }
-                    }
-                    KeyCode::Char('c') | KeyCode::Char('C') => {
-                        app.clear_branch_filter();
-                    }
-                    KeyCode::Char('r') | KeyCode::Char('R') => {
-                        let _ = app.refresh_data();
-                    }
-                    _ => {}
+                Some(Action::SearchNext) => app.search_branch(),
+                Some(Action::SearchDeleteChar) => app.search_input.delete_back(),
+                _ => {
+                    app.search_input.handle_key(key.code);
                 }
             }
+            return Ok(false);
+        }
+        
+        if context == Context::Prompt
+            && action.is_none()
+            && let Some(ref mut prompt) = app.prompt
+        {
+            prompt.input.handle_key(key.code);
+            return Ok(false);
+        }
+        
+        // Menus also accept the entry number
+        if context == Context::Menu
+            && action.is_none()
+            && let KeyCode::Char(c) = key.code
+            && let Some(digit) = c.to_digit(10)
+            && digit >= 1
+        {
+            app.choose_menu_entry(digit as usize - 1);
+            return Ok(false);
+        }
+        
+        if let Some(action) = action {
+            return Ok(app.perform_action(action));
         }
     }
     Ok(false)
 }
 
-fn main() -> Result<()> {
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(fatal) => {
+            fatal.report();
+            ExitCode::from(fatal.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<(), Fatal> {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if !e.use_stderr() => {
+            // --help / --version
+            let _ = e.print();
+            return Ok(());
+        }
+        Err(e) => return Err(Fatal::Usage(e.render().to_string())),
+    };
+    
+    // Everything that can fail before the TUI starts is checked first, so
+    // errors are printed on a normal terminal
     
     // Find git repository
-    let repo_path = std::env::current_dir()?;
-    let repo_path = Repository::discover(&repo_path)?;
+    let cwd = match cli.path {
+        Some(ref path) => path.clone(),
+        None => std::env::current_dir().map_err(|e| Fatal::Runtime(e.into()))?,
+    };
+    let repo = Repository::discover(&cwd).map_err(|e| {
+        if e.code() == git2::ErrorCode::NotFound {
+            Fatal::NoRepository { path: cwd.clone() }
+        } else {
+            Fatal::CorruptRepository { path: cwd.clone(), detail: e.message().to_string() }
+        }
+    })?;
+    let repo_path = repo.path().to_path_buf();
+    let settings = Layers::new(&repo_path, &cli.set).load().map_err(Fatal::Config)?;
+    
+    if let Some(ref branch) = cli.branch
+        && repo.revparse_single(branch).is_err()
+    {
+        return Err(Fatal::Usage(format!("error: unknown branch '{}'", branch)));
+    }
     
     // Create app
-    let mut app = App::new(repo_path.path())?;
+    let mut app = App::new(&repo_path, settings, &cli).map_err(|e| Fatal::CorruptRepository {
+        path: repo_path.clone(),
+        detail: format!("{:#}", e),
+    })?;
+    
+    if let Some(ref path) = cli.export_json {
+        let json = app.export_json().map_err(|e| Fatal::Runtime(e.into()))?;
+        app.save_caches();
+        let written = if path.as_os_str() == "-" {
+            writeln!(io::stdout(), "{}", json)
+        } else {
+            std::fs::write(path, json + "\n")
+        };
+        return match written {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            result => result.map_err(|e| Fatal::Runtime(anyhow::anyhow!("{}: {}", path.display(), e))),
+        };
+    }
+    
+    if let Some(ref path) = cli.export_dot {
+        let text = app.export_dot();
+        app.save_caches();
+        if path.as_os_str() == "-" {
+            return match write!(io::stdout(), "{}", text) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+                result => result.map_err(|e| Fatal::Runtime(e.into())),
+            };
+        }
+        let svg = path.extension().is_some_and(|ext| ext == "svg");
+        let dot_path = if svg { path.with_extension("dot") } else { path.clone() };
+        std::fs::write(&dot_path, text).map_err(|e| Fatal::Runtime(anyhow::anyhow!("{}: {}", dot_path.display(), e)))?;
+        if svg {
+            let output = dot::render_svg(&dot_path, path)
+                .output()
+                .map_err(|e| Fatal::Runtime(anyhow::anyhow!("Graphviz dot could not run to render {}: {}", path.display(), e)))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(Fatal::Runtime(anyhow::anyhow!("dot: {}", process::failure(output.status, &stderr))));
+            }
+        }
+        return Ok(());
+    }
+    
+    if cli.print {
+        let printed = print_graph(&mut app, &cli);
+        app.save_caches();
+        return match printed {
+            // The reader (`| head`) went away; not an error for a printer
+            Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe) => Ok(()),
+            result => result.map_err(Fatal::Runtime),
+        };
+    }
+    
+    app.offer_interrupted_operation();
+    
+    // Setup terminal
+    let mut terminal = setup_terminal().map_err(|e| {
+        let _ = disable_raw_mode();
+        Fatal::TerminalInit(e.to_string())
+    })?;
     
     // Main loop
-    let result = run_app(&mut terminal, &mut app);
+    let result = run_app(&mut terminal, &mut app, &cli);
+    app.save_caches();
     
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    restore_terminal().map_err(|e| Fatal::Runtime(e.into()))?;
+    terminal.show_cursor().map_err(|e| Fatal::Runtime(e.into()))?;
+    
+    // Written even when the session ended with an error
+    if let (Some(path), Some(audit)) = (cli.audit_report.as_deref(), app.audit.as_ref()) {
+        audit.write_report(path)
+            .map_err(|e| Fatal::Runtime(anyhow::anyhow!("writing audit report {}: {}", path.display(), e)))?;
+    }
     
-    if let Err(err) = result {
-        println!("Error: {:?}", err);
+    result.map_err(Fatal::Runtime)
+}
+
+/// Writes the graph as the graph panel shows it, for scripts and CI logs
+fn print_graph(app: &mut App, cli: &Cli) -> Result<()> {
+    let color = cli.color.enabled();
+    let mut out = io::stdout().lock();
+    
+    for line in &app.graph_lines {
+        write_line(&mut out, &app.render_graph_line(line), color)?;
+    }
+    
+    if cli.stats {
+        let (domains, bots) = app.author_domain_stats()?;
+        let total = domains.iter().map(|(_, n)| n).sum::<usize>() + bots;
+        let percent = |n: usize| n * 100 / total.max(1);
+        
+        writeln!(out)?;
+        writeln!(out, "{} commits by author domain:", total)?;
+        for (domain, count) in &domains {
+            writeln!(out, "  {:<30} {:>6}  {:>3}%", domain, count, percent(*count))?;
+        }
+        if bots > 0 {
+            writeln!(out, "  {:<30} {:>6}  {:>3}%", "bots", bots, percent(bots))?;
+        }
     }
     
+    out.flush()?;
     Ok(())
 }
 
+/// Writes a styled line, translating colors and bold/italic to ANSI escapes
+fn write_line(out: &mut impl Write, line: &Line, color: bool) -> io::Result<()> {
+    // ratatui's own crossterm, whose colors convert from ratatui's
+    use ratatui::crossterm::{
+        queue,
+        style::{Attribute, Print, SetAttribute, SetForegroundColor},
+    };
+    
+    for span in &line.spans {
+        if !color || span.style == Style::default() {
+            write!(out, "{}", span.content)?;
+            continue;
+        }
+        if let Some(fg) = span.style.fg {
+            queue!(out, SetForegroundColor(fg.into()))?;
+        }
+        if span.style.add_modifier.contains(Modifier::BOLD) {
+            queue!(out, SetAttribute(Attribute::Bold))?;
+        }
+        if span.style.add_modifier.contains(Modifier::ITALIC) {
+            queue!(out, SetAttribute(Attribute::Italic))?;
+        }
+        queue!(out, Print(&span.content), SetAttribute(Attribute::Reset))?;
+    }
+    writeln!(out)
+}
+
+fn setup_terminal() -> io::Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    window::push_title()?;
+    let backend = CrosstermBackend::new(stdout);
+    Terminal::new(backend)
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    window::pop_title()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
+
+/// Runs `cmd` on the terminal itself: the interface is left for it and set
+/// up again, redrawn in full, once it returns
+fn run_suspended<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, cmd: &ExternalCommand) -> Result<Output> {
+    restore_terminal()?;
+    let result = cmd.interactive();
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    window::push_title()?;
+    terminal.clear()?;
+    result
+}
+
+/// Settings for another repository opened during the session (a submodule
+/// or worktree), which may have its own `git-tui.toml`
+fn load_settings(path: &Path, cli: &Cli) -> Result<Settings> {
+    let repo = Repository::discover(path)?;
+    Ok(Layers::new(repo.path(), &cli.set).load()?)
+}
+
 fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
+    cli: &Cli,
 ) -> Result<()> {
+    // Repositories switched away from, see `switch_repo`
+    let mut parked: Vec<App> = Vec::new();
     loop {
+        // A submodule opened from the overlay runs until it is quit, then
+        // control returns here
+        if let Some(path) = app.open_nested.take() {
+            let nested_cli = Cli { path: Some(path.clone()), branch: None, ..cli.clone() };
+            let nested = load_settings(&path, cli).and_then(|settings| App::new(&path, settings, &nested_cli));
+            match nested {
+                Ok(mut nested) => {
+                    let parent = app.nested_label.as_deref().map(|l| format!("{}/", l)).unwrap_or_default();
+                    let workdir = app.repository.workdir().unwrap_or_else(|| app.repository.path());
+                    let relative = path.strip_prefix(workdir).unwrap_or(&path);
+                    nested.nested_label = Some(format!("{}{}", parent, relative.display()));
+                    nested.audit = app.audit.take();
+                    nested.identity = app.identity.clone();
+                    let result = run_app(terminal, &mut nested, cli);
+                    nested.save_caches();
+                    app.audit = nested.audit.take();
+                    result?;
+                    // The nested view set its own title
+                    app.window_title.clear();
+                    let _ = app.refresh_data();
+                    app.open_submodules();
+                }
+                Err(e) => app.show_toast(format!("Cannot open submodule: {:#}", e), true),
+            }
+        }
+        
+        if let Some(suspend) = app.suspend.take() {
+            // A command from the config asks first, while the interface is up
+            if let Some(needed) = suspend.cmd.needs_confirmation() {
+                app.confirm_command(needed, Approved::Suspend(Box::new(suspend)));
+            } else {
+                let Suspend { cmd, then } = suspend;
+                let result = run_suspended(terminal, &cmd);
+                // The title was put back for the command
+                app.window_title.clear();
+                app.resumed(&cmd, then, result);
+                // The terminal was cleared for it
+                app.images_shown = None;
+            }
+        }
+        
+        // Switching worktrees or repositories replaces the whole view. The
+        // one left is parked as it is, so coming back finds the same
+        // selection and filters.
+        if let Some(path) = app.switch_repo.take() {
+            let target = path.canonicalize().ok();
+            let switched = match parked.iter().position(|p| p.workdir_canonical().is_some() && p.workdir_canonical() == target) {
+                Some(index) => {
+                    let mut returning = parked.remove(index);
+                    // Whatever changed while away
+                    returning.refresh_in_place();
+                    returning.window_title.clear();
+                    Ok(returning)
+                }
+                None => {
+                    let switched_cli = Cli { path: Some(path.clone()), branch: None, ..cli.clone() };
+                    load_settings(&path, cli).and_then(|settings| App::new(&path, settings, &switched_cli))
+                }
+            };
+            match switched {
+                Ok(mut switched) => {
+                    app.save_caches();
+                    switched.audit = app.audit.take();
+                    switched.identity = app.identity.take();
+                    parked.push(std::mem::replace(app, switched));
+                    app.show_toast(format!("Browsing {}", home_relative(&path)), false);
+                }
+                Err(e) => app.show_toast(format!("Cannot open {}: {:#}", path.display(), e), true),
+            }
+        }
+        
+        app.poll_config();
+        app.poll_repo_changes();
+        app.follow_diff();
+        app.verify_selected_tags();
+        app.poll_diff_stream();
+        app.count_selected_files();
+        app.poll_tasks();
+        app.prefetch_diffs();
         terminal.draw(|f| draw_ui(f, app))?;
+        app.place_images(terminal)?;
+        app.update_window_title();
+        app.audit_selected_commit();
+        
+        // Deferred so the "loading more" row is on screen while the page loads
+        if app.load_more_pending {
+            app.load_more_commits();
+        }
         
         if handle_events(app)? {
             break;
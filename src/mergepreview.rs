@@ -0,0 +1,36 @@
+use crate::conflicts::{self, Conflict};
+use git2::{Oid, Repository};
+
+/// What merging a commit into HEAD would do, worked out in memory
+pub enum Preview {
+    /// HEAD already contains it
+    UpToDate,
+    /// HEAD would just move forward this many commits
+    FastForward(usize),
+    /// A merge commit without conflicts, changing this many files
+    Clean(usize),
+    /// Files that would conflict, with how
+    Conflicts(Vec<Conflict>),
+}
+
+/// Merges `theirs` into `ours` in memory (`git merge` without touching the
+/// index or working tree)
+pub fn preview(repo: &Repository, ours: Oid, theirs: Oid) -> Result<Preview, git2::Error> {
+    if ours == theirs || repo.graph_descendant_of(ours, theirs)? {
+        return Ok(Preview::UpToDate);
+    }
+    if repo.graph_descendant_of(theirs, ours)? {
+        let (ahead, _) = repo.graph_ahead_behind(theirs, ours)?;
+        return Ok(Preview::FastForward(ahead));
+    }
+
+    let our_commit = repo.find_commit(ours)?;
+    let index = repo.merge_commits(&our_commit, &repo.find_commit(theirs)?, None)?;
+    if index.has_conflicts() {
+        let mut conflicts: Vec<Conflict> = index.conflicts()?.flatten().filter_map(|c| conflicts::describe(&c)).collect();
+        conflicts.sort_by(|a, b| a.path.cmp(&b.path));
+        return Ok(Preview::Conflicts(conflicts));
+    }
+    let diff = repo.diff_tree_to_index(Some(&our_commit.tree()?), Some(&index), None)?;
+    Ok(Preview::Clean(diff.deltas().len()))
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Local};
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Entries kept; older ones are dropped
+const KEEP: usize = 500;
+
+/// Every command git-tui ran this session, plus the heavier git2 calls, for
+/// the operation log panel. Global like the approved command list, so
+/// commands are logged wherever they are run from.
+static LOG: Mutex<Log> = Mutex::new(Log { entries: VecDeque::new(), next_id: 0 });
+
+struct Log {
+    entries: VecDeque<Entry>,
+    next_id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Running,
+    Ok,
+    /// Exit status or error, with the first line of what it said
+    Failed(String),
+    /// Stopped before it finished (a stream that was dropped)
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    id: u64,
+    pub started: DateTime<Local>,
+    /// The command line, or the git2 call and its arguments
+    pub what: String,
+    /// Unset while running
+    pub duration: Option<Duration>,
+    pub outcome: Outcome,
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Log> {
+    LOG.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Logs something that has started; [`finish`] fills in how it went
+pub fn start(what: String) -> u64 {
+    let mut log = lock();
+    let id = log.next_id;
+    log.next_id += 1;
+    log.entries.push_back(Entry { id, started: Local::now(), what, duration: None, outcome: Outcome::Running });
+    if log.entries.len() > KEEP {
+        log.entries.pop_front();
+    }
+    id
+}
+
+pub fn finish(id: u64, duration: Duration, outcome: Outcome) {
+    if let Some(entry) = lock().entries.iter_mut().rev().find(|e| e.id == id) {
+        entry.duration = Some(duration);
+        entry.outcome = outcome;
+    }
+}
+
+/// Logs something that already finished, `started` being when it began
+pub fn record(what: String, started: Instant, outcome: Outcome) {
+    let id = start(what);
+    finish(id, started.elapsed(), outcome);
+}
+
+/// Runs and logs a git2 call (or a few, under one name)
+pub fn timed<T, E: fmt::Display>(what: impl Into<String>, call: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    let started = Instant::now();
+    let result = call();
+    let outcome = match result {
+        Ok(_) => Outcome::Ok,
+        Err(ref e) => Outcome::Failed(first_line(&e.to_string())),
+    };
+    record(what.into(), started, outcome);
+    result
+}
+
+/// The newest `count` entries, oldest first
+pub fn latest(count: usize) -> Vec<Entry> {
+    let log = lock();
+    log.entries.iter().skip(log.entries.len().saturating_sub(count)).cloned().collect()
+}
+
+pub fn first_line(text: &str) -> String {
+    text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default().to_string()
+}
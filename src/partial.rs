@@ -0,0 +1,52 @@
+use crate::process::ExternalCommand;
+use git2::{FileMode, Oid, Repository};
+
+/// The remote a partial clone (`git clone --filter=blob:none`) gets the
+/// objects it left out from; None for a complete repository
+pub fn promisor_remote(repo: &Repository) -> Option<String> {
+    let config = repo.config().ok()?.snapshot().ok()?;
+    if let Ok(remote) = config.get_string("extensions.partialclone") {
+        return Some(remote);
+    }
+    let remotes = repo.remotes().ok()?;
+    remotes
+        .iter()
+        .flatten()
+        .find(|name| config.get_bool(&format!("remote.{}.promisor", name)).unwrap_or(false))
+        .map(str::to_string)
+}
+
+/// The blobs the diff of a commit against its only parent reads that are
+/// not in the object database. Empty for merges, whose combined diff
+/// `git show` makes, fetching what it lacks itself.
+pub fn missing_blobs(repo: &Repository, oid: Oid) -> Result<Vec<Oid>, git2::Error> {
+    let commit = repo.find_commit(oid)?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        1 => Some(commit.parent(0)?.tree()?),
+        _ => return Ok(Vec::new()),
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    let files = diff.deltas().flat_map(|delta| [delta.old_file(), delta.new_file()]);
+    // Submodule entries name commits of another repository
+    missing(repo, files.filter(|file| file.mode() != FileMode::Commit).map(|file| file.id()))
+}
+
+/// Those of `ids` not in the object database, each once
+pub fn missing(repo: &Repository, ids: impl IntoIterator<Item = Oid>) -> Result<Vec<Oid>, git2::Error> {
+    let odb = repo.odb()?;
+    let mut missing: Vec<Oid> = ids.into_iter().filter(|id| !id.is_zero() && !odb.exists(*id)).collect();
+    missing.sort();
+    missing.dedup();
+    Ok(missing)
+}
+
+/// `git` fetching `ids` from `remote` the way git itself fetches missing
+/// objects of a partial clone: by id, without refs or negotiation
+pub fn fetch(git: ExternalCommand, remote: &str, ids: &[Oid]) -> ExternalCommand {
+    let input: String = ids.iter().map(|id| format!("{}\n", id)).collect();
+    git.args(["-c", "fetch.negotiationAlgorithm=noop", "fetch", "--progress", "--no-tags", "--no-write-fetch-head"])
+        .args(["--recurse-submodules=no", "--filter=blob:none", "--stdin"])
+        .arg(remote)
+        .input(input)
+}
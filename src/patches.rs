@@ -0,0 +1,103 @@
+use git2::{Commit, Email, EmailCreateOptions, Oid, Repository};
+use std::path::{Path, PathBuf};
+
+/// Commits `git format-patch` would write for those reachable from `tips`
+/// but not from `hidden` (`<base>..<head>` hides the base), oldest first.
+/// Merges are left out, as they have no single patch.
+pub fn series(repo: &Repository, tips: &[Oid], hidden: &[Oid]) -> Result<Vec<Oid>, git2::Error> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+    for tip in tips {
+        walk.push(*tip)?;
+    }
+    for base in hidden {
+        walk.hide(*base)?;
+    }
+    let mut ids = Vec::new();
+    for id in walk {
+        let id = id?;
+        if repo.find_commit(id)?.parent_count() <= 1 {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// The commit as an mbox-format email, `[PATCH n/count]` when part of a series
+pub fn email(repo: &Repository, commit: &Commit<'_>, number: usize, count: usize) -> Result<Vec<u8>, git2::Error> {
+    let old_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?.tree()?),
+    };
+    let mut options = EmailCreateOptions::new();
+    let diff = repo.diff_tree_to_tree(old_tree.as_ref(), Some(&commit.tree()?), Some(options.diff_options()))?;
+    let email = Email::from_diff(
+        &diff,
+        number,
+        count,
+        &commit.id(),
+        commit.summary().unwrap_or(""),
+        commit.body().unwrap_or(""),
+        &commit.author(),
+        &mut options,
+    )?;
+    Ok(email.as_slice().to_vec())
+}
+
+/// Writes one `NNNN-subject.patch` file per commit into `dir`, creating it
+pub fn write_series(repo: &Repository, ids: &[Oid], dir: &Path) -> Result<Vec<PathBuf>, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut written = Vec::new();
+    for (index, id) in ids.iter().enumerate() {
+        let commit = repo.find_commit(*id).map_err(|e| e.message().to_string())?;
+        let text = email(repo, &commit, index + 1, ids.len()).map_err(|e| e.message().to_string())?;
+        let path = dir.join(file_name(index + 1, commit.summary().unwrap_or("")));
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// The patch files at `path`: the file itself, or the `.patch` files of a
+/// directory in name order (so an exported series applies in sequence)
+pub fn patch_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return match path.exists() {
+            true => Ok(vec![path.to_path_buf()]),
+            false => Err(format!("{}: no such file", path.display())),
+        };
+    }
+    let entries = std::fs::read_dir(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "patch") && p.is_file())
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        return Err(format!("{}: no .patch files", path.display()));
+    }
+    Ok(files)
+}
+
+/// True for a mailbox (`git format-patch` output, starting with a `From `
+/// line), which `git am` can commit; anything else is a plain diff
+pub fn is_mailbox(path: &Path) -> bool {
+    std::fs::read(path).is_ok_and(|data| data.starts_with(b"From "))
+}
+
+/// `0001-fix-the-thing.patch`: the subject with runs of anything but
+/// letters, digits, `.` and `_` turned into `-`, cut at 64 characters, as
+/// `git format-patch` names them
+pub fn file_name(number: usize, subject: &str) -> String {
+    let mut name = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name: String = name.trim_end_matches(['-', '.']).chars().take(64).collect();
+    format!("{:04}-{}.patch", number, name.trim_end_matches(['-', '.']))
+}
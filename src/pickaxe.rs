@@ -0,0 +1,73 @@
+use crate::process::ExternalCommand;
+use git2::{ObjectType, Oid};
+use regex::Regex;
+use std::ops::Range;
+
+/// Search for commits whose diff adds or removes some text, with git's
+/// pickaxe options
+#[derive(Debug, Clone)]
+pub struct Pickaxe {
+    pub pattern: String,
+    /// `-G` (a regex matching added or removed lines) rather than `-S` (a
+    /// string whose number of occurrences changed)
+    pub is_regex: bool,
+    /// Finds the hits in diff lines
+    matcher: Regex,
+}
+
+impl Pickaxe {
+    /// `/regex/` searches with `-G`, anything else is a literal string for `-S`
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let regex = input
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+            .filter(|pattern| !pattern.is_empty());
+        let (pattern, is_regex) = match regex {
+            Some(pattern) => (pattern, true),
+            None => (input, false),
+        };
+        let matcher = if is_regex {
+            Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?
+        } else {
+            Regex::new(&regex::escape(pattern)).map_err(|e| e.to_string())?
+        };
+        Ok(Pickaxe { pattern: pattern.to_string(), is_regex, matcher })
+    }
+
+    pub fn log_arg(&self) -> String {
+        format!("-{}{}", self.option(), self.pattern)
+    }
+
+    /// For the graph title, as it would be passed to `git log`
+    pub fn label(&self) -> String {
+        format!("-{} {}", self.option(), self.pattern)
+    }
+
+    fn option(&self) -> char {
+        if self.is_regex { 'G' } else { 'S' }
+    }
+
+    /// A command failing with git's own message when it cannot compile the
+    /// `-G` pattern, which it reads as a POSIX extended regex: `git diff
+    /// -G` between two empty trees, which compiles it and compares nothing.
+    /// None for an `-S` string.
+    pub fn check(&self, git: ExternalCommand) -> Option<ExternalCommand> {
+        let empty = Oid::hash_object(ObjectType::Tree, &[]).ok()?.to_string();
+        self.is_regex.then(|| git.args(["diff", "--quiet"]).arg(self.log_arg()).rev(&empty).rev(&empty))
+    }
+
+    /// Byte ranges of the hits in an added or removed diff line
+    pub fn hits(&self, line: &str) -> Vec<Range<usize>> {
+        let is_change = (line.starts_with('+') && !line.starts_with("+++"))
+            || (line.starts_with('-') && !line.starts_with("---"));
+        if !is_change {
+            return Vec::new();
+        }
+        // Offset by the +/- marker, which is not part of the content
+        self.matcher
+            .find_iter(&line[1..])
+            .filter(|m| !m.is_empty())
+            .map(|m| m.start() + 1..m.end() + 1)
+            .collect()
+    }
+}
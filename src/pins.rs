@@ -0,0 +1,51 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Commits pinned with `m`, oldest first. Kept with the objects they name,
+/// so every worktree of the repository shares them.
+const FILE_NAME: &str = "git-tui-pins";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pin {
+    /// Full commit id
+    pub id: String,
+    /// Subject when pinned, to list the pin without reading the commit
+    pub summary: String,
+    pub pinned: DateTime<Local>,
+}
+
+fn path(common_dir: &Path) -> PathBuf {
+    common_dir.join(FILE_NAME)
+}
+
+/// The pins saved for the repository; none when the file is missing or
+/// unreadable
+pub fn load(common_dir: &Path) -> Vec<Pin> {
+    std::fs::read_to_string(path(common_dir))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Pins `id`, or unpins it when it already is. Read again first so pins
+/// another session made meanwhile are kept. Returns the pins now saved.
+pub fn toggle(common_dir: &Path, id: &str, summary: &str) -> io::Result<Vec<Pin>> {
+    let mut pins = load(common_dir);
+    match pins.iter().position(|pin| pin.id == id) {
+        Some(index) => {
+            pins.remove(index);
+        }
+        None => pins.push(Pin {
+            id: id.to_string(),
+            summary: summary.to_string(),
+            pinned: Local::now(),
+        }),
+    }
+    let json = serde_json::to_string_pretty(&pins).map_err(io::Error::other)?;
+    std::fs::write(path(common_dir), json + "\n")?;
+    Ok(pins)
+}
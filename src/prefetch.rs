@@ -0,0 +1,61 @@
+use crate::commitdiff;
+use git2::{Oid, Repository};
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+};
+
+/// Makes the diffs of commits around the graph selection on a worker thread,
+/// so they are in the diff cache by the time one is opened. The worker has
+/// its own handle on the repository; it stops when this is dropped.
+pub struct Prefetcher {
+    requests: Sender<Vec<Oid>>,
+    diffs: Receiver<(Oid, String)>,
+}
+
+impl Prefetcher {
+    pub fn start(git_dir: PathBuf) -> Prefetcher {
+        let (requests, wanted) = channel::<Vec<Oid>>();
+        let (done, diffs) = channel();
+        std::thread::spawn(move || {
+            let Ok(repo) = Repository::open(&git_dir) else {
+                return;
+            };
+            let mailmap = repo.mailmap().ok();
+            while let Ok(mut ids) = wanted.recv() {
+                let mut next = 0;
+                while next < ids.len() {
+                    // The selection moved on: what it wants now comes first
+                    match wanted.try_recv() {
+                        Ok(newer) => {
+                            ids = newer;
+                            next = 0;
+                            continue;
+                        }
+                        Err(TryRecvError::Disconnected) => return,
+                        Err(TryRecvError::Empty) => {}
+                    }
+                    let oid = ids[next];
+                    next += 1;
+                    // Merges are left to `git show` when opened
+                    if let Some(Ok(text)) = commitdiff::render(&repo, oid, mailmap.as_ref())
+                        && done.send((oid, text)).is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+        });
+        Prefetcher { requests, diffs }
+    }
+
+    /// Replaces what is still to be made with `ids`, nearest first
+    pub fn request(&self, ids: Vec<Oid>) {
+        let _ = self.requests.send(ids);
+    }
+
+    /// Diffs made since the last call, by full commit id
+    pub fn finished(&self) -> Vec<(String, String)> {
+        self.diffs.try_iter().map(|(oid, text)| (oid.to_string(), text)).collect()
+    }
+}
@@ -0,0 +1,476 @@
+use crate::ansi;
+use crate::oplog::{self, Outcome};
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashSet,
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::{
+        mpsc::{channel, Receiver},
+        Mutex,
+    },
+    time::Instant,
+};
+
+/// Variables through which a parent git process (a hook, an alias, `git -C`)
+/// would point a child git at its own repository. They are removed so git
+/// resolves the repository, and with it `includeIf "gitdir:"` sections and
+/// `config.worktree`, from the working directory git-tui runs it in.
+const REPO_LOCATION_ENV: [&str; 11] = [
+    "GIT_DIR",
+    "GIT_WORK_TREE",
+    "GIT_COMMON_DIR",
+    "GIT_INDEX_FILE",
+    "GIT_OBJECT_DIRECTORY",
+    "GIT_ALTERNATE_OBJECT_DIRECTORIES",
+    "GIT_IMPLICIT_WORK_TREE",
+    "GIT_PREFIX",
+    "GIT_NAMESPACE",
+    "GIT_SHALLOW_FILE",
+    "GIT_GRAFT_FILE",
+];
+
+/// Command lines of user-configured commands the user has already confirmed
+static APPROVED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Returned when a user-configured command has not been confirmed yet.
+/// Callers downcast to this to show the command line and ask before running it.
+#[derive(Debug)]
+pub struct NeedsConfirmation {
+    pub command_line: String,
+}
+
+impl fmt::Display for NeedsConfirmation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "command needs confirmation before first run: {}", self.command_line)
+    }
+}
+
+impl std::error::Error for NeedsConfirmation {}
+
+/// An external process invocation. Arguments are passed straight to the
+/// program (never through a shell) and validated as they are added; the first
+/// invalid argument is reported when the command is run.
+#[derive(Debug, Clone)]
+pub struct ExternalCommand {
+    program: String,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    envs: Vec<(String, String)>,
+    /// Written to the command's stdin; kept out of the logged command line
+    input: Option<Vec<u8>>,
+    user_configured: bool,
+    /// How many trailing arguments are left out of the approved command line
+    script_args: usize,
+    invalid: Option<String>,
+}
+
+impl ExternalCommand {
+    pub fn new(program: &str) -> Self {
+        let mut command = ExternalCommand {
+            program: program.to_string(),
+            args: Vec::new(),
+            cwd: None,
+            envs: Vec::new(),
+            input: None,
+            user_configured: false,
+            script_args: 0,
+            invalid: None,
+        };
+        if let Err(e) = validate_arg(program) {
+            command.invalid = Some(e);
+        }
+        command
+    }
+
+    pub fn git() -> Self {
+        Self::new("git")
+    }
+
+    /// Marks the command as coming from user configuration: it will refuse to
+    /// run until its exact command line, but for any [`Self::script_args`],
+    /// has been approved with [`approve`].
+    pub fn user_configured(mut self) -> Self {
+        self.user_configured = true;
+        self
+    }
+
+    /// Adds a literal argument (an option or a value)
+    pub fn arg(mut self, arg: impl AsRef<str>) -> Self {
+        let arg = arg.as_ref();
+        if self.invalid.is_none()
+            && let Err(e) = validate_arg(arg)
+        {
+            self.invalid = Some(e);
+        }
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for arg in args {
+            self = self.arg(arg);
+        }
+        self
+    }
+
+    /// Adds a revision or ref name coming from repository data or user input.
+    /// These must never be interpretable as options, so a leading `-` is
+    /// rejected (`^` exclusions are fine).
+    pub fn rev(mut self, rev: impl AsRef<str>) -> Self {
+        let rev = rev.as_ref();
+        if self.invalid.is_none() && rev.starts_with('-') {
+            self.invalid = Some(format!("revision looks like an option: {:?}", rev));
+        }
+        self.arg(rev)
+    }
+
+    /// Ends options and revisions with `--` and adds pathspecs, which then
+    /// can never be taken for either
+    pub fn paths<I, S>(self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.arg("--").args(paths)
+    }
+
+    /// Sets an environment variable for the command
+    pub fn env(mut self, key: &str, value: impl AsRef<str>) -> Self {
+        let value = value.as_ref();
+        if self.invalid.is_none()
+            && let Err(e) = validate_arg(value)
+        {
+            self.invalid = Some(e);
+        }
+        self.envs.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Feeds `input` to the command's stdin, for secrets that must not show
+    /// up in the process list or the command log
+    pub fn input(mut self, input: impl Into<Vec<u8>>) -> Self {
+        self.input = Some(input.into());
+        self
+    }
+
+    /// Adds the positional arguments of a user-configured script (`$1`,
+    /// `$2` of `sh -c`), which may differ at every run: approving the
+    /// script approves it with any of them
+    pub fn script_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for arg in args {
+            self = self.arg(arg);
+            self.script_args += 1;
+        }
+        self
+    }
+
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cwd = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// The command line as it would be typed, with arguments quoted as needed
+    pub fn display(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(self.args.iter())
+            .map(|part| quote(part))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn output(&self) -> Result<Output> {
+        let started = Instant::now();
+        let result = self.run();
+        let outcome = match result {
+            Ok(ref output) if output.status.success() => Outcome::Ok,
+            Ok(ref output) => Outcome::Failed(failure(output.status, &String::from_utf8_lossy(&output.stderr))),
+            Err(ref e) => Outcome::Failed(e.to_string()),
+        };
+        oplog::record(self.display(), started, outcome);
+        result
+    }
+
+    fn run(&self) -> Result<Output> {
+        let Some(ref input) = self.input else {
+            return Ok(self.build()?.output()?);
+        };
+        let mut child = self
+            .build()?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // From a thread, as the command may fill stdout before reading all of
+        // its input; dropped after writing, so it sees the end of it
+        if let Some(mut stdin) = child.stdin.take() {
+            let input = input.clone();
+            std::thread::spawn(move || stdin.write_all(&input));
+        }
+        Ok(child.wait_with_output()?)
+    }
+
+    /// Starts the command and delivers its stdout line by line as it is produced
+    pub fn stream(&self) -> Result<LineStream> {
+        let (mut child, log_id) = self.spawn()?;
+        let started = Instant::now();
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("no stdout pipe"))?;
+        let stderr = child.stderr.take().ok_or_else(|| anyhow!("no stderr pipe"))?;
+
+        let (tx, events) = channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            let mut buf = Vec::new();
+            loop {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        let line = String::from_utf8_lossy(&buf);
+                        let line = ansi::strip(line.trim_end_matches(['\n', '\r'])).into_owned();
+                        if tx.send(StreamEvent::Line(line)).is_err() {
+                            // Receiver dropped: the stream was cancelled
+                            return;
+                        }
+                    }
+                }
+            }
+            let mut errors = String::new();
+            let _ = BufReader::new(stderr).read_to_string(&mut errors);
+            let _ = tx.send(StreamEvent::Done(errors));
+        });
+
+        Ok(LineStream { child, events, finished: None, log_id, started })
+    }
+
+    /// Starts the command with stdout and stderr piped, for callers that read
+    /// them while it runs. Also returns its operation log entry, which the
+    /// caller finishes once it exits.
+    pub fn spawn(&self) -> Result<(Child, u64)> {
+        let started = Instant::now();
+        let spawned = self.build().and_then(|mut command| {
+            if self.input.is_some() {
+                command.stdin(Stdio::piped());
+            }
+            let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+            // From a thread, as the command may fill stdout before reading all of it
+            if let (Some(input), Some(mut stdin)) = (self.input.clone(), child.stdin.take()) {
+                std::thread::spawn(move || stdin.write_all(&input));
+            }
+            Ok(child)
+        });
+        match spawned {
+            Ok(child) => Ok((child, oplog::start(self.display()))),
+            Err(e) => {
+                oplog::record(self.display(), started, Outcome::Failed(e.to_string()));
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs the command on the terminal, for editors and commands starting
+    /// one. Only stderr is captured, to report a failure.
+    pub fn interactive(&self) -> Result<Output> {
+        let started = Instant::now();
+        let result = self.build().and_then(|mut command| {
+            let child = command.stdin(Stdio::inherit()).stdout(Stdio::inherit()).stderr(Stdio::piped()).spawn()?;
+            Ok(child.wait_with_output()?)
+        });
+        let outcome = match result {
+            Ok(ref output) if output.status.success() => Outcome::Ok,
+            Ok(ref output) => Outcome::Failed(failure(output.status, &String::from_utf8_lossy(&output.stderr))),
+            Err(ref e) => Outcome::Failed(e.to_string()),
+        };
+        oplog::record(self.display(), started, outcome);
+        result
+    }
+
+    /// Runs the command with stdout/stderr discarded
+    pub fn status(&self) -> Result<ExitStatus> {
+        let started = Instant::now();
+        let result = self
+            .build()
+            .and_then(|mut command| Ok(command.stdout(Stdio::null()).stderr(Stdio::null()).status()?));
+        let outcome = match result {
+            Ok(status) if status.success() => Outcome::Ok,
+            Ok(status) => Outcome::Failed(failure(status, "")),
+            Err(ref e) => Outcome::Failed(e.to_string()),
+        };
+        oplog::record(self.display(), started, outcome);
+        result
+    }
+
+    /// What the user has to approve before this command may run: a
+    /// user-configured command line not confirmed yet
+    pub fn needs_confirmation(&self) -> Option<NeedsConfirmation> {
+        let approved = &self.args[..self.args.len() - self.script_args];
+        let command_line = std::iter::once(&self.program)
+            .chain(approved)
+            .map(|part| quote(part))
+            .collect::<Vec<_>>()
+            .join(" ");
+        (self.user_configured && !is_approved(&command_line)).then_some(NeedsConfirmation { command_line })
+    }
+
+    fn build(&self) -> Result<Command> {
+        if let Some(ref reason) = self.invalid {
+            return Err(anyhow!("Refusing to run `{}`: {}", self.display(), reason));
+        }
+
+        if let Some(needed) = self.needs_confirmation() {
+            return Err(needed.into());
+        }
+
+        let command_line = self.display();
+        log_command(&command_line, self.cwd.as_deref());
+
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).stdin(Stdio::null());
+        if let Some(ref cwd) = self.cwd {
+            command.current_dir(cwd);
+            if self.program == "git" {
+                for name in REPO_LOCATION_ENV {
+                    command.env_remove(name);
+                }
+            }
+        }
+        command.envs(self.envs.iter().map(|(k, v)| (k, v)));
+        Ok(command)
+    }
+}
+
+enum StreamEvent {
+    Line(String),
+    /// Output ended; carries whatever was written to stderr
+    Done(String),
+}
+
+/// Output of a running command, see [`ExternalCommand::stream`]. Dropping it
+/// kills the command if it is still running.
+pub struct LineStream {
+    child: Child,
+    events: Receiver<StreamEvent>,
+    finished: Option<std::result::Result<(), String>>,
+    /// Its entry in the operation log, completed when it finishes
+    log_id: u64,
+    started: Instant,
+}
+
+impl LineStream {
+    /// Lines produced since the last call, at most `max` so a burst of output
+    /// cannot stall the caller
+    pub fn drain(&mut self, max: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        while lines.len() < max {
+            match self.events.try_recv() {
+                Ok(StreamEvent::Line(line)) => lines.push(line),
+                Ok(StreamEvent::Done(errors)) => {
+                    let status = self.child.wait();
+                    let outcome = match status {
+                        Ok(status) if status.success() => Outcome::Ok,
+                        Ok(status) => Outcome::Failed(failure(status, &errors)),
+                        Err(ref e) => Outcome::Failed(e.to_string()),
+                    };
+                    oplog::finish(self.log_id, self.started.elapsed(), outcome);
+                    let success = status.is_ok_and(|s| s.success());
+                    self.finished = Some(if success { Ok(()) } else { Err(errors.trim().to_string()) });
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        lines
+    }
+
+    /// `None` while running, then whether the command succeeded (with its stderr if not)
+    pub fn finished(&self) -> Option<&std::result::Result<(), String>> {
+        self.finished.as_ref()
+    }
+}
+
+impl Drop for LineStream {
+    fn drop(&mut self) {
+        if self.finished.is_none() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+            oplog::finish(self.log_id, self.started.elapsed(), Outcome::Cancelled);
+        }
+    }
+}
+
+/// Records that the user confirmed running this exact command line
+pub fn approve(command_line: &str) {
+    let mut approved = APPROVED.lock().unwrap_or_else(|e| e.into_inner());
+    approved
+        .get_or_insert_with(HashSet::new)
+        .insert(command_line.to_string());
+}
+
+fn is_approved(command_line: &str) -> bool {
+    let approved = APPROVED.lock().unwrap_or_else(|e| e.into_inner());
+    approved
+        .as_ref()
+        .is_some_and(|set| set.contains(command_line))
+}
+
+fn validate_arg(arg: &str) -> std::result::Result<(), String> {
+    if arg.contains('\0') {
+        return Err(format!("argument contains a NUL byte: {:?}", arg));
+    }
+    if arg.chars().any(|c| c.is_control() && c != '\t') {
+        return Err(format!("argument contains control characters: {:?}", arg));
+    }
+    Ok(())
+}
+
+fn quote(part: &str) -> String {
+    let is_plain = !part.is_empty()
+        && part
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=^@%+,".contains(c));
+    if is_plain {
+        part.to_string()
+    } else {
+        format!("'{}'", part.replace('\'', r"'\''"))
+    }
+}
+
+/// Exit status and the first line of stderr, for the operation log
+pub fn failure(status: ExitStatus, stderr: &str) -> String {
+    let status = match status.code() {
+        Some(code) => format!("exit {}", code),
+        None => "killed".to_string(),
+    };
+    match oplog::first_line(stderr) {
+        line if line.is_empty() => status,
+        line => format!("{}: {}", status, line),
+    }
+}
+
+/// Appends the exact command line to the file named by `GIT_TUI_LOG`, if set.
+/// The TUI owns the terminal, so there is nowhere else to print it.
+fn log_command(command_line: &str, cwd: Option<&Path>) {
+    let Some(path) = std::env::var_os("GIT_TUI_LOG") else {
+        return;
+    };
+    if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open(path) {
+        let cwd = cwd.map(|p| p.display().to_string()).unwrap_or_default();
+        let _ = writeln!(
+            file,
+            "{} [{}] {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            cwd,
+            command_line
+        );
+    }
+}
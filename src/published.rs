@@ -0,0 +1,72 @@
+use git2::{BranchType, Oid, ReferenceType, Repository};
+use std::collections::HashSet;
+
+/// What rewriting the commits after `base` up to `head` would touch that
+/// others may already have
+#[derive(Debug, Default)]
+pub struct RewriteCheck {
+    /// Commits that would be replaced or dropped
+    pub rewritten: usize,
+    /// How many of them some remote-tracking branch contains
+    pub published: usize,
+    /// Remote-tracking branches containing any, with how many, most first
+    pub branches: Vec<(String, usize)>,
+}
+
+impl RewriteCheck {
+    /// Warning for the confirmation prompt, if anything was pushed
+    pub fn warning(&self) -> Option<String> {
+        let (first, _) = self.branches.first()?;
+        let verb = if self.published == 1 { "is" } else { "are" };
+        let mut message = match self.rewritten {
+            1 => format!("This commit is already on {}", first),
+            rewritten if rewritten == self.published => format!("These {} commits are already on {}", rewritten, first),
+            rewritten => format!("{} of these {} commits {} already on {}", self.published, rewritten, verb, first),
+        };
+        let others: Vec<&str> = self.branches[1..].iter().map(|(name, _)| name.as_str()).collect();
+        if !others.is_empty() {
+            message.push_str(&format!(" (also {})", others.join(", ")));
+        }
+        let them = if self.published == 1 { "it" } else { "them" };
+        message.push_str(&format!(".\nRewriting {} changes history others may have pulled.", them));
+        Some(message)
+    }
+}
+
+/// Checks `base..head`: reachable from `head` but not from `base`
+pub fn check(repo: &Repository, base: Oid, head: Oid) -> Result<RewriteCheck, git2::Error> {
+    let range = |tip: Oid| -> Result<Vec<Oid>, git2::Error> {
+        let mut walk = repo.revwalk()?;
+        walk.push(tip)?;
+        walk.hide(base)?;
+        walk.collect()
+    };
+    let rewritten: HashSet<Oid> = range(head)?.into_iter().collect();
+    if rewritten.is_empty() {
+        return Ok(RewriteCheck::default());
+    }
+
+    let mut published = HashSet::new();
+    let mut branches = Vec::new();
+    for (branch, _) in repo.branches(Some(BranchType::Remote))?.flatten() {
+        // Skip `origin/HEAD`, which only points at another remote branch
+        if branch.get().kind() == Some(ReferenceType::Symbolic) {
+            continue;
+        }
+        let (Some(tip), Ok(Some(name))) = (branch.get().target(), branch.name()) else {
+            continue;
+        };
+        let contained: Vec<Oid> = range(tip)?.into_iter().filter(|id| rewritten.contains(id)).collect();
+        if !contained.is_empty() {
+            branches.push((name.to_string(), contained.len()));
+            published.extend(contained);
+        }
+    }
+    branches.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(RewriteCheck {
+        rewritten: rewritten.len(),
+        published: published.len(),
+        branches,
+    })
+}
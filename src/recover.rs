@@ -0,0 +1,75 @@
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{BranchType, Oid, Repository};
+use std::{cmp::Reverse, collections::HashSet};
+
+/// A commit a branch could be recreated at
+pub struct Candidate {
+    pub id: Oid,
+    /// The deleted branch it was the tip of; none for a dangling commit
+    pub branch: Option<String>,
+    pub summary: String,
+    /// Commit date
+    pub time: DateTime<Utc>,
+}
+
+/// Branches left at some point (`checkout: moving from X to Y` in the HEAD
+/// reflog) that no longer exist, each at the commit it was on when last
+/// left, most recently left first. A renamed branch is not listed, as its
+/// tip is still another branch's.
+pub fn deleted_branches(repo: &Repository) -> Result<Vec<Candidate>, git2::Error> {
+    let tips: HashSet<Oid> = repo
+        .branches(Some(BranchType::Local))?
+        .flatten()
+        .filter_map(|(branch, _)| branch.get().target())
+        .collect();
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for entry in repo.reflog("HEAD")?.iter() {
+        let Some((name, _)) = entry
+            .message()
+            .and_then(|message| message.strip_prefix("checkout: moving from "))
+            .and_then(|rest| rest.split_once(" to "))
+        else {
+            continue;
+        };
+        // Left detached, or already listed with a later tip
+        if is_object_id(name) || !seen.insert(name.to_string()) {
+            continue;
+        }
+        if repo.find_branch(name, BranchType::Local).is_ok() || tips.contains(&entry.id_old()) {
+            continue;
+        }
+        if let Some(candidate) = candidate(repo, entry.id_old(), Some(name.to_string())) {
+            found.push(candidate);
+        }
+    }
+    Ok(found)
+}
+
+/// The commits named by `dangling commit <id>` lines of `git fsck`: ones
+/// nothing refers to, not even a reflog. Newest first.
+pub fn dangling_commits(repo: &Repository, fsck_output: &str) -> Vec<Candidate> {
+    let mut found: Vec<Candidate> = fsck_output
+        .lines()
+        .filter_map(|line| line.strip_prefix("dangling commit "))
+        .filter_map(|id| Oid::from_str(id.trim()).ok())
+        .filter_map(|id| candidate(repo, id, None))
+        .collect();
+    found.sort_by_key(|candidate| Reverse(candidate.time));
+    found
+}
+
+fn is_object_id(name: &str) -> bool {
+    name.len() >= 40 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// None when the commit is gone, pruned by `git gc`
+fn candidate(repo: &Repository, id: Oid, branch: Option<String>) -> Option<Candidate> {
+    let commit = repo.find_commit(id).ok()?;
+    Some(Candidate {
+        id,
+        branch,
+        summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default()).into_owned(),
+        time: Utc.timestamp_opt(commit.time().seconds(), 0).single()?,
+    })
+}
@@ -0,0 +1,49 @@
+use std::path::{Path, PathBuf};
+
+/// `~/src/app` as `$HOME/src/app`; other paths as they are
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Whether `dir` is the top of a working tree (`.git` is a directory, or a
+/// file for worktrees and submodules)
+fn is_repository(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+/// Repositories the picker offers: the configured ones that exist, then,
+/// with `discover`, the ones in the directory holding `current`, by name.
+/// Paths are canonical so the same repository is listed once.
+pub fn candidates(current: &Path, configured: &[PathBuf], discover: bool) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = Vec::new();
+    let mut add = |path: PathBuf| {
+        if let Ok(path) = path.canonicalize()
+            && !found.contains(&path)
+        {
+            found.push(path);
+        }
+    };
+    for path in configured {
+        if is_repository(path) {
+            add(path.clone());
+        }
+    }
+    if discover
+        && let Some(parent) = current.canonicalize().ok().as_deref().and_then(Path::parent)
+        && let Ok(entries) = std::fs::read_dir(parent)
+    {
+        let mut siblings: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && is_repository(path))
+            .collect();
+        siblings.sort();
+        for sibling in siblings {
+            add(sibling);
+        }
+    }
+    found
+}
@@ -0,0 +1,55 @@
+use git2::{ErrorCode, Oid, Repository};
+
+/// A revision range as git writes it: `A..B` for the commits in B but not
+/// in A, `A...B` for those in either but not in both. A missing end stands
+/// for HEAD, as in git.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevRange {
+    pub from: String,
+    pub to: String,
+    pub symmetric: bool,
+}
+
+impl RevRange {
+    pub fn parse(text: &str) -> Result<RevRange, String> {
+        let text = text.trim();
+        let (from, to, symmetric) = match (text.split_once("..."), text.split_once("..")) {
+            (Some((from, to)), _) => (from, to, true),
+            (None, Some((from, to))) => (from, to, false),
+            (None, None) => return Err(format!("'{}' is not a range (A..B or A...B)", text)),
+        };
+        let end = |rev: &str| -> Result<String, String> {
+            match rev {
+                "" => Ok("HEAD".to_string()),
+                rev if rev.starts_with('-') || rev.contains(char::is_whitespace) || rev.contains("..") => {
+                    Err(format!("'{}' is not a revision", rev))
+                }
+                rev => Ok(rev.to_string()),
+            }
+        };
+        Ok(RevRange { from: end(from)?, to: end(to)?, symmetric })
+    }
+
+    /// As typed to git
+    pub fn label(&self) -> String {
+        let dots = if self.symmetric { "..." } else { ".." };
+        format!("{}{}{}", self.from, dots, self.to)
+    }
+
+    /// The commits a revision walk of the range starts from, and those it
+    /// stops at: for `A...B` both ends, stopping at their merge bases
+    pub fn ends(&self, repo: &Repository) -> Result<(Vec<Oid>, Vec<Oid>), git2::Error> {
+        let from = repo.revparse_single(&self.from)?.peel_to_commit()?.id();
+        let to = repo.revparse_single(&self.to)?.peel_to_commit()?.id();
+        if !self.symmetric {
+            return Ok((vec![to], vec![from]));
+        }
+        let bases = match repo.merge_bases(from, to) {
+            Ok(bases) => bases.iter().copied().collect(),
+            // Unrelated histories: everything on either side
+            Err(e) if e.code() == ErrorCode::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok((vec![from, to], bases))
+    }
+}
@@ -0,0 +1,58 @@
+use git2::Repository;
+
+/// A worktree's sparse checkout, as git's config, `git sparse-checkout
+/// list` and the index have it
+#[derive(Debug, Clone, Default)]
+pub struct SparseCheckout {
+    /// Off, every file is checked out and there are no patterns
+    pub enabled: bool,
+    /// Patterns are directories, checked out whole along with the files of
+    /// the directories above them
+    pub cone: bool,
+    pub patterns: Vec<String>,
+    /// Files in the index
+    pub files: usize,
+    /// Those of them left out of the working tree (skip-worktree)
+    pub skipped: usize,
+}
+
+impl SparseCheckout {
+    /// Mode and counts, for the view's title
+    pub fn summary(&self) -> String {
+        if !self.enabled {
+            return format!("off, all {} files checked out", self.files);
+        }
+        let mode = if self.cone { "cone mode" } else { "patterns" };
+        format!("{}, {} of {} files checked out", mode, self.files - self.skipped, self.files)
+    }
+}
+
+/// Whether sparse checkout is on, and in cone mode, from `git config
+/// --get-regexp ^core\.sparsecheckout` output. Without
+/// `core.sparseCheckoutCone` git reads the patterns as full patterns.
+pub fn parse_config(output: &str) -> (bool, bool) {
+    let mut enabled = false;
+    let mut cone = false;
+    for line in output.lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, "true"));
+        let value = matches!(value.trim().to_lowercase().as_str(), "true" | "yes" | "on" | "1");
+        match key.to_lowercase().as_str() {
+            "core.sparsecheckout" => enabled = value,
+            "core.sparsecheckoutcone" => cone = value,
+            _ => {}
+        }
+    }
+    (enabled, cone)
+}
+
+/// The patterns `git sparse-checkout list` prints, one per line
+pub fn parse_list(output: &str) -> Vec<String> {
+    output.lines().map(str::trim_end).filter(|line| !line.is_empty()).map(str::to_string).collect()
+}
+
+/// `(files, skipped)`: the index's entries and those not checked out
+pub fn index_counts(repo: &Repository) -> Result<(usize, usize), git2::Error> {
+    let index = repo.index()?;
+    let skipped = index.iter().filter(|entry| entry.flags_extended & git2::IndexEntryExtendedFlag::SKIP_WORKTREE.bits() != 0).count();
+    Ok((index.len(), skipped))
+}
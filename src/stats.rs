@@ -0,0 +1,100 @@
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Utc};
+use std::collections::HashMap;
+
+/// A loaded commit, as far as the statistics need it
+pub struct Commit<'a> {
+    pub id: &'a str,
+    pub author: &'a str,
+    pub time: DateTime<Utc>,
+    pub is_merge: bool,
+}
+
+/// Figures for the dashboard, over the commits loaded in the view
+pub struct Stats {
+    pub commits: usize,
+    /// Commits per author, most first
+    pub authors: Vec<(String, u64)>,
+    /// Monday of the week of the oldest commit, in local time
+    pub first_day: NaiveDate,
+    /// Commits per day from `first_day` to the newest commit's week's Sunday
+    pub days: Vec<u64>,
+    /// Files by the number of commits changing them, most first
+    pub files: Vec<(String, u64)>,
+    /// Commits that are not merges, which the sizes below are taken over
+    pub changes: usize,
+    pub added: u64,
+    pub removed: u64,
+    pub files_changed: u64,
+}
+
+impl Stats {
+    /// Commits per week, oldest first
+    pub fn weeks(&self) -> Vec<u64> {
+        self.days.chunks(7).map(|week| week.iter().sum()).collect()
+    }
+
+    /// `(lines added, lines removed, files)` per commit that is not a merge
+    pub fn average_size(&self) -> Option<(f64, f64, f64)> {
+        let count = self.changes as f64;
+        (self.changes > 0).then(|| (self.added as f64 / count, self.removed as f64 / count, self.files_changed as f64 / count))
+    }
+}
+
+/// Statistics of `commits`, given `git log --numstat --format=%x00%H`
+/// output for them. Merges have no numstat lines and are left out of the
+/// sizes; binary files count as changed without lines.
+pub fn compute(commits: &[Commit], numstat: &str) -> Option<Stats> {
+    let local_day = |time: DateTime<Utc>| time.with_timezone(&Local).date_naive();
+    let oldest = commits.iter().map(|commit| local_day(commit.time)).min()?;
+    let newest = commits.iter().map(|commit| local_day(commit.time)).max()?;
+    let first_day = oldest - Days::new(oldest.weekday().num_days_from_monday() as u64);
+    let last_day = newest + Days::new(6 - newest.weekday().num_days_from_monday() as u64);
+    let mut days = vec![0; (last_day - first_day).num_days() as usize + 1];
+    let mut authors: HashMap<&str, u64> = HashMap::new();
+    for commit in commits {
+        days[(local_day(commit.time) - first_day).num_days() as usize] += 1;
+        *authors.entry(commit.author).or_default() += 1;
+    }
+
+    let merges: HashMap<&str, bool> = commits.iter().map(|commit| (commit.id, commit.is_merge)).collect();
+    let mut files: HashMap<&str, u64> = HashMap::new();
+    let (mut added, mut removed, mut files_changed) = (0, 0, 0);
+    let mut counted = true;
+    for line in numstat.lines() {
+        if let Some(id) = line.strip_prefix('\0') {
+            counted = merges.get(id.trim()).is_some_and(|is_merge| !is_merge);
+            continue;
+        }
+        let mut fields = line.splitn(3, '\t');
+        let (Some(plus), Some(minus), Some(path)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if !counted {
+            continue;
+        }
+        // `-` for binary files
+        added += plus.parse::<u64>().unwrap_or(0);
+        removed += minus.parse::<u64>().unwrap_or(0);
+        files_changed += 1;
+        *files.entry(path).or_default() += 1;
+    }
+
+    Some(Stats {
+        commits: commits.len(),
+        authors: most_first(authors),
+        first_day,
+        days,
+        files: most_first(files),
+        changes: commits.iter().filter(|commit| !commit.is_merge).count(),
+        added,
+        removed,
+        files_changed,
+    })
+}
+
+/// Counts, highest first and by name among equals
+fn most_first(counts: HashMap<&str, u64>) -> Vec<(String, u64)> {
+    let mut sorted: Vec<(String, u64)> = counts.into_iter().map(|(name, count)| (name.to_string(), count)).collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted
+}
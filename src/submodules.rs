@@ -0,0 +1,60 @@
+use git2::{Oid, Repository, SubmoduleIgnore};
+use std::path::PathBuf;
+
+/// A submodule with its checked-out commit compared to the one recorded in HEAD
+#[derive(Debug, Clone)]
+pub struct SubmoduleInfo {
+    /// Relative to the superproject's working directory
+    pub path: PathBuf,
+    /// Commit the superproject's HEAD records (the gitlink)
+    pub recorded: Option<Oid>,
+    /// Commit checked out in the submodule
+    pub checked_out: Option<Oid>,
+    /// Registered in `.git/config` by `git submodule init`
+    pub initialized: bool,
+    /// Uncommitted or untracked changes inside the submodule
+    pub dirty: bool,
+}
+
+impl SubmoduleInfo {
+    /// One-word state shown next to the submodule
+    pub fn state(&self) -> &'static str {
+        if !self.initialized {
+            "uninitialized"
+        } else if self.checked_out.is_none() {
+            "not checked out"
+        } else if self.checked_out != self.recorded {
+            "moved"
+        } else if self.dirty {
+            "dirty"
+        } else {
+            "clean"
+        }
+    }
+
+    /// The submodule can be opened as a repository of its own
+    pub fn is_checked_out(&self) -> bool {
+        self.initialized && self.checked_out.is_some()
+    }
+}
+
+/// Submodules of `repo`, in path order
+pub fn list(repo: &Repository) -> Result<Vec<SubmoduleInfo>, git2::Error> {
+    let config = repo.config()?;
+    let mut submodules = Vec::new();
+    for submodule in repo.submodules()? {
+        let name = submodule.name().unwrap_or_default();
+        let status = repo.submodule_status(name, SubmoduleIgnore::None)?;
+        submodules.push(SubmoduleInfo {
+            path: submodule.path().to_path_buf(),
+            recorded: submodule.head_id(),
+            checked_out: submodule.workdir_id().filter(|_| !status.is_wd_uninitialized()),
+            initialized: config.get_string(&format!("submodule.{}.url", name)).is_ok(),
+            dirty: status.is_wd_wd_modified()
+                || status.is_index_modified()
+                || status.is_wd_untracked(),
+        });
+    }
+    submodules.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(submodules)
+}
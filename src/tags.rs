@@ -0,0 +1,107 @@
+use crate::process::ExternalCommand;
+use chrono::{DateTime, Utc};
+use git2::{Oid, Repository};
+
+/// First lines of the signatures `git tag -s` appends to a tag's message
+const SIGNATURE_STARTS: [&str; 3] = ["-----BEGIN PGP SIGNATURE-----", "-----BEGIN SSH SIGNATURE-----", "-----BEGIN SIGNED MESSAGE-----"];
+
+/// What an annotated tag says beyond the commit it points to
+pub struct Annotation {
+    pub name: String,
+    /// The tag object, which `git verify-tag` checks
+    pub id: Oid,
+    /// `Name <email>`, none for tags made without a tagger
+    pub tagger: Option<String>,
+    pub time: Option<DateTime<Utc>>,
+    /// The message, without its signature
+    pub message: String,
+    pub signed: bool,
+}
+
+/// The annotation of tag `name`; None for a lightweight tag, a ref to the
+/// commit itself
+pub fn annotation(repo: &Repository, name: &str) -> Option<Annotation> {
+    let reference = repo.find_reference(&format!("refs/tags/{}", name)).ok()?;
+    let tag = repo.find_tag(reference.target()?).ok()?;
+    let message = String::from_utf8_lossy(tag.message_bytes().unwrap_or_default()).into_owned();
+    let signature_at = message
+        .match_indices("-----BEGIN ")
+        .map(|(at, _)| at)
+        .find(|&at| (at == 0 || message[..at].ends_with('\n')) && SIGNATURE_STARTS.iter().any(|start| message[at..].starts_with(start)));
+    let tagger = tag.tagger();
+    Some(Annotation {
+        name: name.to_string(),
+        id: tag.id(),
+        tagger: tagger.as_ref().map(|tagger| {
+            format!("{} <{}>", String::from_utf8_lossy(tagger.name_bytes()), String::from_utf8_lossy(tagger.email_bytes()))
+        }),
+        time: tagger.as_ref().and_then(|tagger| DateTime::from_timestamp(tagger.when().seconds(), 0)),
+        message: message[..signature_at.unwrap_or(message.len())].trim_end().to_string(),
+        signed: signature_at.is_some(),
+    })
+}
+
+/// What `git verify-tag` made of a signed tag
+#[derive(Debug, Clone)]
+pub enum Verification {
+    /// Still running
+    Checking,
+    /// A good signature by the key of the named signer
+    Good(String),
+    /// Signed by a key that expired or was revoked since, or that nothing
+    /// vouches for; what gpg said
+    Doubtful(String),
+    /// A signature that does not match the tag, or whose key is unknown
+    Bad(String),
+}
+
+impl Verification {
+    pub fn describe(&self) -> String {
+        match self {
+            Verification::Checking => "checking…".to_string(),
+            Verification::Good(signer) => format!("✓ good signature by {}", signer),
+            Verification::Doubtful(reason) => format!("? {}", reason),
+            Verification::Bad(reason) => format!("✗ {}", reason),
+        }
+    }
+}
+
+/// `git verify-tag --raw` of the tag object `id`: gpg's status lines, or
+/// ssh-keygen's verdict for SSH signatures, come on stderr
+pub fn verify(git: ExternalCommand, id: Oid) -> ExternalCommand {
+    git.args(["verify-tag", "--raw"]).arg(id.to_string())
+}
+
+/// The verdict in `git verify-tag --raw` output, `success` being its exit
+/// status
+pub fn parse_verification(success: bool, stderr: &str) -> Verification {
+    for line in stderr.lines() {
+        let Some(status) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        // `GOODSIG <key id> <user id>`
+        let (keyword, rest) = status.split_once(' ').unwrap_or((status, ""));
+        let signer = rest.split_once(' ').map_or(rest, |(_, user)| user).to_string();
+        match keyword {
+            "EXPKEYSIG" => return Verification::Doubtful(format!("good signature by {}, whose key has expired", signer)),
+            "REVKEYSIG" => return Verification::Doubtful(format!("good signature by {}, whose key was revoked", signer)),
+            "BADSIG" => return Verification::Bad(format!("bad signature claiming to be by {}", signer)),
+            "NO_PUBKEY" => return Verification::Bad(format!("cannot check: no public key {}", rest)),
+            "GOODSIG" if !stderr.contains("[GNUPG:] TRUST_UNDEFINED") && !stderr.contains("[GNUPG:] TRUST_NEVER") => {
+                return Verification::Good(signer);
+            }
+            "GOODSIG" => return Verification::Doubtful(format!("good signature by {}, from an untrusted key", signer)),
+            _ => {}
+        }
+    }
+    // ssh-keygen: `Good "git" signature for user@example.com with ED25519 key SHA256:…`
+    if let Some(line) = stderr.lines().find(|line| line.starts_with("Good \"git\" signature for ")) {
+        let signer = line["Good \"git\" signature for ".len()..].split(" with ").next().unwrap_or_default();
+        return Verification::Good(signer.to_string());
+    }
+    let reason = stderr.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("no signature found");
+    match success {
+        true => Verification::Good(reason.to_string()),
+        false => Verification::Bad(reason.to_string()),
+    }
+}
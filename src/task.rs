@@ -0,0 +1,185 @@
+use crate::ansi;
+use crate::oplog::{self, Outcome};
+use crate::process::{self, ExternalCommand};
+use anyhow::{anyhow, Result};
+use std::{
+    collections::VecDeque,
+    io::Read,
+    process::{Child, ChildStderr, Output},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Instant,
+};
+
+/// Spinner frames, a step every [`FRAME_MS`]
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const ASCII_FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+const FRAME_MS: u128 = 100;
+
+/// Commands run one after another in the background, so the interface keeps
+/// drawing while they work. What they print on stderr (git's `--progress`
+/// lines, redrawn in place with `\r`) is shown as it comes. Dropping a task
+/// kills the command it is running and skips the rest.
+pub struct Task {
+    /// What the task does, for the status line: "Fetching origin"
+    pub label: String,
+    pub started: Instant,
+    /// Commands still to start, each with the name its result is given
+    queue: VecDeque<(String, ExternalCommand)>,
+    current: Option<Running>,
+    results: Vec<(String, Result<Output>)>,
+}
+
+struct Running {
+    name: String,
+    child: Child,
+    stdout: JoinHandle<Vec<u8>>,
+    stderr: JoinHandle<Vec<u8>>,
+    /// The newest line the command wrote to stderr
+    progress: Arc<Mutex<String>>,
+    /// Its entry in the operation log
+    log_id: u64,
+    started: Instant,
+}
+
+impl Task {
+    /// A task running `commands` in order; the first starts right away
+    pub fn start(label: impl Into<String>, commands: Vec<(String, ExternalCommand)>) -> Task {
+        let mut task = Task {
+            label: label.into(),
+            started: Instant::now(),
+            queue: commands.into(),
+            current: None,
+            results: Vec::new(),
+        };
+        task.start_next();
+        task
+    }
+
+    /// Starts queued commands until one is running; the ones that cannot
+    /// start get their error as a result
+    fn start_next(&mut self) {
+        while self.current.is_none()
+            && let Some((name, command)) = self.queue.pop_front()
+        {
+            match spawn(&command) {
+                Ok(running) => self.current = Some(Running { name, ..running }),
+                Err(e) => self.results.push((name, Err(e))),
+            }
+        }
+    }
+
+    /// Collects the running command if it exited and starts the next one.
+    /// True once all of them are done.
+    pub fn poll(&mut self) -> bool {
+        if let Some(running) = self.current.as_mut() {
+            match running.child.try_wait() {
+                Ok(None) => return false,
+                Ok(Some(_)) | Err(_) => {
+                    if let Some(running) = self.current.take() {
+                        self.results.push(running.collect());
+                    }
+                }
+            }
+        }
+        self.start_next();
+        self.current.is_none()
+    }
+
+    /// The result of every command, in the order they were given; empty
+    /// until [`poll`](Task::poll) says the task is done
+    pub fn results(&mut self) -> Vec<(String, Result<Output>)> {
+        std::mem::take(&mut self.results)
+    }
+
+    /// The running command's newest progress line
+    pub fn progress(&self) -> String {
+        self.current
+            .as_ref()
+            .map(|running| running.progress.lock().unwrap_or_else(|e| e.into_inner()).clone())
+            .unwrap_or_default()
+    }
+
+    /// The spinner frame for now
+    pub fn spinner(&self, ascii: bool) -> char {
+        let frames: &[char] = if ascii { &ASCII_FRAMES } else { &FRAMES };
+        frames[(self.started.elapsed().as_millis() / FRAME_MS) as usize % frames.len()]
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        if let Some(mut running) = self.current.take() {
+            let _ = running.child.kill();
+            let _ = running.child.wait();
+            oplog::finish(running.log_id, running.started.elapsed(), Outcome::Cancelled);
+        }
+    }
+}
+
+impl Running {
+    /// The output of a command that exited, finishing its log entry
+    fn collect(mut self) -> (String, Result<Output>) {
+        let result = self.child.wait().map_err(anyhow::Error::from).and_then(|status| {
+            let stdout = self.stdout.join().map_err(|_| anyhow!("stdout reader panicked"))?;
+            let stderr = self.stderr.join().map_err(|_| anyhow!("stderr reader panicked"))?;
+            Ok(Output { status, stdout, stderr })
+        });
+        let outcome = match result {
+            Ok(ref output) if output.status.success() => Outcome::Ok,
+            Ok(ref output) => Outcome::Failed(process::failure(output.status, &String::from_utf8_lossy(&output.stderr))),
+            Err(ref e) => Outcome::Failed(e.to_string()),
+        };
+        oplog::finish(self.log_id, self.started.elapsed(), outcome);
+        (self.name, result)
+    }
+}
+
+fn spawn(command: &ExternalCommand) -> Result<Running> {
+    let (mut child, log_id) = command.spawn()?;
+    let mut stdout = child.stdout.take().ok_or_else(|| anyhow!("no stdout pipe"))?;
+    let stderr = child.stderr.take().ok_or_else(|| anyhow!("no stderr pipe"))?;
+    let progress = Arc::new(Mutex::new(String::new()));
+
+    let stdout = std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = stdout.read_to_end(&mut bytes);
+        bytes
+    });
+    let shared = Arc::clone(&progress);
+    let stderr = std::thread::spawn(move || read_progress(stderr, &shared));
+
+    Ok(Running { name: String::new(), child, stdout, stderr, progress, log_id, started: Instant::now() })
+}
+
+/// Reads stderr to its end, keeping `progress` at the newest line. Lines
+/// ended by `\r` are progress redrawn in place and are left out of what is
+/// returned, so error messages come back without them.
+fn read_progress(mut stderr: ChildStderr, progress: &Mutex<String>) -> Vec<u8> {
+    let mut kept = Vec::new();
+    let mut line = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let read = match stderr.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        for &byte in &buf[..read] {
+            if byte != b'\r' && byte != b'\n' {
+                line.push(byte);
+                continue;
+            }
+            let text = ansi::strip(String::from_utf8_lossy(&line).trim()).into_owned();
+            if !text.is_empty() {
+                *progress.lock().unwrap_or_else(|e| e.into_inner()) = text;
+            }
+            if byte == b'\n' {
+                kept.append(&mut line);
+                kept.push(b'\n');
+            }
+            line.clear();
+        }
+    }
+    kept.append(&mut line);
+    kept
+}
@@ -0,0 +1,113 @@
+use ratatui::style::Color;
+use std::{collections::HashMap, str::FromStr};
+
+/// Colors used across the panels. Configured through the `[theme]` table,
+/// where each key is a field name and each value a color name, 256-color
+/// index or `#rrggbb`.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub active_border: Color,
+    pub inactive_border: Color,
+    pub overlay_border: Color,
+    pub selection_bg: Color,
+    pub branch_filter: Color,
+    pub branch_included: Color,
+    pub branch_head: Color,
+    pub branch_remote: Color,
+    pub branch_local: Color,
+    pub branch_marked: Color,
+    pub commit_hash: Color,
+    pub commit_text: Color,
+    pub ref_head: Color,
+    pub ref_remote: Color,
+    pub ref_tag: Color,
+    pub ref_local: Color,
+    pub ref_stash: Color,
+    pub diff_added: Color,
+    pub diff_removed: Color,
+    pub diff_hunk: Color,
+    pub diff_header: Color,
+    pub error: Color,
+    /// Cycled through for the graph's lanes as they open (`lane_1` to `lane_6`)
+    pub lanes: [Color; 6],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            active_border: Color::Yellow,
+            inactive_border: Color::DarkGray,
+            overlay_border: Color::Cyan,
+            selection_bg: Color::DarkGray,
+            branch_filter: Color::Green,
+            branch_included: Color::Yellow,
+            branch_head: Color::Yellow,
+            branch_remote: Color::Cyan,
+            branch_local: Color::White,
+            branch_marked: Color::Magenta,
+            commit_hash: Color::Yellow,
+            commit_text: Color::White,
+            ref_head: Color::Cyan,
+            ref_remote: Color::Red,
+            ref_tag: Color::Yellow,
+            ref_local: Color::Green,
+            ref_stash: Color::LightBlue,
+            diff_added: Color::Green,
+            diff_removed: Color::Red,
+            diff_hunk: Color::Cyan,
+            diff_header: Color::Magenta,
+            error: Color::Red,
+            lanes: [
+                Color::Green,
+                Color::Yellow,
+                Color::Blue,
+                Color::Magenta,
+                Color::Cyan,
+                Color::LightRed,
+            ],
+        }
+    }
+}
+
+impl Theme {
+    /// Default theme with the configured colors applied
+    pub fn from_config(colors: &HashMap<String, String>) -> Result<Theme, String> {
+        let mut theme = Theme::default();
+
+        for (name, value) in colors {
+            let color = Color::from_str(value)
+                .map_err(|_| format!("invalid color '{}' for theme.{}", value, name))?;
+            let slot = match name.as_str() {
+                "active_border" => &mut theme.active_border,
+                "inactive_border" => &mut theme.inactive_border,
+                "overlay_border" => &mut theme.overlay_border,
+                "selection_bg" => &mut theme.selection_bg,
+                "branch_filter" => &mut theme.branch_filter,
+                "branch_included" => &mut theme.branch_included,
+                "branch_head" => &mut theme.branch_head,
+                "branch_remote" => &mut theme.branch_remote,
+                "branch_local" => &mut theme.branch_local,
+                "branch_marked" => &mut theme.branch_marked,
+                "commit_hash" => &mut theme.commit_hash,
+                "commit_text" => &mut theme.commit_text,
+                "ref_head" => &mut theme.ref_head,
+                "ref_remote" => &mut theme.ref_remote,
+                "ref_tag" => &mut theme.ref_tag,
+                "ref_local" => &mut theme.ref_local,
+                "ref_stash" => &mut theme.ref_stash,
+                "diff_added" => &mut theme.diff_added,
+                "diff_removed" => &mut theme.diff_removed,
+                "diff_hunk" => &mut theme.diff_hunk,
+                "diff_header" => &mut theme.diff_header,
+                "error" => &mut theme.error,
+                _ => match name.strip_prefix("lane_").and_then(|n| n.parse::<usize>().ok()) {
+                    Some(n @ 1..=6) => &mut theme.lanes[n - 1],
+                    _ => return Err(format!("unknown theme color '{}'", name)),
+                },
+            };
+            *slot = color;
+        }
+
+        Ok(theme)
+    }
+}
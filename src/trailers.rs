@@ -0,0 +1,98 @@
+/// A `Key: value` line from the trailer block at the end of a commit message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+/// Trailers of a commit message: the last paragraph, when every line in it is
+/// a `Key: value` trailer or an indented continuation of one. The subject
+/// paragraph never counts. Like git, the line `cherry-pick -x` adds is
+/// allowed among the trailers.
+pub fn parse(message: &str) -> Vec<Trailer> {
+    let paragraphs: Vec<&str> = message
+        .trim_end()
+        .split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .collect();
+    let Some((last, rest)) = paragraphs.split_last() else {
+        return Vec::new();
+    };
+    if rest.is_empty() {
+        return Vec::new();
+    }
+
+    let mut trailers: Vec<Trailer> = Vec::new();
+    for line in last.lines() {
+        if line.starts_with("(cherry picked from commit ") {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            match trailers.last_mut() {
+                Some(trailer) => {
+                    trailer.value.push(' ');
+                    trailer.value.push_str(line.trim());
+                }
+                None => return Vec::new(),
+            }
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, value)) if is_token(key) => trailers.push(Trailer {
+                key: key.to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => return Vec::new(),
+        }
+    }
+    trailers
+}
+
+/// `message` with `added` (`Key: value` lines) at its end: in its trailer
+/// block when it has one, else in a paragraph of their own. Trailers it
+/// already carries are not added again.
+pub fn append(message: &str, added: &[String]) -> String {
+    let present: Vec<String> = parse(message).iter().map(|t| format!("{}: {}", t.key.to_lowercase(), t.value)).collect();
+    let added: Vec<&String> = added.iter().filter(|line| !present.contains(&lowercase_key(line))).collect();
+    let mut text = message.trim_end().to_string();
+    if added.is_empty() {
+        return text;
+    }
+    text.push_str(if present.is_empty() { "\n\n" } else { "\n" });
+    for line in added {
+        text.push_str(line);
+        text.push('\n');
+    }
+    text
+}
+
+fn lowercase_key(line: &str) -> String {
+    match line.split_once(':') {
+        Some((key, value)) => format!("{}: {}", key.to_lowercase(), value.trim()),
+        None => line.to_string(),
+    }
+}
+
+/// Value of the `Change-Id` trailer, which Gerrit keeps across amends and cherry-picks
+pub fn change_id(message: &str) -> Option<String> {
+    parse(message)
+        .into_iter()
+        .find(|t| t.key.eq_ignore_ascii_case("Change-Id"))
+        .map(|t| t.value)
+}
+
+/// Link for a trailer: the value itself when it is a URL, else the configured
+/// template for its key with `{value}` substituted
+pub fn link(trailer: &Trailer, templates: &[(String, String)]) -> Option<String> {
+    if trailer.value.starts_with("https://") || trailer.value.starts_with("http://") {
+        return Some(trailer.value.clone());
+    }
+    templates
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(&trailer.key))
+        .map(|(_, template)| template.replace("{value}", &trailer.value))
+}
+
+pub fn is_token(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
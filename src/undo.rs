@@ -0,0 +1,130 @@
+use chrono::{DateTime, Local};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// HEAD changes made by git-tui, newest last, so `u` can go back through
+/// them. Kept next to the reflog it refers to; per worktree.
+const FILE_NAME: &str = "git-tui-undo";
+
+/// Records kept; older ones are dropped
+const KEEP: usize = 20;
+
+/// Where HEAD was
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    /// Full commit id
+    pub head: String,
+    /// Checked-out branch; none when detached
+    pub branch: Option<String>,
+}
+
+impl Position {
+    pub fn of(repo: &Repository) -> Option<Position> {
+        let head = repo.head().ok()?;
+        Some(Position {
+            head: head.target()?.to_string(),
+            branch: head.is_branch().then(|| head.shorthand().map(str::to_string)).flatten(),
+        })
+    }
+}
+
+/// A command git-tui ran that moved HEAD, with the reflog entries it left
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// The command line, as it would be typed
+    pub command: String,
+    pub time: DateTime<Local>,
+    pub before: Position,
+    pub after: Position,
+    /// `git reset` mode that takes the branch back without losing what the
+    /// command left in the index and working tree
+    pub reset_mode: String,
+    /// Size of the HEAD reflog and its newest message once the command was
+    /// done, to tell whether anything else moved HEAD since
+    reflog_len: usize,
+    reflog_message: String,
+}
+
+/// Why the newest record cannot be undone
+pub enum Blocked {
+    Nothing,
+    /// Something else moved HEAD since, with its reflog message
+    MovedSince(String),
+}
+
+fn path(repo: &Repository) -> PathBuf {
+    repo.path().join(FILE_NAME)
+}
+
+fn load(repo: &Repository) -> Vec<Record> {
+    std::fs::read_to_string(path(repo))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save(repo: &Repository, records: &[Record]) {
+    if let Ok(json) = serde_json::to_string_pretty(records) {
+        let _ = std::fs::write(path(repo), json + "\n");
+    }
+}
+
+/// Length and newest message of the HEAD reflog
+fn reflog_tip(repo: &Repository) -> Option<(usize, String)> {
+    let reflog = repo.reflog("HEAD").ok()?;
+    let newest = reflog.get(0)?;
+    Some((reflog.len(), newest.message().unwrap_or("").to_string()))
+}
+
+/// Notes that `command` took HEAD from `before` to where it is now. Nothing
+/// is kept when HEAD did not move. Failing to write only loses the undo.
+pub fn record(repo: &Repository, command: String, before: Option<Position>, reset_mode: &str) {
+    let (Some(before), Some(after), Some((reflog_len, reflog_message))) = (before, Position::of(repo), reflog_tip(repo)) else {
+        return;
+    };
+    if before == after {
+        return;
+    }
+    let mut records = load(repo);
+    records.push(Record {
+        command,
+        time: Local::now(),
+        before,
+        after,
+        reset_mode: reset_mode.to_string(),
+        reflog_len,
+        reflog_message,
+    });
+    let excess = records.len().saturating_sub(KEEP);
+    records.drain(..excess);
+    save(repo, &records);
+}
+
+/// The newest record, if HEAD is still where it left it
+pub fn last(repo: &Repository) -> Result<Record, Blocked> {
+    let record = load(repo).pop().ok_or(Blocked::Nothing)?;
+    let (len, message) = reflog_tip(repo).ok_or(Blocked::Nothing)?;
+    if len != record.reflog_len || message != record.reflog_message || Position::of(repo).as_ref() != Some(&record.after) {
+        return Err(Blocked::MovedSince(message));
+    }
+    Ok(record)
+}
+
+/// Drops the newest record once it was undone. The one before it becomes
+/// undoable again when the undo brought HEAD back to where it left it.
+pub fn undone(repo: &Repository) {
+    let mut records = load(repo);
+    let Some(undone) = records.pop() else {
+        return;
+    };
+    if let Some(previous) = records.last_mut()
+        && previous.after == undone.before
+        && Position::of(repo).as_ref() == Some(&previous.after)
+        && let Some((len, message)) = reflog_tip(repo)
+    {
+        previous.reflog_len = len;
+        previous.reflog_message = message;
+    }
+    save(repo, &records);
+}
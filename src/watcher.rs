@@ -0,0 +1,93 @@
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::{Duration, Instant},
+};
+
+/// Watches HEAD, the index and the refs of a repository so the view can be
+/// refreshed when another process commits, fetches or switches branches.
+pub struct RepoWatcher {
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// Event paths are tested relative to this, so a `refs` directory
+    /// above the repository does not make every change relevant
+    common_dir: PathBuf,
+    pending_since: Option<Instant>,
+}
+
+impl RepoWatcher {
+    /// Changes are coalesced until the repository has been quiet this long
+    const DEBOUNCE: Duration = Duration::from_millis(500);
+
+    /// Watches `git_dir` (HEAD, index) and the common dir holding the refs,
+    /// which differ for linked worktrees
+    pub fn new(git_dir: &Path) -> notify::Result<Self> {
+        // Canonical, so the event paths under it (reported as watched) strip
+        // cleanly of it whatever `..` the commondir file held
+        let common_dir = common_dir(git_dir);
+        let common_dir = std::fs::canonicalize(&common_dir).unwrap_or(common_dir);
+        let git_dir = std::fs::canonicalize(git_dir).unwrap_or_else(|_| git_dir.to_path_buf());
+        let git_dir = git_dir.as_path();
+        let (tx, events) = channel();
+        let mut watcher = notify::recommended_watcher(tx)?;
+
+        watcher.watch(git_dir, RecursiveMode::NonRecursive)?;
+        if common_dir != git_dir {
+            watcher.watch(&common_dir, RecursiveMode::NonRecursive)?;
+        }
+        let refs_dir = common_dir.join("refs");
+        if refs_dir.is_dir() {
+            watcher.watch(&refs_dir, RecursiveMode::Recursive)?;
+        }
+
+        Ok(RepoWatcher {
+            _watcher: watcher,
+            events,
+            common_dir,
+            pending_since: None,
+        })
+    }
+
+    /// Drains pending events; returns true once a burst of relevant changes has settled
+    pub fn poll(&mut self) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if event.kind.is_access() {
+                continue;
+            }
+            if event.paths.iter().any(|p| is_relevant(p.strip_prefix(&self.common_dir).unwrap_or(p))) {
+                self.pending_since = Some(Instant::now());
+            }
+        }
+
+        match self.pending_since {
+            Some(since) if since.elapsed() >= Self::DEBOUNCE => {
+                self.pending_since = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The directory shared by all worktrees, read from `<git_dir>/commondir`
+pub fn common_dir(git_dir: &Path) -> PathBuf {
+    match std::fs::read_to_string(git_dir.join("commondir")) {
+        Ok(content) => git_dir.join(content.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// Whether a change to `path`, relative to the common dir when under it,
+/// can change what is shown
+fn is_relevant(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if name.ends_with(".lock") {
+        return false;
+    }
+    matches!(name, "HEAD" | "index" | "packed-refs" | "ORIG_HEAD" | "MERGE_HEAD")
+        || path.components().any(|c| c.as_os_str() == "refs")
+}
@@ -0,0 +1,63 @@
+use crossterm::{execute, terminal::SetTitle};
+use std::io::{self, Write};
+
+/// Saves the terminal's title on its title stack so it can be put back on exit.
+/// Terminals without a title stack ignore this.
+pub fn push_title() -> io::Result<()> {
+    write!(io::stdout(), "\x1b[22;0t")?;
+    io::stdout().flush()
+}
+
+/// Restores the title saved by [`push_title`]
+pub fn pop_title() -> io::Result<()> {
+    write!(io::stdout(), "\x1b[23;0t")?;
+    io::stdout().flush()
+}
+
+pub fn set_title(title: &str) -> io::Result<()> {
+    execute!(io::stdout(), SetTitle(sanitize(title)))
+}
+
+/// Asks the terminal for a desktop notification. VTE-based terminals
+/// understand OSC 777, most others (iTerm2, kitty, WezTerm, Windows
+/// Terminal) OSC 9; terminals without support drop the sequence.
+pub fn notify(title: &str, body: &str) -> io::Result<()> {
+    let mut out = io::stdout();
+    if std::env::var_os("VTE_VERSION").is_some() {
+        // Fields are separated by ';'
+        write!(out, "\x1b]777;notify;{};{}\x07", sanitize(title).replace(';', ","), sanitize(body))?;
+    } else {
+        write!(out, "\x1b]9;{}: {}\x07", sanitize(title), sanitize(body))?;
+    }
+    out.flush()
+}
+
+/// Puts text on the system clipboard through the terminal (OSC 52). Some
+/// terminals need this allowed first (tmux: `set-clipboard on`).
+pub fn copy_to_clipboard(text: &[u8]) -> io::Result<()> {
+    let mut out = io::stdout();
+    write!(out, "\x1b]52;c;{}\x07", base64(text))?;
+    out.flush()
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let n = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Control characters would end the escape sequence early
+fn sanitize(text: &str) -> String {
+    text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect()
+}
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+/// An entry of `git worktree list`
+#[derive(Debug, Clone, Default)]
+pub struct WorktreeInfo {
+    pub path: PathBuf,
+    /// Checked-out commit; none for a bare repository
+    pub head: Option<String>,
+    /// Short name of the checked-out branch; none when detached or bare
+    pub branch: Option<String>,
+    pub is_bare: bool,
+    pub locked: bool,
+    /// Its directory is gone, so `git worktree prune` would remove it
+    pub prunable: bool,
+}
+
+impl WorktreeInfo {
+    /// What the worktree has checked out, for display
+    pub fn label(&self) -> String {
+        match (&self.branch, &self.head) {
+            _ if self.is_bare => "(bare)".to_string(),
+            (Some(branch), _) => branch.clone(),
+            (None, Some(head)) => format!("(detached at {})", &head[..head.len().min(7)]),
+            (None, None) => "(unknown)".to_string(),
+        }
+    }
+}
+
+/// Parses `git worktree list --porcelain`: one block of `key value` lines per
+/// worktree, the main worktree first
+pub fn parse(porcelain: &str) -> Vec<WorktreeInfo> {
+    let mut worktrees = Vec::new();
+    for block in porcelain.split("\n\n") {
+        let mut worktree = WorktreeInfo::default();
+        for line in block.lines() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "worktree" => worktree.path = PathBuf::from(value),
+                "HEAD" => worktree.head = Some(value.to_string()),
+                "branch" => worktree.branch = Some(value.strip_prefix("refs/heads/").unwrap_or(value).to_string()),
+                "bare" => worktree.is_bare = true,
+                "locked" => worktree.locked = true,
+                "prunable" => worktree.prunable = true,
+                _ => {}
+            }
+        }
+        if !worktree.path.as_os_str().is_empty() {
+            worktrees.push(worktree);
+        }
+    }
+    worktrees
+}